@@ -0,0 +1,380 @@
+use rand::{seq::IndexedRandom, Rng};
+
+use crate::{Action, GameState, SetupState};
+use crate::game::*;
+use crate::moves::Move;
+use crate::new_game::Difficulty;
+use crate::player_view::PlayerView;
+
+/// Plays a CPU seat one move at a time, seeing only what the seat may see.
+/// Easy bots take any legal move.
+/// Normal ones go for the best-producing spots and trade with the bank
+/// to finish a build. Hard ones also play their development cards.
+#[derive(Debug, Clone, Copy)]
+pub struct Bot {
+    pub difficulty: Difficulty,
+}
+
+/// How many of each resource `hand` is still short of `cost`
+fn missing(hand: ResHand, cost: ResHand) -> ResHand {
+    let mut missing = ResHand::new();
+    for res in RESOURCES {
+        missing[res] = cost[res].saturating_sub(hand[res]);
+    }
+    missing
+}
+
+/// The better of an edge's two corners to settle on later
+fn edge_pips(board: &Board, edge: [usize; 3]) -> usize {
+    edge_corner_neighbors(edge)
+        .filter(|&corner| board.can_place_setup_settlement(corner))
+        .map(|corner| board.corner_pips(corner))
+        .max()
+        .unwrap_or(0)
+}
+
+/// The bot's own seat, whose hand its view always shows
+fn own_player(view: &PlayerView) -> &Player {
+    view.get_own_player().expect("Bot: the view doesn't show the bot's hand")
+}
+
+impl Bot {
+    pub fn new(difficulty: Difficulty) -> Bot {
+        Bot { difficulty }
+    }
+
+    /// The option scoring highest, or any of them for an easy bot
+    fn pick<T: Copy, R: Rng + ?Sized>(&self, options: &[T], score: impl Fn(T) -> usize, rng: &mut R) -> Option<T> {
+        if self.difficulty == Difficulty::Easy {
+            return options.choose(rng).copied();
+        }
+        options.iter().copied().max_by_key(|&option| score(option))
+    }
+
+    /// The next settlement, or the road or ship that goes with it
+    pub fn setup_step<R: Rng + ?Sized>(&self, state: &SetupState, rng: &mut R) -> Move {
+        let board = &state.board;
+        let Some(settlement) = state.settlement else {
+            let corners: Vec<[usize; 3]> = board.layout.corner_coords.iter().copied()
+                .filter(|&corner| board.can_place_setup_settlement(corner))
+                .collect();
+            let corner = self.pick(&corners, |corner| board.corner_pips(corner), rng)
+                .expect("Bot::setup_step(): no room for a settlement");
+            return Move::PlaceSettlement(corner);
+        };
+
+        let roads: Vec<[usize; 3]> = board.layout.edge_coords.iter().copied()
+            .filter(|&edge| board.can_place_setup_road(edge, settlement))
+            .collect();
+        let ships: Vec<[usize; 3]> = board.layout.edge_coords.iter().copied()
+            .filter(|&edge| state.ruleset == Ruleset::Seafarers && board.can_place_setup_ship(edge, settlement))
+            .collect();
+        if let Some(edge) = self.pick(&roads, |edge| edge_pips(board, edge), rng) {
+            Move::PlaceRoad(edge)
+        } else {
+            let edge = self.pick(&ships, |edge| edge_pips(board, edge), rng)
+                .expect("Bot::setup_step(): no room for a road or ship");
+            Move::PlaceShip(edge)
+        }
+    }
+
+    /// The move for the seat acting now, which must be this bot's and the view's
+    pub fn play_step<R: Rng + ?Sized>(&self, view: &PlayerView, rng: &mut R) -> Move {
+        let hand = own_player(view).get_hand();
+        match view.get_action() {
+            Action::Discarding | Action::Sabotaged => Move::Discard(self.choose_discards(hand, hand.size() / 2, rng)),
+            Action::Giving => Move::Give(self.choose_discards(hand, view.cards_to_give(), rng)),
+            Action::HarborReturning => {
+                let commodities = own_player(view).get_commodities();
+                let card = COMMODITIES.iter().copied().max_by_key(|&com| commodities[com])
+                    .expect("Bot::play_step(): no commodity to give back");
+                Move::GiveCommodity(card)
+            },
+            Action::Deserting => {
+                let board = view.get_board();
+                let corner = board.get_knights(view.get_current_color()).into_iter()
+                    .min_by_key(|&corner| board.get_knight(corner).map(|knight| knight.level))
+                    .expect("Bot::play_step(): no knight to give up");
+                Move::RemoveKnight(corner)
+            },
+            Action::Displaced(_, from) => {
+                let color = view.get_current_color();
+                let corners: Vec<[usize; 3]> = view.get_board().layout.corner_coords.iter().copied()
+                    .filter(|&corner| corner != from && view.get_board().can_place_knight(corner, color))
+                    .collect();
+                let corner = self.pick(&corners, |corner| view.get_board().corner_pips(corner), rng)
+                    .expect("Bot::play_step(): nowhere for the knight to go");
+                Move::PlaceKnight(corner)
+            },
+            Action::ChoosingGold => {
+                let owed = view.get_gold_owed(view.get_current_color());
+                Move::ChooseGold(self.choose_gold(hand, owed, view.get_board().bank, rng))
+            },
+            Action::MovingRobber => self.move_robber(view, rng),
+            Action::ChoosingVictim => {
+                let victims: Vec<PlayerColor> = view.get_board().get_colors_on_hex(view.get_board().robber).into_iter()
+                    .filter(|&color| view.can_rob(color))
+                    .collect();
+                self.steal_from(view, &victims, rng)
+            },
+            Action::ChoosingPirateVictim => {
+                let victims: Vec<PlayerColor> = view.get_board().pirate
+                    .map(|hex| view.get_board().get_ship_colors_on_hex(hex))
+                    .unwrap_or_default().into_iter()
+                    .filter(|&color| view.can_rob(color))
+                    .collect();
+                self.steal_from(view, &victims, rng)
+            },
+            Action::RoadBuilding(_) => {
+                let color = view.get_current_color();
+                let edges: Vec<[usize; 3]> = view.get_board().layout.edge_coords.iter().copied()
+                    .filter(|&edge| view.get_board().can_place_road(edge, color))
+                    .collect();
+                let edge = self.pick(&edges, |edge| edge_pips(view.get_board(), edge), rng)
+                    .expect("Bot::play_step(): no room for a free road");
+                Move::FreeRoad(edge)
+            },
+            // Bots build straight away, so the other actions are as good as idling
+            _ => self.idling_step(view, rng)
+        }
+    }
+
+    /// `count` cards, from the biggest piles down
+    fn choose_discards<R: Rng + ?Sized>(&self, hand: ResHand, count: usize, rng: &mut R) -> ResHand {
+        let mut left = hand;
+        let mut discards = ResHand::new();
+        for _ in 0..count {
+            let res = if self.difficulty == Difficulty::Easy {
+                left.discard_random(rng).unwrap()
+            } else {
+                let res = RESOURCES.iter().copied().max_by_key(|&res| left[res]).unwrap();
+                left[res] -= 1;
+                res
+            };
+            discards[res] += 1;
+        }
+        discards
+    }
+
+    /// Whatever the hand is shortest of
+    /// Out of what's left in `bank`
+    fn choose_gold<R: Rng + ?Sized>(&self, hand: ResHand, owed: usize, bank: ResHand, rng: &mut R) -> ResHand {
+        let mut chosen = ResHand::new();
+        for _ in 0..owed {
+            let left: Vec<Resource> = RESOURCES.iter().copied().filter(|&res| bank[res] > chosen[res]).collect();
+            let res = if self.difficulty == Difficulty::Easy {
+                left.choose(rng).copied()
+            } else {
+                left.iter().copied().min_by_key(|&res| hand[res] + chosen[res])
+            };
+            let Some(res) = res else { break };
+            chosen[res] += 1;
+        }
+        chosen
+    }
+
+    /// Onto the busiest hex of the opponents, keeping off the bot's own
+    fn move_robber<R: Rng + ?Sized>(&self, view: &PlayerView, rng: &mut R) -> Move {
+        let color = view.get_current_color();
+        let board = view.get_board();
+        let hexes: Vec<[usize; 2]> = board.layout.hex_coords.iter().copied()
+            .filter(|&hex| view.can_move_robber(hex))
+            .collect();
+        let hex = self.pick(&hexes, |hex| {
+            let victims = if board.is_sea(hex) { board.get_ship_colors_on_hex(hex) } else { board.get_colors_on_hex(hex) };
+            if victims.contains(&color) {
+                return 0;
+            }
+            let robbable = victims.iter().filter(|&&victim| view.can_rob(victim)).count();
+            robbable * (board.hex_pips(hex) + 1)
+        }, rng).unwrap();
+        Move::PlaceRobber(hex)
+    }
+
+    /// Whoever holds the most cards
+    fn steal_from<R: Rng + ?Sized>(&self, view: &PlayerView, victims: &[PlayerColor], rng: &mut R) -> Move {
+        let victim = self.pick(victims, |victim| view.get_hand_size(victim), rng)
+            .expect("Bot::steal_from(): nobody to steal from");
+        Move::Steal(victim)
+    }
+
+    /// The turn proper: roll, build what can be built, then pass
+    fn idling_step<R: Rng + ?Sized>(&self, view: &PlayerView, rng: &mut R) -> Move {
+        if view.get_roll().is_none() {
+            return if self.wants_knight(view) { Move::PlayDV(DVCard::Knight) } else { Move::Roll };
+        }
+        if let Some(mv) = self.build(view, rng) {
+            return mv;
+        }
+        if !view.is_special_building() {
+            if let Some(mv) = self.play_dv(view).or_else(|| self.trade(view)) {
+                return mv;
+            }
+        }
+        Move::Pass
+    }
+
+    /// Hard bots chase the robber off their own hexes before rolling
+    fn wants_knight(&self, view: &PlayerView) -> bool {
+        self.difficulty == Difficulty::Hard
+        && !view.has_played_dv()
+        && own_player(view).get_dvs()[DVCard::Knight] > 0
+        && view.get_board().get_colors_on_hex(view.get_board().robber).contains(&view.get_current_color())
+    }
+
+    /// One thing to build, if anything can be built
+    fn build<R: Rng + ?Sized>(&self, view: &PlayerView, rng: &mut R) -> Option<Move> {
+        let color = view.get_current_color();
+        let board = view.get_board();
+        let corners = &board.layout.corner_coords;
+        let edges = &board.layout.edge_coords;
+        let settlement_spots: Vec<[usize; 3]> = corners.iter().copied()
+            .filter(|&corner| board.can_place_settlement(corner, color))
+            .collect();
+
+        if view.can_build_city() {
+            let spots: Vec<[usize; 3]> = corners.iter().copied()
+                .filter(|&corner| board.can_place_city(corner, color))
+                .collect();
+            return self.pick(&spots, |corner| board.corner_pips(corner), rng).map(Move::BuildCity);
+        }
+        if view.can_build_settlement() {
+            return self.pick(&settlement_spots, |corner| board.corner_pips(corner), rng).map(Move::BuildSettlement);
+        }
+        if let Some(&track) = IMPROVEMENTS.iter().find(|&&track| view.get_ruleset() == Ruleset::CitiesKnights && view.can_improve(track)) {
+            return Some(Move::Improve(track));
+        }
+        if view.get_ruleset() == Ruleset::CitiesKnights {
+            let knights = corners.iter().filter(|&&corner| board.knight_is_color(corner, color)).count();
+            let cities = board.get_cities(color).len();
+            if view.can_build_knight() && knights < cities.max(1) {
+                let spots: Vec<[usize; 3]> = corners.iter().copied()
+                    .filter(|&corner| board.can_place_knight(corner, color))
+                    .collect();
+                return self.pick(&spots, |corner| board.corner_pips(corner), rng).map(Move::BuildKnight);
+            }
+            if view.can_activate_knight() {
+                return corners.iter().copied()
+                    .find(|&corner| board.can_activate_knight(corner, color))
+                    .map(Move::ActivateKnight);
+            }
+        }
+        // Roads and ships only once there's nowhere left to settle
+        if settlement_spots.is_empty() {
+            if view.can_build_road() {
+                let spots: Vec<[usize; 3]> = edges.iter().copied()
+                    .filter(|&edge| board.can_place_road(edge, color))
+                    .collect();
+                return self.pick(&spots, |edge| edge_pips(board, edge), rng).map(Move::BuildRoad);
+            }
+            if view.can_build_ship() {
+                let spots: Vec<[usize; 3]> = edges.iter().copied()
+                    .filter(|&edge| board.can_place_ship(edge, color))
+                    .collect();
+                return self.pick(&spots, |edge| edge_pips(board, edge), rng).map(Move::BuildShip);
+            }
+        }
+        if view.get_ruleset() != Ruleset::CitiesKnights && view.can_buy_dv() && !view.is_companion() {
+            return Some(Move::BuyDV);
+        }
+        None
+    }
+
+    /// What the bot is saving up for next
+    fn goal(&self, view: &PlayerView) -> ResHand {
+        let color = view.get_current_color();
+        if view.get_board().can_place_any_city(color) && own_player(view).has_city_piece() {
+            CITY_HAND
+        } else if view.get_board().can_place_any_settlement(color) && own_player(view).has_settlement_piece() {
+            SETTLEMENT_HAND
+        } else if view.get_ruleset() != Ruleset::CitiesKnights && view.get_board().can_draw_dv_card() {
+            DV_CARD_HAND
+        } else {
+            ROAD_HAND
+        }
+    }
+
+    /// Hard bots play Road Building, Monopoly and Year of Plenty as soon as they help
+    fn play_dv(&self, view: &PlayerView) -> Option<Move> {
+        if self.difficulty != Difficulty::Hard || view.has_played_dv() {
+            return None;
+        }
+        let color = view.get_current_color();
+        let player = own_player(view);
+        let dvs = player.get_dvs();
+        let short = missing(player.get_hand(), self.goal(view));
+
+        if dvs[DVCard::RoadBuilding] > 0 && view.get_board().can_place_any_road(color) && player.has_road_piece() {
+            return Some(Move::PlayDV(DVCard::RoadBuilding));
+        }
+        if dvs[DVCard::Monopoly] > 0 {
+            // The others' hands are face down, so it names what the goal is
+            // shortest of once they hold three cards each on average
+            let held: usize = view.get_opponents().iter()
+                .filter(|other| other.color != color)
+                .map(|other| other.cards.resources)
+                .sum();
+            let res = RESOURCES.iter().copied().max_by_key(|&res| short[res]).unwrap();
+            if short[res] > 0 && held >= 3 * (view.get_num_players() - 1) {
+                return Some(Move::Monopoly(res));
+            }
+        }
+        if dvs[DVCard::YearOfPlenty] > 0 {
+            let mut hand = ResHand::new();
+            for res in RESOURCES {
+                while short[res] > hand[res] && view.get_board().bank[res] > hand[res] && hand.size() < 2 {
+                    hand[res] += 1;
+                }
+            }
+            if hand.size() == 2 {
+                return Some(Move::YearOfPlenty(hand));
+            }
+        }
+        None
+    }
+
+    /// Spare cards to the bank for one that's missing from the goal
+    fn trade(&self, view: &PlayerView) -> Option<Move> {
+        if self.difficulty == Difficulty::Easy {
+            return None;
+        }
+        let color = view.get_current_color();
+        let goal = self.goal(view);
+        let hand = own_player(view).get_hand();
+        let want = missing(hand, goal).nth_nonzero(0)?;
+        let spare = RESOURCES.iter().copied().find(
+            |&res| hand[res] >= goal[res] + view.trade_rate(color, res)
+        )?;
+
+        let give = ResHand::from_monopoly(spare, view.trade_rate(color, spare));
+        let get = ResHand::from(want);
+        view.can_trade_with_bank(give, get).then_some(Move::BankTrade(give, get))
+    }
+}
+
+/// Bots play the opening for every seat
+pub fn play_setup<R: Rng + ?Sized>(mut state: SetupState, rng: &mut R) -> GameState {
+    while !state.finished {
+        let bot = state.bots[state.current_player].expect("play_setup(): seat without a bot");
+        let mv = bot.setup_step(&state, rng);
+        state.apply_move(&mv);
+    }
+    state.into()
+}
+
+/// Bots play every seat until someone wins or `max_rounds` have gone by.
+/// `on_turn_start` sees the game as each turn begins, and once more at the end.
+pub fn play_out<R: Rng>(state: &mut GameState, rng: &mut R, max_rounds: usize, mut on_turn_start: impl FnMut(&GameState)) {
+    let mut last_turn = None;
+    while state.winner.is_none() && state.round < max_rounds {
+        let turn = (state.round, state.turn_player);
+        if state.is_turn_start() && last_turn != Some(turn) {
+            on_turn_start(state);
+            last_turn = Some(turn);
+        }
+        let bot = state.bots[state.current_player].expect("play_out(): seat without a bot");
+        let mv = bot.play_step(&PlayerView::new(state, state.get_current_color()), rng);
+        state.apply_move(&mv, rng);
+    }
+    on_turn_start(state);
+}
@@ -0,0 +1,300 @@
+use crate::new_game::{BoardChoice, Difficulty, NewGame, SeatKind, MAX_SEATS, MIN_SEATS};
+
+pub const USAGE: &str = "\
+Usage: catan [options]
+
+With no options the start screen opens.
+
+  --players N         human seats (default 1, or 0 when headless)
+  --cpus N            CPU seats (default enough for a table of four)
+  --difficulty D      easy, normal or hard for every CPU (default normal)
+  --seed S            deal the board and dice from S
+  --rules FILE        house rules, as saved from the rules screen
+  --board FILE        a Seafarers map
+  --cities-knights    play Cities & Knights on a random board
+  --load FILE         carry on a saved game
+  --headless          bots play every seat without a window
+  --games N           how many headless games to play (default 1)
+  --tui               play in the terminal instead of a window
+  --repl              type moves in notation and see what they do
+  --svg FILE          draw the game dealt, or the one given with --load, as
+                      a picture in FILE
+  --replay FILE       step through a recorded game
+  --companion [N]     track a game played on a real board (default 4 players)
+  --join HOST:PORT    take a seat at a game on a catan-server
+  --resume TOKEN      with --join, take back the seat the server gave TOKEN for
+  --watch HOST:PORT   spectate a game on a catan-server
+  --rooms HOST:PORT   open or join a room on a catan-server --lobby
+  --turn FILE         make your moves in a game played by file, which the
+                      options that set up a game start afresh
+  --help              show this message";
+
+/// Everything given on the command line
+#[derive(Debug, Default)]
+pub struct Options {
+    pub players: Option<usize>,
+    pub cpus: Option<usize>,
+    pub difficulty: Option<Difficulty>,
+    pub seed: Option<u64>,
+    pub rules: Option<String>,
+    pub board: Option<String>,
+    pub cities_knights: bool,
+    pub load: Option<String>,
+    pub headless: bool,
+    pub games: Option<usize>,
+    pub tui: bool,
+    pub repl: bool,
+    pub svg: Option<String>,
+    pub replay: Option<String>,
+    pub companion: Option<usize>,
+    pub join: Option<String>,
+    pub resume: Option<String>,
+    pub watch: Option<String>,
+    pub rooms: Option<String>,
+    pub turn: Option<String>,
+    pub port: Option<u16>,
+    pub timeout: Option<u64>,
+    pub save: Option<String>,
+    pub caster: bool,
+    pub delay: Option<usize>,
+    pub lobby: bool,
+    pub help: bool,
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{flag} needs a value"))?;
+    value.parse().map_err(|_| format!("bad value '{value}' for {flag}"))
+}
+
+impl Options {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--players" => options.players = Some(parse_value(&arg, args.next())?),
+                "--cpus" => options.cpus = Some(parse_value(&arg, args.next())?),
+                "--difficulty" => {
+                    let name = args.next().ok_or(format!("{arg} needs a value"))?;
+                    options.difficulty = Some(Difficulty::from_name(&name).ok_or(format!("unknown difficulty '{name}'"))?);
+                },
+                "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+                "--rules" => options.rules = Some(parse_value(&arg, args.next())?),
+                "--board" => options.board = Some(parse_value(&arg, args.next())?),
+                "--cities-knights" => options.cities_knights = true,
+                "--load" => options.load = Some(parse_value(&arg, args.next())?),
+                "--headless" => options.headless = true,
+                "--games" => options.games = Some(parse_value(&arg, args.next())?),
+                "--tui" => options.tui = true,
+                "--repl" => options.repl = true,
+                "--svg" => options.svg = Some(parse_value(&arg, args.next())?),
+                "--replay" => options.replay = Some(parse_value(&arg, args.next())?),
+                "--companion" => {
+                    // The player count is optional, so only take a number
+                    let num_players = match args.peek() {
+                        Some(next) if !next.starts_with("--") => parse_value(&arg, args.next())?,
+                        _ => 4
+                    };
+                    options.companion = Some(num_players);
+                },
+                "--join" => options.join = Some(parse_value(&arg, args.next())?),
+                "--resume" => options.resume = Some(parse_value(&arg, args.next())?),
+                "--watch" => options.watch = Some(parse_value(&arg, args.next())?),
+                "--rooms" => options.rooms = Some(parse_value(&arg, args.next())?),
+                "--turn" => options.turn = Some(parse_value(&arg, args.next())?),
+                "--port" => options.port = Some(parse_value(&arg, args.next())?),
+                "--timeout" => options.timeout = Some(parse_value(&arg, args.next())?),
+                "--save" => options.save = Some(parse_value(&arg, args.next())?),
+                "--caster" => options.caster = true,
+                "--delay" => options.delay = Some(parse_value(&arg, args.next())?),
+                "--lobby" => options.lobby = true,
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option '{arg}'"))
+            }
+        }
+        options.check()?;
+        Ok(options)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.board.is_some() && self.cities_knights {
+            return Err(String::from("--board and --cities-knights can't be played together"));
+        }
+        if self.headless && self.players.is_some_and(|players| players > 0) {
+            return Err(String::from("--headless games have no human players"));
+        }
+        let online = self.join.is_some() || self.watch.is_some() || self.rooms.is_some();
+        if self.headless && (self.replay.is_some() || self.companion.is_some() || online || self.turn.is_some()) {
+            return Err(String::from("--replay, --companion, --join, --watch, --rooms and --turn need a window"));
+        }
+        if self.tui && self.repl {
+            return Err(String::from("--tui and --repl can't be played together"));
+        }
+        if self.svg.is_some() && (self.headless || self.tui || self.repl || self.replay.is_some() || self.companion.is_some()
+        || online || self.turn.is_some()) {
+            return Err(String::from("--svg only draws a game set up by the options or given with --load"));
+        }
+        if (self.tui || self.repl)
+        && (self.headless || self.replay.is_some() || self.companion.is_some() || online || self.turn.is_some()) {
+            return Err(String::from("--tui and --repl can't be played with --headless, --replay, --companion, --join, --watch, --rooms or --turn"));
+        }
+        if self.tui && self.players == Some(0) {
+            return Err(String::from("--tui needs at least one human player"));
+        }
+        if online && self.sets_up_game() {
+            return Err(String::from("a game joined with --join, --watch or --rooms is set up by its server"));
+        }
+        if self.resume.is_some() && self.join.is_none() {
+            return Err(String::from("--resume takes back a seat found with --join"));
+        }
+        if self.games == Some(0) {
+            return Err(String::from("--games must be at least 1"));
+        }
+        if let Some(num_players) = self.companion {
+            if !(3..=6).contains(&num_players) {
+                return Err(format!("bad amount of players '{num_players}' for --companion"));
+            }
+        }
+        let modes = [
+            self.load.is_some(), self.replay.is_some(), self.companion.is_some(),
+            self.join.is_some(), self.watch.is_some(), self.rooms.is_some(), self.turn.is_some()
+        ];
+        if modes.iter().filter(|&&mode| mode).count() > 1 {
+            return Err(String::from("only one of --load, --replay, --companion, --join, --watch, --rooms and --turn can be given"));
+        }
+        Ok(())
+    }
+
+    /// The options `catan-server` takes on top of the ones that set up a game
+    pub fn check_server(&self) -> Result<(), String> {
+        if self.headless || self.tui || self.repl || self.svg.is_some() || self.games.is_some() || self.replay.is_some() || self.companion.is_some()
+        || self.join.is_some() || self.resume.is_some() || self.watch.is_some() || self.rooms.is_some()
+        || self.turn.is_some() {
+            return Err(String::from("catan-server only takes the options that set up a game, and its own"));
+        }
+        // The stand-ins' difficulty is the one game option a lobby uses
+        if self.lobby && (self.players.is_some() || self.cpus.is_some() || self.seed.is_some() || self.rules.is_some()
+        || self.board.is_some() || self.cities_knights || self.load.is_some() || self.save.is_some()) {
+            return Err(String::from("--lobby rooms are set up by their players, and aren't saved"));
+        }
+        Ok(())
+    }
+
+    /// Whether the options describe a new game, so the start screen can be skipped
+    pub fn sets_up_game(&self) -> bool {
+        self.players.is_some() || self.cpus.is_some() || self.difficulty.is_some() || self.seed.is_some()
+        || self.rules.is_some() || self.board.is_some() || self.cities_knights
+    }
+
+    /// The table the options describe: humans first, then the CPUs,
+    /// in the colors' usual order
+    pub fn new_game(&self, seed: u64) -> Result<NewGame, String> {
+        let num_humans = self.players.unwrap_or(if self.headless {0} else {1});
+        let num_cpus = self.cpus.unwrap_or(4usize.saturating_sub(num_humans));
+        let num_seats = num_humans + num_cpus;
+        if !(MIN_SEATS..=MAX_SEATS).contains(&num_seats) {
+            return Err(format!("{num_seats} seats; a table has {MIN_SEATS} to {MAX_SEATS}"));
+        }
+
+        let mut new_game = NewGame {
+            seats: Vec::with_capacity(num_seats),
+            board: BoardChoice::Random,
+            rules: None,
+            seed,
+        };
+        let difficulty = self.difficulty.unwrap_or(Difficulty::Normal);
+        for idx in 0..num_seats {
+            new_game.add_seat();
+            new_game.seats[idx].kind = if idx < num_humans { SeatKind::Human } else { SeatKind::Cpu(difficulty) };
+        }
+        if let Some(path) = &self.board {
+            new_game.board = BoardChoice::Scenario(path.clone());
+        } else if self.cities_knights {
+            new_game.board = BoardChoice::CitiesKnights;
+        }
+        if let Some(path) = &self.rules {
+            new_game.rules = Some(new_game.get_rules().load(path)?);
+        }
+        Ok(new_game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn options_are_read_with_their_values() {
+        let options = parse("--players 2 --cpus 3 --difficulty HARD --seed 42 --cities-knights --tui").unwrap();
+        assert_eq!(options.players, Some(2));
+        assert_eq!(options.cpus, Some(3));
+        assert_eq!(options.difficulty, Some(Difficulty::Hard));
+        assert_eq!(options.seed, Some(42));
+        assert!(options.cities_knights && options.tui);
+        assert!(options.sets_up_game());
+        assert!(!parse("--tui").unwrap().sets_up_game());
+    }
+
+    #[test]
+    fn companion_takes_its_player_count_only_if_given() {
+        assert_eq!(parse("--companion").unwrap().companion, Some(4));
+        assert_eq!(parse("--companion 5").unwrap().companion, Some(5));
+        assert_eq!(parse("--companion --seed 3").unwrap().companion, Some(4));
+        assert_eq!(parse("--companion 2").unwrap_err(), "bad amount of players '2' for --companion");
+    }
+
+    #[test]
+    fn bad_options_are_named() {
+        assert_eq!(parse("--seed").unwrap_err(), "--seed needs a value");
+        assert_eq!(parse("--seed many").unwrap_err(), "bad value 'many' for --seed");
+        assert_eq!(parse("--colour red").unwrap_err(), "unknown option '--colour'");
+        assert_eq!(parse("--difficulty brutal").unwrap_err(), "unknown difficulty 'brutal'");
+        assert_eq!(parse("--games 0").unwrap_err(), "--games must be at least 1");
+    }
+
+    #[test]
+    fn options_that_cant_go_together_are_refused() {
+        for args in [
+            "--board map.txt --cities-knights",
+            "--headless --players 1",
+            "--headless --replay game.txt",
+            "--tui --repl",
+            "--svg board.svg --headless",
+            "--repl --companion",
+            "--tui --players 0",
+            "--join localhost:7878 --seed 3",
+            "--resume abc",
+            "--load saved.txt --replay game.txt",
+        ] {
+            assert!(parse(args).is_err(), "{args}");
+        }
+        assert!(parse("--headless --games 10 --seed 3").is_ok());
+        assert!(parse("--join localhost:7878 --resume abc").is_ok());
+        assert!(parse("--svg board.svg --load saved.txt").is_ok());
+    }
+
+    #[test]
+    fn the_table_is_humans_then_cpus() {
+        let new_game = parse("--players 2 --cpus 1 --difficulty easy").unwrap().new_game(9).unwrap();
+        let kinds: Vec<SeatKind> = new_game.seats.iter().map(|seat| seat.kind).collect();
+        assert_eq!(kinds, [SeatKind::Human, SeatKind::Human, SeatKind::Cpu(Difficulty::Easy)]);
+        assert_eq!(new_game.seed, 9);
+
+        let headless = parse("--headless").unwrap().new_game(1).unwrap();
+        assert_eq!(headless.seats.len(), 4);
+        assert_eq!(headless.num_humans(), 0);
+        assert_eq!(parse("--cpus 6").unwrap().new_game(1).unwrap_err(), "7 seats; a table has 2 to 6");
+    }
+
+    #[test]
+    fn the_server_takes_only_game_options() {
+        assert!(parse("--port 7878 --seed 3 --timeout 60").unwrap().check_server().is_ok());
+        assert!(parse("--port 7878 --tui").unwrap().check_server().is_err());
+        assert!(parse("--lobby --difficulty hard").unwrap().check_server().is_ok());
+        assert!(parse("--lobby --seed 3").unwrap().check_server().is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::game::PlayerColor;
+use crate::moves::Move;
+use crate::notation::to_notation;
+use crate::save;
+use crate::server::{RoomSummary, END_ROOMS_MARKER, END_STATE_MARKER, STATE_MARKER};
+use crate::Table;
+
+/// Something the server said
+pub enum Message {
+    /// The seat taken, and the token that takes it back after a dropped connection
+    Seat(PlayerColor, String),
+    /// Spectating, with every hand showing if it's a caster's view,
+    /// and that many moves behind
+    Watching(bool, usize),
+    State(Box<Table>),
+    /// A chat line from a seat, which the next snapshot's log has as well
+    Chat(PlayerColor, String),
+    /// Every room in a lobby
+    Rooms(Vec<RoomSummary>),
+    /// The room and seat sat down in, or `None` back in the lobby
+    Room(Option<(usize, PlayerColor)>),
+    Notice(String),
+    Error(String),
+    Closed(String),
+}
+
+/// What a connection asks the server for
+pub enum Request {
+    Join,
+    /// The seat the token was given for
+    Resume(String),
+    Watch,
+    /// The room list of a `catan-server --lobby`
+    Lobby,
+}
+
+/// A seat at a game on a `catan-server`, or a spectator's place at one.
+/// The server's lines are read on their own thread, so the window never
+/// waits on the network.
+pub struct Connection {
+    address: String,
+    request: Request,
+    stream: TcpStream,
+    messages: Receiver<Message>,
+}
+
+fn connect(address: &str, request: &Request) -> Result<(TcpStream, Receiver<Message>), String> {
+    let mut stream = TcpStream::connect(address).map_err(|err| format!("{address}: {err}"))?;
+    let greeting = match request {
+        Request::Join => String::from("join\n"),
+        Request::Resume(token) => format!("resume {token}\n"),
+        Request::Watch => String::from("watch\n"),
+        Request::Lobby => String::from("rooms\n"),
+    };
+    stream.write_all(greeting.as_bytes()).map_err(|err| format!("{address}: {err}"))?;
+    let reader = stream.try_clone().map_err(|err| format!("{address}: {err}"))?;
+    let (sender, messages) = mpsc::channel();
+    thread::spawn(move || read_server(reader, sender));
+    Ok((stream, messages))
+}
+
+impl Connection {
+    pub fn open(address: &str, request: Request) -> Result<Connection, String> {
+        let (stream, messages) = connect(address, &request)?;
+        Ok(Connection { address: address.to_string(), request, stream, messages })
+    }
+
+    /// Connects again after the last connection was lost, taking back
+    /// the seat if one was given
+    pub fn reconnect(&mut self) -> Result<(), String> {
+        (self.stream, self.messages) = connect(&self.address, &self.request)?;
+        Ok(())
+    }
+
+    /// Sends `mv` in notation, as it's to be played on `table`
+    pub fn send(&mut self, table: &Table, mv: &Move) -> Result<(), String> {
+        writeln!(self.stream, "{}", to_notation(table, mv)).map_err(|err| format!("move not sent: {err}"))
+    }
+
+    pub fn send_chat(&mut self, text: &str) -> Result<(), String> {
+        self.send_line(&format!("chat {text}"))
+    }
+
+    /// A lobby command, such as `join 3` or `ready`
+    pub fn send_line(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stream, "{line}").map_err(|err| format!("not sent: {err}"))
+    }
+
+    /// Leaves the game for the lobby it was started from
+    pub fn back_to_lobby(&mut self) -> Result<(), String> {
+        self.request = Request::Lobby;
+        self.send_line("leave")
+    }
+
+    /// The next message, if one has come in
+    pub fn poll(&mut self) -> Option<Message> {
+        let message = self.messages.try_recv().ok()?;
+        if let Message::Seat(_, token) = &message {
+            self.request = Request::Resume(token.clone());
+        }
+        Some(message)
+    }
+}
+
+fn read_message(line: &str, snapshot: &mut Option<String>, rooms: &mut Vec<RoomSummary>) -> Option<Message> {
+    if let Some(text) = snapshot {
+        if line.trim() != END_STATE_MARKER {
+            *text += line;
+            *text += "\n";
+            return None;
+        }
+        let text = snapshot.take().unwrap();
+        return Some(match save::parse_table(&text) {
+            Ok(table) => Message::State(Box::new(table)),
+            Err(err) => Message::Error(format!("bad state from the server: {err}")),
+        });
+    }
+
+    let (keyword, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+    match keyword {
+        "seat" => {
+            let (name, token) = rest.split_once(' ').unwrap_or((rest, ""));
+            match PlayerColor::from_name(name) {
+                Some(color) => Some(Message::Seat(color, token.to_string())),
+                None => Some(Message::Error(format!("unknown seat '{name}' from the server"))),
+            }
+        },
+        "watching" => {
+            let (mode, delay) = rest.split_once(' ').unwrap_or((rest, "0"));
+            Some(Message::Watching(mode == "caster", delay.parse().unwrap_or(0)))
+        },
+        "room" => match RoomSummary::parse(line) {
+            Ok(room) => {
+                rooms.push(room);
+                None
+            },
+            Err(err) => Some(Message::Error(format!("bad room from the server: {err}"))),
+        },
+        "sitting" => {
+            let (room_id, name) = rest.split_once(' ').unwrap_or((rest, ""));
+            match (room_id.parse(), PlayerColor::from_name(name)) {
+                (Ok(room_id), Some(color)) => Some(Message::Room(Some((room_id, color)))),
+                _ => Some(Message::Error(format!("bad seat '{rest}' from the server"))),
+            }
+        },
+        "lobby" => Some(Message::Room(None)),
+        "chat" => {
+            let (name, text) = rest.split_once(' ').unwrap_or((rest, ""));
+            match PlayerColor::from_name(name) {
+                Some(color) => Some(Message::Chat(color, text.to_string())),
+                None => Some(Message::Error(format!("unknown seat '{name}' from the server"))),
+            }
+        },
+        "notice" => Some(Message::Notice(rest.to_string())),
+        "error" => Some(Message::Error(rest.to_string())),
+        _ if keyword == END_ROOMS_MARKER => Some(Message::Rooms(std::mem::take(rooms))),
+        _ if keyword == STATE_MARKER => {
+            *snapshot = Some(String::new());
+            None
+        },
+        _ => None
+    }
+}
+
+fn read_server(stream: TcpStream, messages: Sender<Message>) {
+    let mut snapshot = None;
+    let mut rooms = Vec::new();
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if let Some(message) = read_message(&line, &mut snapshot, &mut rooms) {
+            if messages.send(message).is_err() {
+                return;
+            }
+        }
+    }
+    let _ = messages.send(Message::Closed(String::from("The server closed the connection")));
+}
@@ -0,0 +1,279 @@
+use std::fs;
+use std::io::Write;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::game::PlayerColor;
+use crate::moves::Move;
+use crate::notation::{parse_move, to_notation};
+use crate::{save, SetupState, Table};
+
+/// Ends the game as dealt; every line after it is a move
+const MOVES_MARKER: &str = "moves";
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a over the last link and `text`. Enough to notice a line changed
+/// by hand, though not to stop someone recomputing every link after it.
+fn chain(prev: u64, text: &str) -> u64 {
+    prev.to_le_bytes().iter().chain(text.as_bytes())
+        .fold(FNV_OFFSET, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// A game played by file, a few moves at a time, passed from one player
+/// to the next. The file holds the seed, the game as it was dealt, and
+/// every move since in notation, each one carrying a hash of it and
+/// every line before it. The game is only ever rebuilt by making those moves again,
+/// so nothing can be edited in without breaking the chain. Each move's
+/// dice and draws come from the link it adds, so they hang on every line
+/// before it and opening the file again can't reroll them.
+///
+/// Anyone holding the file could work the hands out from it, or try a
+/// move to see what it rolls, so it's for players who trust each other
+/// not to look.
+pub struct Correspondence {
+    path: String,
+    table: Table,
+    /// The hash of the last line
+    head: u64,
+}
+
+impl Correspondence {
+    fn new(path: &str, mut table: Table, head: u64) -> Correspondence {
+        match &mut table {
+            Table::Setup(state) => state.hot_seat = false,
+            Table::Playing(state) => state.hot_seat = false,
+        }
+        Correspondence { path: path.to_string(), table, head }
+    }
+
+    /// Starts a file at `path` for a game just dealt from `seed`.
+    /// A file that's already there is left alone.
+    pub fn create(path: &str, seed: u64, state: SetupState) -> Result<Correspondence, String> {
+        let table = Table::Setup(Box::new(state));
+        let start = format!("seed {seed}\n{}", save::table_to_text(&table, None));
+        fs::OpenOptions::new().write(true).create_new(true).open(path)
+            .and_then(|mut file| writeln!(file, "{start}{MOVES_MARKER}"))
+            .map_err(|err| format!("{path}: {err}"))?;
+        Ok(Correspondence::new(path, table, chain(0, &start)))
+    }
+
+    /// Deals the game the file at `path` starts with and makes its moves
+    /// again, checking each link of the chain on the way
+    pub fn open(path: &str) -> Result<Correspondence, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        let err = |line_num: usize, msg: String| format!("{path}: line {}: {msg}", line_num + 1);
+        let mut lines = text.lines().enumerate();
+
+        let mut start = String::new();
+        // Kept with a blank line for the seed, so errors still give the file's line numbers
+        let mut dealt = String::new();
+        let mut seed: Option<u64> = None;
+        let mut found_moves = false;
+        for (line_num, line) in lines.by_ref() {
+            if line.trim() == MOVES_MARKER {
+                found_moves = true;
+                break;
+            }
+            start += line;
+            start += "\n";
+            match line.trim().strip_prefix("seed ") {
+                Some(arg) if seed.is_none() => {
+                    seed = Some(arg.trim().parse().map_err(|_| err(line_num, format!("bad seed '{}'", arg.trim())))?);
+                },
+                _ => dealt += line
+            }
+            dealt += "\n";
+        }
+        if seed.is_none() {
+            return Err(format!("{path}: no seed"));
+        }
+        if !found_moves {
+            return Err(format!("{path}: no '{MOVES_MARKER}' line"));
+        }
+        let table = save::parse_table(&dealt).map_err(|err| format!("{path}: {err}"))?;
+        let mut game = Correspondence::new(path, table, chain(0, &start));
+
+        for (line_num, line) in lines {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let (keyword, rest) = trimmed.split_once(' ').unwrap_or((trimmed, ""));
+            if keyword != "move" {
+                return Err(err(line_num, format!("expected a move, not '{keyword}'")));
+            }
+            let (link, rest) = rest.trim_start().split_once(' ').unwrap_or((rest, ""));
+            let (color, text) = rest.trim_start().split_once(' ').unwrap_or((rest, ""));
+            let link = u64::from_str_radix(link, 16).map_err(|_| err(line_num, format!("bad link '{link}'")))?;
+            let color = save::parse_color(Some(&color)).map_err(|msg| err(line_num, msg))?;
+            let mv = parse_move(&game.table, text).map_err(|msg| err(line_num, msg))?;
+            game.check_move(color, &mv).map_err(|msg| err(line_num, msg))?;
+            if link != game.link(color, &mv) {
+                return Err(err(line_num, String::from("the chain breaks here, so the file has been changed")));
+            }
+            game.apply_move(&mv, link);
+        }
+        Ok(game)
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_table(&self) -> &Table {
+        &self.table
+    }
+
+    /// The hash of the whole file so far. Players can pass it on along
+    /// with the file to show nothing went missing on the way.
+    pub fn get_head(&self) -> u64 {
+        self.head
+    }
+
+    /// The game as `seat` may see it, to click about in. It's rebuilt
+    /// from the file after every move, so nothing done on screen but a
+    /// move makes it back into the game.
+    pub fn snapshot(&self, seat: PlayerColor) -> Table {
+        save::parse_table(&save::table_to_text(&self.table, Some(seat)))
+            .expect("Correspondence::snapshot(): the game didn't read back")
+    }
+
+    fn link(&self, color: PlayerColor, mv: &Move) -> u64 {
+        chain(self.head, &format!("{:?} {}", color, to_notation(&self.table, mv)))
+    }
+
+    fn check_move(&self, color: PlayerColor, mv: &Move) -> Result<(), String> {
+        if self.table.is_over() {
+            return Err(String::from("the game is over"));
+        }
+        if self.table.get_current_color() != color {
+            return Err(format!("it's {:?}'s move", self.table.get_current_color()));
+        }
+        if !self.table.can_make_move(mv) {
+            return Err(format!("'{}' can't be played now", to_notation(&self.table, mv)));
+        }
+        Ok(())
+    }
+
+    fn apply_move(&mut self, mv: &Move, link: u64) {
+        let mut rng = StdRng::seed_from_u64(link);
+        self.table.play(Some(mv), &mut rng);
+        self.head = link;
+    }
+
+    /// Makes `mv` for the seat acting now, once it's been added to the file
+    pub fn play(&mut self, mv: &Move) -> Result<(), String> {
+        let color = self.table.get_current_color();
+        self.check_move(color, mv)?;
+        let link = self.link(color, mv);
+        fs::OpenOptions::new().append(true).open(&self.path)
+            .and_then(|mut file| writeln!(file, "move {link:016x} {:?} {}", color, to_notation(&self.table, mv)))
+            .map_err(|err| format!("{}: {err}", self.path))?;
+        self.apply_move(mv, link);
+        Ok(())
+    }
+
+    /// Lets the bot acting now make its move
+    pub fn play_bot(&mut self) -> Result<(), String> {
+        let mv = self.table.get_bot_move(&mut rand::rng());
+        self.play(&mv)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+    use crate::new_game::{Difficulty, NewGame};
+
+    /// A file the CPUs have played `num_moves` moves into, and its text
+    fn played_file(name: &str, num_moves: usize) -> (Correspondence, String) {
+        let path = std::env::temp_dir().join(format!("catan-{}-{name}.txt", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = fs::remove_file(&path);
+        let new_game = NewGame::new(47);
+        let mut state = SetupState::from_new_game(&new_game, &mut StdRng::seed_from_u64(new_game.seed)).unwrap();
+        state.bots.fill(Some(Bot::new(Difficulty::Normal)));
+        let mut game = Correspondence::create(&path, new_game.seed, state).unwrap();
+        for _ in 0..num_moves {
+            game.play_bot().unwrap();
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        (game, text)
+    }
+
+    /// Opens the file at `path` after it's been changed by hand to `lines`
+    fn open_changed(path: &str, lines: Vec<String>) -> Result<Correspondence, String> {
+        fs::write(path, lines.join("\n") + "\n").unwrap();
+        Correspondence::open(path)
+    }
+
+    fn move_line_nums(text: &str) -> Vec<usize> {
+        text.lines().enumerate().filter(|(_, line)| line.starts_with("move ")).map(|(line_num, _)| line_num).collect()
+    }
+
+    #[test]
+    fn files_open_to_the_game_that_was_played() {
+        let (played, text) = played_file("replay", 30);
+        let game = Correspondence::open(played.get_path()).unwrap();
+        assert_eq!(move_line_nums(&text).len(), 30);
+        assert_eq!(game.get_head(), played.get_head());
+        // The test sat a CPU in the human seat, which doesn't read back
+        let game_text = |game: &Correspondence| save::table_to_text(game.get_table(), None)
+            .lines().filter(|line| !line.starts_with("bot ")).collect::<Vec<_>>().join("\n");
+        assert_eq!(game_text(&game), game_text(&played));
+        fs::remove_file(played.get_path()).unwrap();
+    }
+
+    #[test]
+    fn changed_lines_break_the_chain() {
+        let (played, text) = played_file("tampered", 30);
+        let path = played.get_path();
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let line_num = move_line_nums(&text)[20];
+
+        // A link made up by hand
+        let mut changed = lines.clone();
+        let link = changed[line_num].split_whitespace().nth(1).unwrap().to_string();
+        let forged = format!("{:016x}", u64::from_str_radix(&link, 16).unwrap() ^ 1);
+        changed[line_num] = changed[line_num].replacen(&link, &forged, 1);
+        let err = open_changed(path, changed).err().unwrap();
+        assert!(err.contains(&format!("line {}:", line_num + 1)), "{err}");
+
+        // The dealt game, which every link hangs on
+        let mut changed = lines.clone();
+        let dealt = changed.iter().position(|line| line.starts_with("seed ")).unwrap();
+        changed[dealt] = String::from("seed 48");
+        assert!(open_changed(path, changed).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reordered_lines_break_the_chain() {
+        let (played, text) = played_file("reordered", 30);
+        let path = played.get_path();
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+        let line_nums = move_line_nums(&text);
+        lines.swap(line_nums[24], line_nums[25]);
+        let err = open_changed(path, lines).err().unwrap();
+        assert!(err.contains(&format!("line {}:", line_nums[24] + 1)), "{err}");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn moves_credited_to_the_wrong_seat_are_refused() {
+        let (played, text) = played_file("wrong-seat", 30);
+        let path = played.get_path();
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+        let line_num = move_line_nums(&text)[12];
+        let color = lines[line_num].split_whitespace().nth(2).unwrap().to_string();
+        let other = if color == "Red" {"Blue"} else {"Red"};
+        lines[line_num] = lines[line_num].replacen(&format!(" {color} "), &format!(" {other} "), 1);
+        let err = open_changed(path, lines).err().unwrap();
+        assert!(err.contains(&format!("line {}:", line_num + 1)), "{err}");
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use rand::{seq::SliceRandom, Rng, RngCore};
+
+/// Where production rolls come from. Games are handed between threads
+/// online, so their dice are too.
+pub trait DiceSource: Send {
+    fn roll(&mut self, rng: &mut dyn RngCore) -> [usize; 2];
+
+    /// Hands over a roll made somewhere else. Only sources that take
+    /// their rolls from outside have any use for it.
+    fn enter(&mut self, _roll: [usize; 2]) {}
+
+    /// The source as the players see it, for a copy of the game that
+    /// never rolls. A deck keeps which cards are left but not their order.
+    fn face_down(&self) -> Box<dyn DiceSource>;
+
+    /// What's left to draw, for a source that deals its rolls from a deck
+    fn cards_left(&self) -> Option<&[[usize; 2]]> {
+        None
+    }
+}
+
+/// Two independent six-sided dice
+pub struct RandomDice;
+
+impl DiceSource for RandomDice {
+    fn roll(&mut self, rng: &mut dyn RngCore) -> [usize; 2] {
+        [rng.random_range(1..=6), rng.random_range(1..=6)]
+    }
+
+    fn face_down(&self) -> Box<dyn DiceSource> {
+        Box::new(RandomDice)
+    }
+}
+
+pub const EVENT_DECK_RESHUFFLE: usize = 5;
+
+/// One card for every combination of two dice.
+/// The deck is reshuffled once only `EVENT_DECK_RESHUFFLE` cards are left,
+/// so the last few rolls can't be counted out.
+#[derive(Default)]
+pub struct EventDeck {
+    cards: Vec<[usize; 2]>,
+}
+
+impl EventDeck {
+    /// A deck part of the way through, drawn from the end
+    pub fn from_cards(cards: Vec<[usize; 2]>) -> EventDeck {
+        EventDeck { cards }
+    }
+
+    fn reshuffle(&mut self, rng: &mut dyn RngCore) {
+        self.cards = (1..=6)
+            .flat_map(|d1| (1..=6).map(move |d2| [d1, d2]))
+            .collect();
+        self.cards.shuffle(rng);
+    }
+}
+
+impl DiceSource for EventDeck {
+    fn roll(&mut self, rng: &mut dyn RngCore) -> [usize; 2] {
+        if self.cards.len() <= EVENT_DECK_RESHUFFLE {
+            self.reshuffle(rng);
+        }
+        self.cards.pop().unwrap()
+    }
+
+    fn face_down(&self) -> Box<dyn DiceSource> {
+        let mut cards = self.cards.clone();
+        cards.sort();
+        Box::new(EventDeck { cards })
+    }
+
+    fn cards_left(&self) -> Option<&[[usize; 2]]> {
+        Some(&self.cards)
+    }
+}
+
+/// Dice thrown on a real table and typed in
+#[derive(Default)]
+pub struct ManualDice {
+    entered: Option<[usize; 2]>,
+}
+
+impl DiceSource for ManualDice {
+    fn roll(&mut self, _rng: &mut dyn RngCore) -> [usize; 2] {
+        self.entered.take().expect("ManualDice::roll(): no roll was entered")
+    }
+
+    fn enter(&mut self, roll: [usize; 2]) {
+        self.entered = Some(roll);
+    }
+
+    fn face_down(&self) -> Box<dyn DiceSource> {
+        Box::new(ManualDice { entered: self.entered })
+    }
+}
+
+pub fn new_dice_source(event_deck: bool) -> Box<dyn DiceSource> {
+    if event_deck {
+        Box::new(EventDeck::default())
+    } else {
+        Box::new(RandomDice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn the_event_deck_deals_every_combination_before_reshuffling() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut deck = EventDeck::default();
+        let first = deck.roll(&mut rng);
+        let mut drawn = vec![first];
+        drawn.extend(deck.cards_left().unwrap());
+        drawn.sort();
+        let all: Vec<[usize; 2]> = (1..=6).flat_map(|d1| (1..=6).map(move |d2| [d1, d2])).collect();
+        assert_eq!(drawn, all);
+
+        // Reshuffled once only the last few are left
+        for _ in 0..36 - 1 - EVENT_DECK_RESHUFFLE {
+            deck.roll(&mut rng);
+        }
+        assert_eq!(deck.cards_left().unwrap().len(), EVENT_DECK_RESHUFFLE);
+        deck.roll(&mut rng);
+        assert_eq!(deck.cards_left().unwrap().len(), 36 - 1);
+    }
+
+    #[test]
+    fn a_face_down_deck_keeps_the_cards_but_not_their_order() {
+        let cards = vec![[6, 6], [1, 2], [4, 5], [2, 2], [5, 1], [3, 6], [3, 3]];
+        let mut deck = EventDeck::from_cards(cards.clone());
+        let mut sorted = cards;
+        sorted.sort();
+        assert_eq!(deck.face_down().cards_left().unwrap(), sorted.as_slice());
+        assert_eq!(deck.roll(&mut StdRng::seed_from_u64(0)), [3, 3]);
+    }
+}
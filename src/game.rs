@@ -1,35 +1,48 @@
 use std::{collections::HashSet, ops::{Index, IndexMut}};
 use rand::{seq::{IndexedRandom, SliceRandom}, Rng};
 
-//// Typedefs
+// Typedefs
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PlayerColor {
     Red=0,
     Blue=1,
     Orange=2,
-    White=3
+    White=3,
+    Green=4,
+    Brown=5
 }
 
-pub const PLAYER_COLORS: [PlayerColor; 4] = [
+pub const PLAYER_COLORS: [PlayerColor; 6] = [
     PlayerColor::Red,
     PlayerColor::Blue,
     PlayerColor::Orange,
     PlayerColor::White,
+    PlayerColor::Green,
+    PlayerColor::Brown,
 ];
 
+impl PlayerColor {
+    /// Reads a color back from its name, in any case
+    pub fn from_name(name: &str) -> Option<PlayerColor> {
+        PLAYER_COLORS.iter().copied().find(|color| format!("{:?}", color).eq_ignore_ascii_case(name))
+    }
+}
+
 impl From<usize> for PlayerColor {
     fn from(value: usize) -> Self {
-        PLAYER_COLORS[value % 4]
+        PLAYER_COLORS[value % PLAYER_COLORS.len()]
     }
 }
 
-impl Into<macroquad::color::Color> for PlayerColor {
-    fn into(self) -> macroquad::color::Color {
-        match self {
+impl From<PlayerColor> for macroquad::color::Color {
+    fn from(value: PlayerColor) -> Self {
+        match value {
             PlayerColor::Red => macroquad::color::RED,
             PlayerColor::Blue => macroquad::color::BLUE,
             PlayerColor::Orange => macroquad::color::ORANGE,
-            PlayerColor::White => macroquad::color::WHITE
+            PlayerColor::White => macroquad::color::WHITE,
+            PlayerColor::Green => macroquad::color::LIME,
+            PlayerColor::Brown => macroquad::color::BROWN
         }
     }
 }
@@ -51,9 +64,9 @@ pub const RESOURCES: [Resource; 5] = [
     Resource::Ore
 ];
 
-impl Into<macroquad::color::Color> for Resource {
-    fn into(self) -> macroquad::color::Color {
-        match self {
+impl From<Resource> for macroquad::color::Color {
+    fn from(value: Resource) -> Self {
+        match value {
             Resource::Wood => macroquad::color::DARKGREEN,
             Resource::Brick => macroquad::color::RED,
             Resource::Wheat => macroquad::color::GOLD,
@@ -92,16 +105,24 @@ impl DVCard {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct ResHand([usize; 5]);
 
 pub const ROAD_HAND: ResHand = ResHand([1, 1, 0, 0, 0]);
 pub const SETTLEMENT_HAND: ResHand = ResHand([1, 1, 1, 1, 0]);
 pub const CITY_HAND: ResHand = ResHand([0, 0, 2, 0, 3]);
 pub const DV_CARD_HAND: ResHand = ResHand([0, 0, 1, 1, 1]);
-
-const STARTING_BANK_HAND: ResHand = ResHand([19, 19, 19, 19, 19]);
-const STARTING_DV_BANK_HAND: DVHand = DVHand([14, 2, 2, 2, 5]);
+pub const SHIP_HAND: ResHand = ResHand([1, 0, 0, 1, 0]);
+pub const KNIGHT_HAND: ResHand = ResHand([0, 0, 0, 1, 1]);
+pub const WALL_HAND: ResHand = ResHand([0, 2, 0, 0, 0]);
+/// What a city costs with the Medicine progress card
+pub const MEDICINE_HAND: ResHand = ResHand([0, 0, 1, 0, 2]);
+pub const ACTIVATE_HAND: ResHand = ResHand([0, 0, 1, 0, 0]);
+
+pub const STARTING_BANK_HAND: ResHand = ResHand([19, 19, 19, 19, 19]);
+pub const STARTING_DV_BANK_HAND: DVHand = DVHand([14, 2, 2, 2, 5]);
+const EXTENSION_BANK_HAND: ResHand = ResHand([24, 24, 24, 24, 24]);
+pub const EXTENSION_DV_BANK_HAND: DVHand = DVHand([20, 3, 3, 3, 5]);
 // const STARTING_DV_BANK: [DVCard; 25] = [
 //     DVCard::Knight, DVCard::Knight, DVCard::Knight, DVCard::Knight, DVCard::Knight,
 //     DVCard::Knight, DVCard::Knight, DVCard::Knight, DVCard::Knight, DVCard::Knight,
@@ -115,6 +136,11 @@ impl ResHand {
         ResHand([0; 5])
     }
 
+    /// A hand with `count` of every resource
+    pub fn from_count(count: usize) -> ResHand {
+        ResHand([count; 5])
+    }
+
     pub fn from_monopoly(resource: Resource, count: usize) -> ResHand {
         let mut  hand = ResHand::new();
         hand[resource] = count;
@@ -319,6 +345,318 @@ impl From<DVCard> for DVHand {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Commodity {
+    Paper,
+    Cloth,
+    Coin,
+}
+
+pub const COMMODITIES: [Commodity; 3] = [
+    Commodity::Paper,
+    Commodity::Cloth,
+    Commodity::Coin
+];
+
+impl From<Commodity> for macroquad::color::Color {
+    fn from(value: Commodity) -> Self {
+        match value {
+            Commodity::Paper => macroquad::color::LIGHTGRAY,
+            Commodity::Cloth => macroquad::color::PINK,
+            Commodity::Coin => macroquad::color::YELLOW
+        }
+    }
+}
+
+impl Commodity {
+    /// The commodity a city makes instead of its second resource, if any
+    pub fn from_resource(resource: Resource) -> Option<Commodity> {
+        match resource {
+            Resource::Wood => Some(Commodity::Paper),
+            Resource::Sheep => Some(Commodity::Cloth),
+            Resource::Ore => Some(Commodity::Coin),
+            _ => None
+        }
+    }
+
+    pub fn into_label(self) -> String {
+        String::from(match self {
+            Commodity::Paper => "PA",
+            Commodity::Cloth => "CL",
+            Commodity::Coin => "CO"
+        })
+    }
+
+    pub fn from_label(label: &str) -> Option<Commodity> {
+        COMMODITIES.iter().copied().find(|com| com.into_label() == label)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ComHand([usize; 3]);
+
+impl ComHand {
+    pub fn new() -> ComHand {
+        ComHand([0; 3])
+    }
+
+    pub fn size(&self) -> usize {
+        self.0.iter().sum()
+    }
+
+    pub fn count_nonzero(&self) -> usize {
+        self.0.iter().filter(|&com| *com != 0).count()
+    }
+
+    pub fn add(&mut self, rhs: ComHand) {
+        for com in COMMODITIES {
+            self[com] += rhs[com];
+        }
+    }
+
+    pub fn add_card(&mut self, card: Commodity) {
+        self[card] += 1;
+    }
+}
+
+impl Index<Commodity> for ComHand {
+    type Output = usize;
+    fn index(&self, index: Commodity) -> &Self::Output {
+        match index {
+            Commodity::Paper => &self.0[0],
+            Commodity::Cloth => &self.0[1],
+            Commodity::Coin => &self.0[2],
+        }
+    }
+}
+
+impl IndexMut<Commodity> for ComHand {
+    fn index_mut(&mut self, index: Commodity) -> &mut Self::Output {
+        match index {
+            Commodity::Paper => &mut self.0[0],
+            Commodity::Cloth => &mut self.0[1],
+            Commodity::Coin => &mut self.0[2],
+        }
+    }
+}
+
+/// The three city improvement tracks of Cities & Knights
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Improvement {
+    Trade,
+    Politics,
+    Science,
+}
+
+pub const IMPROVEMENTS: [Improvement; 3] = [
+    Improvement::Trade,
+    Improvement::Politics,
+    Improvement::Science
+];
+
+impl From<Improvement> for macroquad::color::Color {
+    fn from(value: Improvement) -> Self {
+        match value {
+            Improvement::Trade => macroquad::color::YELLOW,
+            Improvement::Politics => macroquad::color::SKYBLUE,
+            Improvement::Science => macroquad::color::GREEN
+        }
+    }
+}
+
+impl Improvement {
+    pub fn commodity(self) -> Commodity {
+        match self {
+            Improvement::Trade => Commodity::Cloth,
+            Improvement::Politics => Commodity::Coin,
+            Improvement::Science => Commodity::Paper
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+pub const MAX_IMPROVEMENT: usize = 5;
+pub const METROPOLIS_LEVEL: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventDie {
+    Barbarians,
+    Gate(Improvement),
+}
+
+/// Half of the event die's faces move the barbarian ship
+pub fn roll_event_die<R: Rng + ?Sized>(rng: &mut R) -> EventDie {
+    match rng.random_range(0..6) {
+        0..=2 => EventDie::Barbarians,
+        3 => EventDie::Gate(Improvement::Trade),
+        4 => EventDie::Gate(Improvement::Politics),
+        _ => EventDie::Gate(Improvement::Science)
+    }
+}
+
+pub const BARBARIAN_TRACK_LEN: usize = 7;
+
+/// Progress cards replace development cards in Cities & Knights
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressCard {
+    // Science
+    Alchemist,
+    Crane,
+    Engineer,
+    Inventor,
+    Irrigation,
+    Medicine,
+    Mining,
+    Printer,
+    RoadBuilding,
+    Smith,
+    // Trade
+    CommercialHarbor,
+    MasterMerchant,
+    Merchant,
+    MerchantFleet,
+    ResourceMonopoly,
+    TradeMonopoly,
+    // Politics
+    Bishop,
+    Constitution,
+    Deserter,
+    Diplomat,
+    Intrigue,
+    Saboteur,
+    Spy,
+    Warlord,
+    Wedding,
+}
+
+pub const PROGRESS_CARDS: [ProgressCard; 25] = [
+    ProgressCard::Alchemist,
+    ProgressCard::Crane,
+    ProgressCard::Engineer,
+    ProgressCard::Inventor,
+    ProgressCard::Irrigation,
+    ProgressCard::Medicine,
+    ProgressCard::Mining,
+    ProgressCard::Printer,
+    ProgressCard::RoadBuilding,
+    ProgressCard::Smith,
+    ProgressCard::CommercialHarbor,
+    ProgressCard::MasterMerchant,
+    ProgressCard::Merchant,
+    ProgressCard::MerchantFleet,
+    ProgressCard::ResourceMonopoly,
+    ProgressCard::TradeMonopoly,
+    ProgressCard::Bishop,
+    ProgressCard::Constitution,
+    ProgressCard::Deserter,
+    ProgressCard::Diplomat,
+    ProgressCard::Intrigue,
+    ProgressCard::Saboteur,
+    ProgressCard::Spy,
+    ProgressCard::Warlord,
+    ProgressCard::Wedding
+];
+
+pub const MAX_PROGRESS_CARDS: usize = 4;
+pub const MAX_WALLS: usize = 3;
+
+impl ProgressCard {
+    pub fn track(self) -> Improvement {
+        match self {
+            ProgressCard::Alchemist | ProgressCard::Crane | ProgressCard::Engineer
+            | ProgressCard::Inventor | ProgressCard::Irrigation | ProgressCard::Medicine
+            | ProgressCard::Mining | ProgressCard::Printer | ProgressCard::RoadBuilding
+            | ProgressCard::Smith => Improvement::Science,
+            ProgressCard::CommercialHarbor | ProgressCard::MasterMerchant | ProgressCard::Merchant
+            | ProgressCard::MerchantFleet | ProgressCard::ResourceMonopoly
+            | ProgressCard::TradeMonopoly => Improvement::Trade,
+            ProgressCard::Bishop | ProgressCard::Constitution | ProgressCard::Deserter
+            | ProgressCard::Diplomat | ProgressCard::Intrigue | ProgressCard::Saboteur
+            | ProgressCard::Spy | ProgressCard::Warlord | ProgressCard::Wedding => Improvement::Politics,
+        }
+    }
+
+    /// VP cards are revealed as soon as they're drawn
+    pub fn is_vp(self) -> bool {
+        matches!(self, ProgressCard::Printer | ProgressCard::Constitution)
+    }
+
+    /// Cards played on something their player picks, each with a move of its own
+    pub fn needs_choice(self) -> bool {
+        matches!(self,
+            ProgressCard::Alchemist | ProgressCard::Crane | ProgressCard::Engineer
+            | ProgressCard::Inventor | ProgressCard::Medicine | ProgressCard::MasterMerchant
+            | ProgressCard::Merchant | ProgressCard::MerchantFleet | ProgressCard::ResourceMonopoly
+            | ProgressCard::TradeMonopoly | ProgressCard::Bishop | ProgressCard::Deserter
+            | ProgressCard::Diplomat | ProgressCard::Intrigue | ProgressCard::Spy
+        )
+    }
+
+    pub fn into_label(self) -> String {
+        String::from(match self {
+            ProgressCard::Alchemist => "AL",
+            ProgressCard::Crane => "CR",
+            ProgressCard::Engineer => "EN",
+            ProgressCard::Inventor => "IN",
+            ProgressCard::Irrigation => "IR",
+            ProgressCard::Medicine => "ME",
+            ProgressCard::Mining => "MI",
+            ProgressCard::Printer => "PR",
+            ProgressCard::RoadBuilding => "RB",
+            ProgressCard::Smith => "SM",
+            ProgressCard::CommercialHarbor => "CH",
+            ProgressCard::MasterMerchant => "MM",
+            ProgressCard::Merchant => "MC",
+            ProgressCard::MerchantFleet => "MF",
+            ProgressCard::ResourceMonopoly => "RM",
+            ProgressCard::TradeMonopoly => "TM",
+            ProgressCard::Bishop => "BI",
+            ProgressCard::Constitution => "CO",
+            ProgressCard::Deserter => "DE",
+            ProgressCard::Diplomat => "DI",
+            ProgressCard::Intrigue => "IT",
+            ProgressCard::Saboteur => "SA",
+            ProgressCard::Spy => "SP",
+            ProgressCard::Warlord => "WL",
+            ProgressCard::Wedding => "WE"
+        })
+    }
+
+    pub fn from_label(label: &str) -> Option<ProgressCard> {
+        PROGRESS_CARDS.iter().copied().find(|card| card.into_label() == label)
+    }
+}
+
+/// A shuffled draw pile of one track's progress cards
+pub fn progress_deck<R: Rng + ?Sized>(track: Improvement, rng: &mut R) -> Vec<ProgressCard> {
+    let counts: &[(ProgressCard, usize)] = match track {
+        Improvement::Science => &[
+            (ProgressCard::Alchemist, 2), (ProgressCard::Crane, 2), (ProgressCard::Engineer, 1),
+            (ProgressCard::Inventor, 2), (ProgressCard::Irrigation, 2), (ProgressCard::Medicine, 2),
+            (ProgressCard::Mining, 2), (ProgressCard::Printer, 1), (ProgressCard::RoadBuilding, 2),
+            (ProgressCard::Smith, 2)
+        ],
+        Improvement::Trade => &[
+            (ProgressCard::CommercialHarbor, 2), (ProgressCard::MasterMerchant, 2),
+            (ProgressCard::Merchant, 6), (ProgressCard::MerchantFleet, 2),
+            (ProgressCard::ResourceMonopoly, 4), (ProgressCard::TradeMonopoly, 2)
+        ],
+        Improvement::Politics => &[
+            (ProgressCard::Bishop, 2), (ProgressCard::Constitution, 1), (ProgressCard::Deserter, 2),
+            (ProgressCard::Diplomat, 2), (ProgressCard::Intrigue, 2), (ProgressCard::Saboteur, 2),
+            (ProgressCard::Spy, 3), (ProgressCard::Warlord, 2), (ProgressCard::Wedding, 2)
+        ],
+    };
+    let mut deck: Vec<ProgressCard> = counts.iter()
+        .flat_map(|&(card, count)| std::iter::repeat_n(card, count))
+        .collect();
+    deck.shuffle(rng);
+    deck
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum StructureType {
     Settlement,
@@ -331,30 +669,98 @@ pub struct Structure {
     pub color: PlayerColor
 }
 
+/// A Cities & Knights knight. Levels go from 1 (basic) to 3 (mighty),
+/// and only active knights defend against the barbarians.
+#[derive(Debug, Clone, Copy)]
+pub struct Knight {
+    pub color: PlayerColor,
+    pub level: usize,
+    pub active: bool
+}
+
+pub const MAX_KNIGHT_LEVEL: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Terrain {
+    Resource(Resource),
+    Gold,
+    Desert,
+    Sea
+}
+
+/// A hex on the board. `number` is 0 for hexes without a number token.
 #[derive(Debug, Clone, Copy)]
 pub struct Hex {
-    pub resource: Resource,
+    pub terrain: Terrain,
     pub number: usize
 }
 
+impl Hex {
+    pub fn desert() -> Hex {
+        Hex { terrain: Terrain::Desert, number: 0 }
+    }
+
+    pub fn resource(&self) -> Option<Resource> {
+        match self.terrain {
+            Terrain::Resource(res) => Some(res),
+            _ => None
+        }
+    }
+
+    pub fn is_sea(&self) -> bool {
+        self.terrain == Terrain::Sea
+    }
+}
+
+/// Which expansion's rules the game is played with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ruleset {
+    Base,
+    Seafarers,
+    CitiesKnights
+}
+
+pub const RULESETS: [Ruleset; 3] = [
+    Ruleset::Base,
+    Ruleset::Seafarers,
+    Ruleset::CitiesKnights
+];
+
+impl Ruleset {
+    pub fn from_name(name: &str) -> Option<Ruleset> {
+        RULESETS.iter().copied().find(|ruleset| format!("{:?}", ruleset) == name)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Port {
     ThreeForOne,
     TwoForOne(Resource)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Board {
-    pub hexes: [[Option<Hex>; 5]; 5],
-    pub ports: [Port; 9],
-    pub structures: [[[Option<Structure>; 6]; 5]; 5],
-    pub roads: [[[Option<PlayerColor>; 6]; 5]; 5],
+    pub layout: Layout,
+    pub hexes: Vec<Vec<Option<Hex>>>,
+    pub ports: Vec<Port>,
+    pub structures: Vec<Vec<[Option<Structure>; 6]>>,
+    pub roads: Vec<Vec<[Option<PlayerColor>; 6]>>,
+    pub ships: Vec<Vec<[Option<PlayerColor>; 6]>>,
+    pub knights: Vec<Vec<[Option<Knight>; 6]>>,
+    /// City walls, on the corners of their cities
+    pub walls: Vec<Vec<[bool; 6]>>,
     pub robber: [usize; 2],
+    pub pirate: Option<[usize; 2]>,
+    /// The hex the merchant stands on and who placed it there
+    pub merchant: Option<([usize; 2], PlayerColor)>,
     pub bank: ResHand,
     pub dv_bank: DVHand,
+    /// Set on a board seen face down: `dv_bank` is empty and only
+    /// how many cards are left in it is known
+    pub hidden_dvs: Option<usize>,
 }
 
-pub const HEX_COORDS: [[usize; 2]; 19] = [
+pub const BASE_HEX_COORDS: [[usize; 2]; 19] = [
     [0, 2], [0, 3], [0, 4],
     [1, 1], [1, 2], [1, 3], [1, 4],
     [2, 0], [2, 1], [2, 2], [2, 3], [2, 4],
@@ -362,68 +768,39 @@ pub const HEX_COORDS: [[usize; 2]; 19] = [
     [4, 0], [4, 1], [4, 2]
 ];
 
-pub const PORT_COORDS: [[usize; 3]; 9] = [
+pub const BASE_PORT_COORDS: [[usize; 3]; 9] = [
     [0, 3, 0], [0, 4, 1], [1, 4, 2],
     [3, 3, 2], [4, 2, 3], [4, 1, 4],
     [3, 0, 4], [2, 0, 5], [1, 1, 0]
 ];
 
-const fn corner_coords() -> [[usize; 3]; 54] {
-    let mut corners = [[0; 3]; 54];
-    let mut idx = 0;
-
-    let mut hex = 0;
-    while hex < HEX_COORDS.len() {
-        let [r, q] = HEX_COORDS[hex];
-        let mut c = 0;
-        while c < 6 {
-            let [r_, q_, c_] = reduce_corner([r, q, c]);
-            if r == r_ && q == q_ && c == c_ {
-                corners[idx] = [r, q, c];
-                idx += 1;
-            }
-            c += 1;
-        } 
-        hex += 1;
-    }
-    corners
-}
-
-const fn edge_coords() -> [[usize; 3]; 72] {
-    let mut edges = [[0; 3]; 72];
-    let mut idx = 0;
-
-    let mut hex = 0;
-    while hex < HEX_COORDS.len() {
-        let [r, q] = HEX_COORDS[hex];
-        let mut e = 0;
-        while e < 6 {
-            let [r_, q_, e_] = reduce_edge([r, q, e]);
-            if r == r_ && q == q_ && e == e_ {
-                edges[idx] = [r, q, e];
-                idx += 1;
-            }
-            e += 1;
-        } 
-        hex += 1;
-    }
-    edges
-}
+pub const EXTENSION_HEX_COORDS: [[usize; 2]; 30] = [
+    [0, 3], [0, 4], [0, 5],
+    [1, 2], [1, 3], [1, 4], [1, 5],
+    [2, 1], [2, 2], [2, 3], [2, 4], [2, 5],
+    [3, 0], [3, 1], [3, 2], [3, 3], [3, 4], [3, 5],
+    [4, 0], [4, 1], [4, 2], [4, 3], [4, 4],
+    [5, 0], [5, 1], [5, 2], [5, 3],
+    [6, 0], [6, 1], [6, 2]
+];
 
-pub const CORNER_COORDS: [[usize; 3]; 54] = corner_coords();
-pub const EDGE_COORDS: [[usize; 3]; 72] = edge_coords();
+pub const EXTENSION_PORT_COORDS: [[usize; 3]; 11] = [
+    [0, 3, 0], [0, 4, 1], [1, 5, 1], [2, 5, 2],
+    [3, 5, 3], [5, 3, 3], [6, 1, 3], [6, 0, 4],
+    [4, 0, 4], [2, 1, 5], [1, 2, 0]
+];
 
-const BOARD_RESOURCES: [Resource; 18] = [
+const BASE_RESOURCES: [Resource; 18] = [
     Resource::Wood, Resource::Wood, Resource::Wood, Resource::Wood,
     Resource::Brick, Resource::Brick, Resource::Brick,
     Resource::Wheat, Resource::Wheat, Resource::Wheat, Resource::Wheat,
     Resource::Sheep, Resource::Sheep, Resource::Sheep, Resource::Sheep,
     Resource::Ore, Resource::Ore, Resource::Ore,
 ];
-const BOARD_NUMBERS: [usize; 18] = [
+const BASE_NUMBERS: [usize; 18] = [
     2, 3, 3, 4, 4, 5, 5, 6, 6, 8, 8, 9, 9, 10, 10, 11, 11, 12
 ];
-const BOARD_PORTS: [Port; 9] = [
+const BASE_PORTS: [Port; 9] = [
     Port::ThreeForOne, Port::ThreeForOne, Port::ThreeForOne, Port::ThreeForOne,
     Port::TwoForOne(Resource::Wood),
     Port::TwoForOne(Resource::Brick),
@@ -432,65 +809,224 @@ const BOARD_PORTS: [Port; 9] = [
     Port::TwoForOne(Resource::Ore)
 ];
 
+const EXTENSION_RESOURCES: [Resource; 28] = [
+    Resource::Wood, Resource::Wood, Resource::Wood, Resource::Wood, Resource::Wood, Resource::Wood,
+    Resource::Brick, Resource::Brick, Resource::Brick, Resource::Brick, Resource::Brick,
+    Resource::Wheat, Resource::Wheat, Resource::Wheat, Resource::Wheat, Resource::Wheat, Resource::Wheat,
+    Resource::Sheep, Resource::Sheep, Resource::Sheep, Resource::Sheep, Resource::Sheep, Resource::Sheep,
+    Resource::Ore, Resource::Ore, Resource::Ore, Resource::Ore, Resource::Ore,
+];
+const EXTENSION_NUMBERS: [usize; 28] = [
+    2, 2, 3, 3, 3, 4, 4, 4, 5, 5, 5, 6, 6, 6,
+    8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12
+];
+const EXTENSION_PORTS: [Port; 11] = [
+    Port::ThreeForOne, Port::ThreeForOne, Port::ThreeForOne, Port::ThreeForOne, Port::ThreeForOne,
+    Port::TwoForOne(Resource::Wood),
+    Port::TwoForOne(Resource::Brick),
+    Port::TwoForOne(Resource::Sheep),
+    Port::TwoForOne(Resource::Sheep),
+    Port::TwoForOne(Resource::Wheat),
+    Port::TwoForOne(Resource::Ore)
+];
+
+/// Everything needed to deal out a fresh board of one size.
+/// Any hex without a resource becomes a desert.
+struct BoardSpec {
+    hex_coords: &'static [[usize; 2]],
+    port_coords: &'static [[usize; 3]],
+    resources: &'static [Resource],
+    numbers: &'static [usize],
+    ports: &'static [Port],
+    bank: ResHand,
+    dv_bank: DVHand,
+}
+
+const BASE_BOARD: BoardSpec = BoardSpec {
+    hex_coords: &BASE_HEX_COORDS,
+    port_coords: &BASE_PORT_COORDS,
+    resources: &BASE_RESOURCES,
+    numbers: &BASE_NUMBERS,
+    ports: &BASE_PORTS,
+    bank: STARTING_BANK_HAND,
+    dv_bank: STARTING_DV_BANK_HAND,
+};
+
+const EXTENSION_BOARD: BoardSpec = BoardSpec {
+    hex_coords: &EXTENSION_HEX_COORDS,
+    port_coords: &EXTENSION_PORT_COORDS,
+    resources: &EXTENSION_RESOURCES,
+    numbers: &EXTENSION_NUMBERS,
+    ports: &EXTENSION_PORTS,
+    bank: EXTENSION_BANK_HAND,
+    dv_bank: EXTENSION_DV_BANK_HAND,
+};
+
+/// How many of the 36 throws of two dice add up to `number`
+pub fn pips(number: usize) -> usize {
+    if (2..=12).contains(&number) { 6 - number.abs_diff(7) } else { 0 }
+}
+
+/// Chance of two dice adding up to `number`
+pub fn roll_probability(number: usize) -> f32 {
+    pips(number) as f32 / 36.0
+}
+
+/// Every distinct number token
+pub const NUMBER_TOKENS: [usize; 10] = [2, 3, 4, 5, 6, 8, 9, 10, 11, 12];
+
 impl Board {
     pub fn new<R: Rng + ?Sized>(num_players: usize, rng: &mut R) -> Self {
-        let mut hexes: [[Option<Hex>; 5]; 5] = [[None; 5]; 5];
-        let structures: [[[Option<Structure>; 6]; 5]; 5] = [[[None; 6]; 5]; 5];
-        let roads: [[[Option<PlayerColor>; 6]; 5]; 5] = [[[None; 6]; 5]; 5];
-        let robber = *HEX_COORDS.choose(rng).unwrap();
+        let spec = if num_players > 4 { &EXTENSION_BOARD } else { &BASE_BOARD };
+        Board::from_spec(spec, rng)
+    }
+
+    fn from_spec<R: Rng + ?Sized>(spec: &BoardSpec, rng: &mut R) -> Self {
+        let layout = Layout::new(spec.hex_coords.to_vec(), spec.port_coords.to_vec());
+        let [rows, cols] = layout.size();
+        let mut hexes: Vec<Vec<Option<Hex>>> = vec![vec![None; cols]; rows];
+
+        // Pick deserts; the robber starts on the first one
+        let num_deserts = spec.hex_coords.len() - spec.resources.len();
+        let deserts: Vec<[usize; 2]> = spec.hex_coords.choose_multiple(rng, num_deserts).copied().collect();
+        let robber = deserts[0];
 
         // Shuffle resources
-        let mut resources = BOARD_RESOURCES;
+        let mut resources = spec.resources.to_vec();
         resources.shuffle(rng);
         // Shuffle numbers
-        let mut numbers = BOARD_NUMBERS;
+        let mut numbers = spec.numbers.to_vec();
         numbers.shuffle(rng);
         // Shuffle ports
-        let mut ports = BOARD_PORTS;
+        let mut ports = spec.ports.to_vec();
         ports.shuffle(rng);
-        // // Shuffle DV cards
-        // let mut dv_bank = STARTING_DV_BANK;
-        // dv_bank.shuffle(rng);
 
         let mut i = 0;
-        for [r, q] in HEX_COORDS {
+        for &[r, q] in spec.hex_coords {
             // Check for desert
-            if robber != [r, q] {
-                // No sixes or eights next to each other
-                if numbers[i] == 6 || numbers[i] == 8 {
-                    for dir in [[0, -1], [-1, 0], [-1, 1]] {
-                        let test_r = (r as isize + dir[0]) as usize;
-                        let test_q = (q as isize + dir[1]) as usize;
-                        if is_on_board([test_r, test_q]) {
-                            if let Some(h) = hexes[test_r][test_q] {
-                                if h.number == 6 || h.number == 8 {
-                                    return Board::new(num_players, rng);
-                                }
+            if deserts.contains(&[r, q]) {
+                hexes[r][q] = Some(Hex::desert());
+                continue;
+            }
+
+            // No sixes or eights next to each other
+            if numbers[i] == 6 || numbers[i] == 8 {
+                for dir in [[0, -1], [-1, 0], [-1, 1]] {
+                    let test_r = (r as isize + dir[0]) as usize;
+                    let test_q = (q as isize + dir[1]) as usize;
+                    if layout.is_on_board([test_r, test_q]) {
+                        if let Some(h) = hexes[test_r][test_q] {
+                            if h.number == 6 || h.number == 8 {
+                                return Board::from_spec(spec, rng);
                             }
                         }
                     }
                 }
+            }
 
-                // Create hex
-                let hex = Hex {
-                    resource: resources[i],
-                    number: numbers[i]
-                };
-                hexes[r][q] = Some(hex);
+            // Create hex
+            let hex = Hex {
+                terrain: Terrain::Resource(resources[i]),
+                number: numbers[i]
+            };
+            hexes[r][q] = Some(hex);
 
-                i += 1;
-            }
+            i += 1;
         }
 
+        let mut board = Board::from_hexes(layout, hexes, ports, spec.bank, spec.dv_bank);
+        board.robber = robber;
+        board
+    }
+
+    /// Builds an empty board around fixed hexes, e.g. from a scenario map.
+    /// The robber starts on the first desert, and the pirate off the board.
+    pub fn from_hexes(layout: Layout, hexes: Vec<Vec<Option<Hex>>>, ports: Vec<Port>, bank: ResHand, dv_bank: DVHand) -> Self {
+        let [rows, cols] = layout.size();
+        let structures: Vec<Vec<[Option<Structure>; 6]>> = vec![vec![[None; 6]; cols]; rows];
+        let roads: Vec<Vec<[Option<PlayerColor>; 6]>> = vec![vec![[None; 6]; cols]; rows];
+        let ships: Vec<Vec<[Option<PlayerColor>; 6]>> = vec![vec![[None; 6]; cols]; rows];
+        let knights: Vec<Vec<[Option<Knight>; 6]>> = vec![vec![[None; 6]; cols]; rows];
+        let walls: Vec<Vec<[bool; 6]>> = vec![vec![[false; 6]; cols]; rows];
+        let robber = layout.hex_coords.iter().copied()
+            .find(|&[r, q]| hexes[r][q].is_some_and(|hex| hex.terrain == Terrain::Desert))
+            .unwrap_or(layout.hex_coords[0]);
+
         Board {
+            layout,
             hexes,
             ports,
             structures,
             roads,
+            ships,
+            knights,
+            walls,
             robber,
-            bank: STARTING_BANK_HAND,
-            dv_bank: STARTING_DV_BANK_HAND
+            pirate: None,
+            merchant: None,
+            bank,
+            dv_bank,
+            hidden_dvs: None
+        }
+    }
+
+    pub fn is_sea(&self, hex: [usize; 2]) -> bool {
+        let [r, q] = hex;
+        self.layout.is_on_board(hex) && self.hexes[r][q].is_some_and(|h| h.is_sea())
+    }
+
+    pub fn is_land(&self, hex: [usize; 2]) -> bool {
+        let [r, q] = hex;
+        self.layout.is_on_board(hex) && self.hexes[r][q].is_some_and(|h| !h.is_sea())
+    }
+
+    pub fn is_land_corner(&self, corner: [usize; 3]) -> bool {
+        self.layout.hexes_touched(corner).any(|hex| self.is_land(hex))
+    }
+
+    pub fn is_land_edge(&self, edge: [usize; 3]) -> bool {
+        self.layout.edge_hexes(edge).any(|hex| self.is_land(hex))
+    }
+
+    pub fn is_sea_edge(&self, edge: [usize; 3]) -> bool {
+        self.layout.edge_hexes(edge).any(|hex| self.is_sea(hex))
+    }
+
+    fn touches_pirate(&self, edge: [usize; 3]) -> bool {
+        self.pirate.is_some_and(|pirate| self.layout.edge_hexes(edge).any(|hex| hex == pirate))
+    }
+
+    /// Numbers every island (connected group of land hexes) and returns the one a hex is on
+    pub fn island_of(&self, hex: [usize; 2]) -> Option<usize> {
+        if !self.is_land(hex) {
+            return None;
+        }
+        let mut seen: Vec<[usize; 2]> = Vec::new();
+        let mut island = 0;
+        for &start in self.layout.hex_coords.iter() {
+            if !self.is_land(start) || seen.contains(&start) {
+                continue;
+            }
+            let mut stack = vec![start];
+            seen.push(start);
+            while let Some(current) = stack.pop() {
+                if current == hex {
+                    return Some(island);
+                }
+                for neighbor in self.layout.hex_neighbors(current) {
+                    if self.is_land(neighbor) && !seen.contains(&neighbor) {
+                        seen.push(neighbor);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            island += 1;
         }
+        None
+    }
+
+    pub fn corner_island(&self, corner: [usize; 3]) -> Option<usize> {
+        self.layout.hexes_touched(corner).find_map(|hex| self.island_of(hex))
     }
 
     fn road_is_color(&self, edge: [usize; 3], color: PlayerColor) -> bool {
@@ -501,6 +1037,19 @@ impl Board {
         }
     }
 
+    pub fn ship_is_color(&self, edge: [usize; 3], color: PlayerColor) -> bool {
+        let [r, q, e] = edge;
+        match self.ships[r][q][e] {
+            Some(c) => c == color,
+            None => false
+        }
+    }
+
+    fn edge_is_empty(&self, edge: [usize; 3]) -> bool {
+        let [r, q, e] = edge;
+        self.roads[r][q][e].is_none() && self.ships[r][q][e].is_none()
+    }
+
     pub fn structure_exists(&self, corner: [usize; 3]) -> bool {
         let [r, q, c] = corner;
         self.structures[r][q][c].is_some()
@@ -514,6 +1063,85 @@ impl Board {
         }
     }
 
+    /// Resources `color` can expect per roll, in `RESOURCES` order, with the robber where it is
+    pub fn expected_income(&self, color: PlayerColor) -> [f32; 5] {
+        let mut income = [0.0; 5];
+        for &[r, q, c] in self.layout.corner_coords.iter() {
+            let Some(structure) = self.structures[r][q][c] else { continue };
+            if structure.color != color {
+                continue;
+            }
+            let amount = if structure.structure_type == StructureType::City {2.0} else {1.0};
+            for [hr, hq] in self.layout.hexes_touched([r, q, c]) {
+                if [hr, hq] == self.robber {
+                    continue;
+                }
+                let Some(hex) = self.hexes[hr][hq] else { continue };
+                let Some(res) = hex.resource() else { continue };
+                let idx = RESOURCES.iter().position(|&x| x == res).unwrap();
+                income[idx] += amount * roll_probability(hex.number);
+            }
+        }
+        income
+    }
+
+    /// How often a hex produces, in throws out of 36
+    pub fn hex_pips(&self, hex: [usize; 2]) -> usize {
+        let [r, q] = hex;
+        self.hexes[r][q].map_or(0, |hex| pips(hex.number))
+    }
+
+    /// How often a corner produces, counting every hex it touches
+    pub fn corner_pips(&self, corner: [usize; 3]) -> usize {
+        self.layout.hexes_touched(corner).map(|hex| self.hex_pips(hex)).sum()
+    }
+
+    /// Indices into `ports` of the ports `color` has built on
+    pub fn ports_of(&self, color: PlayerColor) -> Vec<usize> {
+        self.layout.port_coords.iter().enumerate()
+            .filter(|(_, &[r, q, e])|
+                self.structure_is_color([r, q, e], color)
+                || self.structure_is_color([r, q, (e + 5) % 6], color)
+            )
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// How many of `resource` `color` pays the bank per card, before house rules.
+    /// The merchant's owner trades its hex's resource two for one.
+    pub fn trade_rate(&self, color: PlayerColor, resource: Resource) -> usize {
+        let merchant = self.merchant.is_some_and(|([r, q], owner)|
+            owner == color && self.hexes[r][q].and_then(|hex| hex.resource()) == Some(resource)
+        );
+        self.ports_of(color).into_iter().map(|idx| match self.ports[idx] {
+            Port::ThreeForOne => 3,
+            Port::TwoForOne(res) if res == resource => 2,
+            Port::TwoForOne(_) => 4
+        }).fold(if merchant {2} else {4}, usize::min)
+    }
+
+    /// The merchant goes on a resource hex next to one of its owner's buildings
+    pub fn can_place_merchant(&self, hex: [usize; 2], color: PlayerColor) -> bool {
+        let [r, q] = hex;
+        self.is_land(hex)
+        && self.hexes[r][q].is_some_and(|h| h.resource().is_some())
+        && self.get_colors_on_hex(hex).contains(&color)
+    }
+
+    /// The Inventor can swap any number token but the 2, 12, 6 and 8
+    pub fn can_swap_number(&self, hex: [usize; 2]) -> bool {
+        let [r, q] = hex;
+        self.is_land(hex)
+        && self.hexes[r][q].is_some_and(|h| !matches!(h.number, 0 | 2 | 6 | 8 | 12))
+    }
+
+    pub fn swap_numbers(&mut self, hex1: [usize; 2], hex2: [usize; 2]) {
+        let ([r1, q1], [r2, q2]) = (hex1, hex2);
+        let number = self.hexes[r1][q1].unwrap().number;
+        self.hexes[r1][q1].as_mut().unwrap().number = self.hexes[r2][q2].unwrap().number;
+        self.hexes[r2][q2].as_mut().unwrap().number = number;
+    }
+
     pub fn structure_isnt_color(&self, corner: [usize; 3], color: PlayerColor) -> bool {
         let [r, q, c] = corner;
         match self.structures[r][q][c] {
@@ -523,53 +1151,102 @@ impl Board {
     }
 
     pub fn is_robbable(&self, corner: [usize; 3], robber: PlayerColor) -> bool {
-        hexes_touched(corner).any(|hex| hex == self.robber)
+        self.layout.hexes_touched(corner).any(|hex| hex == self.robber)
         && self.structure_exists(corner)
         && self.structure_isnt_color(corner, robber)
     }
 
+    pub fn is_pirate_robbable(&self, edge: [usize; 3], robber: PlayerColor) -> bool {
+        let [r, q, e] = edge;
+        self.touches_pirate(edge)
+        && self.ships[r][q][e].is_some_and(|color| color != robber)
+    }
+
     pub fn get_colors_on_hex(&self, hex: [usize; 2]) -> HashSet<PlayerColor> {
         let [r, q] = hex;
-        (0..6).into_iter().filter_map(|c| self.structures[r][q][c]).map(|s| s.color).collect()
+        (0..6).filter_map(|c| self.structures[r][q][c]).map(|s| s.color).collect()
     }
 
-    pub fn can_place_road(&self, edge: [usize; 3], color: PlayerColor) -> bool {
-        let [r, q, e] = edge;
+    pub fn get_ship_colors_on_hex(&self, hex: [usize; 2]) -> HashSet<PlayerColor> {
+        let [r, q] = hex;
+        (0..6).filter_map(|e| self.ships[r][q][e]).collect()
+    }
 
-        self.roads[r][q][e].is_none()
+    pub fn can_place_road(&self, edge: [usize; 3], color: PlayerColor) -> bool {
+        self.edge_is_empty(edge)
+        && self.is_land_edge(edge)
         && (
             edge_corner_neighbors(edge).any(|corner| self.structure_is_color(corner, color))
-            || edge_edge_neighbors(edge).any(|neighbor_edge| {
-                let int_corner = intersecting_corner(edge, neighbor_edge).unwrap();
+            || self.layout.edge_edge_neighbors(edge).any(|neighbor_edge| {
+                let int_corner = self.layout.intersecting_corner(edge, neighbor_edge).unwrap();
                 self.road_is_color(neighbor_edge, color) && !self.structure_isnt_color(int_corner, color)
             })
         )
     }
 
     pub fn can_place_setup_road(&self, edge: [usize; 3], settlement_coord: [usize; 3]) -> bool {
-        let [r, q, e] = edge;
+        self.edge_is_empty(edge)
+        && self.is_land_edge(edge)
+        && edge_corner_neighbors(edge).any(
+            |neighbor_corner| self.layout.reduce_corner(neighbor_corner) == self.layout.reduce_corner(settlement_coord)
+        )
+    }
+
+    pub fn can_place_ship(&self, edge: [usize; 3], color: PlayerColor) -> bool {
+        self.edge_is_empty(edge)
+        && self.is_sea_edge(edge)
+        && !self.touches_pirate(edge)
+        && (
+            edge_corner_neighbors(edge).any(|corner| self.structure_is_color(corner, color))
+            || self.layout.edge_edge_neighbors(edge).any(|neighbor_edge| {
+                let int_corner = self.layout.intersecting_corner(edge, neighbor_edge).unwrap();
+                self.ship_is_color(neighbor_edge, color) && !self.structure_isnt_color(int_corner, color)
+            })
+        )
+    }
 
-        self.roads[r][q][e].is_none()
+    pub fn can_place_setup_ship(&self, edge: [usize; 3], settlement_coord: [usize; 3]) -> bool {
+        self.edge_is_empty(edge)
+        && self.is_sea_edge(edge)
         && edge_corner_neighbors(edge).any(
-            |neighbor_corner| reduce_corner(neighbor_corner) == reduce_corner(settlement_coord)
+            |neighbor_corner| self.layout.reduce_corner(neighbor_corner) == self.layout.reduce_corner(settlement_coord)
         )
     }
 
+    /// A ship can be moved if it sits at the open end of a shipping route:
+    /// one of its corners has neither a settlement of its owner nor another of their ships.
+    pub fn is_open_ship(&self, edge: [usize; 3], color: PlayerColor) -> bool {
+        self.ship_is_color(edge, color)
+        && !self.touches_pirate(edge)
+        && edge_corner_neighbors(edge).any(|corner| {
+            !self.structure_is_color(corner, color)
+            && !self.layout.corner_edge_neighbors(corner).any(|neighbor_edge|
+                self.layout.reduce_edge(neighbor_edge) != self.layout.reduce_edge(edge)
+                && self.ship_is_color(neighbor_edge, color)
+            )
+        })
+    }
+
     pub fn can_place_settlement(&self, corner: [usize; 3], color: PlayerColor) -> bool {
         let [r, q, c] = corner;
 
         self.structures[r][q][c].is_none()
-        && corner_corner_neighbors(corner).all(
+        && self.knights[r][q][c].is_none()
+        && self.is_land_corner(corner)
+        && self.layout.corner_corner_neighbors(corner).all(
             |[r_, q_, c_]| self.structures[r_][q_][c_].is_none()
         )
-        && corner_edge_neighbors(corner).any(|neighbor_edge| self.road_is_color(neighbor_edge, color))
+        && self.layout.corner_edge_neighbors(corner).any(
+            |neighbor_edge| self.road_is_color(neighbor_edge, color) || self.ship_is_color(neighbor_edge, color)
+        )
     }
 
     pub fn can_place_setup_settlement(&self, corner: [usize; 3]) -> bool {
         let [r, q, c] = corner;
 
         self.structures[r][q][c].is_none()
-        && corner_corner_neighbors(corner).all(
+        && self.is_land_corner(corner)
+        && self.layout.corner_corner_neighbors(corner).all(
             |[r_, q_, c_]| self.structures[r_][q_][c_].is_none()
         )
     }
@@ -580,28 +1257,172 @@ impl Board {
         && self.structures[r][q][c].unwrap().structure_type == StructureType::Settlement
     }
 
-    pub fn can_place_any_road(&self, color: PlayerColor) -> bool {
-        EDGE_COORDS.iter().any(|&edge| self.can_place_road(edge, color))
+    pub fn knight_is_color(&self, corner: [usize; 3], color: PlayerColor) -> bool {
+        let [r, q, c] = corner;
+        self.knights[r][q][c].is_some_and(|knight| knight.color == color)
     }
 
-    pub fn can_place_any_settlement(&self, color: PlayerColor) -> bool {
-        CORNER_COORDS.iter().any(|&corner| self.can_place_settlement(corner, color))
-    }
+    pub fn can_place_knight(&self, corner: [usize; 3], color: PlayerColor) -> bool {
+        let [r, q, c] = corner;
+
+        self.structures[r][q][c].is_none()
+        && self.knights[r][q][c].is_none()
+        && self.is_land_corner(corner)
+        && self.touches_road(corner, color)
+    }
+
+    pub fn touches_road(&self, corner: [usize; 3], color: PlayerColor) -> bool {
+        self.layout.corner_edge_neighbors(corner).any(|neighbor_edge| self.road_is_color(neighbor_edge, color))
+    }
+
+    pub fn can_place_any_knight(&self, color: PlayerColor) -> bool {
+        self.layout.corner_coords.iter().any(|&corner| self.can_place_knight(corner, color))
+    }
+
+    pub fn get_knight(&self, corner: [usize; 3]) -> Option<Knight> {
+        let [r, q, c] = corner;
+        self.knights[r][q][c]
+    }
+
+    pub fn get_knights(&self, color: PlayerColor) -> Vec<[usize; 3]> {
+        self.layout.corner_coords.iter().copied()
+            .filter(|&corner| self.knight_is_color(corner, color))
+            .collect()
+    }
+
+    pub fn get_road_color(&self, edge: [usize; 3]) -> Option<PlayerColor> {
+        let [r, q, e] = edge;
+        self.roads[r][q][e]
+    }
+
+    /// The Diplomat can remove a road with an end that leads
+    /// to neither another of its owner's roads nor their buildings
+    pub fn is_open_road(&self, edge: [usize; 3]) -> bool {
+        let Some(color) = self.get_road_color(edge) else {
+            return false;
+        };
+        edge_corner_neighbors(edge).any(|corner| {
+            !self.structure_is_color(corner, color)
+            && !self.layout.corner_edge_neighbors(corner).any(|neighbor_edge|
+                self.layout.reduce_edge(neighbor_edge) != self.layout.reduce_edge(edge)
+                && self.road_is_color(neighbor_edge, color)
+            )
+        })
+    }
+
+    pub fn has_wall(&self, corner: [usize; 3]) -> bool {
+        let [r, q, c] = corner;
+        self.walls[r][q][c]
+    }
+
+    pub fn count_walls(&self, color: PlayerColor) -> usize {
+        self.layout.corner_coords.iter()
+            .filter(|&&corner| self.has_wall(corner) && self.structure_is_color(corner, color))
+            .count()
+    }
+
+    /// Each player has three city walls, one to a city
+    pub fn can_place_wall(&self, corner: [usize; 3], color: PlayerColor) -> bool {
+        let [r, q, c] = corner;
+        self.structure_is_color(corner, color)
+        && self.structures[r][q][c].unwrap().structure_type == StructureType::City
+        && !self.has_wall(corner)
+        && self.count_walls(color) < MAX_WALLS
+    }
+
+    pub fn can_activate_knight(&self, corner: [usize; 3], color: PlayerColor) -> bool {
+        let [r, q, c] = corner;
+        self.knight_is_color(corner, color) && !self.knights[r][q][c].unwrap().active
+    }
+
+    pub fn can_promote_knight(&self, corner: [usize; 3], color: PlayerColor, max_level: usize) -> bool {
+        let [r, q, c] = corner;
+        self.knight_is_color(corner, color) && self.knights[r][q][c].unwrap().level < max_level
+    }
+
+    /// An active knight next to the robber can chase it away
+    pub fn can_chase_robber(&self, corner: [usize; 3], color: PlayerColor) -> bool {
+        let [r, q, c] = corner;
+        self.knight_is_color(corner, color)
+        && self.knights[r][q][c].unwrap().active
+        && self.layout.hexes_touched(corner).any(|hex| hex == self.robber)
+    }
+
+    pub fn get_knight_level(&self, corner: [usize; 3]) -> usize {
+        let [r, q, c] = corner;
+        self.knights[r][q][c].map_or(0, |knight| knight.level)
+    }
+
+    /// Total level of a player's active knights
+    pub fn get_knight_strength(&self, color: PlayerColor) -> usize {
+        self.layout.corner_coords.iter()
+            .filter_map(|&[r, q, c]| self.knights[r][q][c])
+            .filter(|knight| knight.color == color && knight.active)
+            .map(|knight| knight.level)
+            .sum()
+    }
+
+    pub fn get_cities(&self, color: PlayerColor) -> Vec<[usize; 3]> {
+        self.layout.corner_coords.iter().copied()
+            .filter(|&[r, q, c]| self.structures[r][q][c].is_some_and(
+                |s| s.color == color && s.structure_type == StructureType::City
+            ))
+            .collect()
+    }
+
+    pub fn can_place_any_road(&self, color: PlayerColor) -> bool {
+        self.layout.edge_coords.iter().any(|&edge| self.can_place_road(edge, color))
+    }
+
+    pub fn can_place_any_ship(&self, color: PlayerColor) -> bool {
+        self.layout.edge_coords.iter().any(|&edge| self.can_place_ship(edge, color))
+    }
+
+    pub fn can_place_any_settlement(&self, color: PlayerColor) -> bool {
+        self.layout.corner_coords.iter().any(|&corner| self.can_place_settlement(corner, color))
+    }
 
     pub fn can_place_any_city(&self, color: PlayerColor) -> bool {
-        CORNER_COORDS.iter().any(|&corner| self.can_place_city(corner, color))
+        self.layout.corner_coords.iter().any(|&corner| self.can_place_city(corner, color))
+    }
+
+    pub fn can_place_any_wall(&self, color: PlayerColor) -> bool {
+        self.layout.corner_coords.iter().any(|&corner| self.can_place_wall(corner, color))
     }
 
     pub fn place_road(&mut self, edge: [usize; 3], color: PlayerColor) {
         self.bank.add(ROAD_HAND);
-        for [r, q, e] in get_dup_edges(edge) {
+        for [r, q, e] in self.layout.get_dup_edges(edge) {
             self.roads[r][q][e] = Some(color);
         }
     }
 
+    pub fn place_ship(&mut self, edge: [usize; 3], color: PlayerColor) {
+        self.bank.add(SHIP_HAND);
+        self.place_setup_ship(edge, color);
+    }
+
+    pub fn place_setup_ship(&mut self, edge: [usize; 3], color: PlayerColor) {
+        for [r, q, e] in self.layout.get_dup_edges(edge) {
+            self.ships[r][q][e] = Some(color);
+        }
+    }
+
+    pub fn remove_ship(&mut self, edge: [usize; 3]) {
+        for [r, q, e] in self.layout.get_dup_edges(edge) {
+            self.ships[r][q][e] = None;
+        }
+    }
+
+    pub fn remove_road(&mut self, edge: [usize; 3]) {
+        for [r, q, e] in self.layout.get_dup_edges(edge) {
+            self.roads[r][q][e] = None;
+        }
+    }
+
     pub fn place_settlement(&mut self, corner: [usize; 3], color: PlayerColor) {
         self.bank.add(SETTLEMENT_HAND);
-        for [r, q, c] in get_dup_corners(corner) {
+        for [r, q, c] in self.layout.get_dup_corners(corner) {
             self.structures[r][q][c] = Some(Structure {
                 structure_type: StructureType::Settlement,
                 color
@@ -611,7 +1432,7 @@ impl Board {
 
     pub fn place_city(&mut self, corner: [usize; 3], color: PlayerColor) {
         self.bank.add(CITY_HAND);
-        for [r, q, c] in get_dup_corners(corner) {
+        for [r, q, c] in self.layout.get_dup_corners(corner) {
             self.structures[r][q][c] = Some(Structure {
                 structure_type: StructureType::City,
                 color
@@ -619,14 +1440,95 @@ impl Board {
         }
     }
 
+    pub fn place_setup_city(&mut self, corner: [usize; 3], color: PlayerColor) {
+        for [r, q, c] in self.layout.get_dup_corners(corner) {
+            self.structures[r][q][c] = Some(Structure {
+                structure_type: StructureType::City,
+                color
+            });
+        }
+    }
+
+    /// Barbarians knock a city back down to a settlement, and its wall with it
+    pub fn pillage_city(&mut self, corner: [usize; 3]) {
+        for [r, q, c] in self.layout.get_dup_corners(corner) {
+            if let Some(structure) = self.structures[r][q][c].as_mut() {
+                structure.structure_type = StructureType::Settlement;
+            }
+            self.walls[r][q][c] = false;
+        }
+    }
+
+    pub fn place_wall(&mut self, corner: [usize; 3]) {
+        self.bank.add(WALL_HAND);
+        self.place_free_wall(corner);
+    }
+
+    pub fn place_free_wall(&mut self, corner: [usize; 3]) {
+        for [r, q, c] in self.layout.get_dup_corners(corner) {
+            self.walls[r][q][c] = true;
+        }
+    }
+
+    pub fn place_knight(&mut self, corner: [usize; 3], color: PlayerColor) {
+        self.bank.add(KNIGHT_HAND);
+        self.set_knight(corner, Some(Knight { color, level: 1, active: false }));
+    }
+
+    pub fn activate_knight(&mut self, corner: [usize; 3]) {
+        let [r, q, c] = corner;
+        let mut knight = self.knights[r][q][c].unwrap();
+        knight.active = true;
+        self.set_knight(corner, Some(knight));
+    }
+
+    pub fn deactivate_knight(&mut self, corner: [usize; 3]) {
+        let [r, q, c] = corner;
+        let mut knight = self.knights[r][q][c].unwrap();
+        knight.active = false;
+        self.set_knight(corner, Some(knight));
+    }
+
+    pub fn promote_knight(&mut self, corner: [usize; 3]) {
+        let [r, q, c] = corner;
+        let mut knight = self.knights[r][q][c].unwrap();
+        knight.level += 1;
+        self.set_knight(corner, Some(knight));
+    }
+
+    pub fn deactivate_all_knights(&mut self) {
+        for row in self.knights.iter_mut() {
+            for knight in row.iter_mut().flatten().flatten() {
+                knight.active = false;
+            }
+        }
+    }
+
+    /// Puts a knight back on the board as it was, or one taken from another player
+    pub fn place_free_knight(&mut self, corner: [usize; 3], knight: Knight) {
+        self.set_knight(corner, Some(knight));
+    }
+
+    pub fn remove_knight(&mut self, corner: [usize; 3]) -> Knight {
+        let knight = self.get_knight(corner).unwrap();
+        self.set_knight(corner, None);
+        knight
+    }
+
+    fn set_knight(&mut self, corner: [usize; 3], knight: Option<Knight>) {
+        for [r, q, c] in self.layout.get_dup_corners(corner) {
+            self.knights[r][q][c] = knight;
+        }
+    }
+
     pub fn place_setup_road(&mut self, edge: [usize; 3], color: PlayerColor) {
-        for [r, q, e] in get_dup_edges(edge) {
+        for [r, q, e] in self.layout.get_dup_edges(edge) {
             self.roads[r][q][e] = Some(color);
         }
     }
 
     pub fn place_setup_settlement(&mut self, corner: [usize; 3], color: PlayerColor) {
-        for [r, q, c] in get_dup_corners(corner) {
+        for [r, q, c] in self.layout.get_dup_corners(corner) {
             self.structures[r][q][c] = Some(Structure {
                 structure_type: StructureType::Settlement,
                 color
@@ -635,7 +1537,20 @@ impl Board {
     }
 
     pub fn can_draw_dv_card(&self) -> bool {
-        self.dv_bank.size() > 0
+        self.get_dv_bank_size() > 0
+    }
+
+    pub fn get_dv_bank_size(&self) -> usize {
+        self.hidden_dvs.unwrap_or(self.dv_bank.size())
+    }
+
+    /// The board as the players see it, with the development cards left
+    /// in the bank counted but not known
+    pub fn face_down(&self) -> Board {
+        let mut board = self.clone();
+        board.dv_bank = DVHand::new();
+        board.hidden_dvs = Some(self.get_dv_bank_size());
+        board
     }
 
     pub fn draw_dv_card<R: Rng + ?Sized>(&mut self, rng: &mut R) -> DVCard {
@@ -643,11 +1558,69 @@ impl Board {
         self.dv_bank.discard_random(rng).unwrap()
     }
 
+    pub fn can_take_dv_card(&self, card: DVCard) -> bool {
+        self.dv_bank.can_discard_card(card)
+    }
+
+    /// Takes a particular card, for when the draw happened off the screen
+    pub fn take_dv_card(&mut self, card: DVCard) {
+        self.bank.add(DV_CARD_HAND);
+        self.dv_bank.discard_card(card);
+    }
+
+    /// Board editor: steps a land hex through the five resources and the desert
+    pub fn cycle_terrain(&mut self, hex: [usize; 2]) {
+        let [r, q] = hex;
+        let Some(old) = self.hexes[r][q] else { return };
+        self.hexes[r][q] = Some(match old.terrain {
+            Terrain::Resource(Resource::Ore) => Hex::desert(),
+            Terrain::Resource(res) => {
+                let idx = RESOURCES.iter().position(|&r| r == res).unwrap();
+                Hex { terrain: Terrain::Resource(RESOURCES[idx + 1]), number: old.number }
+            },
+            _ => Hex { terrain: Terrain::Resource(RESOURCES[0]), number: NUMBER_TOKENS[0] }
+        });
+    }
+
+    /// Board editor: steps a resource hex through the number tokens
+    pub fn cycle_number(&mut self, hex: [usize; 2]) {
+        let [r, q] = hex;
+        if let Some(Hex { terrain: Terrain::Resource(_), number }) = self.hexes[r][q].as_mut() {
+            let idx = NUMBER_TOKENS.iter().position(|n| n == number).unwrap_or(0);
+            *number = NUMBER_TOKENS[(idx + 1) % NUMBER_TOKENS.len()];
+        }
+    }
+
+    /// Board editor: steps a port through 3:1 and the five 2:1 ports
+    pub fn cycle_port(&mut self, idx: usize) {
+        self.ports[idx] = match self.ports[idx] {
+            Port::ThreeForOne => Port::TwoForOne(RESOURCES[0]),
+            Port::TwoForOne(Resource::Ore) => Port::ThreeForOne,
+            Port::TwoForOne(res) => {
+                let idx = RESOURCES.iter().position(|&r| r == res).unwrap();
+                Port::TwoForOne(RESOURCES[idx + 1])
+            }
+        };
+    }
+
+    /// Puts the robber on a desert, if the robber isn't on one already
+    pub fn reset_robber(&mut self) {
+        let [r, q] = self.robber;
+        if self.hexes[r][q].is_some_and(|hex| hex.terrain == Terrain::Desert) {
+            return;
+        }
+        if let Some(&desert) = self.layout.hex_coords.iter().find(
+            |&&[r, q]| self.hexes[r][q].is_some_and(|hex| hex.terrain == Terrain::Desert)
+        ) {
+            self.robber = desert;
+        }
+    }
+
     pub fn get_starting_resources(&self, corner: [usize; 3]) -> ResHand {
         let mut hand = ResHand::new();
-        for [r, q] in hexes_touched(corner) {
-            if let Some(hex) = self.hexes[r][q] {
-                hand.add_card(hex.resource);
+        for [r, q] in self.layout.hexes_touched(corner) {
+            if let Some(res) = self.hexes[r][q].and_then(|hex| hex.resource()) {
+                hand.add_card(res);
             }
         }
         hand
@@ -659,29 +1632,141 @@ impl Board {
             new_cards.push(ResHand::new());
         }
 
-        for [r, q] in HEX_COORDS {
-            if [r, q] == self.robber || self.hexes[r][q].is_none() {
+        for &[r, q] in self.layout.hex_coords.iter() {
+            if [r, q] == self.robber {
                 continue;
             }
 
-            let hex = self.hexes[r][q].unwrap();
+            let Some(hex) = self.hexes[r][q] else { continue };
+            let Some(resource) = hex.resource() else { continue };
             if hex.number == roll {
-                for corner in self.structures[r][q] {
-                    if let Some(s) = corner {
-                        let idx = players.iter().position(|&color| s.color == color).unwrap();
-                        new_cards[idx].add_card(hex.resource);
-                        if s.structure_type == StructureType::City {
-                            new_cards[idx].add_card(hex.resource);
-                        }
+                for s in self.structures[r][q].iter().flatten() {
+                    let idx = players.iter().position(|&color| s.color == color).unwrap();
+                    new_cards[idx].add_card(resource);
+                    if s.structure_type == StructureType::City {
+                        new_cards[idx].add_card(resource);
                     }
                 }
             }
         }
         new_cards
     }
+
+    /// Cities & Knights production: a city on a forest, pasture or mountain
+    /// makes one resource and one commodity instead of two resources.
+    pub fn get_new_production(&self, players: Vec<PlayerColor>, roll: usize) -> Vec<(ResHand, ComHand)> {
+        let mut production = vec![(ResHand::new(), ComHand::new()); players.len()];
+
+        for &[r, q] in self.layout.hex_coords.iter() {
+            if [r, q] == self.robber {
+                continue;
+            }
+
+            let Some(hex) = self.hexes[r][q] else { continue };
+            let Some(resource) = hex.resource() else { continue };
+            if hex.number == roll {
+                for s in self.structures[r][q].iter().flatten() {
+                    let idx = players.iter().position(|&color| s.color == color).unwrap();
+                    let (resources, commodities) = &mut production[idx];
+                    resources.add_card(resource);
+                    if s.structure_type == StructureType::City {
+                        match Commodity::from_resource(resource) {
+                            Some(commodity) => commodities.add_card(commodity),
+                            None => resources.add_card(resource)
+                        }
+                    }
+                }
+            }
+        }
+        production
+    }
+
+    /// How many hexes of a resource touch at least one of a player's buildings
+    pub fn count_adjacent_hexes(&self, color: PlayerColor, resource: Resource) -> usize {
+        let hexes: HashSet<[usize; 2]> = self.layout.corner_coords.iter()
+            .filter(|&&corner| self.structure_is_color(corner, color))
+            .flat_map(|&corner| self.layout.hexes_touched(corner))
+            .filter(|&[r, q]| self.hexes[r][q].and_then(|hex| hex.resource()) == Some(resource))
+            .collect();
+        hexes.len()
+    }
+
+    /// How many resources of their choice each player gets from gold fields
+    pub fn get_new_gold(&self, players: Vec<PlayerColor>, roll: usize) -> Vec<usize> {
+        let mut gold = vec![0; players.len()];
+
+        for &[r, q] in self.layout.hex_coords.iter() {
+            if [r, q] == self.robber {
+                continue;
+            }
+
+            let Some(hex) = self.hexes[r][q] else { continue };
+            if hex.terrain == Terrain::Gold && hex.number == roll {
+                for s in self.structures[r][q].iter().flatten() {
+                    let idx = players.iter().position(|&color| s.color == color).unwrap();
+                    gold[idx] += if s.structure_type == StructureType::City {2} else {1};
+                }
+            }
+        }
+        gold
+    }
+
+    /// Length of a player's longest trade route. Roads and ships only
+    /// join up at one of the player's own settlements or cities.
+    pub fn longest_route(&self, color: PlayerColor) -> usize {
+        let pieces: Vec<([usize; 3], bool)> = self.layout.edge_coords.iter()
+            .filter_map(|&edge| {
+                if self.road_is_color(edge, color) {
+                    Some((edge, false))
+                } else if self.ship_is_color(edge, color) {
+                    Some((edge, true))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut used = vec![false; pieces.len()];
+        let mut longest = 0;
+        for idx in 0..pieces.len() {
+            for start in edge_corner_neighbors(pieces[idx].0) {
+                used[idx] = true;
+                longest = longest.max(self.extend_route(color, &pieces, &mut used, idx, start));
+                used[idx] = false;
+            }
+        }
+        longest
+    }
+
+    fn extend_route(&self, color: PlayerColor, pieces: &[([usize; 3], bool)], used: &mut [bool], idx: usize, from: [usize; 3]) -> usize {
+        let (edge, is_ship) = pieces[idx];
+        let from = self.layout.reduce_corner(from);
+        let Some(to) = edge_corner_neighbors(edge).find(|&corner| self.layout.reduce_corner(corner) != from) else {
+            return 1;
+        };
+        if self.structure_isnt_color(to, color) {
+            return 1;
+        }
+        let to = self.layout.reduce_corner(to);
+        let own_structure = self.structure_is_color(to, color);
+
+        let mut best = 0;
+        for next in 0..pieces.len() {
+            let (next_edge, next_is_ship) = pieces[next];
+            if used[next] || (next_is_ship != is_ship && !own_structure) {
+                continue;
+            }
+            if edge_corner_neighbors(next_edge).any(|corner| self.layout.reduce_corner(corner) == to) {
+                used[next] = true;
+                best = best.max(self.extend_route(color, pieces, used, next, to));
+                used[next] = false;
+            }
+        }
+        1 + best
+    }
 }
 
-//// Coordinate manipulation
+// Coordinate manipulation
 // - Hex coords: axial coordinates (r, q)
 // r loosely corresponds with row, q with col.
 //
@@ -703,170 +1788,263 @@ const DIRS: [[isize; 2]; 6] = [
     [0, -1]
 ];
 
-const fn is_on_board(hex: [usize; 2]) -> bool {
-    let [r, q] = hex;
-    r < 5 && q < 5 && r + q >= 2 && r + q <= 6
+/// The shape of a board: which axial hexes exist, and every
+/// corner, edge and port on them in canonical (reduced) form.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    on_board: Vec<Vec<bool>>,
+    pub hex_coords: Vec<[usize; 2]>,
+    pub corner_coords: Vec<[usize; 3]>,
+    pub edge_coords: Vec<[usize; 3]>,
+    pub port_coords: Vec<[usize; 3]>,
 }
 
-const fn reduce_corner(corner: [usize; 3]) -> [usize; 3] {
-    let [r, q, c] = corner;
-    match c {
-        0 => if r != 0 && is_on_board([r - 1, q]) {
-            [r - 1, q, 2]
-        } else if r != 0 && is_on_board([r - 1, q + 1]) {
-            [r - 1, q + 1, 4]
-        } else {
-            [r, q, 0]
-        },
-        1 => if r != 0 && is_on_board([r - 1, q + 1]) {
-            [r - 1, q + 1, 3]
-        } else {
-            [r, q, 1]
-        },
-        2 => [r, q, 2],
-        3 => [r, q, 3],
-        4 => if q != 0 && is_on_board([r, q - 1]) {
-            [r, q - 1, 2]
-        } else {
-            [r, q, 4]
-        },
-        5 => if r != 0 && is_on_board([r - 1, q]) {
-            [r - 1, q, 3]
-        } else if q != 0 && is_on_board([r, q - 1]) {
-            [r, q - 1, 1]
-        } else {
-            [r, q, 5]
-        },
-        _ => panic!("main::reduce_corner(): invalid corner")
+impl Layout {
+    pub fn new(hex_coords: Vec<[usize; 2]>, port_coords: Vec<[usize; 3]>) -> Layout {
+        let rows = hex_coords.iter().map(|&[r, _]| r + 1).max().unwrap_or(0);
+        let cols = hex_coords.iter().map(|&[_, q]| q + 1).max().unwrap_or(0);
+        let mut on_board = vec![vec![false; cols]; rows];
+        for &[r, q] in hex_coords.iter() {
+            on_board[r][q] = true;
+        }
+
+        let mut layout = Layout {
+            on_board,
+            hex_coords,
+            corner_coords: Vec::new(),
+            edge_coords: Vec::new(),
+            port_coords,
+        };
+
+        for &[r, q] in layout.hex_coords.iter() {
+            for c in 0..6 {
+                if layout.reduce_corner([r, q, c]) == [r, q, c] {
+                    layout.corner_coords.push([r, q, c]);
+                }
+            }
+            for e in 0..6 {
+                if layout.reduce_edge([r, q, e]) == [r, q, e] {
+                    layout.edge_coords.push([r, q, e]);
+                }
+            }
+        }
+        layout
     }
-}
 
-const fn reduce_edge(edge: [usize; 3]) -> [usize; 3] {
-    let [r, q, e] = edge;
-    match e {
-        0 => if r != 0 && is_on_board([r - 1, q]) {
-            [r - 1, q, 3]
-        } else {
-            [r, q, 0]
-        },
-        1 => if r != 0 && is_on_board([r - 1, q + 1]) {
-            [r - 1, q + 1, 4]
-        } else {
-            [r, q, 1]
-        },
-        2 => [r, q, 2],
-        3 => [r, q, 3],
-        4 => [r, q, 4],
-        5 => if q != 0 && is_on_board([r, q - 1]) {
-            [r, q - 1, 2]
-        } else {
-            [r, q, 5]
-        },
-        _ => panic!("main::reduce_edge(): invalid edge")
+    /// Rows and columns of the axial grid the hexes sit in
+    pub fn size(&self) -> [usize; 2] {
+        [self.on_board.len(), self.on_board.first().map_or(0, |row| row.len())]
     }
-}
 
-fn hexes_touched(corner: [usize; 3]) -> impl Iterator<Item = [usize; 2]> {
-    let [r, q, c] = corner;
-    let mut neighbors = vec![[r, q]];
+    pub fn is_on_board(&self, hex: [usize; 2]) -> bool {
+        let [r, q] = hex;
+        r < self.on_board.len() && q < self.on_board[r].len() && self.on_board[r][q]
+    }
 
-    let neighbor1 = [(r as isize + DIRS[c][0]) as usize, (q as isize + DIRS[c][1]) as usize];
-    if is_on_board(neighbor1) {
-        neighbors.push(neighbor1);
+    pub fn reduce_corner(&self, corner: [usize; 3]) -> [usize; 3] {
+        let [r, q, c] = corner;
+        match c {
+            0 => if r != 0 && self.is_on_board([r - 1, q]) {
+                [r - 1, q, 2]
+            } else if r != 0 && self.is_on_board([r - 1, q + 1]) {
+                [r - 1, q + 1, 4]
+            } else {
+                [r, q, 0]
+            },
+            1 => if r != 0 && self.is_on_board([r - 1, q + 1]) {
+                [r - 1, q + 1, 3]
+            } else {
+                [r, q, 1]
+            },
+            2 => [r, q, 2],
+            3 => [r, q, 3],
+            4 => if q != 0 && self.is_on_board([r, q - 1]) {
+                [r, q - 1, 2]
+            } else {
+                [r, q, 4]
+            },
+            5 => if r != 0 && self.is_on_board([r - 1, q]) {
+                [r - 1, q, 3]
+            } else if q != 0 && self.is_on_board([r, q - 1]) {
+                [r, q - 1, 1]
+            } else {
+                [r, q, 5]
+            },
+            _ => panic!("Layout::reduce_corner(): invalid corner")
+        }
     }
-    let neighbor2 = [(r as isize + DIRS[(c + 1) % 6][0]) as usize, (q as isize + DIRS[(c + 1) % 6][1]) as usize];
-    if is_on_board(neighbor2) {
-        neighbors.push(neighbor2);
+
+    pub fn reduce_edge(&self, edge: [usize; 3]) -> [usize; 3] {
+        let [r, q, e] = edge;
+        match e {
+            0 => if r != 0 && self.is_on_board([r - 1, q]) {
+                [r - 1, q, 3]
+            } else {
+                [r, q, 0]
+            },
+            1 => if r != 0 && self.is_on_board([r - 1, q + 1]) {
+                [r - 1, q + 1, 4]
+            } else {
+                [r, q, 1]
+            },
+            2 => [r, q, 2],
+            3 => [r, q, 3],
+            4 => [r, q, 4],
+            5 => if q != 0 && self.is_on_board([r, q - 1]) {
+                [r, q - 1, 2]
+            } else {
+                [r, q, 5]
+            },
+            _ => panic!("Layout::reduce_edge(): invalid edge")
+        }
     }
-    neighbors.into_iter()
-}
 
-fn get_dup_corners(corner: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
-    let [r, q, c] = corner;
-    let mut dups = vec![[r, q, c]];
-    let neighbor1 = [(r as isize + DIRS[c][0]) as usize, (q as isize + DIRS[c][1]) as usize];
-    if is_on_board(neighbor1) {
-        dups.push([neighbor1[0], neighbor1[1], (c + 2) % 6]);
+    pub fn hexes_touched(&self, corner: [usize; 3]) -> impl Iterator<Item = [usize; 2]> {
+        let [r, q, c] = corner;
+        let mut neighbors = vec![[r, q]];
+
+        let neighbor1 = [(r as isize + DIRS[c][0]) as usize, (q as isize + DIRS[c][1]) as usize];
+        if self.is_on_board(neighbor1) {
+            neighbors.push(neighbor1);
+        }
+        let neighbor2 = [(r as isize + DIRS[(c + 1) % 6][0]) as usize, (q as isize + DIRS[(c + 1) % 6][1]) as usize];
+        if self.is_on_board(neighbor2) {
+            neighbors.push(neighbor2);
+        }
+        neighbors.into_iter()
     }
-    let neighbor2 = [(r as isize + DIRS[(c + 1) % 6][0]) as usize, (q as isize + DIRS[(c + 1) % 6][1]) as usize];
-    if is_on_board(neighbor2) {
-        dups.push([neighbor2[0], neighbor2[1], (c + 4) % 6]);
+
+    pub fn get_dup_corners(&self, corner: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
+        let [r, q, c] = corner;
+        let mut dups = vec![[r, q, c]];
+        let neighbor1 = [(r as isize + DIRS[c][0]) as usize, (q as isize + DIRS[c][1]) as usize];
+        if self.is_on_board(neighbor1) {
+            dups.push([neighbor1[0], neighbor1[1], (c + 2) % 6]);
+        }
+        let neighbor2 = [(r as isize + DIRS[(c + 1) % 6][0]) as usize, (q as isize + DIRS[(c + 1) % 6][1]) as usize];
+        if self.is_on_board(neighbor2) {
+            dups.push([neighbor2[0], neighbor2[1], (c + 4) % 6]);
+        }
+        dups.into_iter()
     }
-    dups.into_iter()
-}
 
-fn get_dup_edges(edge: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
-    let [r, q, e] = edge;
-    let mut dups = vec![[r, q, e]];
-    let neighbor = [(r as isize + DIRS[e][0]) as usize, (q as isize + DIRS[e][1]) as usize];
-    if is_on_board(neighbor) {
-        dups.push([neighbor[0], neighbor[1], (e + 3) % 6]);
+    pub fn hex_neighbors(&self, hex: [usize; 2]) -> impl Iterator<Item = [usize; 2]> {
+        let [r, q] = hex;
+        let neighbors: Vec<[usize; 2]> = DIRS.iter()
+            .map(|dir| [(r as isize + dir[0]) as usize, (q as isize + dir[1]) as usize])
+            .filter(|&neighbor| self.is_on_board(neighbor))
+            .collect();
+        neighbors.into_iter()
     }
-    dups.into_iter()
-}
 
-fn corner_corner_neighbors(corner: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
-    let [r, q, c] = corner;
-    let mut neighbors = vec![[r, q, (c + 5) % 6], [r, q, (c + 1) % 6]];
+    /// The one or two hexes on either side of an edge
+    pub fn edge_hexes(&self, edge: [usize; 3]) -> impl Iterator<Item = [usize; 2]> {
+        let [r, q, e] = edge;
+        let mut hexes = vec![[r, q]];
+        let neighbor = [(r as isize + DIRS[e][0]) as usize, (q as isize + DIRS[e][1]) as usize];
+        if self.is_on_board(neighbor) {
+            hexes.push(neighbor);
+        }
+        hexes.into_iter()
+    }
 
-    let hex_neighbor1 = [(r as isize + DIRS[c][0]) as usize, (q as isize + DIRS[c][1]) as usize];
-    let hex_neighbor2 = [(r as isize + DIRS[(c + 1) % 6][0]) as usize, (q as isize + DIRS[(c + 1) % 6][1]) as usize];
-    if is_on_board(hex_neighbor1) {
-        neighbors.push([hex_neighbor1[0], hex_neighbor1[1], (c + 1) % 6]);
-    } else if is_on_board(hex_neighbor2) {
-        neighbors.push([hex_neighbor2[0], hex_neighbor2[1], (c + 5) % 6]);
+    pub fn get_dup_edges(&self, edge: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
+        let [r, q, e] = edge;
+        let mut dups = vec![[r, q, e]];
+        let neighbor = [(r as isize + DIRS[e][0]) as usize, (q as isize + DIRS[e][1]) as usize];
+        if self.is_on_board(neighbor) {
+            dups.push([neighbor[0], neighbor[1], (e + 3) % 6]);
+        }
+        dups.into_iter()
     }
-    neighbors.into_iter()
-}
 
-fn edge_edge_neighbors(edge: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
-    let [r, q, e] = edge;
-    let mut neighbors = vec![[r, q, (e + 5) % 6], [r, q, (e + 1) % 6]];
-    let full_neighbor = [(r as isize + DIRS[e][0]) as usize, (q as isize + DIRS[e][1]) as usize];
-    let half_neighbor_l = [(r as isize + DIRS[(e + 5) % 6][0]) as usize, (q as isize + DIRS[(e + 5) % 6][1]) as usize];
-    let half_neighbor_r = [(r as isize + DIRS[(e + 1) % 6][0]) as usize, (q as isize + DIRS[(e + 1) % 6][1]) as usize];
-    if is_on_board(full_neighbor) {
-        neighbors.push([full_neighbor[0], full_neighbor[1], (e + 2) % 6]);
-        neighbors.push([full_neighbor[0], full_neighbor[1], (e + 4) % 6]);
+    pub fn corner_corner_neighbors(&self, corner: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
+        let [r, q, c] = corner;
+        let mut neighbors = vec![[r, q, (c + 5) % 6], [r, q, (c + 1) % 6]];
+
+        let hex_neighbor1 = [(r as isize + DIRS[c][0]) as usize, (q as isize + DIRS[c][1]) as usize];
+        let hex_neighbor2 = [(r as isize + DIRS[(c + 1) % 6][0]) as usize, (q as isize + DIRS[(c + 1) % 6][1]) as usize];
+        if self.is_on_board(hex_neighbor1) {
+            neighbors.push([hex_neighbor1[0], hex_neighbor1[1], (c + 1) % 6]);
+        } else if self.is_on_board(hex_neighbor2) {
+            neighbors.push([hex_neighbor2[0], hex_neighbor2[1], (c + 5) % 6]);
+        }
+        neighbors.into_iter()
     }
-    else {
-        if is_on_board(half_neighbor_l) {
-            neighbors.push([half_neighbor_l[0], half_neighbor_l[1], (e + 1) % 6]);
+
+    pub fn edge_edge_neighbors(&self, edge: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
+        let [r, q, e] = edge;
+        let mut neighbors = vec![[r, q, (e + 5) % 6], [r, q, (e + 1) % 6]];
+        let full_neighbor = [(r as isize + DIRS[e][0]) as usize, (q as isize + DIRS[e][1]) as usize];
+        let half_neighbor_l = [(r as isize + DIRS[(e + 5) % 6][0]) as usize, (q as isize + DIRS[(e + 5) % 6][1]) as usize];
+        let half_neighbor_r = [(r as isize + DIRS[(e + 1) % 6][0]) as usize, (q as isize + DIRS[(e + 1) % 6][1]) as usize];
+        if self.is_on_board(full_neighbor) {
+            neighbors.push([full_neighbor[0], full_neighbor[1], (e + 2) % 6]);
+            neighbors.push([full_neighbor[0], full_neighbor[1], (e + 4) % 6]);
         }
-        if is_on_board(half_neighbor_r) {
-            neighbors.push([half_neighbor_r[0], half_neighbor_r[1], (e + 5) % 6]);
+        else {
+            if self.is_on_board(half_neighbor_l) {
+                neighbors.push([half_neighbor_l[0], half_neighbor_l[1], (e + 1) % 6]);
+            }
+            if self.is_on_board(half_neighbor_r) {
+                neighbors.push([half_neighbor_r[0], half_neighbor_r[1], (e + 5) % 6]);
+            }
         }
+        neighbors.into_iter()
     }
-    neighbors.into_iter()
-}
 
-fn corner_edge_neighbors(corner: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
-    let [r, q, c] = corner;
-    let mut neighbors = vec![[r, q, c], [r, q, (c + 1) % 6]];
+    pub fn corner_edge_neighbors(&self, corner: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
+        let [r, q, c] = corner;
+        let mut neighbors = vec![[r, q, c], [r, q, (c + 1) % 6]];
+
+        let hex_neighbor1 = [(r as isize + DIRS[c][0]) as usize, (q as isize + DIRS[c][1]) as usize];
+        let hex_neighbor2 = [(r as isize + DIRS[(c + 1) % 6][0]) as usize, (q as isize + DIRS[(c + 1) % 6][1]) as usize];
+        if self.is_on_board(hex_neighbor1) {
+            neighbors.push([hex_neighbor1[0], hex_neighbor1[1], (c + 2) % 6]);
+        } else if self.is_on_board(hex_neighbor2) {
+            neighbors.push([hex_neighbor2[0], hex_neighbor2[1], (c + 5) % 6]);
+        }
+        neighbors.into_iter()
+    }
 
-    let hex_neighbor1 = [(r as isize + DIRS[c][0]) as usize, (q as isize + DIRS[c][1]) as usize];
-    let hex_neighbor2 = [(r as isize + DIRS[(c + 1) % 6][0]) as usize, (q as isize + DIRS[(c + 1) % 6][1]) as usize];
-    if is_on_board(hex_neighbor1) {
-        neighbors.push([hex_neighbor1[0], hex_neighbor1[1], (c + 2) % 6]);
-    } else if is_on_board(hex_neighbor2) {
-        neighbors.push([hex_neighbor2[0], hex_neighbor2[1], (c + 5) % 6]);
+    pub fn intersecting_corner(&self, edge1: [usize; 3], edge2: [usize; 3]) -> Option<[usize; 3]> {
+        edge_corner_neighbors(edge1)
+        .flat_map(|neighbor_corner| self.get_dup_corners(neighbor_corner))
+        .find(|&c1|
+            edge_corner_neighbors(edge2).any(|c2| c1 == c2)
+        )
     }
-    neighbors.into_iter()
 }
 
-fn edge_corner_neighbors(edge: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
+pub fn edge_corner_neighbors(edge: [usize; 3]) -> impl Iterator<Item = [usize; 3]> {
     let [r, q, e] = edge;
     [[r, q, e], [r, q, (e + 5) % 6]].into_iter()
 }
 
-fn intersecting_corner(edge1: [usize; 3], edge2: [usize; 3]) -> Option<[usize; 3]> {
-    edge_corner_neighbors(edge1)
-    .flat_map(|neighbor_corner| get_dup_corners(neighbor_corner))
-    .find(|&c1|
-        edge_corner_neighbors(edge2).any(|c2| c1 == c2)
-    )
+/// The next `N` numbers after `key`, for reading a saved player back
+fn take_numbers<const N: usize>(tokens: &mut std::str::SplitWhitespace, key: &str) -> Result<[usize; N], String> {
+    if tokens.next() != Some(key) {
+        return Err(format!("expected '{key}'"));
+    }
+    let mut numbers = [0; N];
+    for number in numbers.iter_mut() {
+        *number = tokens.next()
+            .and_then(|token| token.parse().ok())
+            .ok_or(format!("bad value for '{key}'"))?;
+    }
+    Ok(numbers)
 }
 
+/// How many cards of each hidden kind a player holds
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CardCounts {
+    pub resources: usize,
+    pub dvs: usize,
+    pub commodities: usize,
+    pub progress_cards: usize,
+}
+
+#[derive(Clone)]
 pub struct Player {
     color: PlayerColor,
     is_human: bool,
@@ -879,8 +2057,18 @@ pub struct Player {
     road_len: usize,
     longest_road: bool,
     road_pool: usize,
+    ship_pool: usize,
     settlement_pool: usize,
     city_pool: usize,
+    bonus_vps: usize,
+    commodities: ComHand,
+    improvements: [usize; 3],
+    progress_cards: Vec<ProgressCard>,
+    knight_pool: [usize; MAX_KNIGHT_LEVEL],
+    metropolises: usize,
+    /// Set on a player seen face down: their cards are all empty
+    /// and only how many they hold is known
+    hidden: Option<CardCounts>,
 }
 
 impl Player {
@@ -897,8 +2085,16 @@ impl Player {
             road_len: 0,
             longest_road: false,
             road_pool: 15,
+            ship_pool: 15,
             settlement_pool: 5,
             city_pool: 4,
+            bonus_vps: 0,
+            commodities: ComHand::new(),
+            improvements: [0; 3],
+            progress_cards: Vec::new(),
+            knight_pool: [2; MAX_KNIGHT_LEVEL],
+            metropolises: 0,
+            hidden: None,
         }
     }
 
@@ -906,13 +2102,24 @@ impl Player {
         self.color
     }
 
+    pub fn is_human(&self) -> bool {
+        self.is_human
+    }
+
     pub fn get_vps(&self) -> usize {
         self.base_vps
+        + self.bonus_vps
+        + 2 * self.metropolises
         + self.dvs[DVCard::VictoryPoint]
         + if self.largest_army {2} else {0}
         + if self.longest_road {2} else {0}
     }
 
+    /// VPs everyone can see, leaving out VP cards in hand
+    pub fn get_visible_vps(&self) -> usize {
+        self.get_vps() - self.dvs[DVCard::VictoryPoint]
+    }
+
     pub fn get_hand(&self) -> ResHand {
         self.hand
     }
@@ -921,10 +2128,6 @@ impl Player {
         self.dvs
     }
 
-    pub fn get_new_dvs(&self) -> DVHand {
-        self.new_dvs
-    }
-
     pub fn get_combined_dvs(&self) -> DVHand {
         let mut combined = self.dvs;
         combined.add(self.new_dvs);
@@ -943,20 +2146,32 @@ impl Player {
         self.longest_road = value;
     }
 
+    pub fn get_road_len(&self) -> usize {
+        self.road_len
+    }
+
     pub fn set_road_len(&mut self, value: usize) {
         self.road_len = value;
     }
 
+    pub fn add_bonus_vps(&mut self, value: usize) {
+        self.bonus_vps += value;
+    }
+
+    pub fn remove_bonus_vps(&mut self, value: usize) {
+        self.bonus_vps -= value;
+    }
+
     pub fn is_color(&self, color: PlayerColor) -> bool {
         self.color == color
     }
 
-    pub fn has_won(&self) -> bool {
-        self.get_vps() >= 10
+    pub fn has_won(&self, vp_target: usize) -> bool {
+        self.get_vps() >= vp_target
     }
 
-    pub fn must_discard(&self) -> bool {
-        self.hand.size() > 7
+    pub fn must_discard(&self, limit: usize) -> bool {
+        self.hand.size() > limit
     }
 
     pub fn get_cards(&mut self, new: ResHand) {
@@ -984,15 +2199,43 @@ impl Player {
     }
 
     pub fn can_build_road(&self) -> bool {
-        self.hand.can_discard(ROAD_HAND) && self.road_pool > 0
+        self.hand.can_discard(ROAD_HAND) && self.has_road_piece()
+    }
+
+    pub fn get_road_pool(&self) -> usize {
+        self.road_pool
+    }
+
+    pub fn get_settlement_pool(&self) -> usize {
+        self.settlement_pool
+    }
+
+    pub fn get_city_pool(&self) -> usize {
+        self.city_pool
+    }
+
+    pub fn has_road_piece(&self) -> bool {
+        self.road_pool > 0
+    }
+
+    pub fn has_settlement_piece(&self) -> bool {
+        self.settlement_pool > 0
+    }
+
+    pub fn has_city_piece(&self) -> bool {
+        self.city_pool > 0
+    }
+
+    pub fn can_build_ship(&self) -> bool {
+        self.hand.can_discard(SHIP_HAND) && self.ship_pool > 0
     }
 
     pub fn can_build_settlement(&self) -> bool {
-        self.hand.can_discard(SETTLEMENT_HAND) && self.settlement_pool > 0
+        self.hand.can_discard(SETTLEMENT_HAND) && self.has_settlement_piece()
     }
 
     pub fn can_build_city(&self) -> bool {
-        self.hand.can_discard(CITY_HAND) && self.city_pool > 0
+        self.hand.can_discard(CITY_HAND) && self.has_city_piece()
     }
 
     pub fn buy_dv(&mut self, dv: DVCard) {
@@ -1005,6 +2248,15 @@ impl Player {
         self.road_pool -= 1;
     }
 
+    pub fn build_ship(&mut self) {
+        self.hand.discard(SHIP_HAND);
+        self.ship_pool -= 1;
+    }
+
+    pub fn place_setup_ship(&mut self) {
+        self.ship_pool -= 1;
+    }
+
     pub fn build_settlement(&mut self) {
         self.hand.discard(SETTLEMENT_HAND);
         self.base_vps += 1;
@@ -1015,13 +2267,22 @@ impl Player {
         self.road_pool -= 1;
     }
 
+    /// Takes back a road removed from the board
+    pub fn return_road(&mut self) {
+        self.road_pool += 1;
+    }
+
     pub fn place_setup_settlement(&mut self) {
         self.base_vps += 1;
         self.settlement_pool -= 1;
     }
 
     pub fn build_city(&mut self) {
-        self.hand.discard(CITY_HAND);
+        self.build_city_for(CITY_HAND);
+    }
+
+    pub fn build_city_for(&mut self, cost: ResHand) {
+        self.hand.discard(cost);
         self.base_vps += 1;
         self.city_pool -= 1;
         self.settlement_pool += 1;
@@ -1038,4 +2299,261 @@ impl Player {
         self.dvs.add(self.new_dvs);
         self.new_dvs.clear();
     }
+
+    pub fn place_setup_city(&mut self) {
+        self.base_vps += 2;
+        self.city_pool -= 1;
+    }
+
+    pub fn pillage_city(&mut self) {
+        self.base_vps -= 1;
+        self.city_pool += 1;
+        self.settlement_pool = self.settlement_pool.saturating_sub(1);
+    }
+
+    pub fn get_commodities(&self) -> ComHand {
+        self.commodities
+    }
+
+    pub fn add_commodities(&mut self, new: ComHand) {
+        self.commodities.add(new);
+    }
+
+    pub fn add_commodity(&mut self, new: Commodity) {
+        self.commodities.add_card(new);
+    }
+
+    pub fn discard_commodity(&mut self, lost: Commodity) {
+        self.commodities[lost] -= 1;
+    }
+
+    pub fn get_improvement(&self, track: Improvement) -> usize {
+        self.improvements[track.index()]
+    }
+
+    /// The next level of a track costs as many commodities as the level
+    /// itself, less `discount`
+    pub fn can_improve_for(&self, track: Improvement, discount: usize) -> bool {
+        let level = self.get_improvement(track);
+        level < MAX_IMPROVEMENT && self.commodities[track.commodity()] + discount > level
+    }
+
+    pub fn improve(&mut self, track: Improvement) {
+        self.improve_for(track, 0);
+    }
+
+    pub fn improve_for(&mut self, track: Improvement, discount: usize) {
+        let level = self.get_improvement(track);
+        self.commodities[track.commodity()] -= (level + 1).saturating_sub(discount);
+        self.improvements[track.index()] += 1;
+    }
+
+    pub fn add_metropolis(&mut self) {
+        self.metropolises += 1;
+    }
+
+    pub fn remove_metropolis(&mut self) {
+        self.metropolises -= 1;
+    }
+
+    /// Progress cards in hand and how many of each, in `PROGRESS_CARDS` order
+    pub fn get_progress_counts(&self) -> Vec<(ProgressCard, usize)> {
+        PROGRESS_CARDS.iter()
+            .map(|&card| (card, self.progress_cards.iter().filter(|&&c| c == card).count()))
+            .filter(|&(_, count)| count > 0)
+            .collect()
+    }
+
+    pub fn can_draw_progress_card(&self) -> bool {
+        self.progress_cards.len() < MAX_PROGRESS_CARDS
+    }
+
+    pub fn add_progress_card(&mut self, card: ProgressCard) {
+        self.progress_cards.push(card);
+    }
+
+    pub fn play_progress_card(&mut self, card: ProgressCard) {
+        let idx = self.progress_cards.iter().position(|&c| c == card).unwrap();
+        self.progress_cards.remove(idx);
+    }
+
+    pub fn has_progress_card(&self, card: ProgressCard) -> bool {
+        self.progress_cards.contains(&card)
+    }
+
+    pub fn has_knight_piece(&self, level: usize) -> bool {
+        self.knight_pool[level - 1] > 0
+    }
+
+    pub fn can_build_knight(&self) -> bool {
+        self.hand.can_discard(KNIGHT_HAND) && self.has_knight_piece(1)
+    }
+
+    pub fn can_activate_knight(&self) -> bool {
+        self.hand.can_discard(ACTIVATE_HAND)
+    }
+
+    pub fn can_promote_knight(&self) -> bool {
+        self.hand.can_discard(KNIGHT_HAND)
+    }
+
+    pub fn build_knight(&mut self) {
+        self.hand.discard(KNIGHT_HAND);
+        self.knight_pool[0] -= 1;
+    }
+
+    /// Places a knight piece of `level` without paying for it
+    pub fn place_free_knight(&mut self, level: usize) {
+        self.knight_pool[level - 1] -= 1;
+    }
+
+    /// Takes back a knight removed from the board
+    pub fn return_knight(&mut self, level: usize) {
+        self.knight_pool[level - 1] += 1;
+    }
+
+    pub fn can_build_wall(&self) -> bool {
+        self.hand.can_discard(WALL_HAND)
+    }
+
+    pub fn build_wall(&mut self) {
+        self.hand.discard(WALL_HAND);
+    }
+
+    pub fn activate_knight(&mut self) {
+        self.hand.discard(ACTIVATE_HAND);
+    }
+
+    pub fn get_card_counts(&self) -> CardCounts {
+        self.hidden.unwrap_or(CardCounts {
+            resources: self.hand.size(),
+            dvs: self.get_combined_dvs().size(),
+            commodities: self.commodities.size(),
+            progress_cards: self.progress_cards.len(),
+        })
+    }
+
+    /// The player as the other seats see them: their cards are gone,
+    /// and only how many of each kind they held is left
+    pub fn face_down(&self) -> Player {
+        self.face_down_showing(false, false)
+    }
+
+    /// Face down, but for the resources or progress cards another
+    /// player's progress card lets them look through
+    pub fn face_down_showing(&self, hand: bool, progress_cards: bool) -> Player {
+        let mut player = self.clone();
+        if !hand {
+            player.hand = ResHand::new();
+        }
+        player.dvs = DVHand::new();
+        player.new_dvs = DVHand::new();
+        player.commodities = ComHand::new();
+        if !progress_cards {
+            player.progress_cards = Vec::new();
+        }
+        player.hidden = Some(self.get_card_counts());
+        player
+    }
+
+    /// Everything about the player on one line, in the order `parse` reads it.
+    /// A player seen face down has a `hidden` count in place of their cards,
+    /// followed by whichever of them are shown.
+    pub fn to_text(&self) -> String {
+        let numbers = |values: &[usize]| values.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(" ");
+        let progress: Vec<String> = self.progress_cards.iter().map(|card| card.into_label()).collect();
+        let (cards, commodities, progress) = match self.hidden {
+            Some(counts) => (
+                format!(
+                    "hidden {} {} {} {}{}", counts.resources, counts.dvs, counts.commodities, counts.progress_cards,
+                    if self.hand.size() > 0 { format!(" hand {}", numbers(&self.hand.0)) } else { String::new() }
+                ),
+                String::new(),
+                if progress.is_empty() { String::new() } else { format!(" progress {}", progress.join(" ")) }
+            ),
+            None => (
+                format!("hand {} dvs {} new_dvs {}", numbers(&self.hand.0), numbers(&self.dvs.0), numbers(&self.new_dvs.0)),
+                format!("commodities {} ", numbers(&self.commodities.0)),
+                format!(" progress {}", progress.join(" "))
+            )
+        };
+        let text = format!(
+            "{:?} {} vps {} {} {cards} knights {} awards {} {} pools {} {} {} {} \
+            {commodities}improvements {} knight_pool {} metropolises {}{progress}",
+            self.color,
+            if self.is_human {"human"} else {"cpu"},
+            self.base_vps,
+            self.bonus_vps,
+            self.knights,
+            self.largest_army as usize,
+            self.longest_road as usize,
+            self.road_pool,
+            self.ship_pool,
+            self.settlement_pool,
+            self.city_pool,
+            numbers(&self.improvements),
+            numbers(&self.knight_pool),
+            self.metropolises
+        );
+        text.trim_end().to_string()
+    }
+
+    pub fn parse(text: &str) -> Result<Player, String> {
+        let mut tokens = text.split_whitespace();
+        let name = tokens.next().unwrap_or("");
+        let color = PlayerColor::from_name(name).ok_or(format!("unknown color '{name}'"))?;
+        let is_human = match tokens.next() {
+            Some("human") => true,
+            Some("cpu") => false,
+            _ => return Err(String::from("expected 'human' or 'cpu'"))
+        };
+
+        let mut player = Player::new(color, is_human);
+        [player.base_vps, player.bonus_vps] = take_numbers(&mut tokens, "vps")?;
+        let face_down = tokens.clone().next() == Some("hidden");
+        if face_down {
+            let [resources, dvs, commodities, progress_cards] = take_numbers(&mut tokens, "hidden")?;
+            player.hidden = Some(CardCounts { resources, dvs, commodities, progress_cards });
+            if tokens.clone().next() == Some("hand") {
+                player.hand = ResHand(take_numbers(&mut tokens, "hand")?);
+            }
+        } else {
+            player.hand = ResHand(take_numbers(&mut tokens, "hand")?);
+            player.dvs = DVHand(take_numbers(&mut tokens, "dvs")?);
+            player.new_dvs = DVHand(take_numbers(&mut tokens, "new_dvs")?);
+        }
+        [player.knights] = take_numbers(&mut tokens, "knights")?;
+        let [largest_army, longest_road] = take_numbers(&mut tokens, "awards")?;
+        player.largest_army = largest_army == 1;
+        player.longest_road = longest_road == 1;
+        [player.road_pool, player.ship_pool, player.settlement_pool, player.city_pool] = take_numbers(&mut tokens, "pools")?;
+        if !face_down {
+            player.commodities = ComHand(take_numbers(&mut tokens, "commodities")?);
+        }
+        player.improvements = take_numbers(&mut tokens, "improvements")?;
+        player.knight_pool = take_numbers(&mut tokens, "knight_pool")?;
+        [player.metropolises] = take_numbers(&mut tokens, "metropolises")?;
+        if face_down {
+            match tokens.next() {
+                None => return Ok(player),
+                Some("progress") => {},
+                Some(token) => return Err(format!("unexpected '{token}' after a hidden hand"))
+            }
+        } else {
+            take_numbers::<0>(&mut tokens, "progress")?;
+        }
+        for label in tokens {
+            player.progress_cards.push(ProgressCard::from_label(label).ok_or(format!("unknown progress card '{label}'"))?);
+        }
+        Ok(player)
+    }
+
+    /// Swaps a knight piece of `level` for one a level higher
+    pub fn promote_knight(&mut self, level: usize, paid: bool) {
+        if paid {
+            self.hand.discard(KNIGHT_HAND);
+        }
+        self.knight_pool[level - 1] += 1;
+        self.knight_pool[level] -= 1;
+    }
 }
\ No newline at end of file
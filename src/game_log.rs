@@ -0,0 +1,189 @@
+use crate::game::{DVCard, PlayerColor, ProgressCard, ResHand, Resource, RESOURCES};
+
+/// Longest chat line kept, in characters
+pub const CHAT_MAX_LEN: usize = 120;
+
+/// Something that happened, kept so it can be described to each seat in turn
+#[derive(Debug, Clone)]
+pub enum LogEvent {
+    Rolled(PlayerColor, usize),
+    Built(PlayerColor, &'static str),
+    BoughtDV(PlayerColor),
+    PlayedDV(PlayerColor, DVCard),
+    PlayedProgress(PlayerColor, ProgressCard),
+    MovedRobber(PlayerColor),
+    MovedPirate(PlayerColor),
+    /// The card is only shown to the thief and the victim, and a log kept
+    /// for anyone else doesn't have it
+    Stole { thief: PlayerColor, victim: PlayerColor, card: Option<Resource> },
+    Discarded(PlayerColor, usize),
+    BankTrade { color: PlayerColor, give: ResHand, get: ResHand },
+    Monopolized(PlayerColor, Resource, usize),
+    BarbariansAttacked,
+    PassedTurn(PlayerColor),
+    Won(PlayerColor),
+    /// Something a seat said at the table
+    Chat(PlayerColor, String),
+}
+
+fn name(color: PlayerColor, viewer: Option<PlayerColor>) -> String {
+    if Some(color) == viewer { String::from("you") } else { format!("{:?}", color) }
+}
+
+fn describe_hand(hand: ResHand) -> String {
+    RESOURCES.iter()
+        .filter(|&&res| hand[res] > 0)
+        .map(|&res| format!("{} {:?}", hand[res], res))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// A chat line as it's kept: on one line, trimmed and no longer than
+/// `CHAT_MAX_LEN`, or `None` if that leaves nothing to say
+pub fn clean_chat(text: &str) -> Option<String> {
+    let text: String = text.chars().filter(|c| !c.is_control()).take(CHAT_MAX_LEN).collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The canned line asking the table for a resource
+pub fn canned_chat(res: Resource) -> String {
+    format!("Anyone have {}?", format!("{:?}", res).to_lowercase())
+}
+
+fn capitalize(text: String) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text
+    }
+}
+
+impl LogEvent {
+    /// The entry as `viewer` is allowed to see it. Spectators have no
+    /// seat, and see whatever their copy of the log kept.
+    pub fn describe(&self, viewer: Option<PlayerColor>) -> String {
+        let text = match *self {
+            LogEvent::Rolled(color, sum) => format!("{} rolled {}", name(color, viewer), sum),
+            LogEvent::Built(color, piece) => format!("{} built {}", name(color, viewer), piece),
+            LogEvent::BoughtDV(color) => format!("{} bought a development card", name(color, viewer)),
+            LogEvent::PlayedDV(color, card) => format!("{} played {:?}", name(color, viewer), card),
+            LogEvent::PlayedProgress(color, card) => format!("{} played {:?}", name(color, viewer), card),
+            LogEvent::MovedRobber(color) => format!("{} moved the robber", name(color, viewer)),
+            LogEvent::MovedPirate(color) => format!("{} moved the pirate", name(color, viewer)),
+            LogEvent::Stole { thief, victim, card } => match card {
+                Some(card) if viewer.is_none() || viewer == Some(thief) || viewer == Some(victim) =>
+                    format!("{} stole from {} ({:?})", name(thief, viewer), name(victim, viewer), card),
+                _ => format!("{} stole from {}", name(thief, viewer), name(victim, viewer))
+            },
+            LogEvent::Discarded(color, count) => format!("{} discarded {} cards", name(color, viewer), count),
+            LogEvent::BankTrade { color, give, get } => format!(
+                "{} traded {} for {} with the bank", name(color, viewer), describe_hand(give), describe_hand(get)
+            ),
+            LogEvent::Monopolized(color, res, count) => format!("{} took {} {:?} with Monopoly", name(color, viewer), count, res),
+            LogEvent::BarbariansAttacked => String::from("the barbarians attacked"),
+            LogEvent::PassedTurn(color) => format!("{} ended the turn", name(color, viewer)),
+            LogEvent::Won(color) => format!("{} won the game", name(color, viewer)),
+            LogEvent::Chat(color, ref text) => format!("{}: {}", name(color, viewer), text),
+        };
+        capitalize(text)
+    }
+}
+
+/// Everything that's happened so far, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct GameLog {
+    events: Vec<LogEvent>,
+}
+
+impl GameLog {
+    pub fn add(&mut self, event: LogEvent) {
+        self.events.push(event);
+    }
+
+    pub fn size(&self) -> usize {
+        self.events.len()
+    }
+
+    /// What's been said at the table, oldest first
+    pub fn chat(&self) -> impl Iterator<Item = (PlayerColor, &str)> {
+        self.events.iter().filter_map(|event| match event {
+            LogEvent::Chat(color, text) => Some((*color, text.as_str())),
+            _ => None
+        })
+    }
+
+    /// The log as `viewer` may keep it, without the cards others stole from each other
+    pub fn seen_by(&self, viewer: PlayerColor) -> GameLog {
+        self.hiding_thefts(|thief, victim| viewer != thief && viewer != victim)
+    }
+
+    /// The log as a spectator without the hands may keep it
+    pub fn public(&self) -> GameLog {
+        self.hiding_thefts(|_, _| true)
+    }
+
+    fn hiding_thefts(&self, hidden: impl Fn(PlayerColor, PlayerColor) -> bool) -> GameLog {
+        let events = self.events.iter().map(|event| match *event {
+            LogEvent::Stole { thief, victim, .. } if hidden(thief, victim) =>
+                LogEvent::Stole { thief, victim, card: None },
+            _ => event.clone()
+        }).collect();
+        GameLog { events }
+    }
+
+    /// Up to `count` entries as `viewer` sees them, newest first,
+    /// skipping the newest `scroll`
+    pub fn recent(&self, viewer: Option<PlayerColor>, scroll: usize, count: usize) -> Vec<String> {
+        self.events.iter().rev()
+            .skip(scroll)
+            .take(count)
+            .map(|event| event.describe(viewer))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theft() -> GameLog {
+        let mut log = GameLog::default();
+        log.add(LogEvent::Stole { thief: PlayerColor::Red, victim: PlayerColor::Blue, card: Some(Resource::Ore) });
+        log
+    }
+
+    #[test]
+    fn chat_is_kept_on_one_trimmed_line() {
+        assert_eq!(clean_chat("  any\nwood?  ").as_deref(), Some("anywood?"));
+        assert_eq!(clean_chat(" \t "), None);
+        assert_eq!(clean_chat(&"a".repeat(CHAT_MAX_LEN + 10)).unwrap().len(), CHAT_MAX_LEN);
+        assert_eq!(canned_chat(Resource::Wheat), "Anyone have wheat?");
+
+        let mut log = GameLog::default();
+        log.add(LogEvent::Chat(PlayerColor::Green, String::from("hi")));
+        log.add(LogEvent::PassedTurn(PlayerColor::Green));
+        assert_eq!(log.chat().collect::<Vec<_>>(), [(PlayerColor::Green, "hi")]);
+        assert_eq!(log.recent(Some(PlayerColor::Green), 1, 1), ["You: hi"]);
+    }
+
+    #[test]
+    fn stolen_cards_are_named_to_the_thief_and_victim_only() {
+        let log = theft();
+        assert_eq!(log.recent(Some(PlayerColor::Red), 0, 1), ["You stole from Blue (Ore)"]);
+        assert_eq!(log.recent(Some(PlayerColor::Blue), 0, 1), ["Red stole from you (Ore)"]);
+        assert_eq!(log.recent(Some(PlayerColor::White), 0, 1), ["Red stole from Blue"]);
+    }
+
+    #[test]
+    fn logs_handed_out_keep_the_card_only_for_the_two_involved() {
+        let log = theft();
+        let kept = |log: &GameLog| matches!(log.events[0], LogEvent::Stole { card: Some(Resource::Ore), .. });
+        assert!(kept(&log.seen_by(PlayerColor::Red)));
+        assert!(kept(&log.seen_by(PlayerColor::Blue)));
+        assert!(!kept(&log.seen_by(PlayerColor::White)));
+        assert!(!kept(&log.public()));
+        // A spectator's own copy names nothing it wasn't given
+        assert_eq!(log.public().recent(None, 0, 1), ["Red stole from Blue"]);
+    }
+}
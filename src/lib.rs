@@ -0,0 +1,4504 @@
+use macroquad::{
+    input::{get_char_pressed, is_key_pressed, is_mouse_button_pressed, mouse_position, mouse_wheel, KeyCode, MouseButton},
+    time::get_time,
+    window
+};
+use rand::{rngs::StdRng, seq::IndexedRandom, Rng, SeedableRng};
+
+mod bot;
+mod cli;
+mod client;
+mod correspondence;
+mod dice;
+mod game;
+mod game_log;
+mod moves;
+mod new_game;
+mod notation;
+mod player_view;
+mod render;
+mod rules;
+mod save;
+mod scenario;
+mod screen_coords;
+pub mod server;
+mod stats;
+mod svg;
+mod tui;
+
+use crate::bot::Bot;
+use crate::cli::{Options, USAGE};
+use crate::client::{Connection, Message, Request};
+use crate::correspondence::Correspondence;
+use crate::dice::*;
+use crate::game::*;
+use crate::game_log::{canned_chat, clean_chat, GameLog, LogEvent, CHAT_MAX_LEN};
+use crate::moves::Move;
+use crate::new_game::{find_scenarios, BoardChoice, Difficulty, NewGame, SeatKind, MAX_SEATS, SCENARIOS_DIR};
+use crate::player_view::PlayerView;
+use crate::render::*;
+use crate::rules::*;
+use crate::scenario::Scenario;
+use crate::save::{RECORDED_GAME_PATH, SAVED_GAME_PATH};
+use crate::screen_coords::{LobbyCoords, MainMenuCoords, RulesCoords, ScreenCoords};
+use crate::server::{RoomSeat, RoomSummary};
+use crate::stats::Stats;
+
+#[derive(Clone)]
+pub struct SetupState {
+    num_players: usize,
+    ruleset: Ruleset,
+    rules: RuleConfig,
+    island_bonus: usize,
+    board: Board,
+    players: Vec<Player>,
+    progress_decks: Vec<Vec<ProgressCard>>,
+    current_player: usize,
+    all_placed_once: bool,
+    settlement: Option<[usize; 3]>,
+    finished: bool,
+    hot_seat: bool,
+    /// Who plays each CPU seat; `None` for the humans
+    bots: Vec<Option<Bot>>,
+    /// Only ever has chat in it, which carries on into the game's log
+    log: GameLog,
+}
+
+/// Random colors in a random seat order
+fn random_players<R: Rng + ?Sized>(num_humans: usize, num_cpus: usize, rng: &mut R) -> Vec<Player> {
+    PLAYER_COLORS
+        .iter().copied()
+        .enumerate().collect::<Vec<(usize, PlayerColor)>>()
+        .choose_multiple(rng, num_humans + num_cpus)
+        .map(|&(i, pc)| Player::new(pc, i < num_humans))
+        .collect()
+}
+
+impl SetupState {
+    /// The table picked on the start screen, dealt from its seed
+    fn from_new_game(new_game: &NewGame, rng: &mut StdRng) -> Result<SetupState, String> {
+        let rules = new_game.get_rules();
+        let players = new_game.seats.iter()
+            .map(|seat| Player::new(seat.color, seat.kind == SeatKind::Human))
+            .collect();
+        let mut state = match &new_game.board {
+            BoardChoice::Scenario(path) => {
+                let scenario = Scenario::load(path)?;
+                SetupState::with_board(scenario.board, Ruleset::Seafarers, rules, scenario.island_bonus, players, rng)
+            },
+            _ => {
+                let board = Board::new(new_game.seats.len(), rng);
+                SetupState::with_board(board, new_game.get_ruleset(), rules, 0, players, rng)
+            }
+        };
+        state.hot_seat = new_game.num_humans() > 1;
+        state.bots = new_game.seats.iter()
+            .map(|seat| match seat.kind {
+                SeatKind::Human => None,
+                SeatKind::Cpu(difficulty) => Some(Bot::new(difficulty))
+            })
+            .collect();
+        Ok(state)
+    }
+
+    fn with_board<R: Rng + ?Sized>(mut board: Board, ruleset: Ruleset, rules: RuleConfig, island_bonus: usize, players: Vec<Player>, rng: &mut R) -> SetupState {
+        let num_players = players.len();
+        let bots = players.iter()
+            .map(|player| (!player.is_human()).then_some(Bot::new(Difficulty::Normal)))
+            .collect();
+        board.bank = rules.get_bank();
+        board.dv_bank = rules.dv_bank;
+
+        let progress_decks = if ruleset == Ruleset::CitiesKnights {
+            IMPROVEMENTS.iter().map(|&track| progress_deck(track, rng)).collect()
+        } else {
+            vec![Vec::new(); IMPROVEMENTS.len()]
+        };
+
+        SetupState {
+            num_players,
+            ruleset,
+            rules,
+            island_bonus,
+            board,
+            players,
+            progress_decks,
+            current_player: 0,
+            all_placed_once: false,
+            settlement: None,
+            finished: false,
+            hot_seat: false,
+            bots,
+            log: GameLog::default(),
+        }
+    }
+
+    fn get_current_color(&self) -> PlayerColor {
+        self.players[self.current_player].get_color()
+    }
+
+    fn get_current_player(&self) -> &Player {
+        &self.players[self.current_player]
+    }
+
+    fn get_current_player_mut(&mut self) -> &mut Player {
+        &mut self.players[self.current_player]
+    }
+
+    fn is_players_turn(&self, color: PlayerColor) -> bool {
+        self.get_current_color() == color
+    }
+
+    // fn can_place_road(&self, edge: [usize; 3]) -> bool {
+    //     let settlement_coord = self.settlement.unwrap();
+    //     self.board.can_place_setup_road(edge, settlement_coord)
+    // }
+
+    // fn can_place_settlement(&self, corner: [usize; 3]) -> bool {
+    //     self.board.can_place_setup_settlement(corner)
+    // }
+
+    fn place_road(&mut self, edge: [usize; 3]) {
+        let color = self.get_current_color();
+        self.board.place_setup_road(edge, color);
+        self.get_current_player_mut().place_setup_road();
+    }
+
+    fn place_ship(&mut self, edge: [usize; 3]) {
+        let color = self.get_current_color();
+        self.board.place_setup_ship(edge, color);
+        self.get_current_player_mut().place_setup_ship();
+    }
+
+    fn place_settlement(&mut self, corner: [usize; 3]) {
+        let color = self.get_current_color();
+        // In Cities & Knights the second building placed is a city
+        if self.all_placed_once && self.ruleset == Ruleset::CitiesKnights {
+            self.board.place_setup_city(corner, color);
+            self.get_current_player_mut().place_setup_city();
+        } else {
+            self.board.place_setup_settlement(corner, color);
+            self.get_current_player_mut().place_setup_settlement();
+        }
+        self.settlement = Some(corner);
+
+        if self.all_placed_once {
+            let start_hand = self.board.get_starting_resources(corner);
+            self.get_current_player_mut().get_cards(start_hand);
+        }
+    }
+
+    fn advance_turn(&mut self) {
+        if self.all_placed_once {
+            if self.current_player == 0 {
+                self.finished = true;
+            }
+            else {
+                self.current_player -= 1;
+            }
+        }
+        else {
+            if self.current_player == self.num_players - 1 {
+                self.all_placed_once = true;
+            }
+            else {
+                self.current_player += 1;
+            }
+        }
+        self.settlement = None;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuButton {
+    BuyDV,
+    Knight,
+    ActivateKnight,
+    PromoteKnight,
+    ChaseRobber,
+    Road,
+    Ship,
+    MoveShip,
+    Settlement,
+    City,
+    Wall,
+    Improve(Improvement),
+    RecordDV(DVCard),
+    Pass,
+}
+
+impl MenuButton {
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuButton::BuyDV => "Devel",
+            MenuButton::Knight => "Kngt",
+            MenuButton::ActivateKnight => "Actv",
+            MenuButton::PromoteKnight => "Prmt",
+            MenuButton::ChaseRobber => "Chase",
+            MenuButton::Road => "Road",
+            MenuButton::Ship => "Ship",
+            MenuButton::MoveShip => "Move",
+            MenuButton::Settlement => "Settl",
+            MenuButton::City => "City",
+            MenuButton::Wall => "Wall",
+            MenuButton::Improve(Improvement::Trade) => "Trd",
+            MenuButton::Improve(Improvement::Politics) => "Pol",
+            MenuButton::Improve(Improvement::Science) => "Sci",
+            MenuButton::RecordDV(DVCard::Knight) => "Kngt",
+            MenuButton::RecordDV(DVCard::RoadBuilding) => "Roads",
+            MenuButton::RecordDV(DVCard::YearOfPlenty) => "YoP",
+            MenuButton::RecordDV(DVCard::Monopoly) => "Mono",
+            MenuButton::RecordDV(DVCard::VictoryPoint) => "VP",
+            MenuButton::Pass => "Pass",
+        }
+    }
+}
+
+pub fn menu_buttons(ruleset: Ruleset) -> Vec<MenuButton> {
+    match ruleset {
+        Ruleset::Base => vec![
+            MenuButton::BuyDV, MenuButton::Road, MenuButton::Settlement, MenuButton::City, MenuButton::Pass
+        ],
+        Ruleset::Seafarers => vec![
+            MenuButton::BuyDV, MenuButton::Road, MenuButton::Ship, MenuButton::MoveShip,
+            MenuButton::Settlement, MenuButton::City, MenuButton::Pass
+        ],
+        Ruleset::CitiesKnights => vec![
+            MenuButton::Knight, MenuButton::ActivateKnight, MenuButton::PromoteKnight, MenuButton::ChaseRobber,
+            MenuButton::Road, MenuButton::Settlement, MenuButton::City, MenuButton::Wall,
+            MenuButton::Improve(Improvement::Trade), MenuButton::Improve(Improvement::Politics),
+            MenuButton::Improve(Improvement::Science), MenuButton::Pass
+        ],
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Action {
+    Idling,
+    Discarding,
+    ChoosingGold,
+    MovingRobber,
+    ChoosingVictim,
+    ChoosingPirateVictim,
+    BuildingRoad,
+    BuildingShip,
+    MovingShip(Option<[usize; 3]>),
+    BuildingSettlement,
+    BuildingCity,
+    BuildingKnight,
+    ActivatingKnight,
+    /// Paid promotions carry `None`, the Smith's free ones how many are left
+    PromotingKnight(Option<usize>),
+    ChasingRobber,
+    RoadBuilding(bool),
+    /// Companion mode: which DV card came off the real deck
+    RecordingDV,
+    BuildingWall,
+    /// Picking what a progress card is played on. The Inventor keeps
+    /// its first hex here, and the Alchemist the faces picked so far.
+    Targeting(ProgressCard, Option<[usize; 2]>),
+    /// Wedding guests each give the turn player two resources
+    Giving,
+    /// Saboteur victims each discard half their resources
+    Sabotaged,
+    /// Commercial Harbor: the turn player offers this player a resource...
+    HarborOffering(PlayerColor),
+    /// ...and they hand a commodity back
+    HarborReturning,
+    /// Master Merchant: picking two of this player's resources
+    Taking(PlayerColor),
+    /// Spy: picking one of this player's progress cards
+    Spying(PlayerColor),
+    /// Deserter: the victim picks a knight to lose...
+    Deserting,
+    /// ...and the turn player places one of its level
+    PlacingDeserter(usize),
+    /// Intrigue: the knight's owner moves it away from the corner
+    Displaced(Knight, [usize; 3]),
+}
+
+#[derive(Clone)]
+pub enum RngAction {
+    RollingDice,
+    /// The Alchemist's dice, with only the event die left to chance
+    Alchemy([usize; 2]),
+    Stealing(PlayerColor),
+    /// Takes that many random cards from each listed player
+    Collecting(Vec<(PlayerColor, usize)>),
+    BuyingDV,
+}
+
+#[derive(Clone, Copy)]
+pub enum Selector {
+    Discarding(ResHand),
+    ChoosingGold(ResHand),
+    Trading(ResHand, ResHand),
+    Yopping(ResHand),
+    Monopolizing(ResHand),
+    ResourceMonopoly(ResHand),
+    MerchantFleet(ResHand),
+    Giving(ResHand),
+    Taking(ResHand),
+}
+
+impl Selector {
+    pub fn get_bottom(&self) -> ResHand {
+        match self {
+            Selector::Discarding(hand) => *hand,
+            Selector::ChoosingGold(hand) => *hand,
+            Selector::Trading(give, _) => *give,
+            Selector::Yopping(hand) => *hand,
+            Selector::Monopolizing(hand) => *hand,
+            Selector::ResourceMonopoly(hand) => *hand,
+            Selector::MerchantFleet(hand) => *hand,
+            Selector::Giving(hand) => *hand,
+            Selector::Taking(hand) => *hand,
+        }
+    }
+
+    pub fn get_top(&self) -> Option<ResHand> {
+        match self {
+            Selector::Trading(_, get) => Some(*get),
+            _ => None
+        }
+    }
+
+    pub fn get_bottom_mut(&mut self) -> &mut ResHand {
+        match self {
+            Selector::Discarding(hand) => hand,
+            Selector::ChoosingGold(hand) => hand,
+            Selector::Trading(give, _) => give,
+            Selector::Yopping(hand) => hand,
+            Selector::Monopolizing(hand) => hand,
+            Selector::ResourceMonopoly(hand) => hand,
+            Selector::MerchantFleet(hand) => hand,
+            Selector::Giving(hand) => hand,
+            Selector::Taking(hand) => hand,
+        }
+    }
+
+    pub fn get_top_mut(&mut self) -> &mut ResHand {
+        match self {
+            Selector::Trading(_, get) => get,
+            _ => panic!("Selector::get_top_mut(): no top")
+        }
+    }
+
+    pub fn add_top_card(&mut self, card: Resource) {
+        self.get_top_mut()[card] += 1;
+    }
+
+    pub fn add_bottom_card(&mut self, card: Resource) {
+        self.get_bottom_mut()[card] += 1;
+    }
+
+    pub fn discard_top_card(&mut self, card: Resource) {
+        self.get_top_mut()[card] -= 1;
+    }
+
+    pub fn discard_bottom_card(&mut self, card: Resource) {
+        self.get_bottom_mut()[card] -= 1;
+    }
+}
+
+pub struct GameState {
+    num_players: usize,
+    ruleset: Ruleset,
+    rules: RuleConfig,
+    dice: Box<dyn DiceSource>,
+    /// Tracking a game played on a real board
+    companion: bool,
+    /// Several humans sharing one screen
+    hot_seat: bool,
+    bots: Vec<Option<Bot>>,
+    typed_dice: Vec<usize>,
+    round: usize,
+    winner: Option<PlayerColor>,
+    stats: Stats,
+    log: GameLog,
+    island_bonus: usize,
+    home_islands: Vec<usize>,
+    settled_islands: Vec<(PlayerColor, usize)>,
+    board: Board,
+    players: Vec<Player>,
+    largest_army: Option<PlayerColor>,
+    largest_army_size: usize,
+    longest_road: Option<PlayerColor>,
+    longest_road_size: usize,
+    current_player: usize,
+    turn_player: usize,
+    roll: Option<[usize; 2]>,
+    played_dv: bool,
+    new_ships: Vec<[usize; 3]>,
+    moved_ship: bool,
+    gold_owed: Vec<usize>,
+    event: Option<EventDie>,
+    barbarians: usize,
+    barbarians_attacked: bool,
+    metropolises: [Option<(PlayerColor, [usize; 3])>; 3],
+    progress_decks: Vec<Vec<ProgressCard>>,
+    /// The resource the Merchant Fleet trades two for one this turn
+    merchant_fleet: Option<Resource>,
+    selector: Option<Selector>,
+    offered_trades: Vec<(ResHand, ResHand)>,
+    special_building: bool,
+    action: Action,
+    rng_action: Option<RngAction>,
+}
+
+impl From<SetupState> for GameState {
+    fn from(setup_state: SetupState) -> Self {
+        // Every island somebody started on is a home island
+        let board = &setup_state.board;
+        let mut home_islands: Vec<usize> = board.layout.corner_coords.iter()
+            .filter(|&&corner| board.structure_exists(corner))
+            .filter_map(|&corner| board.corner_island(corner))
+            .collect();
+        home_islands.sort();
+        home_islands.dedup();
+
+        let mut state = GameState {
+            num_players: setup_state.num_players,
+            ruleset: setup_state.ruleset,
+            rules: setup_state.rules,
+            dice: new_dice_source(setup_state.rules.event_deck),
+            companion: false,
+            hot_seat: setup_state.hot_seat,
+            bots: setup_state.bots,
+            typed_dice: Vec::with_capacity(2),
+            round: 0,
+            winner: None,
+            stats: Stats::default(),
+            log: setup_state.log,
+            island_bonus: setup_state.island_bonus,
+            home_islands,
+            settled_islands: Vec::new(),
+            board: setup_state.board,
+            players: setup_state.players,
+            largest_army: None,
+            largest_army_size: 2,
+            longest_road: None,
+            longest_road_size: 4,
+            current_player: 0,
+            turn_player: 0,
+            roll: None,
+            played_dv: false,
+            new_ships: Vec::new(),
+            moved_ship: false,
+            gold_owed: vec![0; setup_state.num_players],
+            event: None,
+            barbarians: 0,
+            barbarians_attacked: false,
+            metropolises: [None; 3],
+            progress_decks: setup_state.progress_decks,
+            merchant_fleet: None,
+            selector: None,
+            offered_trades: Vec::with_capacity(3),
+            special_building: false,
+            action: Action::Idling,
+            rng_action: None,
+        };
+        state.update_longest_road();
+        state
+    }
+}
+
+impl GameState {
+    // fn new<R: Rng + ?Sized>(num_humans: usize, num_cpus: usize, rng: &mut R) -> GameState {
+    //     let num_players = num_humans + num_cpus;
+
+    //     let board = Board::new(num_players, rng);
+    //     let players = PLAYER_COLORS
+    //         .iter().copied()
+    //         .enumerate().collect::<Vec<(usize, PlayerColor)>>()
+    //         .choose_multiple(rng, num_players)
+    //         .map(|&(i, pc)| Player::new(pc, i < num_humans))
+    //         .collect();
+
+    //     GameState {
+    //         num_players,
+    //         board,
+    //         players,
+    //         largest_army: None,
+    //         largest_army_size: 2,
+    //         longest_road: None,
+    //         longest_road_size: 4,
+    //         current_player: 0,
+    //         turn_player: 0,
+    //         action: Action::Idling,
+    //         roll: None,
+    //         played_dv: false,
+    //         selector: None,
+    //         offered_trades: Vec::with_capacity(3),
+    //         trade_responses: Vec::with_capacity(3),
+    //         rng_action: None
+    //     }
+    // }
+
+    fn get_current_color(&self) -> PlayerColor {
+        self.get_current_player().get_color()
+    }
+
+    fn get_current_player(&self) -> &Player {
+        &self.players[self.current_player]
+    }
+    
+    fn get_current_player_mut(&mut self) -> &mut Player {
+        &mut self.players[self.current_player]
+    }
+
+    fn get_player(&self, color: PlayerColor) -> Option<&Player> {
+        self.players.iter().find(|player| player.is_color(color))
+    }
+
+    fn get_player_mut(&mut self, color: PlayerColor) -> Option<&mut Player> {
+        self.players.iter_mut().find(|player| player.is_color(color))
+    }
+
+    fn get_order(&self) -> Vec<PlayerColor> {
+        self.players.iter().map(|player| player.get_color()).collect()
+    }
+
+    fn is_players_turn(&self, color: PlayerColor) -> bool {
+        self.get_current_color() == color
+    }
+
+    fn can_buy_dv(&self) -> bool {
+        (self.get_current_player().can_buy_dv() || self.companion)
+        && self.board.can_draw_dv_card()
+    }
+
+    fn can_build_road(&self) -> bool {
+        let player = self.get_current_player();
+        (player.can_build_road() || self.companion && player.has_road_piece())
+        && self.board.can_place_any_road(self.get_current_color()) 
+    }
+
+    fn can_build_ship(&self) -> bool {
+        self.ruleset == Ruleset::Seafarers
+        && self.get_current_player().can_build_ship()
+        && self.board.can_place_any_ship(self.get_current_color())
+    }
+
+    fn can_move_ship_from(&self, edge: [usize; 3]) -> bool {
+        self.board.is_open_ship(edge, self.get_current_color())
+        && !self.new_ships.contains(&edge)
+    }
+
+    fn can_move_ship(&self) -> bool {
+        self.ruleset == Ruleset::Seafarers
+        && !self.moved_ship
+        && self.board.layout.edge_coords.iter().any(|&edge| self.can_move_ship_from(edge))
+    }
+
+    fn can_build_settlement(&self) -> bool {
+        let player = self.get_current_player();
+        (player.can_build_settlement() || self.companion && player.has_settlement_piece())
+        && self.board.can_place_any_settlement(self.get_current_color())
+    }
+
+    fn can_build_knight(&self) -> bool {
+        let color = self.get_current_color();
+        self.get_current_player().can_build_knight()
+        && self.board.layout.corner_coords.iter().any(|&corner| self.board.can_place_knight(corner, color))
+    }
+
+    fn can_activate_knight(&self) -> bool {
+        let color = self.get_current_color();
+        self.get_current_player().can_activate_knight()
+        && self.board.layout.corner_coords.iter().any(|&corner| self.board.can_activate_knight(corner, color))
+    }
+
+    /// Knights can only be made mighty once the player has a fortress
+    fn max_knight_level(&self) -> usize {
+        if self.get_current_player().get_improvement(Improvement::Politics) >= 3 { MAX_KNIGHT_LEVEL } else { MAX_KNIGHT_LEVEL - 1 }
+    }
+
+    fn can_promote_knight_at(&self, corner: [usize; 3]) -> bool {
+        self.board.can_promote_knight(corner, self.get_current_color(), self.max_knight_level())
+        && self.get_current_player().has_knight_piece(self.board.get_knight_level(corner) + 1)
+    }
+
+    fn can_promote_any_knight(&self) -> bool {
+        self.board.layout.corner_coords.iter().any(|&corner| self.can_promote_knight_at(corner))
+    }
+
+    fn can_promote_knight(&self) -> bool {
+        self.get_current_player().can_promote_knight() && self.can_promote_any_knight()
+    }
+
+    fn can_chase_robber(&self) -> bool {
+        let color = self.get_current_color();
+        self.robber_is_active()
+        && self.board.layout.corner_coords.iter().any(|&corner| self.board.can_chase_robber(corner, color))
+    }
+
+    fn can_improve(&self, track: Improvement) -> bool {
+        self.can_improve_for(track, 0)
+    }
+
+    /// The Crane takes a commodity off the price
+    fn can_improve_for(&self, track: Improvement, discount: usize) -> bool {
+        self.get_current_player().can_improve_for(track, discount)
+        && !self.board.get_cities(self.get_current_color()).is_empty()
+    }
+
+    fn can_build_wall(&self) -> bool {
+        self.get_current_player().can_build_wall() && self.board.can_place_any_wall(self.get_current_color())
+    }
+
+    fn can_build_city(&self) -> bool {
+        let player = self.get_current_player();
+        (player.can_build_city() || self.companion && player.has_city_piece())
+        && self.board.can_place_any_city(self.get_current_color())
+    }
+
+    fn get_menu(&self) -> Vec<MenuButton> {
+        match self.action {
+            Action::RecordingDV => DV_CARDS.iter().map(|&card| MenuButton::RecordDV(card)).collect(),
+            _ => menu_buttons(self.ruleset)
+        }
+    }
+
+    fn menu_position(&self, button: MenuButton) -> usize {
+        self.get_menu().iter().position(|&b| b == button).unwrap()
+    }
+
+    fn get_available_actions(&self, color: PlayerColor) -> Vec<bool> {
+        let menu = self.get_menu();
+        if color != self.get_current_color() || self.roll.is_none() {
+            return vec![false; menu.len()];
+        }
+        menu.into_iter().map(|button| match self.action {
+            Action::Idling => match button {
+                MenuButton::BuyDV => self.can_buy_dv(),
+                MenuButton::Knight => self.can_build_knight(),
+                MenuButton::ActivateKnight => self.can_activate_knight(),
+                MenuButton::PromoteKnight => self.can_promote_knight(),
+                MenuButton::ChaseRobber => self.can_chase_robber(),
+                MenuButton::Road => self.can_build_road(),
+                MenuButton::Ship => self.can_build_ship(),
+                MenuButton::MoveShip => self.can_move_ship(),
+                MenuButton::Settlement => self.can_build_settlement(),
+                MenuButton::City => self.can_build_city(),
+                MenuButton::Wall => self.can_build_wall(),
+                MenuButton::Improve(track) => self.can_improve(track),
+                MenuButton::RecordDV(_) => false,
+                MenuButton::Pass => true,
+            },
+            Action::RecordingDV => match button {
+                MenuButton::RecordDV(card) => self.board.can_take_dv_card(card),
+                _ => false
+            },
+            Action::BuildingRoad => button == MenuButton::Road,
+            Action::BuildingShip => button == MenuButton::Ship,
+            Action::MovingShip(_) => button == MenuButton::MoveShip,
+            Action::BuildingSettlement => button == MenuButton::Settlement,
+            Action::BuildingCity => button == MenuButton::City,
+            Action::BuildingKnight => button == MenuButton::Knight,
+            Action::ActivatingKnight => button == MenuButton::ActivateKnight,
+            Action::PromotingKnight(_) => button == MenuButton::PromoteKnight,
+            Action::ChasingRobber => button == MenuButton::ChaseRobber,
+            Action::BuildingWall => button == MenuButton::Wall,
+            Action::Targeting(ProgressCard::Crane, _) => match button {
+                MenuButton::Improve(track) => self.can_improve_for(track, 1),
+                _ => false
+            },
+            Action::Targeting(ProgressCard::TradeMonopoly, _) => matches!(button, MenuButton::Improve(_)),
+            _ => false
+        }).collect()
+    }
+
+    fn roll_dice<R: Rng>(&mut self, rng: &mut R) -> usize {
+        let dice = loop {
+            let dice = self.dice.roll(rng);
+            if !self.is_barred_roll(dice) {
+                break dice;
+            }
+        };
+        self.set_dice(dice, rng)
+    }
+
+    /// Sevens can be ruled out for the first rounds
+    fn is_barred_roll(&self, dice: [usize; 2]) -> bool {
+        dice[0] + dice[1] == 7 && self.round < self.rules.no_sevens_rounds && !self.companion
+    }
+
+    fn set_dice<R: Rng>(&mut self, dice: [usize; 2], rng: &mut R) -> usize {
+        self.roll = Some(dice);
+        if self.ruleset == Ruleset::CitiesKnights {
+            self.event = Some(roll_event_die(rng));
+        }
+        let sum = self.roll.unwrap()[0] + self.roll.unwrap()[1];
+        self.stats.add_roll(sum);
+        self.log.add(LogEvent::Rolled(self.get_current_color(), sum));
+        sum
+    }
+
+    /// Carries out whatever is waiting on chance: a roll, a theft or a draw
+    fn resolve_rng_action<R: Rng>(&mut self, rng: &mut R) {
+        let Some(rng_action) = &self.rng_action else { return };
+        match rng_action {
+            RngAction::RollingDice => {
+                let sum = self.roll_dice(rng);
+                self.resolve_roll(sum);
+            },
+            &RngAction::Alchemy(dice) => {
+                let sum = self.set_dice(dice, rng);
+                self.resolve_roll(sum);
+            },
+            &RngAction::Stealing(color) => {
+                let stolen = self.get_player_mut(color).unwrap().discard_random_card(rng);
+                if let Some(res) = stolen {
+                    self.get_current_player_mut().get_card(res);
+                    self.stats.get_mut(color).lost_to_robber += 1;
+                    let thief = self.get_current_color();
+                    self.log.add(LogEvent::Stole { thief, victim: color, card: Some(res) });
+                }
+                self.action = Action::Idling;
+            },
+            RngAction::Collecting(victims) => {
+                for (color, count) in victims.clone() {
+                    for _ in 0..count {
+                        let stolen = self.get_player_mut(color).unwrap().discard_random_card(rng);
+                        if let Some(res) = stolen {
+                            self.get_current_player_mut().get_card(res);
+                            let thief = self.get_current_color();
+                            self.log.add(LogEvent::Stole { thief, victim: color, card: Some(res) });
+                        }
+                    }
+                }
+            },
+            RngAction::BuyingDV => {
+                self.buy_dv_card(rng);
+            },
+        }
+        self.rng_action = None;
+    }
+
+    fn resolve_roll(&mut self, sum: usize) {
+        self.resolve_event();
+        if sum != 7 {
+            self.give_resources(sum);
+        }
+        else {
+            if self.someone_must_discard() {
+                self.initiate_discarding();
+            } else {
+                self.start_moving_robber();
+            }
+        }
+    }
+
+    /// Resolves the event die, which comes before production.
+    /// The second die plays the part of the red die.
+    fn resolve_event(&mut self) {
+        match self.event {
+            None => (),
+            Some(EventDie::Barbarians) => {
+                self.barbarians += 1;
+                if self.barbarians == BARBARIAN_TRACK_LEN {
+                    self.barbarian_attack();
+                    self.barbarians = 0;
+                }
+            },
+            Some(EventDie::Gate(track)) => {
+                let red = self.roll.unwrap()[1];
+                for i in 0..self.num_players {
+                    let idx = (self.turn_player + i) % self.num_players;
+                    let level = self.players[idx].get_improvement(track);
+                    if level > 0 && red <= level + 1 {
+                        self.draw_progress_card(idx, track);
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_progress_card(&mut self, idx: usize, track: Improvement) {
+        if !self.players[idx].can_draw_progress_card() {
+            return;
+        }
+        let Some(card) = self.progress_decks[track.index()].pop() else { return };
+        if card.is_vp() {
+            self.players[idx].add_bonus_vps(1);
+        } else {
+            self.players[idx].add_progress_card(card);
+        }
+    }
+
+    /// The barbarians' strength is the number of cities on the board. If the
+    /// active knights hold them off, the strongest defender gets a VP (or on a
+    /// tie, every tied player a progress card). Otherwise the weakest defenders
+    /// each lose a city that isn't a metropolis.
+    fn barbarian_attack(&mut self) {
+        self.barbarians_attacked = true;
+
+        let colors = self.get_order();
+        let strength: usize = colors.iter().map(|&color| self.board.get_cities(color).len()).sum();
+        let defense: Vec<usize> = colors.iter().map(|&color| self.board.get_knight_strength(color)).collect();
+
+        self.log.add(LogEvent::BarbariansAttacked);
+        if defense.iter().sum::<usize>() >= strength {
+            let best = *defense.iter().max().unwrap();
+            let defenders: Vec<usize> = (0..self.num_players).filter(|&idx| best > 0 && defense[idx] == best).collect();
+            if defenders.len() == 1 {
+                self.players[defenders[0]].add_bonus_vps(1);
+            } else {
+                for idx in defenders {
+                    let track = *IMPROVEMENTS.iter()
+                        .max_by_key(|&&track| self.players[idx].get_improvement(track))
+                        .unwrap();
+                    self.draw_progress_card(idx, track);
+                }
+            }
+        } else {
+            let exposed: Vec<usize> = (0..self.num_players)
+                .filter(|&idx| self.get_pillageable_city(colors[idx]).is_some())
+                .collect();
+            if let Some(weakest) = exposed.iter().map(|&idx| defense[idx]).min() {
+                for idx in exposed.into_iter().filter(|&idx| defense[idx] == weakest) {
+                    let city = self.get_pillageable_city(colors[idx]).unwrap();
+                    self.board.pillage_city(city);
+                    self.players[idx].pillage_city();
+                }
+            }
+        }
+
+        self.board.deactivate_all_knights();
+    }
+
+    /// A city that isn't a metropolis, if the player has one
+    fn get_pillageable_city(&self, color: PlayerColor) -> Option<[usize; 3]> {
+        self.board.get_cities(color).into_iter().find(
+            |&city| !self.metropolises.iter().flatten().any(|&(_, metropolis)| metropolis == city)
+        )
+    }
+
+    fn robber_is_active(&self) -> bool {
+        self.ruleset != Ruleset::CitiesKnights || self.barbarians_attacked
+    }
+
+    /// In Cities & Knights the robber stays put until the first barbarian attack
+    fn start_moving_robber(&mut self) {
+        self.action = if self.robber_is_active() { Action::MovingRobber } else { Action::Idling };
+    }
+
+    fn give_resources(&mut self, roll: usize) {
+        if self.ruleset == Ruleset::CitiesKnights {
+            self.give_production(roll);
+            return;
+        }
+
+        let resources = self.board.get_new_resources(self.get_order(), roll);
+        for (player, new) in self.players.iter_mut().zip(resources) {
+            self.stats.get_mut(player.get_color()).produced.add(new);
+            player.get_cards(new);
+        }
+
+        let gold = self.board.get_new_gold(self.get_order(), roll);
+        if gold.iter().any(|&count| count > 0) {
+            self.gold_owed = gold;
+            self.initiate_choosing_gold();
+        }
+    }
+
+    /// Cities & Knights production. Players with an aqueduct who get
+    /// nothing pick a resource of their choice instead.
+    fn give_production(&mut self, roll: usize) {
+        let production = self.board.get_new_production(self.get_order(), roll);
+        let mut aqueduct = vec![0; self.num_players];
+        for ((player, (resources, commodities)), owed) in self.players.iter_mut().zip(production).zip(aqueduct.iter_mut()) {
+            if resources.size() + commodities.size() == 0 && player.get_improvement(Improvement::Science) >= 3 {
+                *owed = 1;
+            }
+            self.stats.get_mut(player.get_color()).produced.add(resources);
+            player.get_cards(resources);
+            player.add_commodities(commodities);
+        }
+
+        if aqueduct.iter().any(|&count| count > 0) {
+            self.gold_owed = aqueduct;
+            self.initiate_choosing_gold();
+        }
+    }
+
+    fn initiate_choosing_gold(&mut self) {
+        self.action = Action::ChoosingGold;
+        self.selector = Some(Selector::ChoosingGold(ResHand::new()));
+        while self.gold_owed[self.current_player] == 0 {
+            self.current_player = (self.current_player + 1) % self.num_players;
+        }
+    }
+
+    fn can_move_robber(&self, hex: [usize; 2]) -> bool {
+        if self.board.is_sea(hex) {
+            self.ruleset == Ruleset::Seafarers && self.board.pirate != Some(hex)
+        } else {
+            hex != self.board.robber
+        }
+    }
+
+    /// Under the friendly robber rule, players with few VPs on show can't be robbed
+    fn is_protected(&self, color: PlayerColor) -> bool {
+        self.rules.friendly_robber
+        && self.get_player(color).unwrap().get_visible_vps() <= FRIENDLY_ROBBER_VPS
+    }
+
+    fn can_rob(&self, color: PlayerColor) -> bool {
+        color != self.get_current_color() && !self.is_protected(color)
+    }
+
+    fn move_robber(&mut self, hex: [usize; 2]) {
+        let color = self.get_current_color();
+        self.stats.get_mut(color).robber_moves += 1;
+        let is_pirate = self.board.is_sea(hex);
+        self.log.add(if is_pirate {LogEvent::MovedPirate(color)} else {LogEvent::MovedRobber(color)});
+        let mut robbable = if is_pirate {
+            self.board.pirate = Some(hex);
+            self.board.get_ship_colors_on_hex(hex)
+        } else {
+            self.board.robber = hex;
+            self.board.get_colors_on_hex(hex)
+        };
+        robbable.retain(|&color| self.can_rob(color));
+
+        if robbable.is_empty() {
+            self.action = Action::Idling;
+        } else if robbable.len() == 1 {
+            self.rng_action = Some(RngAction::Stealing(robbable.into_iter().next().unwrap()));
+            self.action = Action::Idling;
+        } else if is_pirate {
+            self.action = Action::ChoosingPirateVictim;
+        } else {
+            self.action = Action::ChoosingVictim;
+        }
+    }
+
+    fn buy_dv_card<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let dv = self.board.draw_dv_card(rng);
+        self.get_current_player_mut().buy_dv(dv);
+        let color = self.get_current_color();
+        self.stats.get_mut(color).dvs_bought += 1;
+        self.log.add(LogEvent::BoughtDV(color));
+    }
+
+    /// Companion mode never refuses a build for want of cards. The real
+    /// cards came from trades made across the table, so the shortfall is
+    /// added to the tracked hand before paying.
+    fn cover_cost(&mut self, cost: ResHand) {
+        if !self.companion {
+            return;
+        }
+        let hand = self.get_current_player().get_hand();
+        for res in RESOURCES {
+            if hand[res] < cost[res] {
+                self.get_current_player_mut().get_cards(ResHand::from_monopoly(res, cost[res] - hand[res]));
+            }
+        }
+    }
+
+    fn record_dv_card(&mut self, card: DVCard) {
+        self.cover_cost(DV_CARD_HAND);
+        self.board.take_dv_card(card);
+        self.get_current_player_mut().buy_dv(card);
+        let color = self.get_current_color();
+        self.stats.get_mut(color).dvs_bought += 1;
+        self.log.add(LogEvent::BoughtDV(color));
+        self.action = Action::Idling;
+    }
+
+    fn build_road(&mut self, edge: [usize; 3]) {
+        self.cover_cost(ROAD_HAND);
+        let color = self.get_current_color();
+        self.board.place_road(edge, color);
+        self.get_current_player_mut().build_road();
+        self.log.add(LogEvent::Built(color, "a road"));
+        self.update_longest_road();
+    }
+
+    fn build_ship(&mut self, edge: [usize; 3]) {
+        let color = self.get_current_color();
+        self.board.place_ship(edge, color);
+        self.get_current_player_mut().build_ship();
+        self.log.add(LogEvent::Built(color, "a ship"));
+        self.new_ships.push(edge);
+        self.update_longest_road();
+    }
+
+    fn move_ship(&mut self, from: [usize; 3], to: [usize; 3]) {
+        let color = self.get_current_color();
+        self.board.remove_ship(from);
+        self.board.place_setup_ship(to, color);
+        self.moved_ship = true;
+        self.update_longest_road();
+    }
+
+    fn build_settlement(&mut self, corner: [usize; 3]) {
+        self.cover_cost(SETTLEMENT_HAND);
+        let color = self.get_current_color();
+        self.board.place_settlement(corner, color);
+        self.get_current_player_mut().build_settlement();
+        self.log.add(LogEvent::Built(color, "a settlement"));
+        self.claim_island(corner);
+        self.update_longest_road();
+    }
+
+    fn build_knight(&mut self, corner: [usize; 3]) {
+        let color = self.get_current_color();
+        self.board.place_knight(corner, color);
+        self.get_current_player_mut().build_knight();
+        self.log.add(LogEvent::Built(color, "a knight"));
+    }
+
+    fn activate_knight(&mut self, corner: [usize; 3]) {
+        self.board.bank.add(ACTIVATE_HAND);
+        self.board.activate_knight(corner);
+        self.get_current_player_mut().activate_knight();
+    }
+
+    fn promote_knight(&mut self, corner: [usize; 3], paid: bool) {
+        if paid {
+            self.board.bank.add(KNIGHT_HAND);
+        }
+        let level = self.board.get_knight_level(corner);
+        self.board.promote_knight(corner);
+        self.get_current_player_mut().promote_knight(level, paid);
+    }
+
+    fn chase_robber(&mut self, corner: [usize; 3]) {
+        self.board.deactivate_knight(corner);
+        self.action = Action::MovingRobber;
+    }
+
+    fn improve(&mut self, track: Improvement) {
+        self.get_current_player_mut().improve(track);
+        self.update_metropolis(track);
+    }
+
+    /// The first player to reach level 4 of a track gets its metropolis,
+    /// and the first to reach level 5 takes it from a holder still at 4
+    fn update_metropolis(&mut self, track: Improvement) {
+        let color = self.get_current_color();
+        let level = self.get_current_player().get_improvement(track);
+        if level < METROPOLIS_LEVEL {
+            return;
+        }
+
+        let holder = self.metropolises[track.index()];
+        let takes_it = match holder {
+            None => true,
+            Some((holder, _)) => holder != color
+                && level == MAX_IMPROVEMENT
+                && self.get_player(holder).unwrap().get_improvement(track) < MAX_IMPROVEMENT
+        };
+        if !takes_it {
+            return;
+        }
+        let Some(city) = self.get_pillageable_city(color) else { return };
+
+        if let Some((old, _)) = holder {
+            self.get_player_mut(old).unwrap().remove_metropolis();
+        }
+        self.metropolises[track.index()] = Some((color, city));
+        self.get_current_player_mut().add_metropolis();
+    }
+
+    /// Whether the current player can play `card` now, with something for it
+    /// to act on. The Alchemist comes before the roll and the rest after it.
+    fn can_play_progress_card(&self, card: ProgressCard) -> bool {
+        let color = self.get_current_color();
+        let player = self.get_current_player();
+        let layout = &self.board.layout;
+        if !player.has_progress_card(card) || (card == ProgressCard::Alchemist) != self.roll.is_none() {
+            return false;
+        }
+        match card {
+            ProgressCard::Crane => IMPROVEMENTS.iter().any(|&track| self.can_improve_for(track, 1)),
+            ProgressCard::Engineer => self.board.can_place_any_wall(color),
+            ProgressCard::Inventor => layout.hex_coords.iter().filter(|&&hex| self.board.can_swap_number(hex)).count() >= 2,
+            ProgressCard::Medicine =>
+                player.get_hand().can_discard(MEDICINE_HAND) && player.has_city_piece() && self.board.can_place_any_city(color),
+            ProgressCard::RoadBuilding => player.has_road_piece() && self.board.can_place_any_road(color),
+            ProgressCard::Smith => self.can_promote_any_knight(),
+            ProgressCard::CommercialHarbor =>
+                player.get_hand().size() > 0 && self.next_harbor_partner(self.turn_player).is_some(),
+            ProgressCard::MasterMerchant => self.players.iter().any(|p| self.can_take_from(p.get_color())),
+            ProgressCard::Merchant => layout.hex_coords.iter().any(|&hex| self.board.can_place_merchant(hex, color)),
+            ProgressCard::Bishop => self.robber_is_active(),
+            ProgressCard::Deserter => self.players.iter().any(|p| self.can_desert(p.get_color())),
+            ProgressCard::Diplomat => layout.edge_coords.iter().any(|&edge| self.board.is_open_road(edge)),
+            ProgressCard::Intrigue => layout.corner_coords.iter().any(|&corner| self.can_intrigue(corner)),
+            ProgressCard::Saboteur => self.next_saboteur_victim(self.turn_player).is_some(),
+            ProgressCard::Spy => self.players.iter().any(|p| self.can_spy_on(p.get_color())),
+            ProgressCard::Warlord => layout.corner_coords.iter().any(|&corner| self.board.can_activate_knight(corner, color)),
+            ProgressCard::Wedding => self.next_wedding_guest(self.turn_player).is_some(),
+            _ => true
+        }
+    }
+
+    /// The first seat after `idx`, before the turn comes back round, that `owes` something
+    fn next_seat(&self, idx: usize, owes: impl Fn(&Player) -> bool) -> Option<usize> {
+        (idx + 1..).map(|i| i % self.num_players)
+            .take_while(|&i| i != self.turn_player)
+            .find(|&i| owes(&self.players[i]))
+    }
+
+    /// Players with more VPs than the bride and groom bring them a gift
+    fn next_wedding_guest(&self, idx: usize) -> Option<usize> {
+        let vps = self.players[self.turn_player].get_vps();
+        self.next_seat(idx, |player| player.get_vps() > vps && player.get_hand().size() > 0)
+    }
+
+    /// The Saboteur hits players with at least as many VPs as its player
+    fn next_saboteur_victim(&self, idx: usize) -> Option<usize> {
+        let vps = self.players[self.turn_player].get_vps();
+        self.next_seat(idx, |player| player.get_vps() >= vps && player.get_hand().size() >= 2)
+    }
+
+    fn next_harbor_partner(&self, idx: usize) -> Option<usize> {
+        self.next_seat(idx, |player| player.get_commodities().size() > 0)
+    }
+
+    /// Cards given to someone else: two, or all the giver has
+    fn cards_to_give(&self) -> usize {
+        match self.action {
+            Action::HarborOffering(_) => 1,
+            Action::Taking(victim) => self.get_player(victim).unwrap().get_hand().size().min(2),
+            _ => self.get_current_player().get_hand().size().min(2)
+        }
+    }
+
+    /// The Master Merchant takes from a player with more VPs
+    fn can_take_from(&self, victim: PlayerColor) -> bool {
+        let player = self.get_player(victim).unwrap();
+        victim != self.get_current_color()
+        && player.get_vps() > self.get_current_player().get_vps()
+        && player.get_hand().size() > 0
+    }
+
+    fn can_desert(&self, victim: PlayerColor) -> bool {
+        victim != self.get_current_color() && !self.board.get_knights(victim).is_empty()
+    }
+
+    fn can_spy_on(&self, victim: PlayerColor) -> bool {
+        victim != self.get_current_color()
+        && self.get_player(victim).unwrap().get_card_counts().progress_cards > 0
+    }
+
+    /// Intrigue displaces another player's knight from the end of one of its player's roads
+    fn can_intrigue(&self, corner: [usize; 3]) -> bool {
+        let color = self.get_current_color();
+        self.board.get_knight(corner).is_some_and(|knight| knight.color != color)
+        && self.board.touches_road(corner, color)
+    }
+
+    /// Played cards go back under their deck
+    fn discard_progress_card(&mut self, card: ProgressCard) {
+        let color = self.get_current_color();
+        self.get_current_player_mut().play_progress_card(card);
+        self.progress_decks[card.track().index()].insert(0, card);
+        self.log.add(LogEvent::PlayedProgress(color, card));
+    }
+
+    /// The cards played without a choice of their own
+    fn play_progress_card(&mut self, card: ProgressCard) {
+        let color = self.get_current_color();
+        if card != ProgressCard::ResourceMonopoly && card != ProgressCard::MerchantFleet {
+            self.discard_progress_card(card);
+        }
+        match card {
+            ProgressCard::Irrigation | ProgressCard::Mining => {
+                let resource = if card == ProgressCard::Irrigation { Resource::Wheat } else { Resource::Ore };
+                let count = 2 * self.board.count_adjacent_hexes(color, resource);
+                self.get_current_player_mut().get_cards(ResHand::from_monopoly(resource, count));
+            },
+            ProgressCard::RoadBuilding => {
+                self.action = Action::RoadBuilding(false);
+            },
+            ProgressCard::Smith => {
+                self.action = Action::PromotingKnight(Some(2));
+            },
+            ProgressCard::ResourceMonopoly => {
+                self.selector = Some(Selector::ResourceMonopoly(ResHand::new()));
+            },
+            ProgressCard::MerchantFleet => {
+                self.selector = Some(Selector::MerchantFleet(ResHand::new()));
+            },
+            ProgressCard::Warlord => {
+                for corner in self.board.layout.corner_coords.clone() {
+                    if self.board.can_activate_knight(corner, color) {
+                        self.board.activate_knight(corner);
+                    }
+                }
+            },
+            ProgressCard::Wedding => {
+                self.current_player = self.next_wedding_guest(self.turn_player).unwrap();
+                self.action = Action::Giving;
+                self.selector = Some(Selector::Giving(ResHand::new()));
+            },
+            ProgressCard::Saboteur => {
+                self.current_player = self.next_saboteur_victim(self.turn_player).unwrap();
+                self.action = Action::Sabotaged;
+                self.selector = Some(Selector::Discarding(ResHand::new()));
+            },
+            ProgressCard::CommercialHarbor => {
+                let partner = self.next_harbor_partner(self.turn_player).unwrap();
+                self.action = Action::HarborOffering(self.players[partner].get_color());
+                self.selector = Some(Selector::Giving(ResHand::new()));
+            },
+            _ => ()
+        }
+    }
+
+    fn play_alchemist(&mut self, dice: [usize; 2]) {
+        self.discard_progress_card(ProgressCard::Alchemist);
+        self.rng_action = Some(RngAction::Alchemy(dice));
+    }
+
+    fn play_crane(&mut self, track: Improvement) {
+        self.discard_progress_card(ProgressCard::Crane);
+        self.get_current_player_mut().improve_for(track, 1);
+        self.update_metropolis(track);
+    }
+
+    fn play_engineer(&mut self, corner: [usize; 3]) {
+        self.discard_progress_card(ProgressCard::Engineer);
+        self.board.place_free_wall(corner);
+        self.log.add(LogEvent::Built(self.get_current_color(), "a city wall"));
+    }
+
+    fn play_inventor(&mut self, hex1: [usize; 2], hex2: [usize; 2]) {
+        self.discard_progress_card(ProgressCard::Inventor);
+        self.board.swap_numbers(hex1, hex2);
+    }
+
+    fn play_medicine(&mut self, corner: [usize; 3]) {
+        self.discard_progress_card(ProgressCard::Medicine);
+        let color = self.get_current_color();
+        self.board.place_setup_city(corner, color);
+        self.board.bank.add(MEDICINE_HAND);
+        self.get_current_player_mut().build_city_for(MEDICINE_HAND);
+        self.log.add(LogEvent::Built(color, "a city"));
+    }
+
+    /// The merchant is worth a VP to whoever placed it last
+    fn play_merchant(&mut self, hex: [usize; 2]) {
+        self.discard_progress_card(ProgressCard::Merchant);
+        let color = self.get_current_color();
+        if let Some((_, owner)) = self.board.merchant {
+            self.get_player_mut(owner).unwrap().remove_bonus_vps(1);
+        }
+        self.board.merchant = Some((hex, color));
+        self.get_current_player_mut().add_bonus_vps(1);
+    }
+
+    fn play_merchant_fleet(&mut self, card: Resource) {
+        self.discard_progress_card(ProgressCard::MerchantFleet);
+        self.merchant_fleet = Some(card);
+    }
+
+    /// Every opponent gives up one of the named commodity
+    fn play_trade_monopoly(&mut self, card: Commodity) {
+        self.discard_progress_card(ProgressCard::TradeMonopoly);
+        let monopolizer = self.get_current_color();
+        let mut gained = 0;
+        for player in self.players.iter_mut() {
+            if !player.is_color(monopolizer) && player.get_commodities()[card] > 0 {
+                player.discard_commodity(card);
+                gained += 1;
+            }
+        }
+        for _ in 0..gained {
+            self.get_current_player_mut().add_commodity(card);
+        }
+    }
+
+    fn play_master_merchant(&mut self, victim: PlayerColor) {
+        self.discard_progress_card(ProgressCard::MasterMerchant);
+        self.action = Action::Taking(victim);
+        self.selector = Some(Selector::Taking(ResHand::new()));
+    }
+
+    /// The Bishop moves the robber and takes a card from everyone next to it
+    fn play_bishop(&mut self, hex: [usize; 2]) {
+        self.discard_progress_card(ProgressCard::Bishop);
+        let color = self.get_current_color();
+        self.stats.get_mut(color).robber_moves += 1;
+        self.log.add(LogEvent::MovedRobber(color));
+        self.board.robber = hex;
+        let colors = self.board.get_colors_on_hex(hex);
+        let victims: Vec<(PlayerColor, usize)> = self.get_order().into_iter()
+            .filter(|&victim| colors.contains(&victim) && self.can_rob(victim))
+            .map(|victim| (victim, 1))
+            .collect();
+        if !victims.is_empty() {
+            self.rng_action = Some(RngAction::Collecting(victims));
+        }
+    }
+
+    fn play_deserter(&mut self, victim: PlayerColor) {
+        self.discard_progress_card(ProgressCard::Deserter);
+        self.current_player = self.get_order().iter().position(|&c| c == victim).unwrap();
+        self.action = Action::Deserting;
+    }
+
+    /// The removed road goes back to its owner, who may put it down again
+    /// if it was the Diplomat's own
+    fn play_diplomat(&mut self, edge: [usize; 3]) {
+        self.discard_progress_card(ProgressCard::Diplomat);
+        let color = self.get_current_color();
+        let owner = self.board.get_road_color(edge).unwrap();
+        self.board.remove_road(edge);
+        self.get_player_mut(owner).unwrap().return_road();
+        self.update_longest_road();
+        if owner == color && self.board.can_place_any_road(color) {
+            self.action = Action::RoadBuilding(true);
+        }
+    }
+
+    /// The displaced knight's owner moves it next to another of their roads,
+    /// or takes it back if there's nowhere to go
+    fn play_intrigue(&mut self, corner: [usize; 3]) {
+        self.discard_progress_card(ProgressCard::Intrigue);
+        let knight = self.board.remove_knight(corner);
+        let idx = self.get_order().iter().position(|&c| c == knight.color).unwrap();
+        let can_retreat = self.board.layout.corner_coords.iter()
+            .any(|&to| to != corner && self.board.can_place_knight(to, knight.color));
+        if can_retreat {
+            self.current_player = idx;
+            self.action = Action::Displaced(knight, corner);
+        } else {
+            self.players[idx].return_knight(knight.level);
+        }
+    }
+
+    fn play_spy(&mut self, victim: PlayerColor) {
+        self.discard_progress_card(ProgressCard::Spy);
+        self.action = Action::Spying(victim);
+    }
+
+    fn build_wall(&mut self, corner: [usize; 3]) {
+        let color = self.get_current_color();
+        self.board.place_wall(corner);
+        self.get_current_player_mut().build_wall();
+        self.log.add(LogEvent::Built(color, "a city wall"));
+    }
+
+    /// Wedding gifts go to the turn player, and Commercial Harbor
+    /// offers to the player they're made to
+    fn execute_give(&mut self, hand: ResHand) {
+        self.get_current_player_mut().discard_cards(hand);
+        match self.action {
+            Action::HarborOffering(partner) => {
+                self.get_player_mut(partner).unwrap().get_cards(hand);
+                self.current_player = self.get_order().iter().position(|&c| c == partner).unwrap();
+                self.action = Action::HarborReturning;
+            },
+            _ => {
+                self.players[self.turn_player].get_cards(hand);
+                match self.next_wedding_guest(self.current_player) {
+                    Some(idx) => {
+                        self.current_player = idx;
+                        self.selector = Some(Selector::Giving(ResHand::new()));
+                    },
+                    None => {
+                        self.current_player = self.turn_player;
+                        self.action = Action::Idling;
+                    }
+                }
+            }
+        }
+    }
+
+    /// A commodity back for the Commercial Harbor's resource, after which
+    /// the next player with commodities gets an offer
+    fn execute_give_commodity(&mut self, card: Commodity) {
+        self.get_current_player_mut().discard_commodity(card);
+        self.players[self.turn_player].add_commodity(card);
+        let next = self.next_harbor_partner(self.current_player);
+        self.current_player = self.turn_player;
+        match next {
+            Some(idx) if self.get_current_player().get_hand().size() > 0 => {
+                self.action = Action::HarborOffering(self.players[idx].get_color());
+                self.selector = Some(Selector::Giving(ResHand::new()));
+            },
+            _ => self.action = Action::Idling
+        }
+    }
+
+    fn execute_take(&mut self, hand: ResHand) {
+        let Action::Taking(victim) = self.action else {
+            panic!("execute_take(): not taking");
+        };
+        self.get_player_mut(victim).unwrap().discard_cards(hand);
+        self.get_current_player_mut().get_cards(hand);
+        self.action = Action::Idling;
+    }
+
+    fn execute_take_progress(&mut self, card: ProgressCard) {
+        let Action::Spying(victim) = self.action else {
+            panic!("execute_take_progress(): not spying");
+        };
+        self.get_player_mut(victim).unwrap().play_progress_card(card);
+        self.get_current_player_mut().add_progress_card(card);
+        self.action = Action::Idling;
+    }
+
+    /// The deserter comes over to the turn player, if they have a piece of
+    /// its level and somewhere to put it
+    fn remove_deserter(&mut self, corner: [usize; 3]) {
+        let knight = self.board.remove_knight(corner);
+        self.get_current_player_mut().return_knight(knight.level);
+        self.current_player = self.turn_player;
+        let color = self.get_current_color();
+        self.action = if self.get_current_player().has_knight_piece(knight.level) && self.board.can_place_any_knight(color) {
+            Action::PlacingDeserter(knight.level)
+        } else {
+            Action::Idling
+        };
+    }
+
+    fn place_free_knight(&mut self, corner: [usize; 3]) {
+        let color = self.get_current_color();
+        match self.action {
+            Action::PlacingDeserter(level) => {
+                self.board.place_free_knight(corner, Knight { color, level, active: false });
+                self.get_current_player_mut().place_free_knight(level);
+            },
+            Action::Displaced(knight, _) => {
+                self.board.place_free_knight(corner, knight);
+                self.current_player = self.turn_player;
+            },
+            _ => panic!("place_free_knight(): no knight to place")
+        }
+        self.action = Action::Idling;
+    }
+
+    /// Awards the island bonus for a player's first settlement on an island
+    /// nobody started on
+    fn claim_island(&mut self, corner: [usize; 3]) {
+        let color = self.get_current_color();
+        let Some(island) = self.board.corner_island(corner) else { return };
+        if self.home_islands.contains(&island) || self.settled_islands.contains(&(color, island)) {
+            return;
+        }
+        self.settled_islands.push((color, island));
+        let bonus = self.island_bonus;
+        self.get_current_player_mut().add_bonus_vps(bonus);
+    }
+
+    fn build_city(&mut self, corner: [usize; 3]) {
+        self.cover_cost(CITY_HAND);
+        let color = self.get_current_color();
+        self.board.place_city(corner, color);
+        self.get_current_player_mut().build_city();
+        self.log.add(LogEvent::Built(color, "a city"));
+    }
+
+    fn update_largest_army(&mut self) {
+        let old = self.largest_army.replace(self.get_current_color());
+        self.largest_army_size = self.get_current_player().get_knights();
+        
+        self.get_current_player_mut().set_largest_army(true);
+        if let Some(prev) = old {
+            self.get_player_mut(prev).unwrap().set_largest_army(false);
+        }
+    }
+
+    fn update_longest_road(&mut self) {
+        let lens: Vec<usize> = self.players.iter()
+            .map(|player| self.board.longest_route(player.get_color()))
+            .collect();
+        for (player, &len) in self.players.iter_mut().zip(lens.iter()) {
+            player.set_road_len(len);
+        }
+
+        let best = lens.iter().copied().max().unwrap_or(0);
+        let leaders: Vec<PlayerColor> = self.players.iter()
+            .filter(|player| player.get_road_len() == best)
+            .map(|player| player.get_color())
+            .collect();
+        let holder_len = self.longest_road.map(|color| self.get_player(color).unwrap().get_road_len());
+
+        let new_holder = match holder_len {
+            // The holder keeps it unless someone is now strictly longer
+            Some(len) if len == best && best > 4 => self.longest_road,
+            // A broken road only passes it on to a single clear leader
+            Some(_) => if best > 4 && leaders.len() == 1 { Some(leaders[0]) } else { None },
+            None => if best > self.longest_road_size && leaders.len() == 1 { Some(leaders[0]) } else { None },
+        };
+
+        if let Some(old) = self.longest_road {
+            self.get_player_mut(old).unwrap().set_longest_road(false);
+        }
+        if let Some(new) = new_holder {
+            self.get_player_mut(new).unwrap().set_longest_road(true);
+        }
+        self.longest_road = new_holder;
+        self.longest_road_size = if new_holder.is_some() { best } else { 4 };
+    }
+
+    /// A road from Road Building, which costs nothing
+    fn place_free_road(&mut self, edge: [usize; 3]) {
+        let color = self.get_current_color();
+        self.board.place_setup_road(edge, color);
+        self.get_current_player_mut().place_setup_road();
+        self.log.add(LogEvent::Built(color, "a road"));
+        self.update_longest_road();
+        self.advance_road_building();
+    }
+
+    fn advance_road_building(&mut self) {
+        let placed_once = match self.action {
+            Action::RoadBuilding(placed_once) => placed_once,
+            _ => panic!("advance_road_building(): not road building")
+        };
+
+        // With nowhere or nothing left for the second road, the card is done with
+        if placed_once || !self.get_current_player().has_road_piece()
+        || !self.board.can_place_any_road(self.get_current_color()) {
+            self.action = Action::Idling;
+        } else {
+            self.action = Action::RoadBuilding(true);
+        }
+    }
+
+    fn spend_dv_card(&mut self, card: DVCard) {
+        let color = self.get_current_color();
+        self.get_current_player_mut().play_dv_card(card);
+        self.played_dv = true;
+        self.stats.get_mut(color).dvs_played += 1;
+        self.log.add(LogEvent::PlayedDV(color, card));
+    }
+
+    fn play_dv_card(&mut self, card: DVCard) {
+        match card {
+            DVCard::Knight => {
+                self.spend_dv_card(DVCard::Knight);
+                if self.get_current_player().get_knights() > self.largest_army_size {
+                    self.update_largest_army();
+                }
+                self.action = Action::MovingRobber;
+            },
+            DVCard::RoadBuilding => {
+                self.spend_dv_card(DVCard::RoadBuilding);
+                self.action = Action::RoadBuilding(false);
+            },
+            DVCard::YearOfPlenty => {
+                self.selector = Some(Selector::Yopping(ResHand::new()));
+            },
+            DVCard::Monopoly => {
+                self.selector = Some(Selector::Monopolizing(ResHand::new()));
+            },
+            DVCard::VictoryPoint => (),
+        }
+    }
+
+    /// Each city wall lets its owner hold two more cards
+    fn discard_limit(&self, idx: usize) -> usize {
+        self.rules.discard_limit + 2 * self.board.count_walls(self.players[idx].get_color())
+    }
+
+    fn someone_must_discard(&self) -> bool {
+        (0..self.num_players).any(|idx| self.players[idx].must_discard(self.discard_limit(idx)))
+    }
+
+    fn initiate_discarding(&mut self) {
+        self.action = Action::Discarding;
+        self.selector = Some(Selector::Discarding(ResHand::new()));
+        while !self.players[self.current_player].must_discard(self.discard_limit(self.current_player)) {
+            self.current_player = (self.current_player + 1) % self.num_players;
+        }
+    }
+
+    fn can_add_to_bottom(&self, card: Resource) -> bool {
+        let pool = self.get_current_player().get_hand()[card];
+        match self.selector.as_ref().unwrap() {
+            Selector::Discarding(hand) => hand[card] < pool,
+            Selector::ChoosingGold(hand) => hand.size() < self.gold_owed[self.current_player],
+            Selector::Trading(give, _) => give[card] < pool,
+            Selector::Yopping(hand) => hand.size() < 2,
+            Selector::Monopolizing(hand) => hand.size() < 1,
+            Selector::ResourceMonopoly(hand) => hand.size() < 1,
+            Selector::MerchantFleet(hand) => hand.size() < 1,
+            Selector::Giving(hand) => hand[card] < pool && hand.size() < self.cards_to_give(),
+            Selector::Taking(hand) => {
+                let Action::Taking(victim) = self.action else { return false };
+                hand[card] < self.get_player(victim).unwrap().get_hand()[card] && hand.size() < self.cards_to_give()
+            },
+        }
+    }
+
+    fn can_add_to_top(&self, card: Resource) -> bool {
+        match self.selector.as_ref().unwrap() {
+            Selector::Trading(_, get) => get[card] < self.rules.bank_size,
+            _ => false
+        }
+    }
+
+    fn can_discard_from_bottom(&self, card: Resource) -> bool {
+        let hand = match self.selector.as_ref().unwrap() {
+            Selector::Discarding(hand) => hand,
+            Selector::ChoosingGold(hand) => hand,
+            Selector::Trading(give, _) => give,
+            Selector::Yopping(hand) => hand,
+            Selector::Monopolizing(hand) => hand,
+            Selector::ResourceMonopoly(hand) => hand,
+            Selector::MerchantFleet(hand) => hand,
+            Selector::Giving(hand) => hand,
+            Selector::Taking(hand) => hand,
+        };
+        hand[card] > 0
+    }
+
+    fn can_discard_from_top(&self, card: Resource) -> bool {
+        match self.selector.as_ref().unwrap() {
+            Selector::Trading(_, get) => get[card] > 0,
+            _ => false
+        }
+    }
+
+    fn get_selector(&self) -> &Selector {
+        self.selector.as_ref().unwrap()
+    }
+
+    fn get_selector_mut(&mut self) -> &mut Selector {
+        self.selector.as_mut().unwrap()
+    }
+
+    fn can_cancel_selector(&self) -> bool {
+        !matches!(
+            self.selector.as_ref().unwrap(),
+            Selector::Discarding(_) | Selector::ChoosingGold(_) | Selector::Giving(_) | Selector::Taking(_)
+        )
+    }
+
+    /// The Merchant Fleet makes one resource two for one for the rest of the turn
+    fn trade_rate(&self, color: PlayerColor, resource: Resource) -> usize {
+        let rate = self.rules.trade_rate(&self.board, color, resource);
+        if self.merchant_fleet == Some(resource) && self.players[self.turn_player].is_color(color) {
+            rate.min(2)
+        } else {
+            rate
+        }
+    }
+
+    /// Each resource given goes at its own rate. Every full set is worth
+    /// one card from the bank, and no cards may be left over.
+    fn can_trade_with_bank(&self, give: ResHand, get: ResHand) -> bool {
+        if !self.board.bank.can_discard(get) || give.size() == 0 {
+            return false;
+        }
+
+        let mut credits = 0;
+        for res in RESOURCES {
+            let rate = self.trade_rate(self.get_current_color(), res);
+            if !give[res].is_multiple_of(rate) {
+                return false;
+            }
+            credits += give[res] / rate;
+        }
+        credits == get.size()
+    }
+
+    fn can_execute_selector(&self) -> bool {
+        match self.selector.as_ref().unwrap() {
+            Selector::Discarding(hand) =>
+                hand.size() == self.get_current_player().get_hand().size() / 2,
+            Selector::ChoosingGold(hand) =>
+                hand.size() == self.gold_owed[self.current_player] && self.board.bank.can_discard(*hand),
+            Selector::Trading(give, get) => trade_is_reasonable(*give, *get),
+            Selector::Yopping(hand) => hand.size() == 2 && self.board.bank.can_discard(*hand),
+            Selector::Monopolizing(hand) => hand.size() == 1,
+            Selector::ResourceMonopoly(hand) => hand.size() == 1,
+            Selector::MerchantFleet(hand) => hand.size() == 1,
+            Selector::Giving(hand) | Selector::Taking(hand) => hand.size() == self.cards_to_give(),
+        }
+    }
+
+    fn execute_discard(&mut self, hand: ResHand) {
+        let color = self.get_current_color();
+        self.stats.get_mut(color).discarded += hand.size();
+        self.log.add(LogEvent::Discarded(color, hand.size()));
+        self.get_current_player_mut().discard_cards(hand);
+        while !self.players[self.current_player].must_discard(self.discard_limit(self.current_player)) {
+            self.current_player = (self.current_player + 1) % self.num_players;
+            if self.current_player == self.turn_player {
+                self.start_moving_robber();
+                return;
+            }
+        }
+        self.selector = Some(Selector::Discarding(ResHand::new()));
+    }
+
+    /// Like a discard on a seven, but only for the Saboteur's victims
+    fn execute_sabotage(&mut self, hand: ResHand) {
+        let color = self.get_current_color();
+        self.stats.get_mut(color).discarded += hand.size();
+        self.log.add(LogEvent::Discarded(color, hand.size()));
+        self.get_current_player_mut().discard_cards(hand);
+        match self.next_saboteur_victim(self.current_player) {
+            Some(idx) => {
+                self.current_player = idx;
+                self.selector = Some(Selector::Discarding(ResHand::new()));
+            },
+            None => {
+                self.current_player = self.turn_player;
+                self.action = Action::Idling;
+            }
+        }
+    }
+
+    fn execute_gold(&mut self, hand: ResHand) {
+        self.board.bank.discard(hand);
+        self.get_current_player_mut().get_cards(hand);
+        self.gold_owed[self.current_player] = 0;
+
+        let next = (0..self.num_players)
+            .map(|i| (self.turn_player + i) % self.num_players)
+            .find(|&idx| self.gold_owed[idx] > 0);
+        match next {
+            Some(idx) => {
+                self.current_player = idx;
+                self.selector = Some(Selector::ChoosingGold(ResHand::new()));
+            },
+            None => {
+                self.current_player = self.turn_player;
+                self.action = Action::Idling;
+            }
+        }
+    }
+
+    fn execute_trade(&mut self, give: ResHand, get: ResHand) {
+        if self.can_trade_with_bank(give, get) {
+            self.board.bank.discard(get);
+            self.board.bank.add(give);
+            self.get_current_player_mut().discard_cards(give);
+            self.get_current_player_mut().get_cards(get);
+            let color = self.get_current_color();
+            self.stats.get_mut(color).trades += 1;
+            self.log.add(LogEvent::BankTrade { color, give, get });
+        } else {
+            self.offered_trades.push((give, get));
+        }
+        self.selector = Some(Selector::Trading(ResHand::new(), ResHand::new()))
+    }
+
+    fn execute_yop(&mut self, hand: ResHand) {
+        self.spend_dv_card(DVCard::YearOfPlenty);
+        self.board.bank.discard(hand);
+        self.get_current_player_mut().get_cards(hand);
+    }
+
+    fn execute_monopoly(&mut self, card: Resource) {
+        self.spend_dv_card(DVCard::Monopoly);
+
+        let monopolizer = self.get_current_color();
+        let mut gained = 0;
+        for player in self.players.iter_mut() {
+            if !player.is_color(monopolizer) {
+                gained += player.get_hand()[card];
+                player.discard_all(card);
+            }
+        }
+        let monopolied = ResHand::from_monopoly(card, gained);
+        self.get_current_player_mut().get_cards(monopolied);
+        self.log.add(LogEvent::Monopolized(monopolizer, card, gained));
+    }
+
+    /// Every opponent gives up to two of the named resource
+    fn execute_resource_monopoly(&mut self, card: Resource) {
+        self.discard_progress_card(ProgressCard::ResourceMonopoly);
+
+        let monopolizer = self.get_current_color();
+        let mut gained = 0;
+        for player in self.players.iter_mut() {
+            if !player.is_color(monopolizer) {
+                let taken = player.get_hand()[card].min(2);
+                player.discard_cards(ResHand::from_monopoly(card, taken));
+                gained += taken;
+            }
+        }
+        self.get_current_player_mut().get_cards(ResHand::from_monopoly(card, gained));
+    }
+
+    fn cancel_selector(&mut self) {
+        self.selector = None;
+    }
+
+    fn open_trade_menu(&mut self) {
+        self.selector = Some(Selector::Trading(ResHand::new(), ResHand::new()));
+    }
+
+    /// Opens the trade menu asking for one `card`, the way a canned question
+    /// in the chat does, whenever the Trade button could have opened it
+    fn open_trade_menu_for(&mut self, card: Resource) {
+        if self.selector.is_some() || self.special_building || !matches!(self.action, Action::Idling) {
+            return;
+        }
+        self.open_trade_menu();
+        if self.can_add_to_top(card) {
+            self.get_selector_mut().add_top_card(card);
+        }
+    }
+
+    fn has_special_building_phase(&self) -> bool {
+        self.num_players > 4
+    }
+
+    fn pass_turn(&mut self) {
+        self.log.add(LogEvent::PassedTurn(self.get_current_color()));
+        self.get_current_player_mut().cycle_dvs();
+        self.offered_trades.clear();
+        self.action = Action::Idling;
+
+        if self.special_building {
+            self.current_player = (self.current_player + 1) % self.num_players;
+            if self.current_player == self.turn_player {
+                self.start_next_turn();
+            }
+        } else if self.has_special_building_phase() {
+            // Everyone else gets a chance to build before the next turn starts
+            self.special_building = true;
+            self.current_player = (self.turn_player + 1) % self.num_players;
+        } else {
+            self.start_next_turn();
+        }
+    }
+
+    fn start_companion(&mut self) {
+        self.companion = true;
+        self.dice = Box::new(ManualDice::default());
+    }
+
+    fn can_enter_die(&self) -> bool {
+        self.companion && self.roll.is_none() && self.winner.is_none()
+        && matches!(self.action, Action::Idling)
+    }
+
+    /// Companion mode: the dice are typed in one at a time as they land
+    fn enter_die(&mut self, face: usize) {
+        self.typed_dice.push(face);
+        if let &[die1, die2] = self.typed_dice.as_slice() {
+            self.dice.enter([die1, die2]);
+            self.typed_dice.clear();
+            self.rng_action = Some(RngAction::RollingDice);
+        }
+    }
+
+    /// Nothing has happened yet this turn, so it can be saved and picked up again
+    fn is_turn_start(&self) -> bool {
+        self.roll.is_none() && self.current_player == self.turn_player && !self.special_building
+        && self.selector.is_none() && self.rng_action.is_none() && matches!(self.action, Action::Idling)
+    }
+
+    fn check_for_winner(&mut self) {
+        let player = &self.players[self.turn_player];
+        if self.winner.is_none() && player.has_won(self.rules.vp_target) {
+            self.winner = Some(player.get_color());
+            self.log.add(LogEvent::Won(player.get_color()));
+        }
+    }
+
+    fn start_next_turn(&mut self) {
+        self.special_building = false;
+        self.turn_player = (self.turn_player + 1) % self.num_players;
+        if self.turn_player == 0 {
+            self.round += 1;
+        }
+        self.current_player = self.turn_player;
+        self.roll = None;
+        self.played_dv = false;
+        self.new_ships.clear();
+        self.moved_ship = false;
+        self.event = None;
+        self.merchant_fleet = None;
+    }
+}
+
+/// A game at either stage, for the server and its clients, which follow
+/// it from the first settlement to the end
+pub enum Table {
+    Setup(Box<SetupState>),
+    Playing(Box<GameState>),
+}
+
+impl Table {
+    fn get_players(&self) -> &[Player] {
+        match self {
+            Table::Setup(state) => &state.players,
+            Table::Playing(state) => &state.players,
+        }
+    }
+
+    fn get_current_color(&self) -> PlayerColor {
+        match self {
+            Table::Setup(state) => state.get_current_color(),
+            Table::Playing(state) => state.get_current_color(),
+        }
+    }
+
+    fn get_layout(&self) -> &Layout {
+        match self {
+            Table::Setup(state) => &state.board.layout,
+            Table::Playing(state) => &state.board.layout,
+        }
+    }
+
+    /// Who plays each seat that isn't a person
+    fn get_bots(&self) -> &[Option<Bot>] {
+        match self {
+            Table::Setup(state) => &state.bots,
+            Table::Playing(state) => &state.bots,
+        }
+    }
+
+    fn get_bots_mut(&mut self) -> &mut [Option<Bot>] {
+        match self {
+            Table::Setup(state) => &mut state.bots,
+            Table::Playing(state) => &mut state.bots,
+        }
+    }
+
+    /// Who plays the seat acting now, if it isn't a person
+    fn get_current_bot(&self) -> Option<Bot> {
+        match self {
+            Table::Setup(state) => state.bots[state.current_player],
+            Table::Playing(state) => state.bots[state.current_player],
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        matches!(self, Table::Playing(state) if state.winner.is_some())
+    }
+
+    /// A chat line from `color`, kept in the log with the game's events
+    fn add_chat(&mut self, color: PlayerColor, text: String) {
+        match self {
+            Table::Setup(state) => state.log.add(LogEvent::Chat(color, text)),
+            Table::Playing(state) => state.log.add(LogEvent::Chat(color, text)),
+        }
+    }
+
+    fn can_make_move(&self, mv: &Move) -> bool {
+        match self {
+            Table::Setup(state) => state.can_make_move(mv),
+            Table::Playing(state) => state.can_make_move(mv),
+        }
+    }
+
+    /// The move the bot acting now makes
+    fn get_bot_move<R: Rng>(&self, rng: &mut R) -> Move {
+        match self {
+            Table::Setup(state) => state.bots[state.current_player].unwrap().setup_step(state, rng),
+            Table::Playing(state) => {
+                let view = PlayerView::new(state, state.get_current_color());
+                state.bots[state.current_player].unwrap().play_step(&view, rng)
+            },
+        }
+    }
+
+    /// Makes a move `can_make_move` allows, or lets the bot acting now make
+    /// one. The last setup placement starts the game.
+    fn play<R: Rng>(&mut self, mv: Option<&Move>, rng: &mut R) {
+        let mv = match mv {
+            Some(mv) => *mv,
+            None => self.get_bot_move(rng)
+        };
+        match self {
+            Table::Setup(state) => {
+                state.apply_move(&mv);
+                if state.finished {
+                    *self = Table::Playing(Box::new((**state).clone().into()));
+                }
+            },
+            Table::Playing(state) => state.apply_move(&mv, rng),
+        }
+    }
+}
+
+fn trade_is_reasonable(give: ResHand, get: ResHand) -> bool {
+    give.size() > 0 && get.size() > 0 && RESOURCES.iter().all(|&res| give[res] == 0 || get[res] == 0)
+}
+
+fn mouse_is_on_circle(mouse_pos: (f32, f32), center: [f32; 2], radius: f32) -> bool {
+    (mouse_pos.0 - center[0]).powi(2) + (mouse_pos.1 - center[1]).powi(2) <= radius.powi(2)
+}
+
+fn mouse_is_on_rect(mouse_pos: (f32, f32), pos: [f32; 2], width: f32, height: f32) -> bool {
+    mouse_pos.0 > pos[0] && mouse_pos.0 < pos[0] + width
+    && mouse_pos.1 > pos[1] && mouse_pos.1 < pos[1] + height
+}
+
+fn handle_setup_road_click(state: &SetupState, coords: &ScreenCoords, settlement: [usize; 3], mouse_pos: (f32, f32)) -> Option<Move> {
+    let radius = coords.build_clickable_radius;
+    let idx = coords.edges.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let edge = state.board.layout.edge_coords[idx];
+    if state.board.can_place_setup_road(edge, settlement) {
+        Some(Move::PlaceRoad(edge))
+    } else if state.ruleset == Ruleset::Seafarers && state.board.can_place_setup_ship(edge, settlement) {
+        Some(Move::PlaceShip(edge))
+    } else {
+        None
+    }
+}
+
+fn handle_setup_settlement_click(state: &SetupState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let radius = coords.build_clickable_radius;
+    let idx = coords.corners.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let corner = state.board.layout.corner_coords[idx];
+    state.board.can_place_setup_settlement(corner).then_some(Move::PlaceSettlement(corner))
+}
+
+fn handle_setup_click(state: &SetupState, coords: &ScreenCoords) -> Option<Move> {
+    let mouse_pos = mouse_position();
+    match state.settlement {
+        Some(settlement) => handle_setup_road_click(state, coords, settlement, mouse_pos),
+        None => handle_setup_settlement_click(state, coords, mouse_pos)
+    }
+}
+
+/// Hot-seat privacy. The screen belongs to one seat at a time, and whenever
+/// another human has to act, nothing is drawn until they click through.
+struct HandOff {
+    enabled: bool,
+    viewer: Option<PlayerColor>,
+}
+
+impl HandOff {
+    fn new(enabled: bool) -> HandOff {
+        HandOff { enabled, viewer: None }
+    }
+
+    fn is_waiting(&self, player: &Player) -> bool {
+        self.enabled && player.is_human() && self.viewer != Some(player.get_color())
+    }
+
+    fn reveal(&mut self, color: PlayerColor) {
+        self.viewer = Some(color);
+    }
+
+    /// Whose cards are on screen: the acting seat if it's human,
+    /// otherwise the last human to have had the screen
+    fn get_viewer(&self, players: &[Player], current: usize) -> PlayerColor {
+        let current = &players[current];
+        if current.is_human() {
+            return current.get_color();
+        }
+        self.viewer
+            .or(players.iter().find(|player| player.is_human()).map(|player| player.get_color()))
+            .unwrap_or(current.get_color())
+    }
+}
+
+/// The chat panel's typing line. Enter starts a line and sends it, and
+/// Escape throws it away; while one's being typed, it gets every key.
+struct ChatBox {
+    draft: Option<String>,
+}
+
+impl ChatBox {
+    fn new() -> ChatBox {
+        ChatBox { draft: None }
+    }
+
+    fn get_draft(&self) -> Option<&str> {
+        self.draft.as_deref()
+    }
+
+    /// Reads this frame's keys, and returns the line once it's sent
+    fn handle_keys(&mut self) -> Option<String> {
+        let Some(draft) = self.draft.as_mut() else {
+            if is_key_pressed(KeyCode::Enter) {
+                self.draft = Some(String::new());
+            }
+            return None;
+        };
+        while let Some(key) = get_char_pressed() {
+            if !key.is_control() && draft.chars().count() < CHAT_MAX_LEN {
+                draft.push(key);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            draft.pop();
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.draft = None;
+        } else if is_key_pressed(KeyCode::Enter) {
+            return self.draft.take().and_then(|draft| clean_chat(&draft));
+        }
+        None
+    }
+}
+
+/// The resource a canned question clicked in the chat panel asks for
+fn handle_canned_click(coords: &ScreenCoords) -> Option<Resource> {
+    let [width, height] = coords.canned_button_size;
+    coords.canned_buttons.iter()
+        .position(|&pos| mouse_is_on_rect(mouse_position(), pos, width, height))
+        .map(|idx| RESOURCES[idx])
+}
+
+/// Seconds between bot moves, so the table can follow them
+const BOT_DELAY: f64 = 0.5;
+
+async fn setup_game<R: Rng>(mut state: SetupState, rng: &mut R) -> GameState {
+    let mut coords = ScreenCoords::new(&state.board.layout, menu_buttons(state.ruleset).len());
+    let mut hand_off = HandOff::new(state.hot_seat);
+    let mut chat = ChatBox::new();
+    let mut next_bot_move = 0.0;
+    loop {
+        coords.update(&state.board.layout);
+
+        if hand_off.is_waiting(state.get_current_player()) {
+            if is_mouse_button_pressed(MouseButton::Left) {
+                hand_off.reveal(state.get_current_color());
+            }
+            render_curtain(&coords, state.get_current_color());
+            window::next_frame().await;
+            continue;
+        }
+
+        match state.bots[state.current_player] {
+            Some(bot) => if get_time() >= next_bot_move {
+                let mv = bot.setup_step(&state, rng);
+                state.apply_move(&mv);
+                next_bot_move = get_time() + BOT_DELAY;
+            },
+            None => {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    if let Some(mv) = handle_setup_click(&state, &coords) {
+                        state.apply_move(&mv);
+                    }
+                }
+                next_bot_move = get_time() + BOT_DELAY;
+            }
+        }
+        // Hot-seat players can talk across the table; nobody's trading yet
+        let viewer = hand_off.get_viewer(&state.players, state.current_player);
+        if state.hot_seat {
+            let canned = is_mouse_button_pressed(MouseButton::Left).then(|| handle_canned_click(&coords)).flatten();
+            if let Some(text) = chat.handle_keys().or(canned.map(canned_chat)) {
+                state.log.add(LogEvent::Chat(viewer, text));
+            }
+        }
+
+        if state.finished {
+            return state.into();
+        }
+
+        render_setup_screen(&coords, &state, viewer);
+        if state.hot_seat {
+            render_chat(&coords, chat.get_draft());
+        }
+
+        window::next_frame().await
+    }
+}
+
+/// The move the selector's confirm button makes. Offers to the other
+/// players don't change the game, so they're just noted down.
+fn selector_move(state: &mut GameState) -> Option<Move> {
+    match *state.get_selector() {
+        Selector::Discarding(hand) => Some(Move::Discard(hand)),
+        Selector::ChoosingGold(hand) => Some(Move::ChooseGold(hand)),
+        Selector::Trading(give, get) => if state.can_trade_with_bank(give, get) {
+            Some(Move::BankTrade(give, get))
+        } else {
+            state.execute_trade(give, get);
+            None
+        },
+        Selector::Yopping(hand) => Some(Move::YearOfPlenty(hand)),
+        Selector::Monopolizing(hand) => Some(Move::Monopoly(hand.nth_nonzero(0).unwrap())),
+        Selector::ResourceMonopoly(hand) => Some(Move::ResourceMonopoly(hand.nth_nonzero(0).unwrap())),
+        Selector::MerchantFleet(hand) => Some(Move::MerchantFleet(hand.nth_nonzero(0).unwrap())),
+        Selector::Giving(hand) => Some(if matches!(state.action, Action::Sabotaged) { Move::Discard(hand) } else { Move::Give(hand) }),
+        Selector::Taking(hand) => Some(Move::Take(hand)),
+    }
+}
+
+fn handle_selector_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let buttons = &coords.selector_buttons;
+    let button_size = coords.selector_button_size;
+
+    if mouse_is_on_rect(mouse_pos, buttons[0], button_size, button_size) {
+        if state.can_cancel_selector() {
+            state.cancel_selector();
+        }
+        return None;
+    }
+    if mouse_is_on_rect(mouse_pos, buttons[1], button_size, button_size) {
+        if state.can_execute_selector() {
+            return selector_move(state);
+        }
+        return None;
+    }
+
+    let [selector_card_width, selector_card_height] = coords.selector_card_size;
+    let selector_size = coords.selector_selector_size;
+
+    if let Some(idx) = coords.selector_bottom_cards.iter().position(
+        |pos| mouse_is_on_rect(mouse_pos, *pos, selector_card_width, selector_card_height)
+    ) {
+        let card = RESOURCES[idx];
+        if state.can_discard_from_bottom(card) {
+            state.get_selector_mut().discard_bottom_card(card);
+        }
+        return None;
+    }
+    else if let Some(idx) = coords.selector_bottom_selectors.iter().position(
+        |pos| mouse_is_on_rect(mouse_pos, *pos, selector_size, selector_size)
+    ) {
+        let card = RESOURCES[idx];
+        if state.can_add_to_bottom(card) {
+            state.get_selector_mut().add_bottom_card(card);
+        }
+    }
+
+    if !matches!(state.get_selector(), Selector::Trading(_, _)) {
+        return None;
+    }
+
+    if let Some(idx) = coords.selector_top_cards.iter().position(
+        |pos| mouse_is_on_rect(mouse_pos, *pos, selector_card_width, selector_card_height)
+    ) {
+        let card = RESOURCES[idx];
+        if state.can_discard_from_top(card) {
+            state.get_selector_mut().discard_top_card(card);
+        }
+    }
+    else if let Some(idx) = coords.selector_top_selectors.iter().position(
+        |pos| mouse_is_on_rect(mouse_pos, *pos, selector_size, selector_size)
+    ) {
+        let card = RESOURCES[idx];
+        if state.can_add_to_top(card) {
+            state.get_selector_mut().add_top_card(card);
+        }
+    }
+    None
+}
+
+fn handle_idling_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    if state.selector.is_none() && !state.special_building
+    && mouse_is_on_rect(mouse_pos, coords.trade_button, coords.trade_button_size, coords.trade_button_size) {
+        state.open_trade_menu();
+        return None;
+    }
+
+    let [card_width, card_height] = coords.card_size;
+    if let Some(n) = coords.cards.iter().position(
+        |pos| mouse_is_on_rect(mouse_pos, *pos, card_width, card_height)
+    ) {
+        let player = state.get_current_player();
+        let num_resources = player.get_hand().count_nonzero();
+        let num_dvs = player.get_combined_dvs().count_nonzero();
+        let num_commodities = player.get_commodities().count_nonzero();
+        if n < num_resources || state.special_building {
+            return None;
+        }
+        if let Some(card) = player.get_combined_dvs().nth_nonzero(n - num_resources) {
+            if player.get_dvs()[card] == 0 || state.played_dv {
+                return None;
+            }
+            // The cards that need a choice open the selector first
+            match card {
+                DVCard::Knight | DVCard::RoadBuilding =>
+                    return state.can_make_move(&Move::PlayDV(card)).then_some(Move::PlayDV(card)),
+                _ => state.play_dv_card(card)
+            }
+        } else if n >= num_resources + num_dvs + num_commodities {
+            let progress = player.get_progress_counts();
+            let &(card, _) = progress.get(n - num_resources - num_dvs - num_commodities)?;
+            if !state.can_play_progress_card(card) {
+                return None;
+            }
+            // The cards that need a choice have it picked first
+            match card {
+                ProgressCard::ResourceMonopoly | ProgressCard::MerchantFleet => state.play_progress_card(card),
+                ProgressCard::Alchemist => state.action = Action::Targeting(card, Some([1, 1])),
+                _ if card.needs_choice() => state.action = Action::Targeting(card, None),
+                _ => return Some(Move::PlayProgress(card))
+            }
+        }
+        return None;
+    }
+
+    if state.roll.is_none() {
+        if !state.companion && coords.dice.iter().any(
+            |pos| mouse_is_on_rect(mouse_pos, *pos, coords.dice_size, coords.dice_size)
+        ) {
+            return Some(Move::Roll);
+        }
+        return None;
+    }
+
+    let maybe_menu_id = coords.buttons.iter().position(
+        |&pos| mouse_is_on_rect(mouse_pos, pos, coords.button_size, coords.button_size)
+    );
+    if let Some(id) = maybe_menu_id {
+        match state.get_menu()[id] {
+            MenuButton::BuyDV => {
+                if state.can_buy_dv() && state.companion {
+                    state.action = Action::RecordingDV;
+                } else if state.can_buy_dv() {
+                    return Some(Move::BuyDV);
+                }
+            },
+            MenuButton::Knight => {
+                if state.can_build_knight() {
+                    state.action = Action::BuildingKnight;
+                }
+            },
+            MenuButton::ActivateKnight => {
+                if state.can_activate_knight() {
+                    state.action = Action::ActivatingKnight;
+                }
+            },
+            MenuButton::PromoteKnight => {
+                if state.can_promote_knight() {
+                    state.action = Action::PromotingKnight(None);
+                }
+            },
+            MenuButton::ChaseRobber => {
+                if state.can_chase_robber() {
+                    state.action = Action::ChasingRobber;
+                }
+            },
+            MenuButton::Road => {
+                if state.can_build_road() {
+                    state.action = Action::BuildingRoad;
+                }
+            },
+            MenuButton::Ship => {
+                if state.can_build_ship() {
+                    state.action = Action::BuildingShip;
+                }
+            },
+            MenuButton::MoveShip => {
+                if state.can_move_ship() {
+                    state.action = Action::MovingShip(None);
+                }
+            },
+            MenuButton::Settlement => {
+                if state.can_build_settlement() {
+                    state.action = Action::BuildingSettlement;
+                }
+            },
+            MenuButton::City => {
+                if state.can_build_city() {
+                    state.action = Action::BuildingCity;
+                }
+            },
+            MenuButton::Wall => {
+                if state.can_build_wall() {
+                    state.action = Action::BuildingWall;
+                }
+            },
+            MenuButton::Improve(track) => {
+                if state.can_improve(track) {
+                    return Some(Move::Improve(track));
+                }
+            },
+            MenuButton::RecordDV(_) => (),
+            MenuButton::Pass => return Some(Move::Pass),
+        }
+        return None;
+    }
+
+    if state.selector.is_some() {
+        return handle_selector_click(state, coords, mouse_pos);
+    }
+    None
+}
+
+fn handle_discarding_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    if state.selector.is_some() {
+        return handle_selector_click(state, coords, mouse_pos);
+    }
+    None
+}
+
+fn handle_moving_robber_click(state: &GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let radius = coords.robber_clickable_radius;
+    let idx = coords.centers.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let hex = state.board.layout.hex_coords[idx];
+    state.can_move_robber(hex).then_some(Move::PlaceRobber(hex))
+}
+
+fn handle_choosing_victim_click(state: &GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let radius = coords.build_clickable_radius;
+    let idx = coords.corners.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let corner = state.board.layout.corner_coords[idx];
+    let [r, q, c] = corner;
+    if !state.board.is_robbable(corner, state.get_current_color()) {
+        return None;
+    }
+    let color = state.board.structures[r][q][c].as_ref().unwrap().color;
+    state.can_rob(color).then_some(Move::Steal(color))
+}
+
+fn handle_choosing_pirate_victim_click(state: &GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let radius = coords.build_clickable_radius;
+    let color = state.get_current_color();
+    let idx = coords.edges.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let edge = state.board.layout.edge_coords[idx];
+    if !state.board.is_pirate_robbable(edge, color) {
+        return None;
+    }
+    let [r, q, e] = edge;
+    let victim = state.board.ships[r][q][e].unwrap();
+    state.can_rob(victim).then_some(Move::Steal(victim))
+}
+
+fn handle_road_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let cancel_button = coords.buttons[state.menu_position(MenuButton::Road)];
+    if mouse_is_on_rect(mouse_pos, cancel_button, coords.button_size, coords.button_size) {
+        state.action = Action::Idling;
+        return None;
+    }
+    let radius = coords.build_clickable_radius;
+    let color = state.get_current_color();
+    let idx = coords.edges.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let edge = state.board.layout.edge_coords[idx];
+    state.board.can_place_road(edge, color).then_some(Move::BuildRoad(edge))
+}
+
+fn handle_ship_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let cancel_button = coords.buttons[state.menu_position(MenuButton::Ship)];
+    if mouse_is_on_rect(mouse_pos, cancel_button, coords.button_size, coords.button_size) {
+        state.action = Action::Idling;
+        return None;
+    }
+    let radius = coords.build_clickable_radius;
+    let color = state.get_current_color();
+    let idx = coords.edges.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let edge = state.board.layout.edge_coords[idx];
+    state.board.can_place_ship(edge, color).then_some(Move::BuildShip(edge))
+}
+
+fn handle_moving_ship_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32), from: Option<[usize; 3]>) -> Option<Move> {
+    let color = state.get_current_color();
+    let cancel_button = coords.buttons[state.menu_position(MenuButton::MoveShip)];
+    if mouse_is_on_rect(mouse_pos, cancel_button, coords.button_size, coords.button_size) {
+        if let Some(from) = from {
+            state.board.place_setup_ship(from, color);
+        }
+        state.action = Action::Idling;
+        return None;
+    }
+    let radius = coords.build_clickable_radius;
+    let idx = coords.edges.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let edge = state.board.layout.edge_coords[idx];
+    match from {
+        // The ship is lifted off the board so it can't support its own new spot
+        None => {
+            if state.can_move_ship_from(edge) {
+                state.board.remove_ship(edge);
+                state.action = Action::MovingShip(Some(edge));
+            }
+            None
+        },
+        // and put back before the move, which lifts it again itself
+        Some(from) => if edge != from && state.board.can_place_ship(edge, color) {
+            state.board.place_setup_ship(from, color);
+            Some(Move::ShiftShip(from, edge))
+        } else {
+            None
+        }
+    }
+}
+
+fn handle_structure_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32), structure_type: StructureType) -> Option<Move> {
+    let button = if structure_type == StructureType::Settlement { MenuButton::Settlement } else { MenuButton::City };
+    let cancel_button = coords.buttons[state.menu_position(button)];
+    if mouse_is_on_rect(mouse_pos, cancel_button, coords.button_size, coords.button_size) {
+        state.action = Action::Idling;
+        return None;
+    }
+    let radius = coords.build_clickable_radius;
+    let color = state.get_current_color();
+    let idx = coords.corners.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let corner = state.board.layout.corner_coords[idx];
+    if structure_type == StructureType::Settlement
+    && state.board.can_place_settlement(corner, color) {
+        Some(Move::BuildSettlement(corner))
+    }
+    else if state.board.can_place_city(corner, color) {
+        Some(Move::BuildCity(corner))
+    } else {
+        None
+    }
+}
+
+fn handle_knight_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let button = match state.action {
+        Action::BuildingKnight => MenuButton::Knight,
+        Action::ActivatingKnight => MenuButton::ActivateKnight,
+        Action::PromotingKnight(_) => MenuButton::PromoteKnight,
+        _ => MenuButton::ChaseRobber
+    };
+    let cancel_button = coords.buttons[state.menu_position(button)];
+    if mouse_is_on_rect(mouse_pos, cancel_button, coords.button_size, coords.button_size) {
+        state.action = Action::Idling;
+        return None;
+    }
+    let radius = coords.build_clickable_radius;
+    let color = state.get_current_color();
+    let idx = coords.corners.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let corner = state.board.layout.corner_coords[idx];
+    match state.action {
+        Action::BuildingKnight if state.board.can_place_knight(corner, color) => Some(Move::BuildKnight(corner)),
+        Action::ActivatingKnight if state.board.can_activate_knight(corner, color) => Some(Move::ActivateKnight(corner)),
+        Action::PromotingKnight(_) if state.can_promote_knight_at(corner) => Some(Move::PromoteKnight(corner)),
+        Action::ChasingRobber if state.board.can_chase_robber(corner, color) => Some(Move::ChaseRobber(corner)),
+        _ => None
+    }
+}
+
+fn handle_road_building_click(state: &GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let color = state.get_current_color();
+    let radius = coords.build_clickable_radius;
+    let idx = coords.edges.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let edge = state.board.layout.edge_coords[idx];
+    state.board.can_place_road(edge, color).then_some(Move::FreeRoad(edge))
+}
+
+fn handle_wall_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let cancel_button = coords.buttons[state.menu_position(MenuButton::Wall)];
+    if mouse_is_on_rect(mouse_pos, cancel_button, coords.button_size, coords.button_size) {
+        state.action = Action::Idling;
+        return None;
+    }
+    let radius = coords.build_clickable_radius;
+    let idx = coords.corners.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let corner = state.board.layout.corner_coords[idx];
+    state.board.can_place_wall(corner, state.get_current_color()).then_some(Move::BuildWall(corner))
+}
+
+/// Picks what a progress card is played on: a spot on the board, a seat in
+/// the summary, an improvement button or, for the Alchemist, the dice, which
+/// turn over when clicked and are thrown with the event die. Clicking the
+/// hand puts the card back.
+fn handle_targeting_click(
+    state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32), card: ProgressCard, picked: Option<[usize; 2]>
+) -> Option<Move> {
+    let [card_width, card_height] = coords.card_size;
+    if coords.cards.iter().any(|pos| mouse_is_on_rect(mouse_pos, *pos, card_width, card_height)) {
+        state.action = Action::Idling;
+        return None;
+    }
+    let layout = &state.board.layout;
+    let radius = coords.build_clickable_radius;
+    let corner = coords.corners.iter().position(|pos| mouse_is_on_circle(mouse_pos, *pos, radius))
+        .map(|idx| layout.corner_coords[idx]);
+    let edge = coords.edges.iter().position(|pos| mouse_is_on_circle(mouse_pos, *pos, radius))
+        .map(|idx| layout.edge_coords[idx]);
+    let hex = coords.centers.iter().position(|pos| mouse_is_on_circle(mouse_pos, *pos, coords.robber_clickable_radius))
+        .map(|idx| layout.hex_coords[idx]);
+    let track = coords.buttons.iter()
+        .position(|&pos| mouse_is_on_rect(mouse_pos, pos, coords.button_size, coords.button_size))
+        .and_then(|id| match state.get_menu()[id] {
+            MenuButton::Improve(track) => Some(track),
+            _ => None
+        });
+    let seat = || handle_seat_switch_click(state, coords);
+
+    let mv = match card {
+        ProgressCard::Alchemist => {
+            let mut dice = picked.unwrap_or([1, 1]);
+            let size = coords.dice_size;
+            if let Some(idx) = coords.dice.iter().position(|pos| mouse_is_on_rect(mouse_pos, *pos, size, size)) {
+                dice[idx] = dice[idx] % 6 + 1;
+                state.action = Action::Targeting(card, Some(dice));
+                return None;
+            }
+            if !mouse_is_on_rect(mouse_pos, coords.event_die, size, size) {
+                return None;
+            }
+            Move::Alchemist(dice)
+        },
+        ProgressCard::Crane => Move::Crane(track?),
+        ProgressCard::TradeMonopoly => Move::TradeMonopoly(track?.commodity()),
+        ProgressCard::Engineer => Move::Engineer(corner?),
+        ProgressCard::Medicine => Move::Medicine(corner?),
+        ProgressCard::Intrigue => Move::Intrigue(corner?),
+        ProgressCard::Diplomat => Move::Diplomat(edge?),
+        ProgressCard::Merchant => Move::Merchant(hex?),
+        ProgressCard::Bishop => Move::Bishop(hex?),
+        ProgressCard::Inventor => match picked {
+            None => {
+                if state.board.can_swap_number(hex?) {
+                    state.action = Action::Targeting(card, hex);
+                }
+                return None;
+            },
+            Some(first) => Move::Inventor(first, hex?)
+        },
+        ProgressCard::MasterMerchant => Move::MasterMerchant(seat()?),
+        ProgressCard::Deserter => Move::Deserter(seat()?),
+        ProgressCard::Spy => Move::Spy(seat()?),
+        _ => return None
+    };
+    state.can_make_move(&mv).then_some(mv)
+}
+
+/// Commercial Harbor: the commodity handed back is clicked in the hand
+fn handle_harbor_returning_click(state: &GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let [card_width, card_height] = coords.card_size;
+    let n = coords.cards.iter().position(
+        |pos| mouse_is_on_rect(mouse_pos, *pos, card_width, card_height)
+    )?;
+    let player = state.get_current_player();
+    let skipped = player.get_hand().count_nonzero() + player.get_combined_dvs().count_nonzero();
+    let commodities = player.get_commodities();
+    let card = COMMODITIES.iter().copied()
+        .filter(|&com| commodities[com] > 0)
+        .nth(n.checked_sub(skipped)?)?;
+    Some(Move::GiveCommodity(card))
+}
+
+/// The Spy's victim's progress cards take the place of the hand
+fn handle_spying_click(state: &GameState, coords: &ScreenCoords, mouse_pos: (f32, f32), victim: PlayerColor) -> Option<Move> {
+    let [card_width, card_height] = coords.card_size;
+    let n = coords.cards.iter().position(
+        |pos| mouse_is_on_rect(mouse_pos, *pos, card_width, card_height)
+    )?;
+    let &(card, _) = state.get_player(victim)?.get_progress_counts().get(n)?;
+    Some(Move::TakeProgress(card))
+}
+
+/// A knight to give up to the Deserter, or a spot for a knight placed for free
+fn handle_free_knight_click(state: &GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let radius = coords.build_clickable_radius;
+    let idx = coords.corners.iter().position(
+        |pos| mouse_is_on_circle(mouse_pos, *pos, radius)
+    )?;
+    let corner = state.board.layout.corner_coords[idx];
+    let mv = if matches!(state.action, Action::Deserting) { Move::RemoveKnight(corner) } else { Move::PlaceKnight(corner) };
+    state.can_make_move(&mv).then_some(mv)
+}
+
+fn handle_recording_dv_click(state: &mut GameState, coords: &ScreenCoords, mouse_pos: (f32, f32)) -> Option<Move> {
+    let maybe_menu_id = coords.buttons.iter().position(
+        |&pos| mouse_is_on_rect(mouse_pos, pos, coords.button_size, coords.button_size)
+    );
+    match maybe_menu_id.map(|id| state.get_menu()[id]) {
+        Some(MenuButton::RecordDV(card)) => state.board.can_take_dv_card(card).then_some(Move::RecordDV(card)),
+        _ => {
+            state.action = Action::Idling;
+            None
+        }
+    }
+}
+
+fn handle_dice_keys(state: &mut GameState) {
+    if is_key_pressed(KeyCode::Backspace) {
+        state.typed_dice.clear();
+    }
+    while let Some(key) = get_char_pressed() {
+        let Some(face) = key.to_digit(10) else { continue };
+        if (1..=6).contains(&face) && state.can_enter_die() {
+            state.enter_die(face as usize);
+        }
+    }
+}
+
+/// Whatever the click picks out. Choosing where a piece goes, or which cards
+/// to hand over, happens here; only the finished choice comes back as a move.
+fn handle_click(state: &mut GameState, coords: &ScreenCoords) -> Option<Move> {
+    let mouse_pos = mouse_position();
+    match state.action {
+        Action::Idling => handle_idling_click(state, coords, mouse_pos),
+        Action::Discarding | Action::ChoosingGold => handle_discarding_click(state, coords, mouse_pos),
+        Action::MovingRobber => handle_moving_robber_click(state, coords, mouse_pos),
+        Action::ChoosingVictim => handle_choosing_victim_click(state, coords, mouse_pos),
+        Action::ChoosingPirateVictim => handle_choosing_pirate_victim_click(state, coords, mouse_pos),
+        Action::BuildingRoad => handle_road_click(state, coords, mouse_pos),
+        Action::BuildingShip => handle_ship_click(state, coords, mouse_pos),
+        Action::MovingShip(from) => handle_moving_ship_click(state, coords, mouse_pos, from),
+        Action::BuildingSettlement => handle_structure_click(state, coords, mouse_pos, StructureType::Settlement),
+        Action::BuildingCity => handle_structure_click(state, coords, mouse_pos, StructureType::City),
+        Action::BuildingKnight | Action::ActivatingKnight | Action::PromotingKnight(_) | Action::ChasingRobber =>
+            handle_knight_click(state, coords, mouse_pos),
+        Action::RoadBuilding(_) => handle_road_building_click(state, coords, mouse_pos),
+        Action::RecordingDV => handle_recording_dv_click(state, coords, mouse_pos),
+        Action::BuildingWall => handle_wall_click(state, coords, mouse_pos),
+        Action::Targeting(card, picked) => handle_targeting_click(state, coords, mouse_pos, card, picked),
+        Action::Giving | Action::Sabotaged | Action::HarborOffering(_) | Action::Taking(_) =>
+            handle_discarding_click(state, coords, mouse_pos),
+        Action::HarborReturning => handle_harbor_returning_click(state, coords, mouse_pos),
+        Action::Spying(victim) => handle_spying_click(state, coords, mouse_pos, victim),
+        Action::Deserting | Action::PlacingDeserter(_) | Action::Displaced(..) =>
+            handle_free_knight_click(state, coords, mouse_pos),
+    }
+}
+
+fn handle_rules_click(rules: &mut RuleConfig, coords: &RulesCoords, status: &mut String) -> bool {
+    let mouse_pos = mouse_position();
+    let size = coords.button_size;
+    for (idx, &option) in RULE_OPTIONS.iter().enumerate() {
+        if mouse_is_on_rect(mouse_pos, coords.minus_buttons[idx], size, size) {
+            rules.decrement(option);
+        } else if mouse_is_on_rect(mouse_pos, coords.plus_buttons[idx], size, size) {
+            rules.increment(option);
+        }
+    }
+
+    let [width, height] = coords.bottom_button_size;
+    let [load, save, start] = coords.bottom_buttons;
+    if mouse_is_on_rect(mouse_pos, load, width, height) {
+        match rules.load(HOUSE_RULES_PATH) {
+            Ok(loaded) => {
+                *rules = loaded;
+                *status = format!("Loaded {}", HOUSE_RULES_PATH);
+            },
+            Err(err) => *status = err
+        }
+    } else if mouse_is_on_rect(mouse_pos, save, width, height) {
+        *status = match rules.save(HOUSE_RULES_PATH) {
+            Ok(()) => format!("Saved {}", HOUSE_RULES_PATH),
+            Err(err) => err
+        };
+    } else if mouse_is_on_rect(mouse_pos, start, width, height) {
+        return true;
+    }
+    false
+}
+
+async fn configure_rules(mut rules: RuleConfig) -> RuleConfig {
+    let mut coords = RulesCoords::new(RULE_OPTIONS.len());
+    let mut status = String::new();
+    loop {
+        coords.update();
+
+        if is_mouse_button_pressed(MouseButton::Left)
+        && handle_rules_click(&mut rules, &coords, &mut status) {
+            return rules;
+        }
+
+        render_rules_screen(&coords, &rules, &status);
+
+        window::next_frame().await
+    }
+}
+
+const COMPANION_BOARD_PATH: &str = "companion_board.txt";
+
+/// Board editor clicks. Returns true once the board is done.
+fn handle_editor_click(board: &mut Board, coords: &ScreenCoords, right_click: bool) -> bool {
+    let mouse_pos = mouse_position();
+    if mouse_is_on_rect(mouse_pos, coords.buttons[0], coords.button_size, coords.button_size) {
+        return !right_click;
+    }
+
+    if let Some(idx) = (0..board.ports.len()).find(
+        |&idx| mouse_is_on_circle(mouse_pos, coords.port_marker(idx), coords.build_clickable_radius)
+    ) {
+        board.cycle_port(idx);
+        return false;
+    }
+
+    if let Some(idx) = coords.centers.iter().position(
+        |&pos| mouse_is_on_circle(mouse_pos, pos, coords.robber_clickable_radius)
+    ) {
+        let hex = board.layout.hex_coords[idx];
+        if right_click {
+            board.cycle_number(hex);
+        } else {
+            board.cycle_terrain(hex);
+        }
+    }
+    false
+}
+
+async fn edit_board(mut board: Board) -> Board {
+    let mut coords = ScreenCoords::new(&board.layout, 1);
+    loop {
+        coords.update(&board.layout);
+
+        let left_click = is_mouse_button_pressed(MouseButton::Left);
+        if (left_click || is_mouse_button_pressed(MouseButton::Right))
+        && handle_editor_click(&mut board, &coords, !left_click) {
+            board.reset_robber();
+            return board;
+        }
+
+        render_editor_screen(&coords, &board);
+
+        window::next_frame().await
+    }
+}
+
+/// Tracks a game played on a real board. The layout is copied in once with
+/// the editor and kept in `COMPANION_BOARD_PATH` for next time.
+async fn play_companion_game(num_players: usize) {
+    if !(3..=6).contains(&num_players) { panic!("Error: bad amount of players"); }
+
+    let mut rng = rand::rng();
+
+    let board = match Scenario::load(COMPANION_BOARD_PATH) {
+        Ok(scenario) => scenario.board,
+        Err(_) => Board::new(num_players, &mut rng)
+    };
+    let scenario = Scenario { island_bonus: 0, board: edit_board(board).await };
+    if let Err(err) = scenario.save(COMPANION_BOARD_PATH) {
+        eprintln!("Warning: board not saved: {}", err);
+    }
+
+    let rules = configure_rules(RuleConfig::new(num_players, Ruleset::Base)).await;
+    let players = random_players(num_players, 0, &mut rng);
+    let mut state = SetupState::with_board(scenario.board, Ruleset::Base, rules, 0, players, &mut rng);
+    // Seat order follows the color order, so the table knows who goes when
+    state.players.sort_by_key(|player| player.get_color() as usize);
+    let mut state = setup_game(state, &mut rng).await;
+    state.start_companion();
+    play_game(state, rng).await
+}
+
+/// Plays until the game is won and the player heads back to the start screen
+async fn play_game<R: Rng>(mut state: GameState, mut rng: R) {
+    let mut show_stats = false;
+    let mut show_income = false;
+    let mut log_scroll: usize = 0;
+    let mut hand_off = HandOff::new(state.hot_seat);
+    let mut chat = ChatBox::new();
+    let mut last_saved = None;
+    let mut recorded_end = false;
+    let mut next_bot_move = 0.0;
+
+    // let mut state = GameState::new(1, num_cpus, &mut rng);
+    // state.board.place_settlement([2, 2, 3], PlayerColor::Blue);
+    // state.board.place_settlement([2, 2, 5], PlayerColor::Red);
+    // state.board.place_settlement([2, 2, 1], PlayerColor::Orange);
+    // state.board.place_settlement([0, 2, 0], PlayerColor::Red);
+
+    let mut coords = ScreenCoords::new(&state.board.layout, state.get_menu().len());
+
+    loop {
+        coords.update(&state.board.layout);
+
+        if hand_off.is_waiting(state.get_current_player()) {
+            if is_mouse_button_pressed(MouseButton::Left) {
+                hand_off.reveal(state.get_current_color());
+            }
+            render_curtain(&coords, state.get_current_color());
+            window::next_frame().await;
+            continue;
+        }
+
+        // Hot-seat players chat as whoever has the screen
+        let viewer = hand_off.get_viewer(&state.players, state.current_player);
+        if state.hot_seat {
+            if let Some(text) = chat.handle_keys() {
+                state.log.add(LogEvent::Chat(viewer, text));
+            }
+        }
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let size = coords.toggle_button_size;
+            let canned = if state.hot_seat && !show_stats { handle_canned_click(&coords) } else { None };
+            if mouse_is_on_rect(mouse_position(), coords.stats_button, size, size) {
+                show_stats = !show_stats;
+            } else if mouse_is_on_rect(mouse_position(), coords.income_button, size, size) {
+                show_income = !show_income;
+            } else if state.winner.is_some() && mouse_is_on_rect(mouse_position(), coords.quit_button, size, size) {
+                return;
+            } else if let Some(card) = canned {
+                state.log.add(LogEvent::Chat(viewer, canned_chat(card)));
+                if state.winner.is_none() && state.get_current_color() == viewer {
+                    state.open_trade_menu_for(card);
+                }
+            } else if !show_stats && state.winner.is_none() && state.bots[state.current_player].is_none() {
+                if let Some(mv) = handle_click(&mut state, &coords) {
+                    state.apply_move(&mv, &mut rng);
+                }
+            }
+        }
+        match state.bots[state.current_player] {
+            Some(bot) if state.winner.is_none() && state.rng_action.is_none() => if get_time() >= next_bot_move {
+                let mv = bot.play_step(&PlayerView::new(&state, state.get_current_color()), &mut rng);
+                state.apply_move(&mv, &mut rng);
+                next_bot_move = get_time() + BOT_DELAY;
+            },
+            _ => next_bot_move = get_time() + BOT_DELAY
+        }
+        if state.companion {
+            handle_dice_keys(&mut state);
+        }
+        let [x, y, width, height] = coords.log_zone;
+        if mouse_is_on_rect(mouse_position(), [x, y], width, height) {
+            let wheel = mouse_wheel().1;
+            if wheel > 0.0 {
+                log_scroll += 1;
+            } else if wheel < 0.0 {
+                log_scroll = log_scroll.saturating_sub(1);
+            }
+        }
+        log_scroll = log_scroll.min(state.log.size().saturating_sub(1));
+
+        state.resolve_rng_action(&mut rng);
+        state.check_for_winner();
+
+        // Saved as each turn starts, so closing the window loses at most one turn.
+        // The recording for `--replay` gets the same positions and the final one.
+        let turn = (state.round, state.turn_player);
+        if state.is_turn_start() && last_saved != Some(turn) {
+            if let Err(err) = save::save(&state, SAVED_GAME_PATH) {
+                eprintln!("Warning: game not saved: {}", err);
+            }
+            if let Err(err) = save::record(&state, RECORDED_GAME_PATH, last_saved.is_none()) {
+                eprintln!("Warning: game not recorded: {}", err);
+            }
+            last_saved = Some(turn);
+        }
+        if state.winner.is_some() && !recorded_end {
+            if let Err(err) = save::record(&state, RECORDED_GAME_PATH, false) {
+                eprintln!("Warning: game not recorded: {}", err);
+            }
+            recorded_end = true;
+        }
+
+        let view = PlayerView::new(&state, hand_off.get_viewer(&state.players, state.current_player));
+        render_screen(&coords, &view);
+        render_log(&coords, &view, log_scroll);
+        if state.hot_seat {
+            render_chat(&coords, chat.get_draft());
+        }
+        if show_income {
+            render_income(&coords, &view);
+        }
+        if show_stats {
+            render_stats(&coords, &view);
+        }
+
+        window::next_frame().await
+    }
+}
+
+/// Seconds between tries to get back to the server
+const RECONNECT_DELAY: f64 = 3.0;
+
+/// Tries the server again once a lost connection's wait is up
+fn reconnect_when_due(connection: &mut Connection, reconnect_at: &mut Option<f64>, status: &mut String) {
+    if reconnect_at.is_some_and(|at| get_time() >= at) {
+        *reconnect_at = match connection.reconnect() {
+            Ok(()) => None,
+            Err(err) => {
+                *status = format!("{err}; reconnecting");
+                Some(get_time() + RECONNECT_DELAY)
+            }
+        };
+    }
+}
+
+/// Spectators look through the eyes of whichever seat they click in the summary
+fn handle_seat_switch_click(state: &GameState, coords: &ScreenCoords) -> Option<PlayerColor> {
+    let [x, y, width, height] = coords.summary_zone;
+    let mouse_pos = mouse_position();
+    if !mouse_is_on_rect(mouse_pos, [x, y], width, height) {
+        return None;
+    }
+    let row = ((mouse_pos.1 - y) / (height / state.num_players as f32)) as usize;
+    state.players.get(row).map(|player| player.get_color())
+}
+
+/// A seat at a game on a `catan-server`, or a spectator's place at one.
+/// The server keeps the real game; this shows the last copy it sent and
+/// passes on the moves made here. A lost connection is tried again until
+/// the seat is taken back. Spectators can leave with Escape.
+///
+/// `seat` and `watching` are what a lobby already heard before the game began:
+/// the seat played, or the one a spectator is looking through, and every
+/// hand showing and how many moves behind for a spectator.
+async fn play_online(connection: &mut Connection, mut seat: Option<PlayerColor>, mut watching: Option<(bool, usize)>) {
+    let mut table: Option<Table> = None;
+    let mut coords: Option<ScreenCoords> = None;
+    let mut status = String::from("Waiting for the server");
+    let mut show_stats = false;
+    let mut show_income = false;
+    let mut reconnect_at = None;
+    let mut chat = ChatBox::new();
+
+    loop {
+        while let Some(message) = connection.poll() {
+            match message {
+                Message::Seat(color, token) => {
+                    println!("Seated as {:?}; --resume {token} takes the seat back", color);
+                    seat = Some(color);
+                },
+                Message::Watching(caster, delay) => watching = Some((caster, delay)),
+                // A complaint about the last move is done with once the game moves on
+                Message::State(new_table) => {
+                    if watching.is_some() && seat.is_none() {
+                        seat = new_table.get_players().first().map(|player| player.get_color());
+                    }
+                    table = Some(*new_table);
+                    status.clear();
+                },
+                Message::Chat(color, text) => if let Some(table) = table.as_mut() {
+                    table.add_chat(color, text);
+                },
+                Message::Notice(text) | Message::Error(text) => status = text,
+                Message::Closed(err) => {
+                    status = format!("{err}; reconnecting");
+                    reconnect_at = Some(get_time() + RECONNECT_DELAY);
+                },
+                Message::Rooms(_) | Message::Room(_) => (),
+            }
+        }
+        reconnect_when_due(connection, &mut reconnect_at, &mut status);
+        if watching.is_some() && is_key_pressed(KeyCode::Escape) {
+            return;
+        }
+        let (Some(color), Some(table)) = (seat, table.as_mut()) else {
+            render_waiting(&status);
+            window::next_frame().await;
+            continue;
+        };
+
+        let (layout, ruleset) = match table {
+            Table::Setup(state) => (&state.board.layout, state.ruleset),
+            Table::Playing(state) => (&state.board.layout, state.ruleset),
+        };
+        let coords = coords.get_or_insert_with(|| ScreenCoords::new(layout, menu_buttons(ruleset).len()));
+        coords.update(layout);
+
+        let clicked = is_mouse_button_pressed(MouseButton::Left);
+        let my_move = watching.is_none() && table.get_current_color() == color && !table.is_over();
+        // Only the seated talk; a canned question goes out the moment it's clicked
+        let canned = if clicked && watching.is_none() && !show_stats { handle_canned_click(coords) } else { None };
+        let said = if watching.is_none() { chat.handle_keys() } else { None };
+        if let Some(text) = said.or(canned.map(canned_chat)) {
+            if let Err(err) = connection.send_chat(&text) {
+                status = err;
+            }
+        }
+        let mut mv = None;
+        match table {
+            Table::Setup(state) => {
+                if clicked && my_move && canned.is_none() {
+                    mv = handle_setup_click(state, coords);
+                }
+                render_setup_screen(coords, state, color);
+                if watching.is_none() {
+                    render_chat(coords, chat.get_draft());
+                }
+            },
+            Table::Playing(state) => {
+                let size = coords.toggle_button_size;
+                if clicked && mouse_is_on_rect(mouse_position(), coords.stats_button, size, size) {
+                    show_stats = !show_stats;
+                } else if clicked && mouse_is_on_rect(mouse_position(), coords.income_button, size, size) {
+                    show_income = !show_income;
+                } else if clicked && state.winner.is_some() && mouse_is_on_rect(mouse_position(), coords.quit_button, size, size) {
+                    return;
+                } else if let Some(card) = canned {
+                    if my_move {
+                        state.open_trade_menu_for(card);
+                    }
+                } else if clicked && my_move && !show_stats {
+                    mv = handle_click(state, coords);
+                } else if clicked && watching.is_some() && !show_stats {
+                    seat = handle_seat_switch_click(state, coords).or(seat);
+                }
+                let view = match watching {
+                    Some((caster, _)) => PlayerView::spectating(state, color, caster),
+                    None => PlayerView::new(state, color)
+                };
+                render_screen(coords, &view);
+                render_log(coords, &view, 0);
+                if watching.is_none() {
+                    render_chat(coords, chat.get_draft());
+                }
+                if show_income {
+                    render_income(coords, &view);
+                }
+                if show_stats {
+                    render_stats(coords, &view);
+                }
+            },
+        }
+        // The move is made once the server sends the game back
+        if let Some(mv) = mv {
+            if let Err(err) = connection.send(table, &mv) {
+                status = err;
+            }
+        }
+        match watching {
+            Some((caster, delay)) if status.is_empty() => {
+                let hands = if caster {"every hand showing"} else {"hands face down"};
+                let watch_status = format!(
+                    "Watching through {:?}'s eyes, {hands}, {delay} moves behind (click a seat to switch)", color
+                );
+                render_online_status(coords, &watch_status);
+            },
+            _ => render_online_status(coords, &status)
+        }
+
+        window::next_frame().await
+    }
+}
+
+/// A sitting at a game played by file. Whoever opened the file plays the
+/// first person to act, for as long as they're the one acting; the CPUs
+/// move in between, and every move goes straight into the file. Once
+/// someone else is up, the file is theirs to open next, and Escape
+/// goes back to the start screen.
+async fn play_by_file(mut game: Correspondence) {
+    let humans: Vec<PlayerColor> = game.get_table().get_players().iter()
+        .filter(|player| player.is_human())
+        .map(|player| player.get_color())
+        .collect();
+    let mut seat = None;
+    let mut table = None;
+    let mut coords: Option<ScreenCoords> = None;
+    let mut status = format!("Opened {} at {:016x}", game.get_path(), game.get_head());
+    let mut show_stats = false;
+    let mut show_income = false;
+    let mut next_bot_move = get_time() + BOT_DELAY;
+
+    loop {
+        let current = game.get_table().get_current_color();
+        let over = game.get_table().is_over();
+        let bot_to_move = !over && game.get_table().get_current_bot().is_some();
+        if seat.is_none() && !over && !bot_to_move {
+            seat = Some(current);
+        }
+        let my_move = !over && !bot_to_move && seat == Some(current);
+        if bot_to_move && get_time() >= next_bot_move {
+            if let Err(err) = game.play_bot() {
+                status = err;
+            }
+            table = None;
+            next_bot_move = get_time() + BOT_DELAY;
+        }
+        if !my_move && !bot_to_move && is_key_pressed(KeyCode::Escape) {
+            return;
+        }
+
+        let color = seat.or(humans.first().copied()).unwrap_or(current);
+        let table = table.get_or_insert_with(|| game.snapshot(color));
+        let (layout, ruleset) = match table {
+            Table::Setup(state) => (&state.board.layout, state.ruleset),
+            Table::Playing(state) => (&state.board.layout, state.ruleset),
+        };
+        let coords = coords.get_or_insert_with(|| ScreenCoords::new(layout, menu_buttons(ruleset).len()));
+        coords.update(layout);
+
+        let clicked = is_mouse_button_pressed(MouseButton::Left);
+        let mut mv = None;
+        match table {
+            Table::Setup(state) => {
+                if clicked && my_move {
+                    mv = handle_setup_click(state, coords);
+                }
+                render_setup_screen(coords, state, color);
+            },
+            Table::Playing(state) => {
+                let size = coords.toggle_button_size;
+                if clicked && mouse_is_on_rect(mouse_position(), coords.stats_button, size, size) {
+                    show_stats = !show_stats;
+                } else if clicked && mouse_is_on_rect(mouse_position(), coords.income_button, size, size) {
+                    show_income = !show_income;
+                } else if clicked && over && mouse_is_on_rect(mouse_position(), coords.quit_button, size, size) {
+                    return;
+                } else if clicked && my_move && !show_stats {
+                    mv = handle_click(state, coords);
+                }
+                let view = PlayerView::new(state, color);
+                render_screen(coords, &view);
+                render_log(coords, &view, 0);
+                if show_income {
+                    render_income(coords, &view);
+                }
+                if show_stats {
+                    render_stats(coords, &view);
+                }
+            },
+        }
+        if let Some(mv) = mv {
+            status = match game.play(&mv) {
+                Ok(()) => String::new(),
+                Err(err) => err
+            };
+            // Whatever the click left half-done goes with the old copy
+            *table = game.snapshot(color);
+        }
+        if !my_move && !bot_to_move && !over && status.is_empty() {
+            status = format!(
+                "It's {:?}'s move: pass {} on at {:016x} (Escape to leave)", current, game.get_path(), game.get_head()
+            );
+        }
+        render_online_status(coords, &status);
+
+        window::next_frame().await
+    }
+}
+
+/// Rooms in the lobby's list, below the room being set up
+const LOBBY_ROOM_ROWS: usize = 6;
+/// A row for each seat of a room, and one to leave it
+const LOBBY_PANEL_ROWS: usize = MAX_SEATS + 1;
+
+enum LobbyClick {
+    Nothing,
+    EditRules,
+    Send(String),
+}
+
+fn handle_lobby_click(
+    new_game: &mut NewGame, rooms: &[RoomSummary], sitting: Option<(usize, PlayerColor)>, coords: &LobbyCoords
+) -> LobbyClick {
+    let mouse_pos = mouse_position();
+    let size = coords.button_size;
+    let [width, height] = coords.wide_button_size;
+    let on_button = |idx: usize| mouse_is_on_rect(mouse_pos, coords.buttons[idx], width, height);
+
+    for (row, room) in rooms.iter().take(LOBBY_ROOM_ROWS).enumerate() {
+        if !on_button(LOBBY_PANEL_ROWS + row) {
+            continue;
+        }
+        if room.started {
+            return LobbyClick::Send(format!("watch {}", room.id));
+        } else if room.can_join() && sitting.is_none_or(|(room_id, _)| room_id != room.id) {
+            return LobbyClick::Send(format!("join {}", room.id));
+        }
+    }
+
+    let Some((room_id, color)) = sitting else {
+        if mouse_is_on_rect(mouse_pos, coords.minus, size, size) && new_game.can_remove_seat() {
+            new_game.remove_seat();
+        } else if mouse_is_on_rect(mouse_pos, coords.plus, size, size) && new_game.can_add_seat() {
+            new_game.add_seat();
+        } else if on_button(1) {
+            new_game.cycle_board(&[]);
+        } else if on_button(2) {
+            return LobbyClick::EditRules;
+        } else if on_button(3) {
+            let mut line = format!("create {} {:?}", new_game.seats.len(), new_game.get_ruleset());
+            // House rules go along as rule and value pairs
+            if let Some(rules) = new_game.rules {
+                for word in rules.to_text().split_whitespace() {
+                    line += " ";
+                    line += word;
+                }
+            }
+            return LobbyClick::Send(line);
+        }
+        return LobbyClick::Nothing;
+    };
+
+    if on_button(LOBBY_PANEL_ROWS - 1) {
+        return LobbyClick::Send(String::from("leave"));
+    }
+    let Some(room) = rooms.iter().find(|room| room.id == room_id) else { return LobbyClick::Nothing };
+    for (idx, &(seat_color, seat)) in room.seats.iter().enumerate() {
+        if !on_button(idx) {
+            continue;
+        }
+        // Open seats cycle through the CPUs like the start screen's do
+        let line = match seat {
+            RoomSeat::Taken(ready) if seat_color == color => String::from(if ready {"unready"} else {"ready"}),
+            RoomSeat::Taken(_) => continue,
+            RoomSeat::Open => format!("bot {:?} easy", seat_color),
+            RoomSeat::Cpu(Difficulty::Easy) => format!("bot {:?} normal", seat_color),
+            RoomSeat::Cpu(Difficulty::Normal) => format!("bot {:?} hard", seat_color),
+            RoomSeat::Cpu(Difficulty::Hard) => format!("open {:?}", seat_color),
+        };
+        return LobbyClick::Send(line);
+    }
+    LobbyClick::Nothing
+}
+
+/// The rooms of a `catan-server --lobby`. A new room is set up at the top,
+/// or the seats of the one sat down in are filled there; the list below
+/// joins a room that's filling up or watches one that's playing. A room's
+/// game takes over the window once it starts, and Escape goes back to the
+/// start screen.
+async fn lobby(mut connection: Connection) {
+    let mut coords = LobbyCoords::new(LOBBY_PANEL_ROWS + LOBBY_ROOM_ROWS);
+    let mut new_game = NewGame::new(0);
+    let mut rooms = Vec::new();
+    let mut sitting = None;
+    let mut status = String::new();
+    let mut reconnect_at = None;
+
+    loop {
+        coords.update();
+        while let Some(message) = connection.poll() {
+            match message {
+                Message::Rooms(new_rooms) => rooms = new_rooms,
+                Message::Room(room) => sitting = room,
+                Message::Seat(color, token) => {
+                    println!("Seated as {:?}; --resume {token} takes the seat back", color);
+                    play_online(&mut connection, Some(color), None).await;
+                    status = connection.back_to_lobby().err().unwrap_or_default();
+                },
+                Message::Watching(caster, delay) => {
+                    play_online(&mut connection, None, Some((caster, delay))).await;
+                    status = connection.back_to_lobby().err().unwrap_or_default();
+                },
+                Message::State(_) | Message::Chat(..) => (),
+                Message::Notice(text) | Message::Error(text) => status = text,
+                Message::Closed(err) => {
+                    status = format!("{err}; reconnecting");
+                    sitting = None;
+                    reconnect_at = Some(get_time() + RECONNECT_DELAY);
+                },
+            }
+        }
+        reconnect_when_due(&mut connection, &mut reconnect_at, &mut status);
+        if is_key_pressed(KeyCode::Escape) {
+            return;
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            match handle_lobby_click(&mut new_game, &rooms, sitting, &coords) {
+                LobbyClick::Nothing => (),
+                LobbyClick::EditRules => new_game.rules = Some(configure_rules(new_game.get_rules()).await),
+                LobbyClick::Send(line) => {
+                    status.clear();
+                    if let Err(err) = connection.send_line(&line) {
+                        status = err;
+                    }
+                }
+            }
+        }
+
+        render_lobby(&coords, &new_game, &rooms, sitting, &status);
+
+        window::next_frame().await
+    }
+}
+
+/// Where the start screen sends the player
+enum MenuChoice {
+    /// The table is dealt, and the rng carries on from the seed into the game
+    NewGame(Box<(SetupState, StdRng)>),
+    SavedGame(Box<GameState>),
+}
+
+enum MainMenuClick {
+    Nothing,
+    EditRules,
+    Load,
+    Start,
+}
+
+fn random_seed() -> u64 {
+    rand::rng().random_range(0..1_000_000)
+}
+
+fn handle_main_menu_click(new_game: &mut NewGame, coords: &MainMenuCoords, scenarios: &[String]) -> MainMenuClick {
+    let mouse_pos = mouse_position();
+    let size = coords.button_size;
+    let [wide_width, wide_height] = coords.wide_button_size;
+
+    if mouse_is_on_rect(mouse_pos, coords.seats_minus, size, size) && new_game.can_remove_seat() {
+        new_game.remove_seat();
+    } else if mouse_is_on_rect(mouse_pos, coords.seats_plus, size, size) && new_game.can_add_seat() {
+        new_game.add_seat();
+    } else if mouse_is_on_rect(mouse_pos, coords.board_button, wide_width, wide_height) {
+        new_game.cycle_board(scenarios);
+    } else if mouse_is_on_rect(mouse_pos, coords.rules_button, wide_width, wide_height) {
+        return MainMenuClick::EditRules;
+    } else if mouse_is_on_rect(mouse_pos, coords.seed_button, wide_width, wide_height) {
+        new_game.seed = random_seed();
+    }
+
+    for idx in 0..new_game.seats.len() {
+        if mouse_is_on_rect(mouse_pos, coords.swatches[idx], size, size) {
+            new_game.cycle_color(idx);
+        } else if mouse_is_on_rect(mouse_pos, coords.kind_buttons[idx], wide_width, wide_height) {
+            new_game.cycle_kind(idx);
+        }
+    }
+
+    let [width, height] = coords.bottom_button_size;
+    let [load, start] = coords.bottom_buttons;
+    if mouse_is_on_rect(mouse_pos, load, width, height) {
+        return MainMenuClick::Load;
+    } else if mouse_is_on_rect(mouse_pos, start, width, height) {
+        return MainMenuClick::Start;
+    }
+    MainMenuClick::Nothing
+}
+
+/// The start screen. Typing digits sets the seed.
+async fn main_menu(new_game: &mut NewGame) -> MenuChoice {
+    let mut coords = MainMenuCoords::new(MAX_SEATS);
+    let scenarios = find_scenarios(SCENARIOS_DIR);
+    let mut status = String::new();
+    loop {
+        coords.update();
+
+        if is_key_pressed(KeyCode::Backspace) {
+            new_game.erase_seed_digit();
+        }
+        while let Some(key) = get_char_pressed() {
+            if let Some(digit) = key.to_digit(10) {
+                new_game.type_seed_digit(digit as u64);
+            }
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            match handle_main_menu_click(new_game, &coords, &scenarios) {
+                MainMenuClick::Nothing => (),
+                MainMenuClick::EditRules => new_game.rules = Some(configure_rules(new_game.get_rules()).await),
+                MainMenuClick::Load => match save::load(SAVED_GAME_PATH) {
+                    Ok(state) => return MenuChoice::SavedGame(Box::new(state)),
+                    Err(err) => status = err
+                },
+                MainMenuClick::Start => {
+                    let mut rng = StdRng::seed_from_u64(new_game.seed);
+                    match SetupState::from_new_game(new_game, &mut rng) {
+                        Ok(state) => return MenuChoice::NewGame(Box::new((state, rng))),
+                        Err(err) => status = err
+                    }
+                },
+            }
+        }
+
+        render_main_menu(&coords, new_game, &status);
+
+        window::next_frame().await
+    }
+}
+
+/// Steps through a recorded game with the arrow keys. Escape, or the Menu
+/// button on the final position, goes back to the start screen.
+async fn replay_game(positions: Vec<GameState>) {
+    let mut idx = 0;
+    let mut coords = ScreenCoords::new(&positions[0].board.layout, positions[0].get_menu().len());
+    loop {
+        let state = &positions[idx];
+        coords.update(&state.board.layout);
+
+        let size = coords.toggle_button_size;
+        if is_key_pressed(KeyCode::Escape)
+        || state.winner.is_some() && is_mouse_button_pressed(MouseButton::Left)
+        && mouse_is_on_rect(mouse_position(), coords.quit_button, size, size) {
+            return;
+        }
+        if is_key_pressed(KeyCode::Right) {
+            idx = (idx + 1).min(positions.len() - 1);
+        } else if is_key_pressed(KeyCode::Left) {
+            idx = idx.saturating_sub(1);
+        }
+
+        let state = &positions[idx];
+        render_screen(&coords, &PlayerView::new(state, state.players[state.turn_player].get_color()));
+        render_replay_status(&coords, state, idx, positions.len());
+
+        window::next_frame().await
+    }
+}
+
+/// Rounds a headless game may run before it's called off without a winner
+const HEADLESS_MAX_ROUNDS: usize = 500;
+
+/// Bot-only games with no window. Each game is dealt from the next seed
+/// along, and the last one is left in `RECORDED_GAME_PATH`.
+fn play_headless(options: &Options) -> Result<(), String> {
+    let first_seed = options.seed.unwrap_or_else(random_seed);
+    let num_games = options.games.unwrap_or(1);
+    let difficulty = options.difficulty.unwrap_or(Difficulty::Normal);
+    let mut wins = [0; PLAYER_COLORS.len()];
+    let mut unfinished = 0;
+
+    for idx in 0..num_games {
+        let seed = first_seed.wrapping_add(idx as u64);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut state = match &options.load {
+            Some(path) => {
+                let mut state = save::load(path)?;
+                // Human seats are handed to bots as well
+                for bot in state.bots.iter_mut() {
+                    bot.get_or_insert(Bot::new(difficulty));
+                }
+                state
+            },
+            None => {
+                let setup_state = SetupState::from_new_game(&options.new_game(seed)?, &mut rng)?;
+                bot::play_setup(setup_state, &mut rng)
+            }
+        };
+
+        let mut first = true;
+        bot::play_out(&mut state, &mut rng, HEADLESS_MAX_ROUNDS, |state| {
+            if let Err(err) = save::record(state, RECORDED_GAME_PATH, first) {
+                eprintln!("Warning: game not recorded: {}", err);
+            }
+            first = false;
+        });
+
+        let scores: Vec<String> = state.players.iter()
+            .map(|player| format!("{:?} {}", player.get_color(), player.get_vps()))
+            .collect();
+        let result = match state.winner {
+            Some(winner) => {
+                wins[winner as usize] += 1;
+                format!("{:?} won in round {}", winner, state.round + 1)
+            },
+            None => {
+                unfinished += 1;
+                format!("no winner after {} rounds", state.round)
+            }
+        };
+        println!("Game {} (seed {}): {}; {}", idx + 1, seed, result, scores.join(", "));
+    }
+
+    if num_games > 1 {
+        let totals: Vec<String> = PLAYER_COLORS.iter()
+            .filter(|&&color| wins[color as usize] > 0)
+            .map(|&color| format!("{:?} {}", color, wins[color as usize]))
+            .collect();
+        println!("Wins: {}; unfinished {}", totals.join(", "), unfinished);
+    }
+    Ok(())
+}
+
+/// A bot-only game on `board`, dealt from `seed` and played `rounds` rounds in
+#[cfg(test)]
+fn test_game(board: BoardChoice, seed: u64, rounds: usize) -> GameState {
+    let mut new_game = NewGame::new(seed);
+    new_game.board = board;
+    play_test_game(new_game, rounds)
+}
+
+/// Bots play every seat of `new_game` for `rounds` rounds, then hand
+/// the human seats back
+#[cfg(test)]
+fn play_test_game(new_game: NewGame, rounds: usize) -> GameState {
+    let mut rng = StdRng::seed_from_u64(new_game.seed);
+    let mut setup_state = SetupState::from_new_game(&new_game, &mut rng).unwrap();
+    setup_state.bots.fill(Some(Bot::new(Difficulty::Normal)));
+    let mut state = bot::play_setup(setup_state, &mut rng);
+    bot::play_out(&mut state, &mut rng, rounds, |_| ());
+    for (bot, seat) in state.bots.iter_mut().zip(new_game.seats.iter()) {
+        if seat.kind == SeatKind::Human {
+            *bot = None;
+        }
+    }
+    state
+}
+
+/// What the window shows before the start screen
+enum Opening {
+    Menu,
+    Companion(usize),
+    Online(Connection),
+    Lobby(Connection),
+    ByFile(Box<Correspondence>),
+    Replay(Vec<GameState>),
+    Game(MenuChoice),
+}
+
+/// Reads everything the options name up front, so a bad file is reported
+/// before the window opens
+fn opening(options: &Options) -> Result<Opening, String> {
+    if let Some(num_players) = options.companion {
+        return Ok(Opening::Companion(num_players));
+    }
+    if let Some(path) = &options.replay {
+        return Ok(Opening::Replay(save::load_recording(path)?));
+    }
+    if let Some(address) = &options.join {
+        let request = match &options.resume {
+            Some(token) => Request::Resume(token.clone()),
+            None => Request::Join
+        };
+        return Ok(Opening::Online(Connection::open(address, request)?));
+    }
+    if let Some(address) = &options.watch {
+        return Ok(Opening::Online(Connection::open(address, Request::Watch)?));
+    }
+    if let Some(address) = &options.rooms {
+        return Ok(Opening::Lobby(Connection::open(address, Request::Lobby)?));
+    }
+    if let Some(path) = &options.turn {
+        if !options.sets_up_game() {
+            return Ok(Opening::ByFile(Box::new(Correspondence::open(path)?)));
+        }
+        let seed = options.seed.unwrap_or_else(random_seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let state = SetupState::from_new_game(&options.new_game(seed)?, &mut rng)?;
+        return Ok(Opening::ByFile(Box::new(Correspondence::create(path, seed, state)?)));
+    }
+    if let Some(path) = &options.load {
+        return Ok(Opening::Game(MenuChoice::SavedGame(Box::new(save::load(path)?))));
+    }
+    if !options.sets_up_game() {
+        return Ok(Opening::Menu);
+    }
+    let seed = options.seed.unwrap_or_else(random_seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let state = SetupState::from_new_game(&options.new_game(seed)?, &mut rng)?;
+    Ok(Opening::Game(MenuChoice::NewGame(Box::new((state, rng)))))
+}
+
+/// The game the options set up, or the one they `--load`, for the
+/// frontends that don't open a window
+fn deal(options: &Options) -> Result<(Table, StdRng), String> {
+    let seed = options.seed.unwrap_or_else(random_seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let table = match &options.load {
+        Some(path) => Table::Playing(Box::new(save::load(path)?)),
+        None => {
+            let state = SetupState::from_new_game(&options.new_game(seed)?, &mut rng)?;
+            println!("Dealt from seed {seed}");
+            Table::Setup(Box::new(state))
+        }
+    };
+    Ok((table, rng))
+}
+
+async fn run(opening: Opening) {
+    let mut choice = match opening {
+        Opening::Menu => None,
+        Opening::Companion(num_players) => {
+            play_companion_game(num_players).await;
+            None
+        },
+        Opening::Replay(positions) => {
+            replay_game(positions).await;
+            None
+        },
+        Opening::Online(mut connection) => {
+            play_online(&mut connection, None, None).await;
+            None
+        },
+        Opening::Lobby(connection) => {
+            lobby(connection).await;
+            None
+        },
+        Opening::ByFile(game) => {
+            play_by_file(*game).await;
+            None
+        },
+        Opening::Game(choice) => Some(choice)
+    };
+
+    let mut new_game = NewGame::new(random_seed());
+    loop {
+        let next = match choice.take() {
+            Some(choice) => choice,
+            None => main_menu(&mut new_game).await
+        };
+        match next {
+            MenuChoice::NewGame(table) => {
+                let (state, mut rng) = *table;
+                let state = setup_game(state, &mut rng).await;
+                play_game(state, rng).await
+            },
+            MenuChoice::SavedGame(state) => play_game(*state, rand::rng()).await
+        }
+    }
+}
+
+pub fn main() {
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("Error: {}\n\n{}", err, USAGE);
+        std::process::exit(2)
+    });
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if options.port.is_some() || options.timeout.is_some() || options.save.is_some() || options.caster || options.delay.is_some()
+    || options.lobby {
+        eprintln!("Error: --port, --timeout, --save, --caster, --delay and --lobby are for catan-server\n\n{}", USAGE);
+        std::process::exit(2)
+    }
+    if options.tui {
+        if let Err(err) = tui::play(&options) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1)
+        }
+        return;
+    }
+    if let Some(path) = &options.svg {
+        if let Err(err) = deal(&options).and_then(|(table, _)| svg::save_svg(&table, path)) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1)
+        }
+        return;
+    }
+    if options.repl {
+        if let Err(err) = tui::repl(&options) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1)
+        }
+        return;
+    }
+    if options.headless {
+        if let Err(err) = play_headless(&options) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1)
+        }
+        return;
+    }
+
+    let opening = opening(&options).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(1)
+    });
+    macroquad::Window::new("Catan", run(opening));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The seat to act, on a 2:1 wood port and a 3:1 port
+    fn at_the_docks() -> GameState {
+        let mut state = test_game(BoardChoice::Random, 1, 0);
+        let color = state.get_current_color();
+        let board = &mut state.board;
+        board.ports.fill(Port::ThreeForOne);
+        board.ports[0] = Port::TwoForOne(Resource::Wood);
+        for idx in 0..2 {
+            let corner = board.layout.port_coords[idx];
+            board.place_setup_settlement(corner, color);
+        }
+        state
+    }
+
+    fn hand(cards: &[(Resource, usize)]) -> ResHand {
+        let mut hand = ResHand::new();
+        for &(res, count) in cards {
+            hand[res] += count;
+        }
+        hand
+    }
+
+    /// A bot-only game with `seats` seats, at the turn player's start
+    fn seated(seats: usize, seed: u64) -> GameState {
+        let mut new_game = NewGame::new(seed);
+        while new_game.seats.len() < seats {
+            new_game.add_seat();
+        }
+        play_test_game(new_game, 0)
+    }
+
+    #[test]
+    fn six_players_get_the_extension_board() {
+        let mut new_game = NewGame::new(4);
+        new_game.add_seat();
+        new_game.add_seat();
+        let setup_state = SetupState::from_new_game(&new_game, &mut StdRng::seed_from_u64(4)).unwrap();
+        let board = &setup_state.board;
+        assert_eq!(setup_state.num_players, 6);
+        assert_eq!(board.layout.hex_coords.len(), 30);
+        assert_eq!(board.ports.len(), 11);
+        assert_eq!(board.bank, ResHand::from_count(24));
+        assert_eq!(board.get_dv_bank_size(), 34);
+    }
+
+    #[test]
+    fn four_players_keep_the_base_board() {
+        let new_game = NewGame::new(4);
+        let setup_state = SetupState::from_new_game(&new_game, &mut StdRng::seed_from_u64(4)).unwrap();
+        let board = &setup_state.board;
+        assert_eq!(board.layout.hex_coords.len(), 19);
+        assert_eq!(board.ports.len(), 9);
+        assert_eq!(board.bank, ResHand::from_count(19));
+        assert_eq!(board.get_dv_bank_size(), 25);
+    }
+
+    #[test]
+    fn the_others_build_between_turns_with_five_players() {
+        let mut state = seated(5, 2);
+        let mut rng = StdRng::seed_from_u64(0);
+        let turn = state.turn_player;
+        state.roll = Some([3, 4]);
+        state.apply_move(&Move::Pass, &mut rng);
+        for i in 1..5 {
+            assert!(state.special_building);
+            assert_eq!(state.turn_player, turn);
+            assert_eq!(state.current_player, (turn + i) % 5);
+            // Only building: no cards and no trades
+            let player = state.get_current_player_mut();
+            player.get_cards(ResHand::from_monopoly(Resource::Wood, 4));
+            assert!(!state.can_make_move(&Move::BankTrade(
+                ResHand::from_monopoly(Resource::Wood, 4), ResHand::from_monopoly(Resource::Ore, 1))));
+            assert!(state.can_make_move(&Move::Pass));
+            state.apply_move(&Move::Pass, &mut rng);
+        }
+        assert!(!state.special_building);
+        assert_eq!(state.turn_player, (turn + 1) % 5);
+        assert_eq!(state.current_player, state.turn_player);
+        assert_eq!(state.roll, None);
+    }
+
+    #[test]
+    fn four_players_pass_straight_to_the_next_turn() {
+        let mut state = seated(4, 2);
+        let turn = state.turn_player;
+        state.roll = Some([3, 4]);
+        state.apply_move(&Move::Pass, &mut StdRng::seed_from_u64(0));
+        assert!(!state.special_building);
+        assert_eq!(state.turn_player, (turn + 1) % 4);
+        assert_eq!(state.current_player, state.turn_player);
+    }
+
+    /// New Shores with every piece swept off and the pirate gone, the turn player having rolled.
+    /// Only the main island counts as a home island.
+    fn at_new_shores() -> GameState {
+        let scenario = format!("{SCENARIOS_DIR}/new_shores.txt");
+        let mut state = test_game(BoardChoice::Scenario(scenario), 6, 0);
+        state.current_player = state.turn_player;
+        state.roll = Some([3, 4]);
+        state.action = Action::Idling;
+        state.selector = None;
+        state.rng_action = None;
+        let board = &mut state.board;
+        for corners in board.structures.iter_mut().flatten() {
+            corners.fill(None);
+        }
+        for edges in board.roads.iter_mut().flatten().chain(board.ships.iter_mut().flatten()) {
+            edges.fill(None);
+        }
+        board.pirate = None;
+        // As if everyone had started round the desert on the main island
+        state.home_islands = vec![board.island_of(board.robber).unwrap()];
+        state
+    }
+
+    /// A corner on the coast of the main island
+    fn home_coast(state: &GameState) -> [usize; 3] {
+        let board = &state.board;
+        board.layout.corner_coords.iter().copied()
+            .find(|&corner| board.layout.hexes_touched(corner).any(|hex| board.is_sea(hex))
+                && board.corner_island(corner).is_some_and(|island| state.home_islands.contains(&island)))
+            .unwrap()
+    }
+
+    #[test]
+    fn ships_sail_from_the_coast_and_move_once_a_turn() {
+        let mut state = at_new_shores();
+        let mut rng = StdRng::seed_from_u64(0);
+        let color = state.get_current_color();
+        state.board.place_setup_settlement(home_coast(&state), color);
+        let hand = state.get_current_player().get_hand();
+        state.get_current_player_mut().get_cards(SHIP_HAND);
+        let first = *state.board.layout.edge_coords.iter()
+            .find(|&&edge| state.board.can_place_ship(edge, color))
+            .unwrap();
+        assert!(state.board.is_sea_edge(first));
+        assert!(state.can_make_move(&Move::BuildShip(first)));
+        state.apply_move(&Move::BuildShip(first), &mut rng);
+        assert!(state.board.ship_is_color(first, color));
+        assert_eq!(state.get_current_player().get_hand(), hand);
+
+        // A ship can't sail the turn it was built
+        let mut lifted = state.board.clone();
+        lifted.remove_ship(first);
+        let layout = &state.board.layout;
+        let second = *layout.edge_coords.iter()
+            .find(|&&edge| layout.reduce_edge(edge) != layout.reduce_edge(first) && lifted.can_place_ship(edge, color))
+            .unwrap();
+        assert!(!state.can_make_move(&Move::ShiftShip(first, second)));
+
+        for _ in 0..state.num_players {
+            state.roll = Some([3, 4]);
+            state.apply_move(&Move::Pass, &mut rng);
+        }
+        state.roll = Some([3, 4]);
+        assert!(state.can_make_move(&Move::ShiftShip(first, second)));
+        state.apply_move(&Move::ShiftShip(first, second), &mut rng);
+        assert!(!state.board.ship_is_color(first, color));
+        assert!(state.board.ship_is_color(second, color));
+        assert!(!state.can_make_move(&Move::ShiftShip(second, first)));
+    }
+
+    #[test]
+    fn the_pirate_robs_ships_and_holds_them_still() {
+        let mut state = at_new_shores();
+        let color = state.get_current_color();
+        let other = state.players[(state.turn_player + 1) % state.num_players].get_color();
+        let sea = *state.board.layout.hex_coords.iter().find(|&&hex| state.board.is_sea(hex)).unwrap();
+        let edge = *state.board.layout.edge_coords.iter()
+            .find(|&&edge| state.board.layout.edge_hexes(edge).any(|hex| hex == sea))
+            .unwrap();
+        state.board.place_setup_ship(edge, other);
+        assert!(state.board.is_open_ship(edge, other));
+        let victim = state.get_player_mut(other).unwrap();
+        victim.discard_cards(victim.get_hand());
+        victim.get_cards(ResHand::from_monopoly(Resource::Ore, 1));
+        let ore = state.get_current_player().get_hand()[Resource::Ore];
+
+        state.action = Action::MovingRobber;
+        assert!(state.can_make_move(&Move::PlaceRobber(sea)));
+        state.apply_move(&Move::PlaceRobber(sea), &mut StdRng::seed_from_u64(0));
+        assert_eq!(state.board.pirate, Some(sea));
+        assert_eq!(state.get_player(other).unwrap().get_hand(), ResHand::new());
+        assert_eq!(state.get_current_player().get_hand()[Resource::Ore], ore + 1);
+        assert!(!state.board.is_open_ship(edge, other));
+        assert!(!state.board.is_pirate_robbable(edge, other));
+        assert!(state.board.is_pirate_robbable(edge, color));
+    }
+
+    #[test]
+    fn gold_fields_owe_a_pick_per_building() {
+        let mut state = at_new_shores();
+        let other = (state.turn_player + 1) % state.num_players;
+        let other_color = state.players[other].get_color();
+        let [r, q] = *state.board.layout.hex_coords.iter()
+            .find(|&&[r, q]| state.board.hexes[r][q].is_some_and(|hex| hex.terrain == Terrain::Gold))
+            .unwrap();
+        let number = state.board.hexes[r][q].unwrap().number;
+        state.board.place_setup_settlement([r, q, 0], state.get_current_color());
+        state.board.place_setup_city([r, q, 3], other_color);
+        state.give_resources(number);
+        assert!(matches!(state.action, Action::ChoosingGold));
+        assert_eq!(state.gold_owed[state.turn_player], 1);
+        assert_eq!(state.gold_owed[other], 2);
+
+        // Nothing comes from under the robber
+        state.action = Action::Idling;
+        state.gold_owed.fill(0);
+        state.board.robber = [r, q];
+        state.give_resources(number);
+        assert!(matches!(state.action, Action::Idling));
+        assert!(state.gold_owed.iter().all(|&owed| owed == 0));
+    }
+
+    #[test]
+    fn the_first_settlement_on_a_new_island_earns_the_bonus() {
+        let mut state = at_new_shores();
+        let vps = |state: &GameState| state.get_current_player().get_vps();
+        let [r, q] = *state.board.layout.hex_coords.iter()
+            .find(|&&hex| state.board.island_of(hex).is_some_and(|island| !state.home_islands.contains(&island)))
+            .unwrap();
+        let before = vps(&state);
+        for corner in [[r, q, 0], [r, q, 3]] {
+            state.get_current_player_mut().get_cards(SETTLEMENT_HAND);
+            state.build_settlement(corner);
+        }
+        assert_eq!(state.island_bonus, 2);
+        assert_eq!(vps(&state), before + 2 + 2);
+
+        let before = vps(&state);
+        state.get_current_player_mut().get_cards(SETTLEMENT_HAND);
+        state.build_settlement(home_coast(&state));
+        assert_eq!(vps(&state), before + 1);
+    }
+
+    /// A Cities & Knights game with the board swept, the turn player having rolled
+    fn swept_cities_knights() -> GameState {
+        let mut state = test_game(BoardChoice::CitiesKnights, 5, 0);
+        state.current_player = state.turn_player;
+        state.roll = Some([3, 4]);
+        state.action = Action::Idling;
+        state.selector = None;
+        state.rng_action = None;
+        for corners in state.board.structures.iter_mut().flatten() {
+            corners.fill(None);
+        }
+        for corners in state.board.knights.iter_mut().flatten() {
+            corners.fill(None);
+        }
+        state
+    }
+
+    /// The player `i` seats after the turn player
+    fn seat(state: &GameState, i: usize) -> usize {
+        (state.turn_player + i) % state.num_players
+    }
+
+    /// Progress cards in hand, counting the VP cards already turned over
+    fn progress_held(player: &Player) -> usize {
+        player.get_progress_counts().iter().map(|&(_, count)| count).sum::<usize>() + player.get_vps()
+    }
+
+    fn barbarians_arrive(state: &mut GameState) {
+        state.barbarians = BARBARIAN_TRACK_LEN - 1;
+        state.event = Some(EventDie::Barbarians);
+        state.resolve_event();
+    }
+
+    #[test]
+    fn the_strongest_knights_win_a_vp_off_the_barbarians() {
+        let mut state = swept_cities_knights();
+        let corners = state.board.layout.corner_coords.clone();
+        let (defender, other) = (seat(&state, 0), seat(&state, 1));
+        let colors = [state.players[defender].get_color(), state.players[other].get_color()];
+        state.board.place_setup_city(corners[0], colors[0]);
+        state.board.place_setup_city(corners[20], colors[1]);
+        for (&corner, color) in [corners[10], corners[30], corners[40]].iter().zip([colors[0], colors[0], colors[1]]) {
+            state.board.place_knight(corner, color);
+            state.board.activate_knight(corner);
+        }
+        let vps: Vec<usize> = state.players.iter().map(|player| player.get_vps()).collect();
+        barbarians_arrive(&mut state);
+
+        assert!(state.barbarians_attacked);
+        assert_eq!(state.barbarians, 0);
+        assert_eq!(state.players[defender].get_vps(), vps[defender] + 1);
+        assert_eq!(state.players[other].get_vps(), vps[other]);
+        assert_eq!(state.board.get_cities(colors[1]).len(), 1);
+        assert_eq!(state.board.get_knight_strength(colors[0]), 0);
+    }
+
+    #[test]
+    fn the_barbarians_pillage_the_weakest_defenders() {
+        let mut state = swept_cities_knights();
+        let corners = state.board.layout.corner_coords.clone();
+        let colors: Vec<PlayerColor> = (0..3).map(|i| state.players[seat(&state, i)].get_color()).collect();
+        for (i, &color) in colors.iter().enumerate() {
+            state.board.place_setup_city(corners[20 * i], color);
+        }
+        state.board.place_knight(corners[10], colors[0]);
+        state.board.activate_knight(corners[10]);
+        barbarians_arrive(&mut state);
+
+        assert_eq!(state.board.get_cities(colors[0]).len(), 1);
+        assert!(state.board.get_cities(colors[1]).is_empty());
+        assert!(state.board.get_cities(colors[2]).is_empty());
+        assert!(state.board.structure_is_color(corners[20], colors[1]));
+    }
+
+    #[test]
+    fn the_city_gate_deals_progress_cards_to_high_enough_tracks() {
+        let mut state = swept_cities_knights();
+        let (high, low) = (seat(&state, 0), seat(&state, 1));
+        for (idx, level) in [(high, 2), (low, 1)] {
+            for _ in 0..level {
+                for _ in 0..MAX_IMPROVEMENT {
+                    state.players[idx].add_commodity(Improvement::Trade.commodity());
+                }
+                state.players[idx].improve(Improvement::Trade);
+            }
+        }
+        let held = [progress_held(&state.players[high]), progress_held(&state.players[low])];
+        let deck = state.progress_decks[Improvement::Trade.index()].len();
+        // The red die shows 3, which takes a level of 2 or more
+        state.roll = Some([4, 3]);
+        state.event = Some(EventDie::Gate(Improvement::Trade));
+        state.resolve_event();
+
+        assert_eq!(progress_held(&state.players[high]), held[0] + 1);
+        assert_eq!(progress_held(&state.players[low]), held[1]);
+        assert_eq!(state.progress_decks[Improvement::Trade.index()].len(), deck - 1);
+    }
+
+    #[test]
+    fn cities_make_a_commodity_in_place_of_the_second_card() {
+        let mut state = swept_cities_knights();
+        let (city, settlement) = (seat(&state, 0), seat(&state, 1));
+        let hex = *state.board.layout.hex_coords.iter()
+            .find(|&&[r, q]| state.board.hexes[r][q].is_some_and(|hex| hex.resource() == Some(Resource::Wood)))
+            .unwrap();
+        let [r, q] = hex;
+        let number = state.board.hexes[r][q].unwrap().number;
+        state.board.robber = *state.board.layout.hex_coords.iter().find(|&&other| other != hex).unwrap();
+        state.board.place_setup_city([r, q, 0], state.players[city].get_color());
+        state.board.place_setup_settlement([r, q, 3], state.players[settlement].get_color());
+        let hands: Vec<(ResHand, ComHand)> = state.players.iter()
+            .map(|player| (player.get_hand(), player.get_commodities()))
+            .collect();
+        state.give_resources(number);
+
+        let paper = Commodity::from_resource(Resource::Wood).unwrap();
+        let city_player = &state.players[city];
+        assert_eq!(city_player.get_hand()[Resource::Wood], hands[city].0[Resource::Wood] + 1);
+        assert_eq!(city_player.get_commodities()[paper], hands[city].1[paper] + 1);
+        let settler = &state.players[settlement];
+        assert_eq!(settler.get_hand()[Resource::Wood], hands[settlement].0[Resource::Wood] + 1);
+        assert_eq!(settler.get_commodities().size(), hands[settlement].1.size());
+    }
+
+    /// The turn player of a base game, about to resolve their roll, with everyone's hands emptied
+    fn empty_handed() -> GameState {
+        let mut state = test_game(BoardChoice::Random, 2, 0);
+        state.current_player = state.turn_player;
+        state.action = Action::Idling;
+        for player in state.players.iter_mut() {
+            player.discard_cards(player.get_hand());
+        }
+        state
+    }
+
+    #[test]
+    fn a_raised_discard_limit_spares_bigger_hands() {
+        let mut state = empty_handed();
+        state.rules.discard_limit = 9;
+        state.get_current_player_mut().get_cards(ResHand::from_monopoly(Resource::Ore, 9));
+        state.resolve_roll(7);
+        assert!(matches!(state.action, Action::MovingRobber));
+
+        state.action = Action::Idling;
+        state.get_current_player_mut().get_cards(ResHand::from_monopoly(Resource::Ore, 1));
+        state.resolve_roll(7);
+        assert!(matches!(state.action, Action::Discarding));
+        assert_eq!(state.current_player, state.turn_player);
+    }
+
+    #[test]
+    fn the_friendly_robber_passes_over_small_players() {
+        let mut state = empty_handed();
+        state.rules.friendly_robber = true;
+        let color = state.get_current_color();
+        let victim = state.players[seat(&state, 1)].get_color();
+        let hex = lone_hex(&state, victim);
+        state.get_player_mut(victim).unwrap().get_cards(ResHand::from_monopoly(Resource::Ore, 1));
+        assert!(state.get_player(victim).unwrap().get_visible_vps() <= FRIENDLY_ROBBER_VPS);
+        assert!(!state.can_rob(victim));
+
+        state.action = Action::MovingRobber;
+        state.apply_move(&Move::PlaceRobber(hex), &mut StdRng::seed_from_u64(0));
+        assert_eq!(state.board.robber, hex);
+        assert_eq!(state.get_player(victim).unwrap().get_hand().size(), 1);
+        assert_eq!(state.get_current_player().get_hand().size(), 0);
+
+        state.get_player_mut(victim).unwrap().add_bonus_vps(1);
+        assert!(state.can_rob(victim));
+        assert!(!state.can_rob(color));
+    }
+
+    #[test]
+    fn sevens_are_barred_only_in_the_opening_rounds() {
+        let mut state = empty_handed();
+        state.rules.no_sevens_rounds = 2;
+        state.round = 1;
+        assert!(state.is_barred_roll([3, 4]));
+        assert!(!state.is_barred_roll([3, 5]));
+        state.round = 2;
+        assert!(!state.is_barred_roll([3, 4]));
+    }
+
+    #[test]
+    fn the_house_bank_rate_applies_without_a_port() {
+        let mut state = empty_handed();
+        for corners in state.board.structures.iter_mut().flatten() {
+            corners.fill(None);
+        }
+        let ore = ResHand::from_monopoly(Resource::Ore, 3);
+        let brick = ResHand::from_monopoly(Resource::Brick, 1);
+        assert!(!state.can_trade_with_bank(ore, brick));
+        state.rules.bank_rate = 3;
+        assert!(state.can_trade_with_bank(ore, brick));
+    }
+
+    /// A hex where only `color` has buildings, away from the robber
+    fn lone_hex(state: &GameState, color: PlayerColor) -> [usize; 2] {
+        *state.board.layout.hex_coords.iter()
+            .find(|&&hex| {
+                let colors = state.board.get_colors_on_hex(hex);
+                hex != state.board.robber && colors.len() == 1 && colors.contains(&color)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn stats_count_discards_steals_and_trades() {
+        let mut state = empty_handed();
+        let mut rng = StdRng::seed_from_u64(0);
+        let color = state.get_current_color();
+        let victim = state.players[seat(&state, 1)].get_color();
+        state.get_current_player_mut().get_cards(ResHand::from_monopoly(Resource::Ore, 8));
+        state.get_player_mut(victim).unwrap().get_cards(ResHand::from_monopoly(Resource::Wheat, 1));
+        state.roll = Some([3, 4]);
+        state.resolve_roll(7);
+        state.apply_move(&Move::Discard(ResHand::from_monopoly(Resource::Ore, 4)), &mut rng);
+        assert_eq!(state.stats.get(color).discarded, 4);
+
+        state.apply_move(&Move::PlaceRobber(lone_hex(&state, victim)), &mut rng);
+        assert_eq!(state.stats.get(color).robber_moves, 1);
+        assert_eq!(state.stats.get(victim).lost_to_robber, 1);
+        assert_eq!(state.get_current_player().get_hand()[Resource::Wheat], 1);
+
+        let rate = state.trade_rate(color, Resource::Ore);
+        let trade = Move::BankTrade(ResHand::from_monopoly(Resource::Ore, rate), ResHand::from_monopoly(Resource::Brick, 1));
+        assert!(state.can_make_move(&trade));
+        state.apply_move(&trade, &mut rng);
+        assert_eq!(state.stats.get(color).trades, 1);
+        assert_eq!(state.stats.get(victim).trades, 0);
+        assert_eq!(state.stats.get(victim).discarded, 0);
+    }
+
+    #[test]
+    fn pips_count_the_throws_out_of_36() {
+        assert_eq!([2, 3, 6, 7, 8, 11, 12].map(pips), [1, 2, 5, 6, 5, 2, 1]);
+        assert_eq!(pips(0), 0);
+        assert_eq!(pips(13), 0);
+        assert_eq!(roll_probability(8), 5.0 / 36.0);
+    }
+
+    #[test]
+    fn the_robber_takes_its_hex_out_of_expected_income() {
+        let mut state = empty_handed();
+        for corners in state.board.structures.iter_mut().flatten() {
+            corners.fill(None);
+        }
+        let color = state.get_current_color();
+        let wood = RESOURCES.iter().position(|&res| res == Resource::Wood).unwrap();
+        let hex = *state.board.layout.hex_coords.iter()
+            .find(|&&[r, q]| state.board.hexes[r][q].is_some_and(|hex| hex.resource() == Some(Resource::Wood)))
+            .unwrap();
+        let [r, q] = hex;
+        state.board.robber = *state.board.layout.hex_coords.iter()
+            .find(|&&[r, q]| state.board.hexes[r][q].is_some_and(|hex| hex.resource().is_none()))
+            .unwrap();
+        let share = roll_probability(state.board.hexes[r][q].unwrap().number);
+
+        state.board.place_setup_settlement([r, q, 0], color);
+        let income = state.board.expected_income(color);
+        assert!(income[wood] >= share);
+        state.board.place_setup_city([r, q, 0], color);
+        let city_income = state.board.expected_income(color);
+        assert_eq!(city_income.map(|amount| amount / 2.0), income);
+
+        state.board.robber = hex;
+        let robbed = state.board.expected_income(color);
+        assert!((city_income[wood] - robbed[wood] - 2.0 * share).abs() < 1e-6);
+        // The robber's number pips still count on the board
+        assert_eq!(state.board.hex_pips(hex), pips(state.board.hexes[r][q].unwrap().number));
+    }
+
+    /// A setup with humans in the first `humans` of four seats
+    fn sharing_a_screen(humans: usize) -> SetupState {
+        let mut new_game = NewGame::new(7);
+        for seat in new_game.seats.iter_mut().take(humans) {
+            seat.kind = SeatKind::Human;
+        }
+        SetupState::from_new_game(&new_game, &mut StdRng::seed_from_u64(7)).unwrap()
+    }
+
+    #[test]
+    fn a_second_human_makes_it_hot_seat() {
+        assert!(!sharing_a_screen(1).hot_seat);
+        assert!(sharing_a_screen(2).hot_seat);
+    }
+
+    #[test]
+    fn the_screen_is_handed_to_each_human_in_turn() {
+        let players = sharing_a_screen(2).players;
+        let mut hand_off = HandOff::new(true);
+        assert!(hand_off.is_waiting(&players[0]));
+        assert!(!hand_off.is_waiting(&players[2]));
+        // Before anyone has clicked through, a bot's turn shows the first human
+        assert_eq!(hand_off.get_viewer(&players, 2), players[0].get_color());
+
+        hand_off.reveal(players[1].get_color());
+        assert!(!hand_off.is_waiting(&players[1]));
+        assert!(hand_off.is_waiting(&players[0]));
+        assert_eq!(hand_off.get_viewer(&players, 0), players[0].get_color());
+        assert_eq!(hand_off.get_viewer(&players, 3), players[1].get_color());
+
+        let alone = HandOff::new(false);
+        assert!(players.iter().all(|player| !alone.is_waiting(player)));
+    }
+
+    #[test]
+    fn a_canned_question_opens_a_trade_asking_for_the_card() {
+        let mut state = empty_handed();
+        state.roll = Some([2, 4]);
+        state.open_trade_menu_for(Resource::Sheep);
+        let Some(Selector::Trading(give, get)) = state.selector else { panic!("expected the trade menu") };
+        assert_eq!(give, ResHand::new());
+        assert_eq!(get, ResHand::from_monopoly(Resource::Sheep, 1));
+
+        // Not over a menu that's already open, nor while others build between turns
+        state.open_trade_menu_for(Resource::Ore);
+        let Some(Selector::Trading(_, get)) = state.selector else { panic!("expected the trade menu") };
+        assert_eq!(get[Resource::Ore], 0);
+        state.selector = None;
+        state.special_building = true;
+        state.open_trade_menu_for(Resource::Ore);
+        assert!(state.selector.is_none());
+    }
+
+    /// Companion mode at the start of a turn, nobody holding any cards
+    fn at_the_table() -> GameState {
+        let mut state = empty_handed();
+        state.start_companion();
+        state
+    }
+
+    #[test]
+    fn companion_rolls_are_the_dice_typed_in() {
+        let mut state = at_the_table();
+        state.rules.no_sevens_rounds = 2;
+        assert!(!state.can_make_move(&Move::Roll));
+        assert!(state.can_enter_die());
+        state.enter_die(3);
+        assert!(state.rng_action.is_none());
+        state.enter_die(4);
+        state.resolve_rng_action(&mut StdRng::seed_from_u64(0));
+        // A real 7 stands, whatever the house rules say
+        assert_eq!(state.roll, Some([3, 4]));
+        assert!(state.typed_dice.is_empty());
+        assert!(!state.can_enter_die());
+    }
+
+    #[test]
+    fn companion_cards_are_recorded_off_the_real_deck() {
+        let mut state = at_the_table();
+        state.roll = Some([2, 4]);
+        let dv_bank = state.board.dv_bank;
+        assert!(!state.can_make_move(&Move::BuyDV));
+        assert!(state.can_make_move(&Move::RecordDV(DVCard::Monopoly)));
+        state.apply_move(&Move::RecordDV(DVCard::Monopoly), &mut StdRng::seed_from_u64(0));
+
+        assert_eq!(state.board.dv_bank[DVCard::Monopoly], dv_bank[DVCard::Monopoly] - 1);
+        assert_eq!(state.get_current_player().get_combined_dvs()[DVCard::Monopoly], 1);
+        assert_eq!(state.get_current_player().get_hand(), ResHand::new());
+
+        state.board.dv_bank[DVCard::Monopoly] = 0;
+        assert!(!state.can_make_move(&Move::RecordDV(DVCard::Monopoly)));
+    }
+
+    #[test]
+    fn companion_builds_need_no_tracked_cards() {
+        let mut state = at_the_table();
+        state.roll = Some([2, 4]);
+        let color = state.get_current_color();
+        let edge = *state.board.layout.edge_coords.iter()
+            .find(|&&edge| state.board.can_place_road(edge, color))
+            .unwrap();
+        assert!(state.can_make_move(&Move::BuildRoad(edge)));
+        state.apply_move(&Move::BuildRoad(edge), &mut StdRng::seed_from_u64(0));
+        assert!(!state.board.can_place_road(edge, color));
+        assert_eq!(state.get_current_player().get_hand(), ResHand::new());
+    }
+
+    #[test]
+    fn ports_set_the_rate_per_resource() {
+        let state = at_the_docks();
+        let board = &state.board;
+        let color = state.get_current_color();
+        assert_eq!(board.ports_of(color), vec![0, 1]);
+        assert_eq!(board.trade_rate(color, Resource::Wood), 2);
+        assert_eq!(board.trade_rate(color, Resource::Ore), 3);
+
+        let other = state.players[seat(&state, 1)].get_color();
+        assert!(board.ports_of(other).is_empty());
+        assert_eq!(board.trade_rate(other, Resource::Wood), 4);
+        // A better house rate wins, a worse one doesn't
+        let mut rules = state.rules;
+        rules.bank_rate = 3;
+        assert_eq!(rules.trade_rate(board, other, Resource::Wood), 3);
+        assert_eq!(rules.trade_rate(board, color, Resource::Wood), 2);
+    }
+
+    #[test]
+    fn either_corner_of_a_port_reaches_it() {
+        let mut state = at_the_docks();
+        let other = state.players[seat(&state, 1)].get_color();
+        let [r, q, e] = state.board.layout.port_coords[2];
+        state.board.ports[2] = Port::TwoForOne(Resource::Brick);
+        state.board.place_setup_settlement([r, q, (e + 5) % 6], other);
+        assert_eq!(state.board.ports_of(other), vec![2]);
+        assert_eq!(state.board.trade_rate(other, Resource::Brick), 2);
+        assert_eq!(state.board.trade_rate(other, Resource::Wheat), 4);
+    }
+
+    #[test]
+    fn bank_trades_mix_rates() {
+        let state = at_the_docks();
+        let give = hand(&[(Resource::Wood, 2), (Resource::Ore, 3)]);
+        assert!(state.can_trade_with_bank(give, hand(&[(Resource::Brick, 1), (Resource::Sheep, 1)])));
+        assert!(state.can_trade_with_bank(give, hand(&[(Resource::Wheat, 2)])));
+        assert!(state.can_trade_with_bank(hand(&[(Resource::Wood, 4)]), hand(&[(Resource::Ore, 2)])));
+    }
+
+    #[test]
+    fn bank_trades_take_whole_sets_only() {
+        let state = at_the_docks();
+        // A full set too many, or cards that don't make a set
+        let give = hand(&[(Resource::Wood, 2), (Resource::Ore, 3)]);
+        assert!(!state.can_trade_with_bank(give, hand(&[(Resource::Brick, 1)])));
+        assert!(!state.can_trade_with_bank(give, hand(&[(Resource::Brick, 3)])));
+        assert!(!state.can_trade_with_bank(hand(&[(Resource::Wood, 2), (Resource::Ore, 2)]), hand(&[(Resource::Brick, 1)])));
+        assert!(!state.can_trade_with_bank(hand(&[(Resource::Ore, 4)]), hand(&[(Resource::Brick, 1)])));
+        assert!(!state.can_trade_with_bank(hand(&[(Resource::Wood, 1)]), hand(&[(Resource::Brick, 1)])));
+        assert!(!state.can_trade_with_bank(ResHand::new(), ResHand::new()));
+    }
+
+    #[test]
+    fn gold_is_drawn_from_the_bank() {
+        let mut state = test_game(BoardChoice::Random, 1, 0);
+        let picked = hand(&[(Resource::Wood, 1), (Resource::Ore, 1)]);
+        state.action = Action::ChoosingGold;
+        state.gold_owed[state.current_player] = 2;
+        let mut bank = state.board.bank;
+        state.apply_move(&Move::ChooseGold(picked), &mut StdRng::seed_from_u64(0));
+
+        bank.discard(picked);
+        assert_eq!(state.board.bank, bank);
+    }
+
+    #[test]
+    fn free_roads_cost_nothing() {
+        let mut state = test_game(BoardChoice::Random, 3, 0);
+        let color = state.get_current_color();
+        state.action = Action::RoadBuilding(false);
+        let edge = *state.board.layout.edge_coords.iter()
+            .find(|&&edge| state.board.can_place_road(edge, color))
+            .unwrap();
+        let bank = state.board.bank;
+        let hand = state.get_current_player().get_hand();
+        let pool = state.get_current_player().get_road_pool();
+        state.apply_move(&Move::FreeRoad(edge), &mut StdRng::seed_from_u64(0));
+
+        assert!(!state.board.can_place_road(edge, color));
+        assert_eq!(state.board.bank, bank);
+        assert_eq!(state.get_current_player().get_hand(), hand);
+        assert_eq!(state.get_current_player().get_road_pool(), pool - 1);
+    }
+
+    #[test]
+    fn longest_road_is_set_aside_when_broken_below_five() {
+        let mut state = test_game(BoardChoice::Random, 2, 0);
+        for corners in state.board.structures.iter_mut().flatten() {
+            corners.fill(None);
+        }
+        for edges in state.board.roads.iter_mut().flatten() {
+            edges.fill(None);
+        }
+        let color = state.get_current_color();
+        let other = state.get_order().into_iter().find(|&other| other != color).unwrap();
+        // Five roads around the middle hex, from its corner 5 round to corner 4
+        let [r, q] = state.board.layout.hex_coords[state.board.layout.hex_coords.len() / 2];
+        for e in 0..5 {
+            state.board.place_setup_road([r, q, e], color);
+        }
+        state.update_longest_road();
+        assert_eq!(state.longest_road, Some(color));
+        let vps = state.get_player(color).unwrap().get_vps();
+
+        // Four roads on one side, one on the other
+        state.board.place_setup_settlement([r, q, 3], other);
+        state.update_longest_road();
+        assert_eq!(state.get_player(color).unwrap().get_road_len(), 4);
+        assert_eq!(state.longest_road, None);
+        assert_eq!(state.longest_road_size, 4);
+        assert_eq!(state.get_player(color).unwrap().get_vps(), vps - 2);
+    }
+}
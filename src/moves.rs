@@ -0,0 +1,583 @@
+use rand::Rng;
+
+use crate::game::{Board, Commodity, DVCard, Improvement, PlayerColor, ProgressCard, ResHand, Resource, Ruleset};
+use crate::{trade_is_reasonable, Action, GameState, RngAction, SetupState};
+
+/// One thing a seat can do to the game. Clicks turn into moves, and moves
+/// are all the engine takes from a seat, so they can be checked before
+/// they're carried out and sent over the network in notation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Move {
+    // Setup
+    PlaceSettlement([usize; 3]),
+    PlaceRoad([usize; 3]),
+    PlaceShip([usize; 3]),
+    // Turn
+    Roll,
+    BuyDV,
+    /// Companion mode: which card came off the real deck
+    RecordDV(DVCard),
+    BuildRoad([usize; 3]),
+    BuildShip([usize; 3]),
+    ShiftShip([usize; 3], [usize; 3]),
+    BuildSettlement([usize; 3]),
+    BuildCity([usize; 3]),
+    BuildKnight([usize; 3]),
+    ActivateKnight([usize; 3]),
+    /// Paid for, unless the Smith is being played
+    PromoteKnight([usize; 3]),
+    ChaseRobber([usize; 3]),
+    BuildWall([usize; 3]),
+    Improve(Improvement),
+    /// Knight or Road Building; the other cards are played with their choice
+    PlayDV(DVCard),
+    YearOfPlenty(ResHand),
+    Monopoly(Resource),
+    /// Any progress card that isn't played with a choice of its own
+    PlayProgress(ProgressCard),
+    /// Sets the dice before the roll, leaving only the event die to chance
+    Alchemist([usize; 2]),
+    /// A city improvement for one commodity less
+    Crane(Improvement),
+    /// A free city wall
+    Engineer([usize; 3]),
+    /// Swaps two number tokens
+    Inventor([usize; 2], [usize; 2]),
+    /// A city for two ore and a wheat
+    Medicine([usize; 3]),
+    /// Places the merchant
+    Merchant([usize; 2]),
+    MerchantFleet(Resource),
+    ResourceMonopoly(Resource),
+    TradeMonopoly(Commodity),
+    MasterMerchant(PlayerColor),
+    /// Moves the robber, taking a card from everyone next to it
+    Bishop([usize; 2]),
+    Deserter(PlayerColor),
+    /// Removes an open road
+    Diplomat([usize; 3]),
+    /// Displaces the knight at the corner
+    Intrigue([usize; 3]),
+    Spy(PlayerColor),
+    PlaceRobber([usize; 2]),
+    Steal(PlayerColor),
+    FreeRoad([usize; 3]),
+    Discard(ResHand),
+    ChooseGold(ResHand),
+    /// Wedding gifts, and the Commercial Harbor's offers
+    Give(ResHand),
+    /// A commodity back for a Commercial Harbor offer
+    GiveCommodity(Commodity),
+    /// The Master Merchant's pick of the victim's resources
+    Take(ResHand),
+    /// The Spy's pick of the victim's progress cards
+    TakeProgress(ProgressCard),
+    /// The Deserter's victim gives up a knight
+    RemoveKnight([usize; 3]),
+    /// The deserter, or a knight Intrigue displaced, placed for free
+    PlaceKnight([usize; 3]),
+    BankTrade(ResHand, ResHand),
+    Pass,
+}
+
+fn is_corner(board: &Board, corner: [usize; 3]) -> bool {
+    board.layout.corner_coords.contains(&corner)
+}
+
+fn is_edge(board: &Board, edge: [usize; 3]) -> bool {
+    board.layout.edge_coords.contains(&edge)
+}
+
+impl SetupState {
+    /// Whether the seat placing now may make `mv`
+    pub fn can_make_move(&self, mv: &Move) -> bool {
+        if self.finished {
+            return false;
+        }
+        match (*mv, self.settlement) {
+            (Move::PlaceSettlement(corner), None) =>
+                is_corner(&self.board, corner) && self.board.can_place_setup_settlement(corner),
+            (Move::PlaceRoad(edge), Some(settlement)) =>
+                is_edge(&self.board, edge) && self.board.can_place_setup_road(edge, settlement),
+            (Move::PlaceShip(edge), Some(settlement)) =>
+                self.ruleset == Ruleset::Seafarers
+                && is_edge(&self.board, edge) && self.board.can_place_setup_ship(edge, settlement),
+            _ => false
+        }
+    }
+
+    pub fn apply_move(&mut self, mv: &Move) {
+        match *mv {
+            Move::PlaceSettlement(corner) => self.place_settlement(corner),
+            Move::PlaceRoad(edge) => {
+                self.place_road(edge);
+                self.advance_turn();
+            },
+            Move::PlaceShip(edge) => {
+                self.place_ship(edge);
+                self.advance_turn();
+            },
+            _ => panic!("SetupState::apply_move(): {:?} isn't a setup move", mv)
+        }
+    }
+}
+
+impl GameState {
+    /// Nothing is waiting on the player: only the actions that just pick
+    /// where a piece goes, which the move itself says, are underway
+    fn is_free_to_act(&self) -> bool {
+        self.rng_action.is_none() && matches!(self.action,
+            Action::Idling | Action::BuildingRoad | Action::BuildingShip | Action::MovingShip(_)
+            | Action::BuildingSettlement | Action::BuildingCity | Action::BuildingKnight
+            | Action::ActivatingKnight | Action::PromotingKnight(None) | Action::ChasingRobber
+            | Action::RecordingDV | Action::BuildingWall | Action::Targeting(..)
+        )
+    }
+
+    /// Whether the seat acting now may make `mv`. These are the checks the
+    /// clicks go through, so a move from anywhere else is held to the same rules.
+    pub fn can_make_move(&self, mv: &Move) -> bool {
+        if self.winner.is_some() {
+            return false;
+        }
+        let color = self.get_current_color();
+        let player = self.get_current_player();
+        let board = &self.board;
+        let free = self.is_free_to_act();
+        let rolled = free && self.roll.is_some();
+        // Cards and trades wait while the others build between turns
+        let can_play = free && !self.special_building;
+        let playing = |card| can_play && self.can_play_progress_card(card);
+        let is_hex = |hex| board.layout.hex_coords.contains(&hex);
+        match *mv {
+            Move::PlaceSettlement(_) | Move::PlaceRoad(_) | Move::PlaceShip(_) => false,
+            Move::Roll => free && self.roll.is_none() && !self.companion,
+            Move::BuyDV => rolled && !self.companion && self.can_buy_dv(),
+            Move::RecordDV(card) => rolled && self.companion && self.can_buy_dv() && board.can_take_dv_card(card),
+            Move::BuildRoad(edge) =>
+                rolled && is_edge(board, edge) && self.can_build_road() && board.can_place_road(edge, color),
+            Move::BuildShip(edge) =>
+                rolled && is_edge(board, edge) && self.can_build_ship() && board.can_place_ship(edge, color),
+            Move::ShiftShip(from, to) => {
+                if !rolled || !is_edge(board, from) || !is_edge(board, to) || from == to
+                || !self.can_move_ship() || !self.can_move_ship_from(from) {
+                    return false;
+                }
+                // The ship is lifted off the board so it can't support its own new spot
+                let mut lifted = board.clone();
+                lifted.remove_ship(from);
+                lifted.can_place_ship(to, color)
+            },
+            Move::BuildSettlement(corner) =>
+                rolled && is_corner(board, corner) && self.can_build_settlement() && board.can_place_settlement(corner, color),
+            Move::BuildCity(corner) =>
+                rolled && is_corner(board, corner) && self.can_build_city() && board.can_place_city(corner, color),
+            Move::BuildKnight(corner) =>
+                rolled && is_corner(board, corner) && self.can_build_knight() && board.can_place_knight(corner, color),
+            Move::ActivateKnight(corner) =>
+                rolled && is_corner(board, corner) && self.can_activate_knight() && board.can_activate_knight(corner, color),
+            Move::PromoteKnight(corner) => {
+                let can_promote = match self.action {
+                    Action::PromotingKnight(Some(_)) => self.rng_action.is_none(),
+                    _ => rolled && self.can_promote_knight()
+                };
+                can_promote && is_corner(board, corner) && self.can_promote_knight_at(corner)
+            },
+            Move::ChaseRobber(corner) =>
+                rolled && is_corner(board, corner) && self.can_chase_robber() && board.can_chase_robber(corner, color),
+            Move::BuildWall(corner) =>
+                rolled && is_corner(board, corner) && player.can_build_wall() && board.can_place_wall(corner, color),
+            Move::Improve(track) => rolled && self.can_improve(track),
+            Move::PlayDV(card) =>
+                can_play && !self.played_dv && player.get_dvs()[card] > 0 && match card {
+                    DVCard::Knight => true,
+                    DVCard::RoadBuilding => player.has_road_piece() && board.can_place_any_road(color),
+                    _ => false
+                },
+            Move::YearOfPlenty(hand) =>
+                can_play && !self.played_dv && player.get_dvs()[DVCard::YearOfPlenty] > 0
+                && hand.size() == 2 && board.bank.can_discard(hand),
+            Move::Monopoly(_) => can_play && !self.played_dv && player.get_dvs()[DVCard::Monopoly] > 0,
+            Move::PlayProgress(card) => !card.needs_choice() && playing(card),
+            Move::Alchemist(dice) =>
+                playing(ProgressCard::Alchemist) && dice.iter().all(|die| (1..=6).contains(die)) && !self.is_barred_roll(dice),
+            Move::Crane(track) => playing(ProgressCard::Crane) && self.can_improve_for(track, 1),
+            Move::Engineer(corner) =>
+                playing(ProgressCard::Engineer) && is_corner(board, corner) && board.can_place_wall(corner, color),
+            Move::Inventor(hex1, hex2) =>
+                playing(ProgressCard::Inventor) && hex1 != hex2 && is_hex(hex1) && is_hex(hex2)
+                && board.can_swap_number(hex1) && board.can_swap_number(hex2),
+            Move::Medicine(corner) =>
+                playing(ProgressCard::Medicine) && is_corner(board, corner) && board.can_place_city(corner, color),
+            Move::Merchant(hex) => playing(ProgressCard::Merchant) && is_hex(hex) && board.can_place_merchant(hex, color),
+            Move::MerchantFleet(_) => playing(ProgressCard::MerchantFleet),
+            Move::ResourceMonopoly(_) => playing(ProgressCard::ResourceMonopoly),
+            Move::TradeMonopoly(_) => playing(ProgressCard::TradeMonopoly),
+            Move::MasterMerchant(victim) =>
+                playing(ProgressCard::MasterMerchant) && self.get_player(victim).is_some() && self.can_take_from(victim),
+            Move::Bishop(hex) =>
+                playing(ProgressCard::Bishop) && is_hex(hex) && board.is_land(hex) && hex != board.robber,
+            Move::Deserter(victim) =>
+                playing(ProgressCard::Deserter) && self.get_player(victim).is_some() && self.can_desert(victim),
+            Move::Diplomat(edge) => playing(ProgressCard::Diplomat) && is_edge(board, edge) && board.is_open_road(edge),
+            Move::Intrigue(corner) => playing(ProgressCard::Intrigue) && is_corner(board, corner) && self.can_intrigue(corner),
+            Move::Spy(victim) => playing(ProgressCard::Spy) && self.get_player(victim).is_some() && self.can_spy_on(victim),
+            Move::PlaceRobber(hex) =>
+                matches!(self.action, Action::MovingRobber)
+                && board.layout.hex_coords.contains(&hex) && self.can_move_robber(hex),
+            Move::Steal(victim) => {
+                let robbable = match self.action {
+                    Action::ChoosingVictim => board.get_colors_on_hex(board.robber),
+                    Action::ChoosingPirateVictim => board.pirate.map(|hex| board.get_ship_colors_on_hex(hex)).unwrap_or_default(),
+                    _ => return false
+                };
+                self.rng_action.is_none() && robbable.contains(&victim) && self.can_rob(victim)
+            },
+            Move::FreeRoad(edge) =>
+                matches!(self.action, Action::RoadBuilding(_)) && player.has_road_piece()
+                && is_edge(board, edge) && board.can_place_road(edge, color),
+            Move::Discard(hand) =>
+                matches!(self.action, Action::Discarding | Action::Sabotaged)
+                && player.get_hand().can_discard(hand) && hand.size() == player.get_hand().size() / 2,
+            Move::ChooseGold(hand) =>
+                matches!(self.action, Action::ChoosingGold)
+                && hand.size() == self.gold_owed[self.current_player] && board.bank.can_discard(hand),
+            Move::Give(hand) =>
+                matches!(self.action, Action::Giving | Action::HarborOffering(_))
+                && player.get_hand().can_discard(hand) && hand.size() == self.cards_to_give(),
+            Move::GiveCommodity(card) =>
+                matches!(self.action, Action::HarborReturning) && player.get_commodities()[card] > 0,
+            Move::Take(hand) => match self.action {
+                Action::Taking(victim) =>
+                    self.get_player(victim).unwrap().get_hand().can_discard(hand) && hand.size() == self.cards_to_give(),
+                _ => false
+            },
+            Move::TakeProgress(card) => match self.action {
+                Action::Spying(victim) => self.get_player(victim).unwrap().has_progress_card(card),
+                _ => false
+            },
+            Move::RemoveKnight(corner) =>
+                matches!(self.action, Action::Deserting) && is_corner(board, corner) && board.knight_is_color(corner, color),
+            Move::PlaceKnight(corner) => match self.action {
+                Action::PlacingDeserter(_) => is_corner(board, corner) && board.can_place_knight(corner, color),
+                Action::Displaced(_, from) => is_corner(board, corner) && corner != from && board.can_place_knight(corner, color),
+                _ => false
+            },
+            Move::BankTrade(give, get) =>
+                can_play && self.roll.is_some() && trade_is_reasonable(give, get)
+                && player.get_hand().can_discard(give) && self.can_trade_with_bank(give, get),
+            Move::Pass => rolled,
+        }
+    }
+
+    /// Carries out a move `can_make_move` allows, along with any roll,
+    /// theft or draw it sets off
+    pub fn apply_move<R: Rng>(&mut self, mv: &Move, rng: &mut R) {
+        match *mv {
+            Move::PlaceSettlement(_) | Move::PlaceRoad(_) | Move::PlaceShip(_) =>
+                panic!("GameState::apply_move(): {:?} is a setup move", mv),
+            Move::Roll => self.rng_action = Some(RngAction::RollingDice),
+            Move::BuyDV => self.rng_action = Some(RngAction::BuyingDV),
+            Move::RecordDV(card) => self.record_dv_card(card),
+            Move::BuildRoad(edge) => {
+                self.build_road(edge);
+                self.action = Action::Idling;
+            },
+            Move::BuildShip(edge) => {
+                self.build_ship(edge);
+                self.action = Action::Idling;
+            },
+            Move::ShiftShip(from, to) => {
+                self.move_ship(from, to);
+                self.action = Action::Idling;
+            },
+            Move::BuildSettlement(corner) => {
+                self.build_settlement(corner);
+                self.action = Action::Idling;
+            },
+            Move::BuildCity(corner) => {
+                self.build_city(corner);
+                self.action = Action::Idling;
+            },
+            Move::BuildKnight(corner) => {
+                self.build_knight(corner);
+                self.action = Action::Idling;
+            },
+            Move::ActivateKnight(corner) => {
+                self.activate_knight(corner);
+                self.action = Action::Idling;
+            },
+            Move::PromoteKnight(corner) => {
+                let free = match self.action {
+                    Action::PromotingKnight(free) => free,
+                    _ => None
+                };
+                self.promote_knight(corner, free.is_none());
+                self.action = match free {
+                    Some(left) if left > 1 && self.can_promote_any_knight() => Action::PromotingKnight(Some(left - 1)),
+                    _ => Action::Idling
+                };
+            },
+            Move::ChaseRobber(corner) => self.chase_robber(corner),
+            Move::BuildWall(corner) => {
+                self.build_wall(corner);
+                self.action = Action::Idling;
+            },
+            Move::Improve(track) => self.improve(track),
+            Move::PlayDV(card) => self.play_dv_card(card),
+            Move::YearOfPlenty(hand) => {
+                self.selector = None;
+                self.execute_yop(hand);
+            },
+            Move::Monopoly(res) => {
+                self.selector = None;
+                self.execute_monopoly(res);
+            },
+            Move::PlayProgress(card) => self.play_progress_card(card),
+            Move::Alchemist(_) | Move::Crane(_) | Move::Engineer(_) | Move::Inventor(..) | Move::Medicine(_)
+            | Move::Merchant(_) | Move::MerchantFleet(_) | Move::ResourceMonopoly(_) | Move::TradeMonopoly(_)
+            | Move::MasterMerchant(_) | Move::Bishop(_) | Move::Deserter(_) | Move::Diplomat(_)
+            | Move::Intrigue(_) | Move::Spy(_) => {
+                // Done picking the target, if it was picked on the board
+                self.selector = None;
+                self.action = Action::Idling;
+                match *mv {
+                    Move::Alchemist(dice) => self.play_alchemist(dice),
+                    Move::Crane(track) => self.play_crane(track),
+                    Move::Engineer(corner) => self.play_engineer(corner),
+                    Move::Inventor(hex1, hex2) => self.play_inventor(hex1, hex2),
+                    Move::Medicine(corner) => self.play_medicine(corner),
+                    Move::Merchant(hex) => self.play_merchant(hex),
+                    Move::MerchantFleet(res) => self.play_merchant_fleet(res),
+                    Move::ResourceMonopoly(res) => self.execute_resource_monopoly(res),
+                    Move::TradeMonopoly(card) => self.play_trade_monopoly(card),
+                    Move::MasterMerchant(victim) => self.play_master_merchant(victim),
+                    Move::Bishop(hex) => self.play_bishop(hex),
+                    Move::Deserter(victim) => self.play_deserter(victim),
+                    Move::Diplomat(edge) => self.play_diplomat(edge),
+                    Move::Intrigue(corner) => self.play_intrigue(corner),
+                    Move::Spy(victim) => self.play_spy(victim),
+                    _ => unreachable!()
+                }
+            },
+            Move::PlaceRobber(hex) => self.move_robber(hex),
+            Move::Steal(victim) => self.rng_action = Some(RngAction::Stealing(victim)),
+            Move::FreeRoad(edge) => self.place_free_road(edge),
+            Move::Discard(hand) => {
+                self.selector = None;
+                if matches!(self.action, Action::Sabotaged) {
+                    self.execute_sabotage(hand);
+                } else {
+                    self.execute_discard(hand);
+                }
+            },
+            Move::ChooseGold(hand) => {
+                self.selector = None;
+                self.execute_gold(hand);
+            },
+            Move::Give(hand) => {
+                self.selector = None;
+                self.execute_give(hand);
+            },
+            Move::GiveCommodity(card) => self.execute_give_commodity(card),
+            Move::Take(hand) => {
+                self.selector = None;
+                self.execute_take(hand);
+            },
+            Move::TakeProgress(card) => self.execute_take_progress(card),
+            Move::RemoveKnight(corner) => self.remove_deserter(corner),
+            Move::PlaceKnight(corner) => self.place_free_knight(corner),
+            Move::BankTrade(give, get) => self.execute_trade(give, get),
+            Move::Pass => self.pass_turn(),
+        }
+        self.resolve_rng_action(rng);
+        self.check_for_winner();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::game::{Knight, DV_CARD_HAND};
+    use crate::new_game::BoardChoice;
+    use crate::test_game;
+
+    /// The seat to act has rolled, with `cards` in hand to play
+    fn holding(cards: &[DVCard]) -> GameState {
+        let mut state = test_game(BoardChoice::Random, 3, 2);
+        state.roll = Some([3, 4]);
+        state.action = Action::Idling;
+        state.rng_action = None;
+        let player = state.get_current_player_mut();
+        for &card in cards {
+            player.get_cards(DV_CARD_HAND);
+            player.buy_dv(card);
+        }
+        player.cycle_dvs();
+        state
+    }
+
+    /// The turn player of a Cities & Knights game has rolled, with `cards` in hand to play
+    fn holding_progress(cards: &[ProgressCard]) -> GameState {
+        let mut state = test_game(BoardChoice::CitiesKnights, 5, 3);
+        state.current_player = state.turn_player;
+        state.roll = Some([3, 4]);
+        state.action = Action::Idling;
+        state.selector = None;
+        state.rng_action = None;
+        for &card in cards {
+            state.get_current_player_mut().add_progress_card(card);
+        }
+        state
+    }
+
+    /// Someone other than the turn player, with more VPs than them and `hand` in hand
+    fn richer_opponent(state: &mut GameState, hand: ResHand) -> PlayerColor {
+        let idx = (state.turn_player + 1) % state.num_players;
+        let lead = (state.get_current_player().get_vps() + 1).saturating_sub(state.players[idx].get_vps());
+        let player = &mut state.players[idx];
+        player.add_bonus_vps(lead);
+        player.discard_cards(player.get_hand());
+        player.get_cards(hand);
+        player.get_color()
+    }
+
+    #[test]
+    fn master_merchant_takes_the_players_pick() {
+        let mut state = holding_progress(&[ProgressCard::MasterMerchant]);
+        let mut hand = ResHand::from_monopoly(Resource::Ore, 2);
+        hand[Resource::Wood] = 3;
+        let victim = richer_opponent(&mut state, hand);
+        assert!(!state.can_make_move(&Move::PlayProgress(ProgressCard::MasterMerchant)));
+        assert!(state.can_make_move(&Move::MasterMerchant(victim)));
+        assert!(!state.can_make_move(&Move::MasterMerchant(state.get_current_color())));
+        let mut rng = StdRng::seed_from_u64(0);
+        state.apply_move(&Move::MasterMerchant(victim), &mut rng);
+
+        let taken = ResHand::from_monopoly(Resource::Ore, 2);
+        assert!(!state.can_make_move(&Move::Take(ResHand::from_monopoly(Resource::Ore, 3))));
+        assert!(!state.can_make_move(&Move::Take(ResHand::from_monopoly(Resource::Wood, 1))));
+        assert!(state.can_make_move(&Move::Take(taken)));
+        let before = state.get_current_player().get_hand();
+        state.apply_move(&Move::Take(taken), &mut rng);
+        assert_eq!(state.get_current_player().get_hand()[Resource::Ore], before[Resource::Ore] + 2);
+        assert_eq!(state.get_player(victim).unwrap().get_hand().size(), 3);
+        assert!(matches!(state.action, Action::Idling));
+    }
+
+    #[test]
+    fn wedding_guests_pick_their_own_gifts() {
+        let mut state = holding_progress(&[ProgressCard::Wedding]);
+        let guest = richer_opponent(&mut state, ResHand::from_monopoly(Resource::Sheep, 3));
+        let host = state.get_current_color();
+        state.apply_move(&Move::PlayProgress(ProgressCard::Wedding), &mut StdRng::seed_from_u64(0));
+        assert!(matches!(state.action, Action::Giving));
+        assert_eq!(state.get_current_color(), guest);
+
+        let gift = ResHand::from_monopoly(Resource::Sheep, 2);
+        assert!(!state.can_make_move(&Move::Give(ResHand::from_monopoly(Resource::Sheep, 1))));
+        assert!(state.can_make_move(&Move::Give(gift)));
+        let before = state.get_player(host).unwrap().get_hand()[Resource::Sheep];
+        state.apply_move(&Move::Give(gift), &mut StdRng::seed_from_u64(0));
+        assert_eq!(state.get_player(host).unwrap().get_hand()[Resource::Sheep], before + 2);
+        assert_eq!(state.get_player(guest).unwrap().get_hand().size(), 1);
+    }
+
+    #[test]
+    fn the_deserter_changes_sides() {
+        let mut state = holding_progress(&[ProgressCard::Deserter]);
+        let color = state.get_current_color();
+        let victim = state.players[(state.turn_player + 1) % state.num_players].get_color();
+        let layout = state.board.layout.clone();
+        let post = layout.corner_coords.iter().copied().find(|&corner| state.board.can_place_knight(corner, victim)).unwrap();
+        state.board.place_free_knight(post, Knight { color: victim, level: 2, active: true });
+        assert!(state.can_make_move(&Move::Deserter(victim)));
+        state.apply_move(&Move::Deserter(victim), &mut StdRng::seed_from_u64(0));
+        assert_eq!(state.get_current_color(), victim);
+        assert!(state.can_make_move(&Move::RemoveKnight(post)));
+        state.apply_move(&Move::RemoveKnight(post), &mut StdRng::seed_from_u64(0));
+
+        assert!(state.board.get_knight(post).is_none());
+        assert_eq!(state.get_current_color(), color);
+        assert!(matches!(state.action, Action::PlacingDeserter(2)));
+        let spot = layout.corner_coords.iter().copied().find(|&corner| state.board.can_place_knight(corner, color)).unwrap();
+        assert!(state.can_make_move(&Move::PlaceKnight(spot)));
+        state.apply_move(&Move::PlaceKnight(spot), &mut StdRng::seed_from_u64(0));
+        let knight = state.board.get_knight(spot).unwrap();
+        assert_eq!((knight.color, knight.level, knight.active), (color, 2, false));
+    }
+
+    #[test]
+    fn the_inventor_leaves_the_red_numbers_alone() {
+        let mut state = holding_progress(&[ProgressCard::Inventor]);
+        let layout = state.board.layout.clone();
+        let number = |state: &GameState, [r, q]: [usize; 2]| state.board.hexes[r][q].map_or(0, |hex| hex.number);
+        let swappable: Vec<[usize; 2]> = layout.hex_coords.iter().copied().filter(|&hex| state.board.can_swap_number(hex)).collect();
+        let hot = layout.hex_coords.iter().copied().find(|&hex| matches!(number(&state, hex), 6 | 8)).unwrap();
+        let [hex1, hex2] = [swappable[0], swappable[1]];
+        assert!(!state.can_make_move(&Move::Inventor(hex1, hot)));
+        assert!(!state.can_make_move(&Move::Inventor(hex1, hex1)));
+
+        let (n1, n2) = (number(&state, hex1), number(&state, hex2));
+        state.apply_move(&Move::Inventor(hex1, hex2), &mut StdRng::seed_from_u64(0));
+        assert_eq!((number(&state, hex1), number(&state, hex2)), (n2, n1));
+        assert!(!state.get_current_player().has_progress_card(ProgressCard::Inventor));
+    }
+
+    #[test]
+    fn the_spy_picks_a_card() {
+        let mut state = holding_progress(&[ProgressCard::Spy]);
+        let victim = state.players[(state.turn_player + 1) % state.num_players].get_color();
+        state.get_player_mut(victim).unwrap().add_progress_card(ProgressCard::Warlord);
+        state.apply_move(&Move::Spy(victim), &mut StdRng::seed_from_u64(0));
+        assert!(matches!(state.action, Action::Spying(_)));
+        assert!(!state.can_make_move(&Move::TakeProgress(ProgressCard::Inventor)));
+        state.apply_move(&Move::TakeProgress(ProgressCard::Warlord), &mut StdRng::seed_from_u64(0));
+        assert!(state.get_current_player().has_progress_card(ProgressCard::Warlord));
+        assert!(!state.get_player(victim).unwrap().has_progress_card(ProgressCard::Warlord));
+    }
+
+    #[test]
+    fn the_alchemist_sets_the_dice_before_the_roll() {
+        let mut state = holding_progress(&[ProgressCard::Alchemist]);
+        assert!(!state.can_make_move(&Move::Alchemist([3, 5])));
+        state.roll = None;
+        assert!(state.can_make_move(&Move::Alchemist([3, 5])));
+        assert!(!state.can_make_move(&Move::Alchemist([3, 7])));
+        state.apply_move(&Move::Alchemist([3, 5]), &mut StdRng::seed_from_u64(0));
+        assert_eq!(state.roll, Some([3, 5]));
+    }
+
+    #[test]
+    fn one_dv_card_a_turn() {
+        let mut state = holding(&[DVCard::YearOfPlenty, DVCard::YearOfPlenty, DVCard::Knight, DVCard::Monopoly]);
+        let plenty = ResHand::from_monopoly(Resource::Ore, 2);
+        assert!(state.can_make_move(&Move::YearOfPlenty(plenty)));
+        state.apply_move(&Move::YearOfPlenty(plenty), &mut StdRng::seed_from_u64(0));
+
+        assert!(!state.can_make_move(&Move::YearOfPlenty(plenty)));
+        assert!(!state.can_make_move(&Move::PlayDV(DVCard::Knight)));
+        assert!(!state.can_make_move(&Move::Monopoly(Resource::Wood)));
+    }
+
+    #[test]
+    fn picks_come_out_of_the_bank() {
+        let mut state = holding(&[DVCard::YearOfPlenty]);
+        state.board.bank[Resource::Wood] = 1;
+        let ore = ResHand::from_monopoly(Resource::Ore, 2);
+        assert!(!state.can_make_move(&Move::YearOfPlenty(ResHand::from_monopoly(Resource::Wood, 2))));
+        assert!(state.can_make_move(&Move::YearOfPlenty(ore)));
+
+        let bank = state.board.bank;
+        state.apply_move(&Move::YearOfPlenty(ore), &mut StdRng::seed_from_u64(0));
+        assert_eq!(state.board.bank[Resource::Ore], bank[Resource::Ore] - 2);
+
+        state.action = Action::ChoosingGold;
+        state.gold_owed[state.current_player] = 2;
+        assert!(!state.can_make_move(&Move::ChooseGold(ResHand::from_monopoly(Resource::Wood, 2))));
+        assert!(state.can_make_move(&Move::ChooseGold(ore)));
+    }
+}
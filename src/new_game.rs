@@ -0,0 +1,275 @@
+use std::fs;
+
+use crate::game::{PlayerColor, Ruleset, PLAYER_COLORS};
+use crate::rules::RuleConfig;
+
+pub const MIN_SEATS: usize = 2;
+pub const MAX_SEATS: usize = 6;
+
+/// Where the start screen looks for Seafarers maps
+pub const SCENARIOS_DIR: &str = "scenarios";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+pub const DIFFICULTIES: [Difficulty; 3] = [
+    Difficulty::Easy,
+    Difficulty::Normal,
+    Difficulty::Hard,
+];
+
+impl Difficulty {
+    /// Reads a difficulty back from its name, in any case
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        DIFFICULTIES.iter().copied().find(|difficulty| format!("{:?}", difficulty).eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeatKind {
+    Human,
+    Cpu(Difficulty),
+}
+
+impl SeatKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            SeatKind::Human => "Human",
+            SeatKind::Cpu(Difficulty::Easy) => "CPU (easy)",
+            SeatKind::Cpu(Difficulty::Normal) => "CPU (normal)",
+            SeatKind::Cpu(Difficulty::Hard) => "CPU (hard)",
+        }
+    }
+
+    /// Human, then the CPUs from easiest to hardest, then round again
+    pub fn next(self) -> SeatKind {
+        match self {
+            SeatKind::Human => SeatKind::Cpu(Difficulty::Easy),
+            SeatKind::Cpu(Difficulty::Easy) => SeatKind::Cpu(Difficulty::Normal),
+            SeatKind::Cpu(Difficulty::Normal) => SeatKind::Cpu(Difficulty::Hard),
+            SeatKind::Cpu(Difficulty::Hard) => SeatKind::Human,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Seat {
+    pub color: PlayerColor,
+    pub kind: SeatKind,
+}
+
+/// A random base board, a random Cities & Knights board or a fixed map
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoardChoice {
+    Random,
+    CitiesKnights,
+    Scenario(String),
+}
+
+impl BoardChoice {
+    pub fn label(&self) -> String {
+        match self {
+            BoardChoice::Random => String::from("Random board"),
+            BoardChoice::CitiesKnights => String::from("Cities & Knights"),
+            BoardChoice::Scenario(path) => format!("Seafarers: {path}"),
+        }
+    }
+}
+
+/// The `.txt` maps in `dir`, sorted so they cycle in a stable order
+pub fn find_scenarios(dir: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Everything picked on the start screen. Seats play in the order listed.
+#[derive(Debug, Clone)]
+pub struct NewGame {
+    pub seats: Vec<Seat>,
+    pub board: BoardChoice,
+    /// `None` plays by the official rules for the table size and ruleset
+    pub rules: Option<RuleConfig>,
+    pub seed: u64,
+}
+
+impl NewGame {
+    /// One human against three CPUs on a random board
+    pub fn new(seed: u64) -> NewGame {
+        let mut new_game = NewGame {
+            seats: vec![Seat { color: PlayerColor::Red, kind: SeatKind::Human }],
+            board: BoardChoice::Random,
+            rules: None,
+            seed,
+        };
+        for _ in 0..3 {
+            new_game.add_seat();
+        }
+        new_game
+    }
+
+    pub fn get_ruleset(&self) -> Ruleset {
+        match self.board {
+            BoardChoice::Random => Ruleset::Base,
+            BoardChoice::CitiesKnights => Ruleset::CitiesKnights,
+            BoardChoice::Scenario(_) => Ruleset::Seafarers,
+        }
+    }
+
+    pub fn get_rules(&self) -> RuleConfig {
+        self.rules.unwrap_or(RuleConfig::new(self.seats.len(), self.get_ruleset()))
+    }
+
+    pub fn num_humans(&self) -> usize {
+        self.seats.iter().filter(|seat| seat.kind == SeatKind::Human).count()
+    }
+
+    fn is_taken(&self, color: PlayerColor) -> bool {
+        self.seats.iter().any(|seat| seat.color == color)
+    }
+
+    pub fn can_add_seat(&self) -> bool {
+        self.seats.len() < MAX_SEATS
+    }
+
+    pub fn can_remove_seat(&self) -> bool {
+        self.seats.len() > MIN_SEATS
+    }
+
+    /// A normal CPU in the first free color
+    pub fn add_seat(&mut self) {
+        let color = PLAYER_COLORS.iter().copied().find(|&color| !self.is_taken(color)).unwrap();
+        self.seats.push(Seat { color, kind: SeatKind::Cpu(Difficulty::Normal) });
+    }
+
+    pub fn remove_seat(&mut self) {
+        self.seats.pop();
+    }
+
+    /// Moves a seat on to the next color nobody else has
+    pub fn cycle_color(&mut self, idx: usize) {
+        let current = self.seats[idx].color as usize;
+        let next = (1..PLAYER_COLORS.len())
+            .map(|step| PlayerColor::from(current + step))
+            .find(|&color| !self.is_taken(color));
+        if let Some(color) = next {
+            self.seats[idx].color = color;
+        }
+    }
+
+    pub fn cycle_kind(&mut self, idx: usize) {
+        self.seats[idx].kind = self.seats[idx].kind.next();
+    }
+
+    /// Random, Cities & Knights, then each of `scenarios` in turn
+    pub fn cycle_board(&mut self, scenarios: &[String]) {
+        self.board = match &self.board {
+            BoardChoice::Random => BoardChoice::CitiesKnights,
+            BoardChoice::CitiesKnights => match scenarios.first() {
+                Some(path) => BoardChoice::Scenario(path.clone()),
+                None => BoardChoice::Random
+            },
+            BoardChoice::Scenario(path) => {
+                let idx = scenarios.iter().position(|other| other == path);
+                match idx.and_then(|idx| scenarios.get(idx + 1)) {
+                    Some(next) => BoardChoice::Scenario(next.clone()),
+                    None => BoardChoice::Random
+                }
+            }
+        };
+    }
+
+    /// Typing digits builds the seed up, and backspace takes the last one off
+    pub fn type_seed_digit(&mut self, digit: u64) {
+        self.seed = self.seed.saturating_mul(10).saturating_add(digit);
+    }
+
+    pub fn erase_seed_digit(&mut self) {
+        self.seed /= 10;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seats_stay_between_two_and_six_in_distinct_colors() {
+        let mut new_game = NewGame::new(1);
+        while new_game.can_add_seat() {
+            new_game.add_seat();
+        }
+        assert_eq!(new_game.seats.len(), MAX_SEATS);
+        new_game.cycle_color(0);
+        let colors: Vec<PlayerColor> = new_game.seats.iter().map(|seat| seat.color).collect();
+        assert!(colors.iter().enumerate().all(|(idx, color)| !colors[..idx].contains(color)));
+
+        while new_game.can_remove_seat() {
+            new_game.remove_seat();
+        }
+        assert_eq!(new_game.seats.len(), MIN_SEATS);
+        new_game.cycle_color(1);
+        assert_eq!(new_game.seats[1].color, PlayerColor::Orange);
+    }
+
+    #[test]
+    fn seat_kinds_cycle_back_to_human() {
+        let mut new_game = NewGame::new(1);
+        assert_eq!(new_game.num_humans(), 1);
+        for _ in 0..3 {
+            new_game.cycle_kind(0);
+        }
+        assert_eq!(new_game.seats[0].kind, SeatKind::Cpu(Difficulty::Hard));
+        assert_eq!(new_game.num_humans(), 0);
+        new_game.cycle_kind(0);
+        assert_eq!(new_game.seats[0].kind, SeatKind::Human);
+    }
+
+    #[test]
+    fn the_board_sets_the_ruleset_and_the_official_rules() {
+        let scenarios = [String::from("a.txt"), String::from("b.txt")];
+        let mut new_game = NewGame::new(1);
+        assert_eq!(new_game.get_ruleset(), Ruleset::Base);
+        assert_eq!(new_game.get_rules().vp_target, 10);
+        new_game.cycle_board(&scenarios);
+        assert_eq!(new_game.get_ruleset(), Ruleset::CitiesKnights);
+        assert_eq!(new_game.get_rules().vp_target, 13);
+        new_game.cycle_board(&scenarios);
+        new_game.cycle_board(&scenarios);
+        assert_eq!(new_game.board, BoardChoice::Scenario(String::from("b.txt")));
+        assert_eq!(new_game.get_ruleset(), Ruleset::Seafarers);
+        new_game.cycle_board(&scenarios);
+        assert_eq!(new_game.board, BoardChoice::Random);
+
+        // House rules, once set, are kept whatever the table
+        let mut rules = new_game.get_rules();
+        rules.vp_target = 8;
+        new_game.rules = Some(rules);
+        new_game.add_seat();
+        assert_eq!(new_game.get_rules().vp_target, 8);
+    }
+
+    #[test]
+    fn seeds_are_typed_a_digit_at_a_time() {
+        let mut new_game = NewGame::new(0);
+        for digit in [4, 0, 2] {
+            new_game.type_seed_digit(digit);
+        }
+        assert_eq!(new_game.seed, 402);
+        new_game.erase_seed_digit();
+        assert_eq!(new_game.seed, 40);
+        new_game.seed = u64::MAX;
+        new_game.type_seed_digit(9);
+        assert_eq!(new_game.seed, u64::MAX);
+    }
+}
@@ -0,0 +1,569 @@
+use std::str::SplitWhitespace;
+
+use crate::game::{
+    Commodity, DVCard, Improvement, Layout, ProgressCard, ResHand, Resource, DV_CARDS, IMPROVEMENTS, RESOURCES
+};
+use crate::moves::Move;
+use crate::save::{parse_color, parse_coords};
+use crate::{Action, Table};
+
+pub const NOTATION_HELP: &str = "\
+Moves are written one after another, as many to a line as you like:
+
+  R  R3+5  R8       roll, or roll a 3 and a red 5, or an 8
+  S@12  C@12        settlement, city on corner 12
+  Rd@30  Sh@30      road, ship on side 30; Sh@30>@31 moves a ship
+  N@12              knight; N+@12 activates, N^@12 promotes, N!@12 chases the robber,
+                    N-@12 gives it up to a Deserter
+  W@12              city wall
+  B  B=KN           buy a development card, or say which one came off the deck
+  K  RB  YP WO  MN O    knight, road building, year of plenty, monopoly
+  P:IR  P:RM O      progress cards by label, with what they're played on:
+                    P:AL 3+5, P:CR T, P:EN @12, P:IN H3>H9, P:MC H9, P:TM CL,
+                    P:DI @30, P:SP Blue
+  H9  >Blue         robber or pirate onto hex 9, steal from Blue
+  D 2W1O  G 1S      discard, take from a gold hex
+  GV 1W  GV CL      give cards or a commodity; TK 2W  TK P:WL takes them
+  T 2W>1O@P         trade, with @P marking a port
+  I T|P|S           improve trade, politics or science
+  E                 end the turn
+
+Resources are W wood, B brick, G grain, S sheep and O ore, and commodities
+PA paper, CL cloth and CO coin. Hexes, corners
+and sides are numbered in the order the board lists them. Cities & Knights
+rolls need both dice, since the red one decides the progress cards.";
+
+/// What a roll is written to have come up as
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rolled {
+    /// Like `R3+5`; the second die is the red one
+    Dice([usize; 2]),
+    /// Like `R8`
+    Total(usize),
+}
+
+/// A move read from notation
+#[derive(Debug, Clone, Copy)]
+pub struct Notated {
+    pub mv: Move,
+    /// What a roll is written to come up as, if anything
+    pub roll: Option<Rolled>,
+}
+
+fn resource_letter(res: Resource) -> char {
+    match res {
+        Resource::Wood => 'W',
+        Resource::Brick => 'B',
+        Resource::Wheat => 'G',
+        Resource::Sheep => 'S',
+        Resource::Ore => 'O',
+    }
+}
+
+fn track_letter(track: Improvement) -> char {
+    match track {
+        Improvement::Trade => 'T',
+        Improvement::Politics => 'P',
+        Improvement::Science => 'S',
+    }
+}
+
+fn hand_notation(hand: ResHand) -> String {
+    RESOURCES.iter()
+        .filter(|&&res| hand[res] > 0)
+        .map(|&res| format!("{}{}", hand[res], resource_letter(res)))
+        .collect()
+}
+
+fn hex_notation(layout: &Layout, hex: [usize; 2]) -> String {
+    layout.hex_coords.iter().position(|&coords| coords == hex)
+        .map_or(String::from("H?"), |idx| format!("H{idx}"))
+}
+
+fn corner_notation(layout: &Layout, corner: [usize; 3]) -> String {
+    let corner = layout.reduce_corner(corner);
+    layout.corner_coords.iter().position(|&coords| coords == corner)
+        .map_or(String::from("@?"), |idx| format!("@{idx}"))
+}
+
+fn edge_notation(layout: &Layout, edge: [usize; 3]) -> String {
+    let edge = layout.reduce_edge(edge);
+    layout.edge_coords.iter().position(|&coords| coords == edge)
+        .map_or(String::from("@?"), |idx| format!("@{idx}"))
+}
+
+fn progress_notation(card: ProgressCard, arg: String) -> String {
+    format!("P:{} {arg}", card.into_label())
+}
+
+/// `mv` in notation, to be played on `table`. Rolls are left to the dice,
+/// and trades that go through a port are marked. This is how a seat sends
+/// its moves.
+pub fn to_notation(table: &Table, mv: &Move) -> String {
+    let layout = table.get_layout();
+    match *mv {
+        Move::Roll => String::from("R"),
+        Move::PlaceSettlement(corner) | Move::BuildSettlement(corner) => format!("S{}", corner_notation(layout, corner)),
+        Move::BuildCity(corner) => format!("C{}", corner_notation(layout, corner)),
+        Move::PlaceRoad(edge) | Move::BuildRoad(edge) | Move::FreeRoad(edge) => format!("Rd{}", edge_notation(layout, edge)),
+        Move::PlaceShip(edge) | Move::BuildShip(edge) => format!("Sh{}", edge_notation(layout, edge)),
+        Move::ShiftShip(from, to) => format!("Sh{}>{}", edge_notation(layout, from), edge_notation(layout, to)),
+        Move::BuildKnight(corner) => format!("N{}", corner_notation(layout, corner)),
+        Move::ActivateKnight(corner) => format!("N+{}", corner_notation(layout, corner)),
+        Move::PromoteKnight(corner) => format!("N^{}", corner_notation(layout, corner)),
+        Move::ChaseRobber(corner) => format!("N!{}", corner_notation(layout, corner)),
+        Move::RemoveKnight(corner) => format!("N-{}", corner_notation(layout, corner)),
+        Move::PlaceKnight(corner) => format!("N{}", corner_notation(layout, corner)),
+        Move::BuildWall(corner) => format!("W{}", corner_notation(layout, corner)),
+        Move::BuyDV => String::from("B"),
+        Move::RecordDV(card) => format!("B={}", card.into_label()),
+        Move::PlayDV(DVCard::Knight) => String::from("K"),
+        Move::PlayDV(card) => card.into_label(),
+        Move::YearOfPlenty(hand) => format!("YP {}", hand_notation(hand)),
+        Move::Monopoly(res) => format!("MN {}", resource_letter(res)),
+        Move::PlayProgress(card) => format!("P:{}", card.into_label()),
+        Move::ResourceMonopoly(res) => progress_notation(ProgressCard::ResourceMonopoly, resource_letter(res).to_string()),
+        Move::MerchantFleet(res) => progress_notation(ProgressCard::MerchantFleet, resource_letter(res).to_string()),
+        Move::TradeMonopoly(com) => progress_notation(ProgressCard::TradeMonopoly, com.into_label()),
+        Move::Alchemist([d1, d2]) => progress_notation(ProgressCard::Alchemist, format!("{d1}+{d2}")),
+        Move::Crane(track) => progress_notation(ProgressCard::Crane, track_letter(track).to_string()),
+        Move::Engineer(corner) => progress_notation(ProgressCard::Engineer, corner_notation(layout, corner)),
+        Move::Medicine(corner) => progress_notation(ProgressCard::Medicine, corner_notation(layout, corner)),
+        Move::Intrigue(corner) => progress_notation(ProgressCard::Intrigue, corner_notation(layout, corner)),
+        Move::Diplomat(edge) => progress_notation(ProgressCard::Diplomat, edge_notation(layout, edge)),
+        Move::Inventor(hex1, hex2) => progress_notation(
+            ProgressCard::Inventor, format!("{}>{}", hex_notation(layout, hex1), hex_notation(layout, hex2))
+        ),
+        Move::Merchant(hex) => progress_notation(ProgressCard::Merchant, hex_notation(layout, hex)),
+        Move::Bishop(hex) => progress_notation(ProgressCard::Bishop, hex_notation(layout, hex)),
+        Move::MasterMerchant(color) => progress_notation(ProgressCard::MasterMerchant, format!("{color:?}")),
+        Move::Deserter(color) => progress_notation(ProgressCard::Deserter, format!("{color:?}")),
+        Move::Spy(color) => progress_notation(ProgressCard::Spy, format!("{color:?}")),
+        Move::Improve(track) => format!("I {}", track_letter(track)),
+        Move::PlaceRobber(hex) => hex_notation(layout, hex),
+        Move::Steal(color) => format!(">{:?}", color),
+        Move::Discard(hand) => format!("D {}", hand_notation(hand)),
+        Move::ChooseGold(hand) => format!("G {}", hand_notation(hand)),
+        Move::Give(hand) => format!("GV {}", hand_notation(hand)),
+        Move::GiveCommodity(com) => format!("GV {}", com.into_label()),
+        Move::Take(hand) => format!("TK {}", hand_notation(hand)),
+        Move::TakeProgress(card) => format!("TK P:{}", card.into_label()),
+        Move::BankTrade(give, get) => {
+            let port = match table {
+                Table::Playing(state) => RESOURCES.iter().any(|&res| {
+                    give[res] > 0 && state.board.trade_rate(state.get_current_color(), res) < state.rules.bank_rate
+                }),
+                Table::Setup(_) => false,
+            };
+            format!("T {}>{}{}", hand_notation(give), hand_notation(get), if port {"@P"} else {""})
+        },
+        Move::Pass => String::from("E"),
+    }
+}
+
+/// `mv` in notation, as it was just played on `table`. Rolls are written
+/// with both dice, so typing them back in plays the game out the same.
+pub fn to_played_notation(table: &Table, mv: &Move) -> String {
+    match (mv, table) {
+        (Move::Roll, Table::Playing(state)) => state.roll.map_or(String::from("R"), |[d1, d2]| format!("R{d1}+{d2}")),
+        _ => to_notation(table, mv)
+    }
+}
+
+fn parse_resource(text: &str) -> Result<Resource, String> {
+    RESOURCES.iter().copied()
+        .find(|&res| text.eq_ignore_ascii_case(&resource_letter(res).to_string()))
+        .ok_or(format!("unknown resource '{text}'"))
+}
+
+/// Cards like `2W1O`, or `WO` for one of each
+fn parse_hand(text: &str) -> Result<ResHand, String> {
+    let mut hand = ResHand::new();
+    let mut count = None;
+    for ch in text.chars() {
+        if let Some(digit) = ch.to_digit(10) {
+            count = Some(count.unwrap_or(0) * 10 + digit as usize);
+        } else {
+            hand[parse_resource(&ch.to_string())?] += count.take().unwrap_or(1);
+        }
+    }
+    if count.is_some() || hand.size() == 0 {
+        return Err(format!("bad cards '{text}'"));
+    }
+    Ok(hand)
+}
+
+fn parse_index<const N: usize>(coords: &[[usize; N]], text: &str, what: &str) -> Result<[usize; N], String> {
+    text.parse::<usize>().ok()
+        .and_then(|idx| coords.get(idx).copied())
+        .ok_or(format!("there's no {what} '{text}'"))
+}
+
+/// What follows the `R` of a roll: both dice like `3+5`, or the total
+fn parse_roll(text: &str) -> Option<Rolled> {
+    let die = |text: &str| text.parse().ok().filter(|die| (1..=6).contains(die));
+    match text.split_once('+') {
+        Some((die1, die2)) => Some(Rolled::Dice([die(die1)?, die(die2)?])),
+        None => text.parse().ok().map(Rolled::Total),
+    }
+}
+
+fn parse_track(text: &str) -> Result<Improvement, String> {
+    IMPROVEMENTS.iter().copied()
+        .find(|&track| text.eq_ignore_ascii_case(&track_letter(track).to_string()))
+        .ok_or(format!("unknown track '{text}'"))
+}
+
+fn parse_commodity(text: &str) -> Result<Commodity, String> {
+    Commodity::from_label(&text.to_ascii_uppercase()).ok_or(format!("unknown commodity '{text}'"))
+}
+
+fn parse_progress_label(label: &str) -> Result<ProgressCard, String> {
+    ProgressCard::from_label(label).ok_or(format!("unknown progress card '{label}'"))
+}
+
+fn next_arg<'a>(tokens: &mut SplitWhitespace<'a>, token: &str) -> Result<&'a str, String> {
+    tokens.next().ok_or(format!("'{token}' needs something after it"))
+}
+
+/// A piece on a corner or side, like `S@12` or `Sh@30>@31`
+fn parse_piece(layout: &Layout, piece: &str, place: &str) -> Result<Move, String> {
+    let corner = || parse_index(&layout.corner_coords, place, "corner");
+    let edge = || parse_index(&layout.edge_coords, place, "side");
+    Ok(match piece {
+        "S" => Move::BuildSettlement(corner()?),
+        "C" => Move::BuildCity(corner()?),
+        "Rd" => Move::BuildRoad(edge()?),
+        "Sh" => match place.split_once(">@") {
+            Some((from, to)) => Move::ShiftShip(
+                parse_index(&layout.edge_coords, from, "side")?,
+                parse_index(&layout.edge_coords, to, "side")?
+            ),
+            None => Move::BuildShip(edge()?),
+        },
+        "N" => Move::BuildKnight(corner()?),
+        "N+" => Move::ActivateKnight(corner()?),
+        "N^" => Move::PromoteKnight(corner()?),
+        "N!" => Move::ChaseRobber(corner()?),
+        "N-" => Move::RemoveKnight(corner()?),
+        "W" => Move::BuildWall(corner()?),
+        _ => return Err(format!("unknown piece '{piece}'"))
+    })
+}
+
+/// A progress card played on `arg`, like `P:EN @12`
+fn parse_progress(layout: &Layout, card: ProgressCard, arg: &str) -> Result<Move, String> {
+    let place = |prefix: char| arg.strip_prefix(prefix).ok_or(format!("bad place '{arg}'"));
+    let corner = || parse_index(&layout.corner_coords, place('@')?, "corner");
+    let hex = |text: &str| parse_index(&layout.hex_coords, text.strip_prefix('H').unwrap_or(text), "hex");
+    Ok(match card {
+        ProgressCard::Alchemist => match parse_roll(arg) {
+            Some(Rolled::Dice(dice)) => Move::Alchemist(dice),
+            _ => return Err(format!("the Alchemist needs both dice, like 3+5, not '{arg}'"))
+        },
+        ProgressCard::Crane => Move::Crane(parse_track(arg)?),
+        ProgressCard::Engineer => Move::Engineer(corner()?),
+        ProgressCard::Medicine => Move::Medicine(corner()?),
+        ProgressCard::Intrigue => Move::Intrigue(corner()?),
+        ProgressCard::Diplomat => Move::Diplomat(parse_index(&layout.edge_coords, place('@')?, "side")?),
+        ProgressCard::Inventor => {
+            let (hex1, hex2) = arg.split_once('>').ok_or(format!("the Inventor needs two hexes, like H3>H9, not '{arg}'"))?;
+            Move::Inventor(hex(hex1)?, hex(hex2)?)
+        },
+        ProgressCard::Merchant => Move::Merchant(hex(arg)?),
+        ProgressCard::Bishop => Move::Bishop(hex(arg)?),
+        ProgressCard::ResourceMonopoly => Move::ResourceMonopoly(parse_resource(arg)?),
+        ProgressCard::MerchantFleet => Move::MerchantFleet(parse_resource(arg)?),
+        ProgressCard::TradeMonopoly => Move::TradeMonopoly(parse_commodity(arg)?),
+        ProgressCard::MasterMerchant => Move::MasterMerchant(parse_color(Some(&arg))?),
+        ProgressCard::Deserter => Move::Deserter(parse_color(Some(&arg))?),
+        ProgressCard::Spy => Move::Spy(parse_color(Some(&arg))?),
+        _ => Move::PlayProgress(card)
+    })
+}
+
+/// Reads a line of notation into its moves, in order. Pieces come back
+/// as moves made mid-game; `fit_move` turns them into whatever the table
+/// they're played on needs.
+pub fn parse_line(layout: &Layout, line: &str) -> Result<Vec<Notated>, String> {
+    let mut tokens = line.split_whitespace();
+    let mut moves = Vec::new();
+    while let Some(token) = tokens.next() {
+        let mut roll = None;
+        let mv = match token {
+            "R" => Move::Roll,
+            "B" => Move::BuyDV,
+            "E" => Move::Pass,
+            "K" => Move::PlayDV(DVCard::Knight),
+            "YP" => Move::YearOfPlenty(parse_hand(next_arg(&mut tokens, token)?)?),
+            "MN" => Move::Monopoly(parse_resource(next_arg(&mut tokens, token)?)?),
+            "D" => Move::Discard(parse_hand(next_arg(&mut tokens, token)?)?),
+            "G" => Move::ChooseGold(parse_hand(next_arg(&mut tokens, token)?)?),
+            "T" => {
+                let arg = next_arg(&mut tokens, token)?;
+                // Only the cards count; a mark like `@P` is for whoever reads it
+                let cards = arg.split_once('@').map_or(arg, |(cards, _)| cards);
+                let (give, get) = cards.split_once('>').ok_or(format!("bad trade '{arg}'"))?;
+                Move::BankTrade(parse_hand(give)?, parse_hand(get)?)
+            },
+            "GV" => {
+                let arg = next_arg(&mut tokens, token)?;
+                match parse_commodity(arg) {
+                    Ok(com) => Move::GiveCommodity(com),
+                    Err(_) => Move::Give(parse_hand(arg)?),
+                }
+            },
+            "TK" => {
+                let arg = next_arg(&mut tokens, token)?;
+                match arg.strip_prefix("P:") {
+                    Some(label) => Move::TakeProgress(parse_progress_label(label)?),
+                    None => Move::Take(parse_hand(arg)?),
+                }
+            },
+            "I" => Move::Improve(parse_track(next_arg(&mut tokens, token)?)?),
+            _ => if let Some(rolled) = token.strip_prefix('R').and_then(parse_roll) {
+                roll = Some(rolled);
+                Move::Roll
+            } else if let Some(label) = token.strip_prefix("B=") {
+                Move::RecordDV(
+                    DV_CARDS.iter().copied()
+                        .find(|card| card.into_label() == label)
+                        .ok_or(format!("unknown development card '{label}'"))?
+                )
+            } else if let Some(label) = token.strip_prefix("P:") {
+                let card = parse_progress_label(label)?;
+                if card.needs_choice() {
+                    parse_progress(layout, card, next_arg(&mut tokens, token)?)?
+                } else {
+                    Move::PlayProgress(card)
+                }
+            } else if let Some(color) = token.strip_prefix('>') {
+                Move::Steal(parse_color(Some(&color))?)
+            } else if let Some((piece, place)) = token.split_once('@') {
+                parse_piece(layout, piece, place)?
+            } else if let Some(hex) = token.strip_prefix('H') {
+                Move::PlaceRobber(parse_index(&layout.hex_coords, hex, "hex")?)
+            } else if let Some(card) = DV_CARDS.iter().copied().find(|card| card.into_label() == token) {
+                Move::PlayDV(card)
+            } else {
+                return Err(format!("unknown move '{token}'"))
+            }
+        };
+        moves.push(Notated { mv, roll });
+    }
+    Ok(moves)
+}
+
+/// Notation doesn't tell placing a piece during setup from building it,
+/// a road from one Road Building pays for, or a knight from one placed
+/// for free, so `mv` is made whichever one `table` is waiting on
+pub fn fit_move(table: &Table, mv: Move) -> Move {
+    match (table, mv) {
+        (Table::Setup(_), Move::BuildSettlement(corner)) => Move::PlaceSettlement(corner),
+        (Table::Setup(_), Move::BuildRoad(edge)) => Move::PlaceRoad(edge),
+        (Table::Setup(_), Move::BuildShip(edge)) => Move::PlaceShip(edge),
+        (Table::Playing(state), Move::BuildRoad(edge)) if matches!(state.action, Action::RoadBuilding(_)) =>
+            Move::FreeRoad(edge),
+        (Table::Playing(state), Move::BuildKnight(corner))
+            if matches!(state.action, Action::PlacingDeserter(_) | Action::Displaced(..)) => Move::PlaceKnight(corner),
+        _ => mv
+    }
+}
+
+/// Reads one move as a seat sends it, made whichever `table` is waiting
+/// on. The dice are the game's to roll, so a roll can't say what came up.
+pub fn parse_move(table: &Table, text: &str) -> Result<Move, String> {
+    match parse_line(table.get_layout(), text)?[..] {
+        [Notated { mv, roll: None }] => Ok(fit_move(table, mv)),
+        [Notated { roll: Some(_), .. }] => Err(String::from("the dice decide what a roll comes up")),
+        _ => Err(format!("'{}' isn't one move", text.trim())),
+    }
+}
+
+/// What a place is called both ways: `H9` or `@12` as coordinates, and
+/// `R,Q` or `R,Q,N` in notation
+pub fn locate(layout: &Layout, text: &str) -> Result<String, String> {
+    let coords = |coords: &[usize]| coords.iter().map(|coord| coord.to_string()).collect::<Vec<String>>().join(",");
+    if let Some(idx) = text.strip_prefix('H') {
+        let hex = parse_index(&layout.hex_coords, idx, "hex")?;
+        return Ok(format!("{text} is hex {}", coords(&hex)));
+    }
+    if let Some(idx) = text.strip_prefix('@') {
+        let corner = parse_index(&layout.corner_coords, idx, "corner").map(|corner| format!("corner {}", coords(&corner)));
+        let edge = parse_index(&layout.edge_coords, idx, "side").map(|edge| format!("side {}", coords(&edge)));
+        return match (corner, edge) {
+            (Ok(corner), Ok(edge)) => Ok(format!("{text} is {corner}, or {edge}")),
+            (Ok(place), Err(_)) | (Err(_), Ok(place)) => Ok(format!("{text} is {place}")),
+            (Err(err), Err(_)) => Err(err),
+        };
+    }
+    let args: Vec<&str> = text.split(',').collect();
+    match args.len() {
+        2 => {
+            let hex = parse_coords(&args)?;
+            if !layout.is_on_board(hex) {
+                return Err(format!("there's no hex {text}"));
+            }
+            Ok(format!("hex {text} is {}", hex_notation(layout, hex)))
+        },
+        3 => {
+            let [r, q, n] = parse_coords(&args)?;
+            if !layout.is_on_board([r, q]) || n >= 6 {
+                return Err(format!("there's no corner or side {text}"));
+            }
+            Ok(format!(
+                "corner {text} is {}, side {text} is {}",
+                corner_notation(layout, [r, q, n]), edge_notation(layout, [r, q, n])
+            ))
+        },
+        _ => Err(format!("'{text}' isn't a place"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::game::{PlayerColor, PROGRESS_CARDS};
+    use crate::new_game::{BoardChoice, NewGame};
+    use crate::{test_game, SetupState};
+
+    fn hand(cards: &[(Resource, usize)]) -> ResHand {
+        let mut hand = ResHand::new();
+        for &(res, count) in cards {
+            hand[res] += count;
+        }
+        hand
+    }
+
+    /// `mv` written for `table` and read back
+    fn round_trip(table: &Table, mv: Move) -> Move {
+        let text = to_notation(table, &mv);
+        match parse_line(table.get_layout(), &text).unwrap()[..] {
+            [notated] => fit_move(table, notated.mv),
+            _ => panic!("'{text}' read back as more than one move"),
+        }
+    }
+
+    fn playing_table() -> Table {
+        Table::Playing(Box::new(test_game(BoardChoice::Random, 49, 1)))
+    }
+
+    #[test]
+    fn every_move_reads_back_as_itself() {
+        let mut table = playing_table();
+        let layout = table.get_layout();
+        let (corner, edge, other_edge, hex) =
+            (layout.corner_coords[12], layout.edge_coords[30], layout.edge_coords[31], layout.hex_coords[9]);
+        let mut moves = vec![
+            Move::Roll,
+            Move::BuyDV,
+            Move::BuildRoad(edge),
+            Move::BuildShip(edge),
+            Move::ShiftShip(edge, other_edge),
+            Move::BuildSettlement(corner),
+            Move::BuildCity(corner),
+            Move::BuildKnight(corner),
+            Move::ActivateKnight(corner),
+            Move::PromoteKnight(corner),
+            Move::ChaseRobber(corner),
+            Move::PlayDV(DVCard::Knight),
+            Move::PlayDV(DVCard::RoadBuilding),
+            Move::YearOfPlenty(hand(&[(Resource::Wood, 1), (Resource::Ore, 1)])),
+            Move::Monopoly(Resource::Ore),
+            Move::ResourceMonopoly(Resource::Sheep),
+            Move::PlaceRobber(hex),
+            Move::Steal(PlayerColor::Blue),
+            Move::Discard(hand(&[(Resource::Wood, 2), (Resource::Ore, 1)])),
+            Move::ChooseGold(hand(&[(Resource::Sheep, 1)])),
+            Move::BankTrade(hand(&[(Resource::Wood, 2)]), hand(&[(Resource::Ore, 1)])),
+            Move::Pass,
+        ];
+        moves.extend(DV_CARDS.iter().map(|&card| Move::RecordDV(card)));
+        moves.extend(IMPROVEMENTS.iter().map(|&track| Move::Improve(track)));
+        moves.extend(PROGRESS_CARDS.iter()
+            .filter(|card| !card.needs_choice())
+            .map(|&card| Move::PlayProgress(card)));
+        moves.extend([
+            Move::BuildWall(corner),
+            Move::RemoveKnight(corner),
+            Move::Alchemist([3, 5]),
+            Move::Crane(Improvement::Science),
+            Move::Engineer(corner),
+            Move::Medicine(corner),
+            Move::Intrigue(corner),
+            Move::Diplomat(edge),
+            Move::Inventor(hex, layout.hex_coords[3]),
+            Move::Merchant(hex),
+            Move::Bishop(hex),
+            Move::MerchantFleet(Resource::Wheat),
+            Move::TradeMonopoly(Commodity::Cloth),
+            Move::MasterMerchant(PlayerColor::Blue),
+            Move::Deserter(PlayerColor::Blue),
+            Move::Spy(PlayerColor::Blue),
+            Move::Give(hand(&[(Resource::Wood, 1), (Resource::Ore, 1)])),
+            Move::GiveCommodity(Commodity::Coin),
+            Move::Take(hand(&[(Resource::Wood, 2)])),
+            Move::TakeProgress(ProgressCard::Warlord),
+        ]);
+        for mv in moves {
+            assert_eq!(round_trip(&table, mv), mv, "{}", to_notation(&table, &mv));
+        }
+
+        if let Table::Playing(state) = &mut table {
+            state.action = Action::RoadBuilding(false);
+        }
+        assert_eq!(round_trip(&table, Move::FreeRoad(edge)), Move::FreeRoad(edge));
+
+        let new_game = NewGame::new(49);
+        let setup = Table::Setup(Box::new(SetupState::from_new_game(&new_game, &mut StdRng::seed_from_u64(49)).unwrap()));
+        for mv in [Move::PlaceSettlement(corner), Move::PlaceRoad(edge), Move::PlaceShip(edge)] {
+            assert_eq!(round_trip(&setup, mv), mv, "{}", to_notation(&setup, &mv));
+        }
+    }
+
+    #[test]
+    fn moves_are_written_as_the_help_shows_them() {
+        let table = playing_table();
+        let layout = table.get_layout();
+        assert_eq!(to_notation(&table, &Move::BuildSettlement(layout.corner_coords[12])), "S@12");
+        assert_eq!(to_notation(&table, &Move::PlaceRobber(layout.hex_coords[9])), "H9");
+        assert_eq!(to_notation(&table, &Move::Steal(PlayerColor::Blue)), ">Blue");
+        assert_eq!(to_notation(&table, &Move::Roll), "R");
+
+        let moves: Vec<Move> = parse_line(layout, "K H9 >Blue").unwrap().iter().map(|notated| notated.mv).collect();
+        assert_eq!(moves, [Move::PlayDV(DVCard::Knight), Move::PlaceRobber(layout.hex_coords[9]), Move::Steal(PlayerColor::Blue)]);
+        let trade = parse_line(layout, "T 2W>1O@P").unwrap();
+        assert_eq!(trade[0].mv, Move::BankTrade(hand(&[(Resource::Wood, 2)]), hand(&[(Resource::Ore, 1)])));
+    }
+
+    #[test]
+    fn rolls_are_written_with_both_dice() {
+        let mut table = playing_table();
+        let layout = table.get_layout();
+        for (text, rolled) in [("R", None), ("R8", Some(Rolled::Total(8))), ("R3+5", Some(Rolled::Dice([3, 5])))] {
+            let notated = parse_line(layout, text).unwrap();
+            assert_eq!((notated.len(), notated[0].mv, notated[0].roll), (1, Move::Roll, rolled), "{text}");
+        }
+        assert!(parse_line(layout, "R3+7").is_err());
+
+        assert!(table.can_make_move(&Move::Roll));
+        table.play(Some(&Move::Roll), &mut StdRng::seed_from_u64(49));
+        let Table::Playing(state) = &table else { unreachable!() };
+        let [die1, die2] = state.roll.unwrap();
+        let text = to_played_notation(&table, &Move::Roll);
+        assert_eq!(text, format!("R{die1}+{die2}"));
+        assert_eq!(parse_line(table.get_layout(), &text).unwrap()[0].roll, Some(Rolled::Dice([die1, die2])));
+    }
+
+    #[test]
+    fn sent_moves_are_one_move_and_leave_the_dice_alone() {
+        let table = playing_table();
+        assert_eq!(parse_move(&table, "R"), Ok(Move::Roll));
+        assert_eq!(parse_move(&table, "E"), Ok(Move::Pass));
+        assert!(parse_move(&table, "R3+5").is_err());
+        assert!(parse_move(&table, "R E").is_err());
+        assert!(parse_move(&table, "").is_err());
+    }
+}
@@ -0,0 +1,453 @@
+use crate::game::*;
+use crate::game_log::GameLog;
+use crate::stats::Stats;
+use crate::{save, Action, GameState, MenuButton, Selector};
+
+/// The game as one seat may see it. The board and everything counted on
+/// the table are public and the seat's own cards are exact, but the other
+/// seats only show how many cards they hold, the decks only how many are
+/// left, and the log leaves out what others stole from each other.
+///
+/// The screen and the CPUs only get the game through a view, so whatever
+/// a seat isn't allowed to know simply isn't there to leak.
+pub struct PlayerView {
+    viewer: PlayerColor,
+    /// Someone playing the seat, rather than a spectator looking through its eyes
+    seated: bool,
+    /// A spectator who sees every hand
+    caster: bool,
+    state: GameState,
+}
+
+/// A seat as its opponents see it: what's on the table,
+/// and how many cards of each kind it holds
+#[derive(Debug, Clone, Copy)]
+pub struct Opponent {
+    pub color: PlayerColor,
+    pub is_human: bool,
+    /// Counting VP cards in hand only where the view sees them
+    pub vps: usize,
+    pub cards: CardCounts,
+    pub knights: usize,
+    pub road_len: usize,
+    pub road_pool: usize,
+    pub settlement_pool: usize,
+    pub city_pool: usize,
+    pub improvements: [usize; 3],
+}
+
+impl From<&Player> for Opponent {
+    fn from(player: &Player) -> Self {
+        Opponent {
+            color: player.get_color(),
+            is_human: player.is_human(),
+            vps: player.get_vps(),
+            cards: player.get_card_counts(),
+            knights: player.get_knights(),
+            road_len: player.get_road_len(),
+            road_pool: player.get_road_pool(),
+            settlement_pool: player.get_settlement_pool(),
+            city_pool: player.get_city_pool(),
+            improvements: IMPROVEMENTS.map(|track| player.get_improvement(track)),
+        }
+    }
+}
+
+/// The game with everything `viewer` may not know taken out. Another seat's
+/// cards are turned face down, the decks keep how many cards are left but not
+/// which, and the log leaves out others' thefts. A caster sees every hand.
+///
+/// A game that has been through this already comes out the same.
+fn seen_by(state: &GameState, viewer: PlayerColor, seated: bool, caster: bool) -> GameState {
+    // The companion's dv deck is the one on the real table, counted by hand
+    let board = if state.companion { state.board.clone() } else { state.board.face_down() };
+    // The Master Merchant and the Spy look through their victim's cards
+    let acting = seated && state.get_current_color() == viewer;
+    let players = state.players.iter()
+        .map(|player| if caster || (seated && player.is_color(viewer)) {
+            player.clone()
+        } else {
+            match state.action {
+                Action::Taking(victim) if acting && player.is_color(victim) => player.face_down_showing(true, false),
+                Action::Spying(victim) if acting && player.is_color(victim) => player.face_down_showing(false, true),
+                _ => player.face_down()
+            }
+        })
+        .collect();
+    // A selector is the acting seat's choice in the making
+    let selector = if acting { state.selector } else { None };
+    let log = if caster {
+        state.log.clone()
+    } else if seated {
+        state.log.seen_by(viewer)
+    } else {
+        state.log.public()
+    };
+
+    GameState {
+        num_players: state.num_players,
+        ruleset: state.ruleset,
+        rules: state.rules,
+        dice: state.dice.face_down(),
+        companion: state.companion,
+        hot_seat: false,
+        bots: vec![None; state.num_players],
+        typed_dice: state.typed_dice.clone(),
+        round: state.round,
+        winner: state.winner,
+        stats: state.stats.clone(),
+        log,
+        island_bonus: state.island_bonus,
+        home_islands: state.home_islands.clone(),
+        settled_islands: state.settled_islands.clone(),
+        board,
+        players,
+        largest_army: state.largest_army,
+        largest_army_size: state.largest_army_size,
+        longest_road: state.longest_road,
+        longest_road_size: state.longest_road_size,
+        current_player: state.current_player,
+        turn_player: state.turn_player,
+        roll: state.roll,
+        played_dv: state.played_dv,
+        new_ships: state.new_ships.clone(),
+        moved_ship: state.moved_ship,
+        gold_owed: state.gold_owed.clone(),
+        event: state.event,
+        barbarians: state.barbarians,
+        barbarians_attacked: state.barbarians_attacked,
+        metropolises: state.metropolises,
+        // Nothing on the table shows what's left in them
+        progress_decks: vec![Vec::new(); state.progress_decks.len()],
+        merchant_fleet: state.merchant_fleet,
+        selector,
+        offered_trades: state.offered_trades.clone(),
+        special_building: state.special_building,
+        action: state.action,
+        rng_action: state.rng_action.clone(),
+    }
+}
+
+impl PlayerView {
+    pub fn new(state: &GameState, viewer: PlayerColor) -> PlayerView {
+        PlayerView { viewer, seated: true, caster: false, state: seen_by(state, viewer, true, false) }
+    }
+
+    /// The game for a spectator looking through `seat`'s eyes. A caster sees
+    /// every hand; anyone else only what's public, the seat's own cards included.
+    pub fn spectating(state: &GameState, seat: PlayerColor, caster: bool) -> PlayerView {
+        PlayerView { viewer: seat, seated: false, caster, state: seen_by(state, seat, false, caster) }
+    }
+
+    pub fn get_viewer(&self) -> PlayerColor {
+        self.viewer
+    }
+
+    /// Whether the view belongs to whoever plays the seat, so it can act
+    pub fn is_seated(&self) -> bool {
+        self.seated
+    }
+
+    /// Whether the seat's own cards are face up
+    pub fn shows_hand(&self) -> bool {
+        self.seated || self.caster
+    }
+
+    /// Whether the view's seat is the one to act, so its own moves can be checked
+    fn is_acting(&self) -> bool {
+        self.seated && self.state.get_current_color() == self.viewer
+    }
+
+    /// The view's own seat with its cards, where the view sees them
+    pub fn get_own_player(&self) -> Option<&Player> {
+        if self.shows_hand() { self.state.get_player(self.viewer) } else { None }
+    }
+
+    /// A seat's cards as far as the view sees them: its own, or those
+    /// of a victim a progress card lets it look through
+    pub fn get_shown_player(&self, color: PlayerColor) -> Option<&Player> {
+        self.state.get_player(color)
+    }
+
+    pub fn get_opponent(&self, color: PlayerColor) -> Option<Opponent> {
+        self.state.get_player(color).map(Opponent::from)
+    }
+
+    /// Every seat in turn order, the view's own included
+    pub fn get_opponents(&self) -> Vec<Opponent> {
+        self.state.players.iter().map(Opponent::from).collect()
+    }
+
+    /// How many resource cards `color` holds
+    pub fn get_hand_size(&self, color: PlayerColor) -> usize {
+        self.get_opponent(color).map_or(0, |opponent| opponent.cards.resources)
+    }
+
+    pub fn get_board(&self) -> &Board {
+        &self.state.board
+    }
+
+    pub fn get_ruleset(&self) -> Ruleset {
+        self.state.ruleset
+    }
+
+    pub fn is_companion(&self) -> bool {
+        self.state.companion
+    }
+
+    pub fn get_num_players(&self) -> usize {
+        self.state.num_players
+    }
+
+    pub fn get_order(&self) -> Vec<PlayerColor> {
+        self.state.get_order()
+    }
+
+    pub fn get_current_color(&self) -> PlayerColor {
+        self.state.get_current_color()
+    }
+
+    /// Whose turn it is, even while someone else is discarding or choosing gold
+    pub fn get_turn_color(&self) -> PlayerColor {
+        self.state.players[self.state.turn_player].get_color()
+    }
+
+    pub fn is_players_turn(&self, color: PlayerColor) -> bool {
+        self.state.is_players_turn(color)
+    }
+
+    pub fn get_roll(&self) -> Option<[usize; 2]> {
+        self.state.roll
+    }
+
+    /// Dice typed so far into a companion game
+    pub fn get_typed_dice(&self) -> &[usize] {
+        &self.state.typed_dice
+    }
+
+    pub fn get_event(&self) -> Option<EventDie> {
+        self.state.event
+    }
+
+    pub fn get_barbarians(&self) -> usize {
+        self.state.barbarians
+    }
+
+    pub fn get_metropolises(&self) -> &[Option<(PlayerColor, [usize; 3])>; 3] {
+        &self.state.metropolises
+    }
+
+    pub fn get_winner(&self) -> Option<PlayerColor> {
+        self.state.winner
+    }
+
+    pub fn get_longest_road(&self) -> Option<PlayerColor> {
+        self.state.longest_road
+    }
+
+    pub fn get_largest_army(&self) -> Option<PlayerColor> {
+        self.state.largest_army
+    }
+
+    pub fn get_action(&self) -> Action {
+        self.state.action
+    }
+
+    pub fn is_special_building(&self) -> bool {
+        self.state.special_building
+    }
+
+    pub fn has_played_dv(&self) -> bool {
+        self.state.played_dv
+    }
+
+    /// How many cards `color` gets to pick for their gold fields
+    pub fn get_gold_owed(&self, color: PlayerColor) -> usize {
+        self.state.players.iter().position(|player| player.is_color(color))
+            .map_or(0, |idx| self.state.gold_owed[idx])
+    }
+
+    pub fn get_log(&self) -> &GameLog {
+        &self.state.log
+    }
+
+    pub fn get_stats(&self) -> &Stats {
+        &self.state.stats
+    }
+
+    pub fn get_menu(&self) -> Vec<MenuButton> {
+        self.state.get_menu()
+    }
+
+    /// The selector the view's seat has open, if it's their move
+    pub fn get_selector(&self) -> Option<&Selector> {
+        self.state.selector.as_ref()
+    }
+
+    pub fn can_rob(&self, color: PlayerColor) -> bool {
+        self.state.can_rob(color)
+    }
+
+    pub fn can_move_robber(&self, hex: [usize; 2]) -> bool {
+        self.state.can_move_robber(hex)
+    }
+
+    pub fn can_move_ship_from(&self, edge: [usize; 3]) -> bool {
+        self.state.can_move_ship_from(edge)
+    }
+
+    /// How many of `resource` `color` pays the bank per card
+    pub fn trade_rate(&self, color: PlayerColor, resource: Resource) -> usize {
+        self.state.trade_rate(color, resource)
+    }
+
+    /// How many cards a Wedding gift, Commercial Harbor offer or Master Merchant pick comes to
+    pub fn cards_to_give(&self) -> usize {
+        self.state.cards_to_give()
+    }
+
+    // The rest need the acting seat's cards, so only that seat gets an answer
+
+    /// Which menu buttons the view's seat can press
+    pub fn get_available_actions(&self) -> Vec<bool> {
+        if self.is_acting() {
+            self.state.get_available_actions(self.viewer)
+        } else {
+            vec![false; self.get_menu().len()]
+        }
+    }
+
+    pub fn can_buy_dv(&self) -> bool {
+        self.is_acting() && self.state.can_buy_dv()
+    }
+
+    pub fn can_build_road(&self) -> bool {
+        self.is_acting() && self.state.can_build_road()
+    }
+
+    pub fn can_build_ship(&self) -> bool {
+        self.is_acting() && self.state.can_build_ship()
+    }
+
+    pub fn can_build_settlement(&self) -> bool {
+        self.is_acting() && self.state.can_build_settlement()
+    }
+
+    pub fn can_build_city(&self) -> bool {
+        self.is_acting() && self.state.can_build_city()
+    }
+
+    pub fn can_build_knight(&self) -> bool {
+        self.is_acting() && self.state.can_build_knight()
+    }
+
+    pub fn can_activate_knight(&self) -> bool {
+        self.is_acting() && self.state.can_activate_knight()
+    }
+
+    pub fn can_promote_knight_at(&self, corner: [usize; 3]) -> bool {
+        self.is_acting() && self.state.can_promote_knight_at(corner)
+    }
+
+    pub fn can_improve(&self, track: Improvement) -> bool {
+        self.is_acting() && self.state.can_improve(track)
+    }
+
+    pub fn can_trade_with_bank(&self, give: ResHand, get: ResHand) -> bool {
+        self.is_acting() && self.state.can_trade_with_bank(give, get)
+    }
+
+    pub fn can_execute_selector(&self) -> bool {
+        self.is_acting() && self.state.selector.is_some() && self.state.can_execute_selector()
+    }
+
+    pub fn can_cancel_selector(&self) -> bool {
+        self.is_acting() && self.state.selector.is_some() && self.state.can_cancel_selector()
+    }
+
+    /// The view written out the way a saved game is
+    pub fn to_text(&self) -> String {
+        save::to_text(&self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_log::LogEvent;
+    use crate::new_game::BoardChoice;
+    use crate::test_game;
+
+    #[test]
+    fn opponents_show_counts_only() {
+        let state = test_game(BoardChoice::CitiesKnights, 9, 6);
+        let viewer = state.players[2].get_color();
+        let view = PlayerView::new(&state, viewer);
+
+        assert_eq!(view.get_own_player().unwrap().to_text(), state.players[2].to_text());
+        for (player, opponent) in state.players.iter().zip(view.get_opponents()) {
+            assert_eq!(opponent.cards, player.get_card_counts());
+            assert_eq!(opponent.vps, if player.is_color(viewer) {player.get_vps()} else {player.get_vps() - player.get_dvs()[DVCard::VictoryPoint]});
+        }
+        for player in view.state.players.iter().filter(|player| !player.is_color(viewer)) {
+            assert_eq!(player.get_hand().size(), 0);
+            assert!(player.get_progress_counts().is_empty());
+        }
+        assert_eq!(view.get_board().dv_bank.size(), 0);
+        assert_eq!(view.get_board().get_dv_bank_size(), state.board.dv_bank.size());
+    }
+
+    #[test]
+    fn the_summary_hides_vp_cards_from_everyone_else() {
+        let mut state = test_game(BoardChoice::Random, 4, 0);
+        let viewer = state.players[0].get_color();
+        let player = &mut state.players[1];
+        let color = player.get_color();
+        player.get_cards(DV_CARD_HAND);
+        player.buy_dv(DVCard::VictoryPoint);
+        player.cycle_dvs();
+        player.get_cards(ROAD_HAND);
+        player.build_road();
+        let player = &state.players[1];
+        let visible = player.get_visible_vps();
+        assert_eq!(visible + 1, player.get_vps());
+
+        let opponent = PlayerView::new(&state, viewer).get_opponent(color).unwrap();
+        assert_eq!(opponent.vps, visible);
+        assert_eq!(opponent.cards.dvs, 1);
+        assert_eq!(opponent.cards.resources, player.get_hand().size());
+        assert_eq!(opponent.road_pool, player.get_road_pool());
+        assert_eq!(opponent.settlement_pool, player.get_settlement_pool());
+        assert_eq!(opponent.city_pool, player.get_city_pool());
+        assert_eq!(PlayerView::new(&state, viewer).get_hand_size(color), player.get_hand().size());
+
+        let own = PlayerView::new(&state, color).get_opponent(color).unwrap();
+        assert_eq!(own.vps, visible + 1);
+    }
+
+    #[test]
+    fn views_only_name_a_stolen_card_to_the_two_involved() {
+        let mut state = test_game(BoardChoice::Random, 4, 0);
+        let [thief, victim, other] = [0, 1, 2].map(|idx| state.players[idx].get_color());
+        state.log.add(LogEvent::Stole { thief, victim, card: Some(Resource::Brick) });
+        let last = |view: PlayerView| view.get_log().recent(None, 0, 1).remove(0);
+
+        assert!(last(PlayerView::new(&state, thief)).ends_with("(Brick)"));
+        assert!(last(PlayerView::new(&state, victim)).ends_with("(Brick)"));
+        assert!(!last(PlayerView::new(&state, other)).contains("Brick"));
+        assert!(!last(PlayerView::spectating(&state, thief, false)).contains("Brick"));
+    }
+
+    #[test]
+    fn only_the_acting_seat_gets_answers() {
+        let state = test_game(BoardChoice::Random, 4, 5);
+        let current = state.get_current_color();
+        let waiting = state.get_order().into_iter().find(|&color| color != current).unwrap();
+
+        assert!(PlayerView::new(&state, waiting).get_available_actions().iter().all(|&clickable| !clickable));
+        assert_eq!(PlayerView::new(&state, current).get_available_actions(), state.get_available_actions(current));
+        assert!(PlayerView::spectating(&state, current, false).get_available_actions().iter().all(|&clickable| !clickable));
+        assert!(PlayerView::spectating(&state, current, false).get_own_player().is_none());
+        assert!(PlayerView::spectating(&state, current, true).get_own_player().is_some());
+    }
+}
@@ -1,11 +1,16 @@
 use macroquad::prelude::*;
 
 use crate::game::{
-    Board, DVCard, Hex, Player, PlayerColor, Port, Resource, StructureType,
-    CORNER_COORDS, DV_CARDS, EDGE_COORDS, HEX_COORDS, RESOURCES
+    pips, Board, Commodity, DVCard, EventDie, Hex, Player, PlayerColor, Port, ProgressCard, Resource, Ruleset,
+    StructureType, Terrain, BARBARIAN_TRACK_LEN, COMMODITIES, DV_CARDS, IMPROVEMENTS, RESOURCES
 };
-use crate::screen_coords::ScreenCoords;
-use crate::{Action, GameState, Selector, SetupState};
+use crate::game_log::GameLog;
+use crate::rules::{RuleConfig, RULE_OPTIONS};
+use crate::new_game::NewGame;
+use crate::player_view::{Opponent, PlayerView};
+use crate::screen_coords::{LobbyCoords, MainMenuCoords, RulesCoords, ScreenCoords};
+use crate::server::{RoomSeat, RoomSummary};
+use crate::{menu_buttons, Action, GameState, MenuButton, Selector, SetupState, LOBBY_PANEL_ROWS, LOBBY_ROOM_ROWS};
 
 const SQRT_3: f32 = 1.732050807568877293527446341505872367_f32;
 
@@ -29,7 +34,10 @@ fn render_hex(center: &[f32; 2], hex: Hex, scale: f32) {
     let font_size = scale;
 
     let &[x, y] = center;
-    let color = hex.resource.into();
+    let color = match hex.terrain {
+        Terrain::Resource(res) => res.into(),
+        _ => GOLD
+    };
     let num_color = if hex.number == 6 || hex.number == 8 {MAROON} else {BLACK};
     let digit_offset = if hex.number >= 10 {num_offset * 0.8} else {0.0};
 
@@ -38,6 +46,19 @@ fn render_hex(center: &[f32; 2], hex: Hex, scale: f32) {
     draw_circle(x, y, circle_radius, BEIGE);
     draw_circle_lines(x, y, circle_radius, circle_thickness, BLACK);
     draw_text(hex.number.to_string().as_str(), x - num_offset - digit_offset, y + num_offset, font_size, num_color);
+    render_pips(center, hex.number, scale, num_color);
+}
+
+/// One dot under the number for each way two dice can make it
+fn render_pips(center: &[f32; 2], number: usize, scale: f32, color: Color) {
+    let &[x, y] = center;
+    let radius = 0.035 * scale;
+    let spacing = 0.09 * scale;
+    let count = pips(number);
+    let start_x = x - 0.5 * spacing * (count - 1) as f32;
+    for i in 0..count {
+        draw_circle(start_x + i as f32 * spacing, y + 0.36 * scale, radius, color);
+    }
 }
 
 fn render_desert(center: &[f32; 2], hex_size: f32) {
@@ -49,37 +70,53 @@ fn render_desert(center: &[f32; 2], hex_size: f32) {
     draw_poly_lines(x, y, 6, radius, 30.0, thickness, BLACK);
 }
 
-fn render_hexes(board: &Board, centers: &[[f32; 2]; 19], hex_size: f32) {
-    for idx in 0..HEX_COORDS.len() {
-        let [r, q] = HEX_COORDS[idx];
-        match board.hexes[r][q] {
-            Some(hex) => render_hex(&centers[idx], hex, hex_size),
-            None => render_desert(&centers[idx], hex_size)
+fn render_sea(center: &[f32; 2], hex_size: f32) {
+    let radius = hex_size;
+    let thickness = hex_size / 20.0;
+
+    let &[x, y] = center;
+    draw_poly(x, y, 6, radius, 30.0, SKYBLUE);
+    draw_poly_lines(x, y, 6, radius, 30.0, thickness, DARKBLUE);
+}
+
+fn render_hexes(board: &Board, centers: &[[f32; 2]], hex_size: f32) {
+    for (&[r, q], center) in board.layout.hex_coords.iter().zip(centers) {
+        let hex = board.hexes[r][q].unwrap();
+        match hex.terrain {
+            Terrain::Desert => render_desert(center, hex_size),
+            Terrain::Sea => render_sea(center, hex_size),
+            _ => render_hex(center, hex, hex_size)
         }
     }
 }
 
-fn render_port(coord: &[f32; 3], port: &Port, hex_size: f32) {
+fn render_port(marker: [f32; 2], rotation: f32, port: &Port, hex_size: f32) {
     let radius = hex_size / 3.0;
     let thickness = hex_size / 30.0;
-    let stretch_factor = 0.35 * hex_size;
 
-    let color: Color = match port {
-        &Port::ThreeForOne => WHITE,
-        &Port::TwoForOne(res) => res.into()
+    let color: Color = match *port {
+        Port::ThreeForOne => WHITE,
+        Port::TwoForOne(res) => res.into()
     };
 
-    let &[mut x, mut y, rotation] = coord;
-    x -= stretch_factor * f32::cos((rotation + 45.0).to_radians());
-    y -= stretch_factor * f32::sin((rotation + 45.0).to_radians());
-
+    let [x, y] = marker;
     draw_poly(x, y, 4, radius, rotation, color);
     draw_poly_lines(x, y, 4, radius, rotation, thickness, BLACK);
 }
 
-fn render_ports(board: &Board, ports: &[[f32; 3]; 9], hex_size: f32) {
-    for idx in 0..ports.len() {
-        render_port(&ports[idx], &board.ports[idx], hex_size);
+fn render_ports(coords: &ScreenCoords, board: &Board) {
+    for (idx, port) in board.ports.iter().enumerate() {
+        render_port(coords.port_marker(idx), coords.ports[idx][2], port, coords.hex_size);
+    }
+}
+
+/// Rings the ports `color` can trade at
+fn render_owned_ports(coords: &ScreenCoords, board: &Board, color: PlayerColor) {
+    let radius = coords.hex_size / 2.5;
+    let thickness = coords.hex_size / 12.0;
+    for idx in board.ports_of(color) {
+        let [x, y] = coords.port_marker(idx);
+        draw_circle_lines(x, y, radius, thickness, color.into());
     }
 }
 
@@ -109,11 +146,38 @@ fn render_road(edge: &[f32; 2], e: usize, color: Color, hex_size: f32) {
     draw_line(x1, y1, x2, y2, thickness, color);
 }
 
-fn render_roads(board: &Board, edges: &[[f32; 2]; 72], hex_size: f32) {
-    for idx in 0..EDGE_COORDS.len() {
-        let [r, q, e] = EDGE_COORDS[idx];
+fn render_roads(board: &Board, edges: &[[f32; 2]], hex_size: f32) {
+    for (&[r, q, e], pos) in board.layout.edge_coords.iter().zip(edges) {
         if let Some(road) = board.roads[r][q][e] {
-            render_road(&edges[idx],  e, road.into(), hex_size)
+            render_road(pos, e, road.into(), hex_size)
+        }
+    }
+}
+
+fn render_ship(edge: &[f32; 2], color: Color, hex_size: f32) {
+    let thickness = hex_size / 30.0;
+    let hull_w = 0.45 * hex_size;
+    let hull_h = 0.12 * hex_size;
+    let sail_h = 0.3 * hex_size;
+
+    let &[x, y] = edge;
+    let hull_x = x - 0.5 * hull_w;
+    let hull_y = y + 0.5 * hull_h;
+
+    draw_rectangle(hull_x, hull_y, hull_w, hull_h, color);
+    draw_rectangle_lines(hull_x, hull_y, hull_w, hull_h, thickness, BLACK);
+
+    let top = vec2(x, hull_y - sail_h);
+    let bottom = vec2(x, hull_y);
+    let tip = vec2(x + 0.6 * sail_h, hull_y);
+    draw_triangle(top, bottom, tip, WHITE);
+    draw_triangle_lines(top, bottom, tip, thickness, BLACK);
+}
+
+fn render_ships(board: &Board, edges: &[[f32; 2]], hex_size: f32) {
+    for (&[r, q, e], pos) in board.layout.edge_coords.iter().zip(edges) {
+        if let Some(ship) = board.ships[r][q][e] {
+            render_ship(pos, ship.into(), hex_size)
         }
     }
 }
@@ -160,22 +224,59 @@ fn render_city(corner: &[f32; 2], color: Color, hex_size: f32) {
     draw_triangle(v1, v2, v3, color);
 }
 
-fn render_structures(board: &Board, corners: &[[f32; 2]; 54], hex_size: f32) {
-    for idx in 0..CORNER_COORDS.len() {
-        let [r, q, c] = CORNER_COORDS[idx];
+/// A city wall, as a dark band under the city
+fn render_wall(corner: &[f32; 2], hex_size: f32) {
+    let width = 0.7 * hex_size;
+    let height = hex_size / 8.0;
+    let &[x, y] = corner;
+    draw_rectangle(x - 0.5 * width, y + 0.1 * hex_size, width, height, DARKGRAY);
+    draw_rectangle_lines(x - 0.5 * width, y + 0.1 * hex_size, width, height, hex_size / 20.0, BLACK);
+}
+
+fn render_walls(board: &Board, corners: &[[f32; 2]], hex_size: f32) {
+    for (&corner, pos) in board.layout.corner_coords.iter().zip(corners) {
+        if board.has_wall(corner) {
+            render_wall(pos, hex_size);
+        }
+    }
+}
+
+fn render_structures(board: &Board, corners: &[[f32; 2]], hex_size: f32) {
+    for (&[r, q, c], pos) in board.layout.corner_coords.iter().zip(corners) {
         if let Some(s) = board.structures[r][q][c] {
             if s.structure_type == StructureType::Settlement {
-                render_settlement(&corners[idx],s.color.into(), hex_size);
+                render_settlement(pos, s.color.into(), hex_size);
             } else {
-                render_city(&corners[idx],s.color.into(), hex_size);
+                render_city(pos, s.color.into(), hex_size);
             }
         }
     }
 }
 
-fn render_robber(hex: [usize; 2], centers: &[[f32; 2]; 19], hex_size: f32) {
+fn render_knight(corner: &[f32; 2], color: Color, level: usize, active: bool, hex_size: f32) {
+    let radius = hex_size / 4.5;
+    let thickness = hex_size / 20.0;
+    let font_size = hex_size / 2.5;
+
+    let &[x, y] = corner;
+    let fill = if active { color } else { LIGHTGRAY };
+    draw_circle(x, y, radius, fill);
+    draw_circle_lines(x, y, radius, 2.0 * thickness, color);
+    draw_circle_lines(x, y, radius + thickness, thickness, BLACK);
+    draw_text(level.to_string().as_str(), x - 0.25 * font_size, y + 0.3 * font_size, font_size, BLACK);
+}
+
+fn render_knights(board: &Board, corners: &[[f32; 2]], hex_size: f32) {
+    for (&[r, q, c], pos) in board.layout.corner_coords.iter().zip(corners) {
+        if let Some(knight) = board.knights[r][q][c] {
+            render_knight(pos, knight.color.into(), knight.level, knight.active, hex_size);
+        }
+    }
+}
+
+fn render_robber(board: &Board, centers: &[[f32; 2]], hex_size: f32) {
     let thickness = hex_size / 20.0;
-    let [x, y] = centers[HEX_COORDS.iter().position(|coord| *coord == hex).unwrap()];
+    let [x, y] = centers[board.layout.hex_coords.iter().position(|&coord| coord == board.robber).unwrap()];
 
     let w1 = 0.4 * hex_size;
     let h1 = 0.8 * hex_size;
@@ -193,14 +294,51 @@ fn render_robber(hex: [usize; 2], centers: &[[f32; 2]; 19], hex_size: f32) {
     draw_rectangle_lines(x2, y2, w2, h2, thickness, BLACK);
 }
 
+fn render_pirate(board: &Board, centers: &[[f32; 2]], hex_size: f32) {
+    let Some(pirate) = board.pirate else { return };
+    let thickness = hex_size / 20.0;
+    let [x, y] = centers[board.layout.hex_coords.iter().position(|&coord| coord == pirate).unwrap()];
+
+    let w = 0.7 * hex_size;
+    let h = 0.25 * hex_size;
+    let mast_h = 0.55 * hex_size;
+
+    draw_rectangle(x - 0.5 * w, y, w, h, BLACK);
+    draw_line(x, y, x, y - mast_h, thickness, BLACK);
+    let top = vec2(x, y - mast_h);
+    let bottom = vec2(x, y - 0.1 * hex_size);
+    let tip = vec2(x - 0.35 * hex_size, y - 0.1 * hex_size);
+    draw_triangle(top, bottom, tip, DARKGRAY);
+    draw_triangle_lines(top, bottom, tip, thickness, BLACK);
+}
+
+/// The merchant, in the color of whoever placed it, beside the number
+fn render_merchant(board: &Board, centers: &[[f32; 2]], hex_size: f32) {
+    let Some((hex, color)) = board.merchant else { return };
+    let thickness = hex_size / 20.0;
+    let [x, y] = centers[board.layout.hex_coords.iter().position(|&coord| coord == hex).unwrap()];
+    let x = x + 0.45 * hex_size;
+
+    let top = vec2(x, y - 0.3 * hex_size);
+    let left = vec2(x - 0.15 * hex_size, y + 0.2 * hex_size);
+    let right = vec2(x + 0.15 * hex_size, y + 0.2 * hex_size);
+    draw_triangle(top, left, right, color.into());
+    draw_triangle_lines(top, left, right, thickness, BLACK);
+}
+
 fn render_board(coords: &ScreenCoords, board: &Board) {
-    let ScreenCoords { centers, corners, edges, ports, hex_size, .. } = coords;
+    let ScreenCoords { centers, corners, edges, hex_size, .. } = coords;
 
     render_hexes(board, centers, *hex_size);
-    render_ports(board, ports, *hex_size);
+    render_ports(coords, board);
     render_roads(board, edges, *hex_size);
+    render_ships(board, edges, *hex_size);
+    render_walls(board, corners, *hex_size);
     render_structures(board, corners, *hex_size);
-    render_robber(board.robber, centers, *hex_size);
+    render_knights(board, corners, *hex_size);
+    render_robber(board, centers, *hex_size);
+    render_pirate(board, centers, *hex_size);
+    render_merchant(board, centers, *hex_size);
 }
 
 fn render_count(pos: &[f32; 2], _width: f32, height: f32, count: &str) {
@@ -228,6 +366,13 @@ fn render_resource(pos: &[f32; 2], size: &[f32; 2], resource: Resource, count: &
     render_count(pos, width, height, count);
 }
 
+fn render_card_rate(pos: &[f32; 2], size: &[f32; 2], rate: usize) {
+    let &[x, y] = pos;
+    let &[_, height] = size;
+    let font_size = height / 4.0;
+    draw_text(format!("{rate}:1").as_str(), x + 0.07 * height, y + 0.9 * height, font_size, BLACK);
+}
+
 fn render_dv(pos: &[f32; 2], size: &[f32; 2], dv: DVCard, count: &str) {
     let &[x, y] = pos;
     let &[width, height] = size;
@@ -243,10 +388,47 @@ fn render_dv(pos: &[f32; 2], size: &[f32; 2], dv: DVCard, count: &str) {
     render_count(pos, width, height, count);
 }
 
-fn render_hand(coords: &ScreenCoords, player: &Player) {
+fn render_commodity(pos: &[f32; 2], size: &[f32; 2], commodity: Commodity, count: &str) {
+    let &[x, y] = pos;
+    let &[width, height] = size;
+
+    let thickness = height / 20.0;
+    let font_size = height / 3.0;
+    let text_x = x + 0.2 * height;
+    let text_y = y + 0.75 * height;
+
+    draw_rectangle(x, y, width, height, commodity.into());
+    draw_rectangle_lines(x, y, width, height, thickness, BLACK);
+    draw_text(commodity.into_label().as_str(), text_x, text_y, font_size, BLACK);
+    render_count(pos, width, height, count);
+}
+
+fn render_progress_card(pos: &[f32; 2], size: &[f32; 2], card: ProgressCard, count: &str) {
+    let &[x, y] = pos;
+    let &[width, height] = size;
+
+    let thickness = height / 20.0;
+    let font_size = height / 3.0;
+    let text_x = x + 0.2 * height;
+    let text_y = y + 0.75 * height;
+
+    draw_rectangle(x, y, width, height, card.track().into());
+    draw_rectangle_lines(x, y, width, height, thickness, BLACK);
+    draw_text(card.into_label().as_str(), text_x, text_y, font_size, BLACK);
+    render_count(pos, width, height, count);
+}
+
+/// The Spy picks from its victim's progress cards, shown in place of the hand
+fn render_spied_cards(coords: &ScreenCoords, victim: &Player) {
+    for (card_idx, (card, count)) in victim.get_progress_counts().into_iter().enumerate() {
+        render_progress_card(&coords.cards[card_idx], &coords.card_size, card, count.to_string().as_str());
+    }
+}
+
+fn render_hand(coords: &ScreenCoords, player: &Player, trade_rate: impl Fn(Resource) -> usize) {
     let cards = &coords.cards;
     let size = &coords.card_size;
-    
+
     let hand = player.get_hand();
     let all_dvs = player.get_combined_dvs();
 
@@ -254,6 +436,7 @@ fn render_hand(coords: &ScreenCoords, player: &Player) {
     for res in RESOURCES {
         if hand[res] > 0 {
             render_resource(&cards[card_idx], size, res, hand[res].to_string().as_str());
+            render_card_rate(&cards[card_idx], size, trade_rate(res));
             card_idx += 1;
         }
     }
@@ -263,6 +446,17 @@ fn render_hand(coords: &ScreenCoords, player: &Player) {
             card_idx += 1;
         }
     }
+    let commodities = player.get_commodities();
+    for com in COMMODITIES {
+        if commodities[com] > 0 {
+            render_commodity(&cards[card_idx], size, com, commodities[com].to_string().as_str());
+            card_idx += 1;
+        }
+    }
+    for (card, count) in player.get_progress_counts() {
+        render_progress_card(&cards[card_idx], size, card, count.to_string().as_str());
+        card_idx += 1;
+    }
 }
 
 fn render_button(pos: [f32; 2], size: f32, can_click: bool, label: &str) {
@@ -279,20 +473,19 @@ fn render_button(pos: [f32; 2], size: f32, can_click: bool, label: &str) {
     draw_text(label, text_x, text_y, font_size, BLACK);
 }
 
-fn render_menu(coords: &ScreenCoords, state: &GameState, color: PlayerColor) {
+fn render_menu(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
     let buttons = &coords.buttons;
     let size = coords.button_size;
 
-    let can_click = state.get_available_actions(color);
-    let labels = [
-        "Devel",
-        "Road",
-        "Settl",
-        "City",
-        "Pass"
-    ];
-    for i in 0..buttons.len() {
-        render_button(buttons[i], size, can_click[i], labels[i]);
+    let can_click = view.get_available_actions();
+    let menu = view.get_menu();
+    let seat = view.get_opponent(color).unwrap();
+    for ((&pos, &clickable), button) in buttons.iter().zip(can_click.iter()).zip(menu) {
+        let label = match button {
+            MenuButton::Improve(track) => format!("{}{}", button.label(), seat.improvements[track.index()]),
+            _ => button.label().to_string()
+        };
+        render_button(pos, size, clickable, label.as_str());
     }
 }
 
@@ -315,23 +508,62 @@ fn render_die(pos: [f32; 2], size: f32, roll: Option<usize>) {
     draw_text(label, text_x, text_y, font_size, BLACK);
 }
 
-fn render_dice(coords: &ScreenCoords, state: &GameState) {
+fn render_dice(coords: &ScreenCoords, view: &PlayerView) {
     let dice = &coords.dice;
     let size = coords.dice_size;
-    let rolls = match state.roll {
-        Some([r1, r2]) => [Some(r1), Some(r2)],
-        None => [None, None]
+    let rolls = match (view.get_roll(), view.get_action()) {
+        (Some([r1, r2]), _) => [Some(r1), Some(r2)],
+        (None, Action::Targeting(ProgressCard::Alchemist, Some([d1, d2]))) => [Some(d1), Some(d2)],
+        (None, _) => [view.get_typed_dice().first().copied(), view.get_typed_dice().get(1).copied()]
     };
 
     render_die(dice[0], size, rolls[0]);
     render_die(dice[1], size, rolls[1]);
+
+    if view.get_ruleset() == Ruleset::CitiesKnights {
+        render_event_die(coords, view);
+        render_barbarians(coords, view);
+    }
+}
+
+fn render_event_die(coords: &ScreenCoords, view: &PlayerView) {
+    let [x, y] = coords.event_die;
+    let size = coords.dice_size;
+    let thickness = size / 20.0;
+
+    let color = match view.get_event() {
+        None => WHITE,
+        Some(EventDie::Barbarians) => DARKGRAY,
+        Some(EventDie::Gate(track)) => track.into()
+    };
+    draw_rectangle(x, y, size, size, color);
+    draw_rectangle_lines(x, y, size, size, thickness, BLACK);
+}
+
+fn render_barbarians(coords: &ScreenCoords, view: &PlayerView) {
+    let [x, y] = coords.event_die;
+    let font_size = coords.dice_size / 3.0;
+    let text = format!("Barbarians {}/{}", view.get_barbarians(), BARBARIAN_TRACK_LEN);
+    draw_text(text.as_str(), x, y - 0.2 * font_size, font_size, BLACK);
 }
 
-fn render_info_box(coords: &ScreenCoords, player: &Player) {
+fn render_metropolises(coords: &ScreenCoords, view: &PlayerView) {
+    let size = coords.hex_size / 3.0;
+    let thickness = size / 10.0;
+    for (track, metropolis) in IMPROVEMENTS.iter().zip(view.get_metropolises().iter()) {
+        let Some((_, corner)) = metropolis else { continue };
+        let idx = view.get_board().layout.corner_coords.iter().position(|c| c == corner).unwrap();
+        let [x, y] = coords.corners[idx];
+        draw_rectangle(x - 0.5 * size, y - 1.5 * size, size, size, (*track).into());
+        draw_rectangle_lines(x - 0.5 * size, y - 1.5 * size, size, size, thickness, BLACK);
+    }
+}
+
+fn render_info_box(coords: &ScreenCoords, seat: &Opponent) {
     let &[x, y, width, height] = &coords.info_zone;
-    let vps = player.get_vps().to_string();
+    let vps = seat.vps.to_string();
 
-    draw_rectangle(x, y, width, height, player.get_color().into());
+    draw_rectangle(x, y, width, height, seat.color.into());
     draw_text(vps.as_str(), x, y + height, 40.0, BLACK);
 }
 
@@ -342,99 +574,241 @@ fn render_clickable(pos: [f32; 2], radius: f32, alpha: u8) {
     draw_circle_lines(pos[0], pos[1], radius, thickness, DARKGRAY);
 }
 
-fn render_choosing_victim(coords: &ScreenCoords, state: &GameState) {
+fn render_choosing_victim(coords: &ScreenCoords, view: &PlayerView) {
     let corners = &coords.corners;
     let radius = coords.build_clickable_radius;
     let alpha = 0;
-    for idx in 0..CORNER_COORDS.len() {
-        let corner = CORNER_COORDS[idx];
-        if state.board.is_robbable(corner, state.get_current_color()) {
-            let pos = corners[idx];
+    for (&corner, &pos) in view.get_board().layout.corner_coords.iter().zip(corners) {
+        let [r, q, c] = corner;
+        if view.get_board().is_robbable(corner, view.get_current_color())
+        && view.can_rob(view.get_board().structures[r][q][c].as_ref().unwrap().color) {
+            render_clickable(pos, radius, alpha);
+        }
+    }
+}
+
+fn render_choosing_pirate_victim(coords: &ScreenCoords, view: &PlayerView) {
+    let edges = &coords.edges;
+    let radius = coords.build_clickable_radius;
+    let alpha = 0;
+    for (&edge, &pos) in view.get_board().layout.edge_coords.iter().zip(edges) {
+        let [r, q, e] = edge;
+        if view.get_board().is_pirate_robbable(edge, view.get_current_color())
+        && view.can_rob(view.get_board().ships[r][q][e].unwrap()) {
             render_clickable(pos, radius, alpha);
         }
     }
 }
 
-fn render_moving_robber(coords: &ScreenCoords, state: &GameState) {
+fn render_moving_robber(coords: &ScreenCoords, view: &PlayerView) {
     let centers = &coords.centers;
-    let robber = state.board.robber;
 
     let radius = coords.robber_clickable_radius;
     let alpha = 0;
-    for idx in 0..HEX_COORDS.len() {
-        if HEX_COORDS[idx] == robber {
-            continue;
+    for (&hex, &pos) in view.get_board().layout.hex_coords.iter().zip(centers) {
+        if view.can_move_robber(hex) {
+            render_clickable(pos, radius, alpha);
         }
-        let pos = centers[idx];
-        render_clickable(pos, radius, alpha);
     }
 }
 
-fn render_building_road(coords: &ScreenCoords, state: &GameState, color: PlayerColor) {
+fn render_building_road(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
     let edges = &coords.edges;
-    let board = &state.board;
+    let board = view.get_board();
 
     let radius = coords.build_clickable_radius;
     let alpha = 192;
 
-    for idx in 0..EDGE_COORDS.len() {
-        if board.can_place_road(EDGE_COORDS[idx], color) {
-            let pos = edges[idx];
+    for (&edge, &pos) in board.layout.edge_coords.iter().zip(edges) {
+        if board.can_place_road(edge, color) {
             render_clickable(pos, radius, alpha);
         }
     }
 }
 
-fn render_building_settlement(coords: &ScreenCoords, state: &GameState, color: PlayerColor) {
+fn render_building_ship(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
+    let edges = &coords.edges;
+    let board = view.get_board();
+
+    let radius = coords.build_clickable_radius;
+    let alpha = 192;
+
+    for (&edge, &pos) in board.layout.edge_coords.iter().zip(edges) {
+        if board.can_place_ship(edge, color) {
+            render_clickable(pos, radius, alpha);
+        }
+    }
+}
+
+fn render_moving_ship(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor, from: Option<[usize; 3]>) {
+    let edges = &coords.edges;
+    let board = view.get_board();
+
+    let radius = coords.build_clickable_radius;
+    let alpha = 192;
+
+    for (&edge, &pos) in board.layout.edge_coords.iter().zip(edges) {
+        let clickable = match from {
+            None => view.can_move_ship_from(edge),
+            Some(from) => edge != from && board.can_place_ship(edge, color)
+        };
+        if clickable {
+            render_clickable(pos, radius, alpha);
+        }
+    }
+}
+
+fn render_building_settlement(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
     let corners = &coords.corners;
-    let board = &state.board;
+    let board = view.get_board();
 
     let radius = coords.build_clickable_radius;
     let alpha = 192;
 
-    for idx in 0..CORNER_COORDS.len() {
-        if board.can_place_settlement(CORNER_COORDS[idx], color) {
-            let pos = corners[idx];
+    for (&corner, &pos) in board.layout.corner_coords.iter().zip(corners) {
+        if board.can_place_settlement(corner, color) {
             render_clickable(pos, radius, alpha);
         }
     }
 }
 
-fn render_building_city(coords: &ScreenCoords, state: &GameState, color: PlayerColor) {
+fn render_knight_action(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
     let corners = &coords.corners;
-    let board = &state.board;
+    let board = view.get_board();
+
+    let radius = coords.build_clickable_radius;
+    let alpha = 192;
+
+    for (&corner, &pos) in board.layout.corner_coords.iter().zip(corners) {
+        let clickable = match view.get_action() {
+            Action::BuildingKnight => board.can_place_knight(corner, color),
+            Action::ActivatingKnight => board.can_activate_knight(corner, color),
+            Action::PromotingKnight(_) => view.can_promote_knight_at(corner),
+            Action::ChasingRobber => board.can_chase_robber(corner, color),
+            _ => false
+        };
+        if clickable {
+            render_clickable(pos, radius, if board.knight_is_color(corner, color) { 0 } else { alpha });
+        }
+    }
+}
+
+fn render_building_city(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
+    let corners = &coords.corners;
+    let board = view.get_board();
+
+    let radius = coords.city_clickable_radius;
 
+    for (&corner, &[x, y]) in board.layout.corner_coords.iter().zip(corners) {
+        if board.can_place_city(corner, color) {
+            draw_circle(x, y, radius, DARKGRAY);
+        }
+    }
+}
+
+fn render_building_wall(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
+    let board = view.get_board();
     let radius = coords.city_clickable_radius;
 
-    for idx in 0..CORNER_COORDS.len() {
-        if board.can_place_city(CORNER_COORDS[idx], color) {
-            let [x, y] = corners[idx];
+    for (&corner, &[x, y]) in board.layout.corner_coords.iter().zip(&coords.corners) {
+        if board.can_place_wall(corner, color) {
             draw_circle(x, y, radius, DARKGRAY);
         }
     }
 }
 
+/// Where a progress card can be played: corners, sides or hexes, for the
+/// cards played on the board
+fn render_targeting(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor, card: ProgressCard, picked: Option<[usize; 2]>) {
+    let board = view.get_board();
+    let layout = &board.layout;
+    let radius = coords.build_clickable_radius;
+    let alpha = 0;
+    match card {
+        ProgressCard::Engineer | ProgressCard::Medicine | ProgressCard::Intrigue => {
+            for (&corner, &pos) in layout.corner_coords.iter().zip(&coords.corners) {
+                let clickable = match card {
+                    ProgressCard::Engineer => board.can_place_wall(corner, color),
+                    ProgressCard::Medicine => board.can_place_city(corner, color),
+                    _ => board.get_knight(corner).is_some_and(|knight| knight.color != color) && board.touches_road(corner, color)
+                };
+                if clickable {
+                    render_clickable(pos, radius, alpha);
+                }
+            }
+        },
+        ProgressCard::Diplomat => {
+            for (&edge, &pos) in layout.edge_coords.iter().zip(&coords.edges) {
+                if board.is_open_road(edge) {
+                    render_clickable(pos, radius, alpha);
+                }
+            }
+        },
+        ProgressCard::Inventor | ProgressCard::Merchant | ProgressCard::Bishop => {
+            for (&hex, &pos) in layout.hex_coords.iter().zip(&coords.centers) {
+                let clickable = match card {
+                    ProgressCard::Inventor => board.can_swap_number(hex) && picked != Some(hex),
+                    ProgressCard::Merchant => board.can_place_merchant(hex, color),
+                    _ => view.can_move_robber(hex)
+                };
+                if clickable {
+                    render_clickable(pos, coords.robber_clickable_radius, alpha);
+                }
+            }
+        },
+        _ => ()
+    }
+}
+
+/// The Deserter's victim picks a knight to give up; a knight placed for
+/// free goes next to its owner's roads
+fn render_free_knight(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
+    let board = view.get_board();
+    let radius = coords.build_clickable_radius;
+
+    for (&corner, &pos) in board.layout.corner_coords.iter().zip(&coords.corners) {
+        let clickable = match view.get_action() {
+            Action::Deserting => board.knight_is_color(corner, color),
+            Action::Displaced(_, from) => corner != from && board.can_place_knight(corner, color),
+            _ => board.can_place_knight(corner, color)
+        };
+        if clickable {
+            render_clickable(pos, radius, if board.knight_is_color(corner, color) { 0 } else { 192 });
+        }
+    }
+}
+
 fn render_selector_bg(coords: &ScreenCoords) {
     let [x, y, w, h] = coords.selector_zone;
     draw_rectangle(x, y, w, h, BEIGE);
 }
 
-fn render_selector_bottom(coords: &ScreenCoords, selector: &Selector) {
+fn render_selector_bottom(coords: &ScreenCoords, view: &PlayerView) {
     let cards = &coords.selector_bottom_cards;
     let selectors = &coords.selector_bottom_selectors;
     let size = &coords.selector_card_size;
     let selector_size = coords.selector_selector_size;
+    let Some(selector) = view.get_selector() else { return };
     let hand = selector.get_bottom();
 
-    for idx in 0..RESOURCES.len() {
-        let res = RESOURCES[idx];
+    for (idx, res) in RESOURCES.into_iter().enumerate() {
         if hand[res] > 0 {
             render_resource(&cards[idx], size, res, hand[res].to_string().as_str());
         }
         render_selector_selector(&selectors[idx], selector_size, res);
+        if let Selector::Trading(_, _) = selector {
+            let rate = view.trade_rate(view.get_current_color(), res);
+            render_trade_rate(&selectors[idx], selector_size, rate);
+        }
     }
 }
 
+fn render_trade_rate(pos: &[f32; 2], size: f32, rate: usize) {
+    let &[x, y] = pos;
+    let font_size = 0.5 * size;
+    draw_text(format!("{rate}:1").as_str(), x + 0.1 * size, y + 0.65 * size, font_size, BLACK);
+}
+
 fn render_selector_selector(pos: &[f32; 2], size: f32, resource: Resource) {
     let &[x, y] = pos;
     let thickness = size / 14.0;
@@ -450,8 +824,7 @@ fn render_selector_top(coords: &ScreenCoords, selector: &Selector) {
     let selector_size = coords.selector_selector_size;
     let hand = selector.get_top().unwrap();
 
-    for idx in 0..RESOURCES.len() {
-        let res = RESOURCES[idx];
+    for (idx, res) in RESOURCES.into_iter().enumerate() {
         if hand[res] > 0 {
             render_resource(&cards[idx], card_size, res, hand[res].to_string().as_str());
         }
@@ -459,7 +832,18 @@ fn render_selector_top(coords: &ScreenCoords, selector: &Selector) {
     }
 }
 
-fn render_confirm(coords: &ScreenCoords, state: &GameState) {
+/// The Master Merchant's victim's hand, to pick from
+fn render_victim_hand(coords: &ScreenCoords, view: &PlayerView, victim: PlayerColor) {
+    let Some(victim) = view.get_shown_player(victim) else { return };
+    let hand = victim.get_hand();
+    for (idx, res) in RESOURCES.into_iter().enumerate() {
+        if hand[res] > 0 {
+            render_resource(&coords.selector_top_cards[idx], &coords.selector_card_size, res, hand[res].to_string().as_str());
+        }
+    }
+}
+
+fn render_confirm(coords: &ScreenCoords, view: &PlayerView) {
     let &[x, y] = &coords.selector_buttons[1];
     let size = coords.selector_button_size;
     let thickness = coords.hex_size / 20.0;
@@ -468,7 +852,7 @@ fn render_confirm(coords: &ScreenCoords, state: &GameState) {
     let text_y = y + 0.7 * size;
     let font_size = size;
 
-    let color = if state.can_execute_selector() {WHITE} else {GRAY};
+    let color = if view.can_execute_selector() {WHITE} else {GRAY};
 
     draw_rectangle(x, y, size, size, color);
     draw_rectangle_lines(x, y, size, size, thickness, BLACK);
@@ -489,21 +873,22 @@ fn render_cancel(coords: &ScreenCoords) {
     draw_text("X", text_x, text_y, font_size, BLACK);
 }
 
-fn render_selector(coords: &ScreenCoords, state: &GameState) {
-    let selector = state.get_selector();
+fn render_selector(coords: &ScreenCoords, view: &PlayerView) {
+    let Some(selector) = view.get_selector() else { return };
     
     render_selector_bg(coords);
-    render_selector_bottom(coords, selector);
-    match selector {
-        Selector::Trading(_, _) => render_selector_top(coords, selector),
-        _ => ()
-    };
-    
-    render_confirm(coords, state);
-    match selector {
-        Selector::Discarding(_) => (),
-        _ => render_cancel(coords)
-    };
+    render_selector_bottom(coords, view);
+    if let Selector::Trading(_, _) = selector {
+        render_selector_top(coords, selector);
+    }
+    if let Action::Taking(victim) = view.get_action() {
+        render_victim_hand(coords, view, victim);
+    }
+
+    render_confirm(coords, view);
+    if view.can_cancel_selector() {
+        render_cancel(coords);
+    }
 }
 
 fn render_trade_button(coords: &ScreenCoords) {
@@ -520,54 +905,368 @@ fn render_trade_button(coords: &ScreenCoords) {
     draw_text("Trade", text_x, text_y, font_size, BLACK);
 }
 
-fn render_state_dependents(coords: &ScreenCoords, state: &GameState, color: PlayerColor) {
-    if !state.is_players_turn(color) {
+fn render_state_dependents(coords: &ScreenCoords, view: &PlayerView, color: PlayerColor) {
+    if !view.is_players_turn(color) {
         return;
     }
 
-    if state.selector.is_some() {
-        render_selector(coords, state);
-    } else {
+    if view.get_selector().is_some() {
+        render_selector(coords, view);
+    } else if !view.is_special_building() {
         render_trade_button(coords);
     }
 
-    match state.action {
-        Action::ChoosingVictim => render_choosing_victim(coords, state),
-        Action::MovingRobber => render_moving_robber(coords, state),
-        Action::BuildingRoad => render_building_road(coords, state, color),
-        Action::BuildingSettlement => render_building_settlement(coords, state, color),
-        Action::BuildingCity => render_building_city(coords, state, color),
-        Action::RoadBuilding(_) => render_building_road(coords, state, color),
+    match view.get_action() {
+        Action::ChoosingVictim => render_choosing_victim(coords, view),
+        Action::ChoosingPirateVictim => render_choosing_pirate_victim(coords, view),
+        Action::MovingRobber => render_moving_robber(coords, view),
+        Action::BuildingRoad => render_building_road(coords, view, color),
+        Action::BuildingShip => render_building_ship(coords, view, color),
+        Action::MovingShip(from) => render_moving_ship(coords, view, color, from),
+        Action::BuildingSettlement => render_building_settlement(coords, view, color),
+        Action::BuildingCity => render_building_city(coords, view, color),
+        Action::BuildingKnight | Action::ActivatingKnight | Action::PromotingKnight(_) | Action::ChasingRobber =>
+            render_knight_action(coords, view, color),
+        Action::RoadBuilding(_) => render_building_road(coords, view, color),
+        Action::BuildingWall => render_building_wall(coords, view, color),
+        Action::Targeting(card, picked) => render_targeting(coords, view, color, card, picked),
+        Action::Deserting | Action::PlacingDeserter(_) | Action::Displaced(..) => render_free_knight(coords, view, color),
         _ => ()
     }
 }
 
-pub fn render_screen(coords: &ScreenCoords, state: &GameState, color: PlayerColor) {
-    let board = &state.board;
-    let player = state.get_player(color).unwrap();
+/// The table as the view's seat sees it
+pub fn render_screen(coords: &ScreenCoords, view: &PlayerView) {
+    let color = view.get_viewer();
+    let board = view.get_board();
+    let seat = view.get_opponent(color).unwrap();
 
     render_background(coords);
     render_board(coords, board);
-    render_hand(coords, player);
-    render_menu(coords, state, color);
-    render_dice(coords, state);
-    render_info_box(coords, player);
-    render_state_dependents(coords, state, color);
+    render_owned_ports(coords, board, color);
+    render_metropolises(coords, view);
+    if let Some(player) = view.get_own_player() {
+        match (view.get_action(), view.is_players_turn(color)) {
+            (Action::Spying(victim), true) => render_spied_cards(coords, view.get_shown_player(victim).unwrap()),
+            _ => render_hand(coords, player, |res| view.trade_rate(color, res)),
+        }
+    }
+    // Spectators get the same screen with nothing to click
+    if view.is_seated() {
+        render_menu(coords, view, color);
+    }
+    render_dice(coords, view);
+    render_info_box(coords, &seat);
+    if view.is_seated() {
+        render_state_dependents(coords, view, color);
+    }
+    render_summary(coords, view);
+    render_button(coords.stats_button, coords.toggle_button_size, true, "Stats");
+    render_button(coords.income_button, coords.toggle_button_size, true, "Income");
+    if let Some(winner) = view.get_winner() {
+        render_winner(coords, winner);
+        render_button(coords.quit_button, coords.toggle_button_size, true, "Menu");
+    }
+}
+
+/// Every seat at a glance. The view's seat sees its own hidden VPs; the others don't.
+fn render_summary(coords: &ScreenCoords, view: &PlayerView) {
+    let [x, y, width, height] = coords.summary_zone;
+    let row_height = height / view.get_num_players() as f32;
+    let swatch = 0.3 * row_height;
+    let text_x = x + 1.5 * swatch;
+    let turn_color = view.get_turn_color();
+
+    draw_rectangle(x, y, width, height, WHITE);
+    for (i, opponent) in view.get_opponents().iter().enumerate() {
+        let row_y = y + i as f32 * row_height;
+        let seat = opponent.color;
+        if seat == turn_color {
+            draw_rectangle(x, row_y, width, row_height, LIGHTGRAY);
+        }
+        draw_rectangle(x + 0.2 * swatch, row_y + 0.2 * swatch, swatch, swatch, seat.into());
+        draw_rectangle_lines(x + 0.2 * swatch, row_y + 0.2 * swatch, swatch, swatch, swatch / 10.0, BLACK);
+
+        let mut score = format!("{} VP", opponent.vps);
+        if view.get_longest_road() == Some(seat) {
+            score += "  Longest Road";
+        }
+        if view.get_largest_army() == Some(seat) {
+            score += "  Largest Army";
+        }
+        let cards = format!(
+            "{} cards  {} DV  {} knights  road {}",
+            opponent.cards.resources,
+            opponent.cards.dvs,
+            opponent.knights,
+            opponent.road_len
+        );
+        let pieces = format!(
+            "Left: {} roads  {} settlements  {} cities",
+            opponent.road_pool,
+            opponent.settlement_pool,
+            opponent.city_pool
+        );
+
+        // Shrink the text until the longest line fits
+        let mut font_size = 0.25 * row_height;
+        let text_width = measure_text(pieces.as_str(), None, font_size as u16, 1.0).width;
+        if text_width > x + width - text_x {
+            font_size *= (x + width - text_x) / text_width;
+        }
+        draw_text(score.as_str(), text_x, row_y + 0.3 * row_height, font_size, BLACK);
+        draw_text(cards.as_str(), text_x, row_y + 0.6 * row_height, font_size, BLACK);
+        draw_text(pieces.as_str(), text_x, row_y + 0.9 * row_height, font_size, BLACK);
+    }
+    draw_rectangle_lines(x, y, width, height, row_height / 30.0, BLACK);
+}
+
+/// The newest log entries as the view's seat may see them, `scroll` entries back from the latest
+pub fn render_log(coords: &ScreenCoords, view: &PlayerView, scroll: usize) {
+    render_log_entries(coords, view.get_log(), view.is_seated().then_some(view.get_viewer()), scroll);
+}
+
+fn render_log_entries(coords: &ScreenCoords, log: &GameLog, viewer: Option<PlayerColor>, scroll: usize) {
+    let [x, y, width, height] = coords.log_zone;
+    let rows = 8;
+    let row_height = height / rows as f32;
+    let margin = 0.2 * row_height;
+    let mut font_size = 0.7 * row_height;
+
+    draw_rectangle(x, y, width, height, WHITE);
+    let entries = log.recent(viewer, scroll, rows);
+    // Shrink the text until the longest entry fits
+    for entry in entries.iter() {
+        let text_width = measure_text(entry.as_str(), None, font_size as u16, 1.0).width;
+        if text_width > width - 2.0 * margin {
+            font_size *= (width - 2.0 * margin) / text_width;
+        }
+    }
+    // Newest at the bottom, like a chat
+    for (i, entry) in entries.iter().enumerate() {
+        let row_y = y + height - i as f32 * row_height - margin;
+        let text_color = if i == 0 && scroll == 0 {BLACK} else {DARKGRAY};
+        draw_text(entry.as_str(), x + margin, row_y, font_size, text_color);
+    }
+    if scroll > 0 {
+        let note = format!("{} newer", scroll);
+        let note_width = measure_text(note.as_str(), None, font_size as u16, 1.0).width;
+        draw_text(note.as_str(), x + width - note_width - margin, y + 0.8 * row_height, font_size, MAROON);
+    }
+    draw_rectangle_lines(x, y, width, height, row_height / 15.0, BLACK);
+}
+
+fn render_roll_histogram(view: &PlayerView, zone: [f32; 4]) {
+    let [x, y, width, height] = zone;
+    let stats = view.get_stats();
+    let font_size = height / 12.0;
+    let slot = width / 11.0;
+    let bar_bottom = y + height - 1.2 * font_size;
+    let bar_space = height - 3.5 * font_size;
+
+    let most = (2..=12)
+        .map(|sum| (stats.rolls[sum] as f32).max(stats.expected_rolls(sum)))
+        .fold(1.0, f32::max);
+
+    let title = format!("{} rolls, expected in red", stats.num_rolls());
+    draw_text(title.as_str(), x, y + font_size, font_size, BLACK);
+    for sum in 2..=12 {
+        let bar_x = x + (sum - 2) as f32 * slot;
+        let bar_height = bar_space * stats.rolls[sum] as f32 / most;
+        let expected_y = bar_bottom - bar_space * stats.expected_rolls(sum) / most;
+
+        draw_rectangle(bar_x + 0.1 * slot, bar_bottom - bar_height, 0.8 * slot, bar_height, GRAY);
+        draw_line(bar_x, expected_y, bar_x + slot, expected_y, font_size / 8.0, RED);
+        draw_text(sum.to_string().as_str(), bar_x + 0.2 * slot, y + height, font_size, BLACK);
+        draw_text(stats.rolls[sum].to_string().as_str(), bar_x + 0.2 * slot, bar_bottom - bar_height - 0.2 * font_size, font_size, BLACK);
+    }
+}
+
+fn render_player_stats(view: &PlayerView, zone: [f32; 4]) {
+    let [x, y, width, height] = zone;
+    let headers = ["", "", "", "", "", "Total", "Robbed", "Discard", "Trades", "Bought", "Played"];
+    let col_width = width / (headers.len() + 1) as f32;
+    let row_height = height / (view.get_num_players() + 1) as f32;
+    let font_size = (0.4 * row_height).min(0.35 * col_width);
+    let swatch = 0.5 * row_height.min(col_width);
+
+    for (idx, header) in headers.iter().enumerate() {
+        let col_x = x + (idx + 1) as f32 * col_width;
+        match RESOURCES.get(idx) {
+            Some(&res) => draw_rectangle(col_x, y + 0.25 * row_height, swatch, swatch, res.into()),
+            None => {
+                draw_text(header, col_x, y + 0.6 * row_height, font_size, BLACK);
+            }
+        }
+    }
+
+    for (row, color) in view.get_order().into_iter().enumerate() {
+        let stats = view.get_stats().get(color);
+        let row_y = y + (row + 1) as f32 * row_height;
+        draw_rectangle(x, row_y + 0.25 * row_height, swatch, swatch, color.into());
+        draw_rectangle_lines(x, row_y + 0.25 * row_height, swatch, swatch, swatch / 10.0, BLACK);
+
+        let values = RESOURCES.iter().map(|&res| stats.produced[res]).chain([
+            stats.produced.size(),
+            stats.lost_to_robber,
+            stats.discarded,
+            stats.trades,
+            stats.dvs_bought,
+            stats.dvs_played,
+        ]);
+        for (idx, value) in values.enumerate() {
+            let col_x = x + (idx + 1) as f32 * col_width;
+            draw_text(value.to_string().as_str(), col_x, row_y + 0.6 * row_height, font_size, BLACK);
+        }
+    }
+}
+
+pub fn render_stats(coords: &ScreenCoords, view: &PlayerView) {
+    let [x, y, width, height] = coords.stats_zone;
+    let margin = 0.03 * width;
+
+    draw_rectangle(x, y, width, height, WHITE);
+    draw_rectangle_lines(x, y, width, height, margin / 5.0, BLACK);
+    render_roll_histogram(view, [x + margin, y + margin, 0.4 * width - 2.0 * margin, height - 2.0 * margin]);
+    render_player_stats(view, [x + 0.4 * width, y + margin, 0.6 * width - margin, height - 2.0 * margin]);
+}
+
+/// Expected resources per roll for everyone, and what the view's seat gets on each number
+pub fn render_income(coords: &ScreenCoords, view: &PlayerView) {
+    let color = view.get_viewer();
+    let [x, y, width, height] = coords.income_zone;
+    let board = view.get_board();
+    let numbers: Vec<usize> = (2..=12).filter(|&n| n != 7).collect();
+    let num_rows = view.get_num_players() + numbers.len() + 3;
+    let row_height = height / num_rows as f32;
+    let col_width = width / 7.0;
+    let font_size = (0.7 * row_height).min(0.45 * col_width);
+    let swatch = 0.6 * row_height.min(col_width);
+    let text_y = |row: usize| y + (row as f32 + 0.75) * row_height;
+    let col_x = |col: usize| x + (col as f32 + 0.2) * col_width;
+
+    draw_rectangle(x, y, width, height, WHITE);
+    draw_rectangle_lines(x, y, width, height, row_height / 15.0, BLACK);
+
+    let render_header = |row: usize, title: &str| {
+        draw_text(title, col_x(0), text_y(row), font_size, BLACK);
+        for (idx, &res) in RESOURCES.iter().enumerate() {
+            draw_rectangle(col_x(idx + 1), text_y(row) - swatch, swatch, swatch, res.into());
+        }
+    };
+
+    render_header(0, "Per roll");
+    for (row, seat) in view.get_order().into_iter().enumerate() {
+        let row_y = text_y(row + 1);
+        draw_rectangle(col_x(0), row_y - swatch, swatch, swatch, seat.into());
+        for (idx, amount) in board.expected_income(seat).iter().enumerate() {
+            draw_text(format!("{amount:.2}").as_str(), col_x(idx + 1), row_y, font_size, BLACK);
+        }
+    }
+
+    let first_row = view.get_num_players() + 2;
+    render_header(first_row, "Roll");
+    let player_idx = view.get_order().iter().position(|&c| c == color).unwrap();
+    for (row, &number) in numbers.iter().enumerate() {
+        let income = board.get_new_resources(view.get_order(), number)[player_idx];
+        let row_y = text_y(first_row + 1 + row);
+        let num_color = if number == 6 || number == 8 {MAROON} else {BLACK};
+        draw_text(number.to_string().as_str(), col_x(0), row_y, font_size, num_color);
+        for (idx, &res) in RESOURCES.iter().enumerate() {
+            if income[res] > 0 {
+                draw_text(income[res].to_string().as_str(), col_x(idx + 1), row_y, font_size, BLACK);
+            }
+        }
+    }
+}
+
+pub fn render_editor_screen(coords: &ScreenCoords, board: &Board) {
+    let font_size = coords.hex_size / 3.0;
+
+    clear_background(BLUE);
+    render_board(coords, board);
+    render_button(coords.buttons[0], coords.button_size, true, "Done");
+    draw_text("Click a hex to change its terrain, right-click to change its number", 10.0, 1.5 * font_size, font_size, BLACK);
+    draw_text("Click a port to change its type", 10.0, 3.0 * font_size, font_size, BLACK);
 }
 
-fn render_setup_menu(coords: &ScreenCoords) {
+fn render_winner(coords: &ScreenCoords, winner: PlayerColor) {
+    let font_size = 2.0 * coords.hex_size;
+    let text = format!("{:?} wins!", winner);
+    let dims = measure_text(text.as_str(), None, font_size as u16, 1.0);
+    let x = (screen_width() - dims.width) / 2.0;
+    let y = (screen_height() - dims.height) / 2.0;
+    let margin = font_size / 4.0;
+
+    draw_rectangle(x - margin, y - margin, dims.width + 2.0 * margin, dims.height + 2.0 * margin, winner.into());
+    draw_rectangle_lines(x - margin, y - margin, dims.width + 2.0 * margin, dims.height + 2.0 * margin, margin / 4.0, BLACK);
+    draw_text(text.as_str(), x, y + dims.offset_y, font_size, BLACK);
+}
+
+/// Which recorded position is on screen, along the top edge
+pub fn render_replay_status(coords: &ScreenCoords, state: &GameState, idx: usize, len: usize) {
+    let font_size = 0.6 * coords.hex_size;
+    let turn_color = state.players[state.turn_player].get_color();
+    let text = format!(
+        "Position {} of {}: round {}, {:?} to play (Left/Right to step, Escape to leave)",
+        idx + 1, len, state.round + 1, turn_color
+    );
+    let dims = measure_text(text.as_str(), None, font_size as u16, 1.0);
+    let x = (screen_width() - dims.width) / 2.0;
+    let margin = font_size / 4.0;
+
+    draw_rectangle(x - margin, 0.0, dims.width + 2.0 * margin, dims.height + 2.0 * margin, WHITE);
+    draw_text(text.as_str(), x, margin + dims.offset_y, font_size, BLACK);
+}
+
+/// Online play: what the server last said, across the top
+pub fn render_online_status(coords: &ScreenCoords, status: &str) {
+    if status.is_empty() {
+        return;
+    }
+    let font_size = 0.6 * coords.hex_size;
+    let dims = measure_text(status, None, font_size as u16, 1.0);
+    let x = (screen_width() - dims.width) / 2.0;
+    let margin = font_size / 4.0;
+
+    draw_rectangle(x - margin, 0.0, dims.width + 2.0 * margin, dims.height + 2.0 * margin, WHITE);
+    draw_text(status, x, margin + dims.offset_y, font_size, BLACK);
+}
+
+/// Online play before the server has sent a game
+pub fn render_waiting(status: &str) {
+    let font_size = screen_height() / 20.0;
+    let dims = measure_text(status, None, font_size as u16, 1.0);
+
+    clear_background(DARKGRAY);
+    draw_text(status, (screen_width() - dims.width) / 2.0, (screen_height() - dims.height) / 2.0 + dims.offset_y, font_size, WHITE);
+}
+
+/// Hot-seat hand-off. Covers the whole screen so nothing private shows through.
+pub fn render_curtain(coords: &ScreenCoords, color: PlayerColor) {
+    let font_size = 1.5 * coords.hex_size;
+    let text = format!("Pass the device to {:?}", color);
+    let dims = measure_text(text.as_str(), None, font_size as u16, 1.0);
+    let x = (screen_width() - dims.width) / 2.0;
+    let y = (screen_height() - dims.height) / 2.0;
+    let margin = font_size / 4.0;
+
+    clear_background(DARKGRAY);
+    draw_rectangle(x - margin, y - margin, dims.width + 2.0 * margin, dims.height + 2.0 * margin, color.into());
+    draw_rectangle_lines(x - margin, y - margin, dims.width + 2.0 * margin, dims.height + 2.0 * margin, margin / 4.0, BLACK);
+    draw_text(text.as_str(), x, y + dims.offset_y, font_size, BLACK);
+
+    let hint = "Click when ready";
+    let hint_dims = measure_text(hint, None, (font_size / 2.0) as u16, 1.0);
+    draw_text(hint, (screen_width() - hint_dims.width) / 2.0, y + dims.height + 2.0 * margin + hint_dims.offset_y, font_size / 2.0, WHITE);
+}
+
+fn render_setup_menu(coords: &ScreenCoords, state: &SetupState) {
     let buttons = &coords.buttons;
     let size = coords.button_size;
 
-    let labels = [
-        "Devel",
-        "Road",
-        "Settl",
-        "City",
-        "Pass"
-    ];
-    for i in 0..buttons.len() {
-        render_button(buttons[i], size, false, labels[i]);
+    for (&pos, button) in buttons.iter().zip(menu_buttons(state.ruleset)) {
+        render_button(pos, size, false, button.label());
     }
 }
 
@@ -578,9 +1277,9 @@ fn render_building_road_setup(coords: &ScreenCoords, state: &SetupState, settlem
     let radius = coords.build_clickable_radius;
     let alpha = 192;
 
-    for idx in 0..EDGE_COORDS.len() {
-        if board.can_place_setup_road(EDGE_COORDS[idx], settlement) {
-            let pos = edges[idx];
+    for (&edge, &pos) in board.layout.edge_coords.iter().zip(edges) {
+        if board.can_place_setup_road(edge, settlement)
+        || (state.ruleset == Ruleset::Seafarers && board.can_place_setup_ship(edge, settlement)) {
             render_clickable(pos, radius, alpha);
         }
     }
@@ -593,9 +1292,8 @@ fn render_building_settlement_setup(coords: &ScreenCoords, state: &SetupState) {
     let radius = coords.build_clickable_radius;
     let alpha = 192;
 
-    for idx in 0..CORNER_COORDS.len() {
-        if board.can_place_setup_settlement(CORNER_COORDS[idx]) {
-            let pos = corners[idx];
+    for (&corner, &pos) in board.layout.corner_coords.iter().zip(corners) {
+        if board.can_place_setup_settlement(corner) {
             render_clickable(pos, radius, alpha);
         }
     }
@@ -615,8 +1313,165 @@ fn render_setup_state_dependents(coords: &ScreenCoords, state: &SetupState, colo
 pub fn render_setup_screen(coords: &ScreenCoords, state: &SetupState, color: PlayerColor) {
     render_background(coords);
     render_board(coords, &state.board);
-    render_hand(coords, state.get_current_player());
-    render_info_box(coords, state.get_current_player());
-    render_setup_menu(coords);
+    render_owned_ports(coords, &state.board, color);
+    render_hand(coords, state.get_current_player(), |res| state.rules.trade_rate(&state.board, color, res));
+    render_info_box(coords, &state.get_current_player().into());
+    render_setup_menu(coords, state);
     render_setup_state_dependents(coords, state, color);
-}
\ No newline at end of file
+    // Nothing's happened yet, but there may have been some chat
+    render_log_entries(coords, &state.log, Some(color), 0);
+}
+
+/// Canned questions for each resource, and the line being typed if there is one
+pub fn render_chat(coords: &ScreenCoords, draft: Option<&str>) {
+    let [width, height] = coords.canned_button_size;
+    for (&pos, res) in coords.canned_buttons.iter().zip(RESOURCES) {
+        render_wide_button(pos, [width, height], format!("{:?}?", res).as_str());
+    }
+
+    let [x, y, width, height] = coords.chat_zone;
+    let font_size = 0.6 * height;
+    let margin = 0.2 * height;
+    draw_rectangle(x, y, width, height, WHITE);
+    match draft {
+        Some(draft) => draw_text(format!("> {draft}_").as_str(), x + margin, y + height - margin, font_size, BLACK),
+        None => draw_text("Enter to chat", x + margin, y + height - margin, font_size, GRAY),
+    };
+    draw_rectangle_lines(x, y, width, height, height / 15.0, BLACK);
+}
+fn render_wide_button(pos: [f32; 2], size: [f32; 2], label: &str) {
+    let [x, y] = pos;
+    let [width, height] = size;
+    let thickness = height / 20.0;
+    let font_size = height / 2.0;
+
+    draw_rectangle(x, y, width, height, BEIGE);
+    draw_rectangle_lines(x, y, width, height, thickness, BLACK);
+    draw_text(label, x + 0.1 * width, y + 0.65 * height, font_size, BLACK);
+}
+
+pub fn render_rules_screen(coords: &RulesCoords, rules: &RuleConfig, status: &str) {
+    clear_background(BEIGE);
+    let font_size = coords.font_size;
+    let size = coords.button_size;
+
+    for (idx, &option) in RULE_OPTIONS.iter().enumerate() {
+        let [label_x, label_y] = coords.labels[idx];
+        let [value_x, value_y] = coords.values[idx];
+        let value = rules.get(option);
+        let value = if option.is_toggle() {
+            String::from(if value == 1 {"On"} else {"Off"})
+        } else {
+            value.to_string()
+        };
+
+        draw_text(option.label(), label_x, label_y, font_size, BLACK);
+        draw_text(value.as_str(), value_x, value_y, font_size, BLACK);
+        render_button(coords.minus_buttons[idx], size, true, "-");
+        render_button(coords.plus_buttons[idx], size, true, "+");
+    }
+
+    for (&pos, label) in coords.bottom_buttons.iter().zip(["Load", "Save", "Start"]) {
+        render_wide_button(pos, coords.bottom_button_size, label);
+    }
+    let [status_x, status_y] = coords.status;
+    draw_text(status, status_x, status_y, font_size, BLACK);
+}
+
+pub fn render_main_menu(coords: &MainMenuCoords, new_game: &NewGame, status: &str) {
+    clear_background(BEIGE);
+    let font_size = coords.font_size;
+    let size = coords.button_size;
+
+    let [title_x, title_y] = coords.title;
+    draw_text("Catan", title_x, title_y, 1.4 * font_size, BLACK);
+
+    for (&[x, y], label) in coords.labels.iter().zip(["Seats", "Board", "Rules", "Seed"]) {
+        draw_text(label, x, y, font_size, BLACK);
+    }
+    let [seats_x, seats_y] = coords.seats_value;
+    draw_text(new_game.seats.len().to_string().as_str(), seats_x, seats_y, font_size, BLACK);
+    render_button(coords.seats_minus, size, new_game.can_remove_seat(), "-");
+    render_button(coords.seats_plus, size, new_game.can_add_seat(), "+");
+
+    for (idx, seat) in new_game.seats.iter().enumerate() {
+        let [label_x, label_y] = coords.seat_labels[idx];
+        let [swatch_x, swatch_y] = coords.swatches[idx];
+        draw_text(format!("{}. {:?}", idx + 1, seat.color).as_str(), label_x, label_y, font_size, BLACK);
+        draw_rectangle(swatch_x, swatch_y, size, size, seat.color.into());
+        draw_rectangle_lines(swatch_x, swatch_y, size, size, size / 20.0, BLACK);
+        render_wide_button(coords.kind_buttons[idx], coords.wide_button_size, seat.kind.label());
+    }
+
+    render_wide_button(coords.board_button, coords.wide_button_size, new_game.board.label().as_str());
+    let rules_label = if new_game.rules.is_some() {"House rules"} else {"Official rules"};
+    render_wide_button(coords.rules_button, coords.wide_button_size, rules_label);
+    let [seed_x, seed_y] = coords.seed_value;
+    draw_text(new_game.seed.to_string().as_str(), seed_x, seed_y, font_size, BLACK);
+    render_wide_button(coords.seed_button, coords.wide_button_size, "New seed");
+
+    for (&pos, label) in coords.bottom_buttons.iter().zip(["Load saved game", "Start"]) {
+        render_wide_button(pos, coords.bottom_button_size, label);
+    }
+    let [status_x, status_y] = coords.status;
+    draw_text(status, status_x, status_y, font_size, BLACK);
+}
+
+/// The room being set up, or the seats of the one sat down in, then the room list
+pub fn render_lobby(
+    coords: &LobbyCoords, new_game: &NewGame, rooms: &[RoomSummary], sitting: Option<(usize, PlayerColor)>, status: &str
+) {
+    clear_background(BEIGE);
+    let font_size = coords.font_size;
+    let size = coords.wide_button_size;
+    let label = |idx: usize, text: &str| {
+        let [x, y] = coords.labels[idx];
+        draw_text(text, x, y, font_size, BLACK);
+    };
+
+    let [title_x, title_y] = coords.title;
+    draw_text("Rooms", title_x, title_y, 1.4 * font_size, BLACK);
+
+    match sitting {
+        Some((room_id, color)) => {
+            for room in rooms.iter().filter(|room| room.id == room_id) {
+                for (idx, &(seat_color, seat)) in room.seats.iter().enumerate() {
+                    let own = seat_color == color;
+                    label(idx, format!("{:?}{}", seat_color, if own {" (you)"} else {""}).as_str());
+                    if own || !matches!(seat, RoomSeat::Taken(_)) {
+                        render_wide_button(coords.buttons[idx], size, seat.label());
+                    }
+                }
+            }
+            label(LOBBY_PANEL_ROWS - 1, format!("Room {room_id} starts once everyone's ready").as_str());
+            render_wide_button(coords.buttons[LOBBY_PANEL_ROWS - 1], size, "Leave room");
+        },
+        None => {
+            label(0, format!("New room for {}", new_game.seats.len()).as_str());
+            render_button(coords.minus, coords.button_size, new_game.can_remove_seat(), "-");
+            render_button(coords.plus, coords.button_size, new_game.can_add_seat(), "+");
+            label(1, "Board");
+            render_wide_button(coords.buttons[1], size, new_game.board.label().as_str());
+            label(2, "Rules");
+            let rules_label = if new_game.rules.is_some() {"House rules"} else {"Official rules"};
+            render_wide_button(coords.buttons[2], size, rules_label);
+            render_wide_button(coords.buttons[3], size, "Open room");
+        }
+    }
+
+    if rooms.is_empty() {
+        label(LOBBY_PANEL_ROWS, "No rooms yet");
+    }
+    for (row, room) in rooms.iter().take(LOBBY_ROOM_ROWS).enumerate() {
+        let idx = LOBBY_PANEL_ROWS + row;
+        label(idx, room.describe().as_str());
+        if room.started {
+            render_wide_button(coords.buttons[idx], size, "Watch");
+        } else if room.can_join() && sitting.is_none_or(|(room_id, _)| room_id != room.id) {
+            render_wide_button(coords.buttons[idx], size, "Join");
+        }
+    }
+
+    let [status_x, status_y] = coords.status;
+    draw_text(status, status_x, status_y, font_size, BLACK);
+}
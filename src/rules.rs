@@ -0,0 +1,231 @@
+use std::fs;
+
+use crate::game::{
+    Board, DVCard, DVHand, PlayerColor, ResHand, Resource, Ruleset,
+    EXTENSION_DV_BANK_HAND, STARTING_DV_BANK_HAND
+};
+
+pub const HOUSE_RULES_PATH: &str = "house_rules.txt";
+
+/// House rules, fixed when a game is created
+#[derive(Debug, Clone, Copy)]
+pub struct RuleConfig {
+    pub vp_target: usize,
+    pub discard_limit: usize,
+    pub bank_size: usize,
+    pub dv_bank: DVHand,
+    /// Players with `FRIENDLY_ROBBER_VPS` or fewer VPs can't be robbed
+    pub friendly_robber: bool,
+    /// 7s are rerolled during this many opening rounds
+    pub no_sevens_rounds: usize,
+    pub bank_rate: usize,
+    /// Rolls are drawn from a 36-card deck instead of thrown
+    pub event_deck: bool,
+}
+
+/// One editable line of the rules screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleOption {
+    VpTarget,
+    DiscardLimit,
+    BankSize,
+    DVCount(DVCard),
+    FriendlyRobber,
+    NoSevensRounds,
+    BankRate,
+    EventDeck,
+}
+
+pub const RULE_OPTIONS: [RuleOption; 12] = [
+    RuleOption::VpTarget,
+    RuleOption::DiscardLimit,
+    RuleOption::BankSize,
+    RuleOption::DVCount(DVCard::Knight),
+    RuleOption::DVCount(DVCard::RoadBuilding),
+    RuleOption::DVCount(DVCard::YearOfPlenty),
+    RuleOption::DVCount(DVCard::Monopoly),
+    RuleOption::DVCount(DVCard::VictoryPoint),
+    RuleOption::FriendlyRobber,
+    RuleOption::NoSevensRounds,
+    RuleOption::BankRate,
+    RuleOption::EventDeck,
+];
+
+pub const FRIENDLY_ROBBER_VPS: usize = 2;
+
+impl RuleOption {
+    pub fn label(self) -> &'static str {
+        match self {
+            RuleOption::VpTarget => "VPs to win",
+            RuleOption::DiscardLimit => "Discard above",
+            RuleOption::BankSize => "Bank cards per resource",
+            RuleOption::DVCount(DVCard::Knight) => "Knight cards",
+            RuleOption::DVCount(DVCard::RoadBuilding) => "Road Building cards",
+            RuleOption::DVCount(DVCard::YearOfPlenty) => "Year of Plenty cards",
+            RuleOption::DVCount(DVCard::Monopoly) => "Monopoly cards",
+            RuleOption::DVCount(DVCard::VictoryPoint) => "VP cards",
+            RuleOption::FriendlyRobber => "Friendly robber",
+            RuleOption::NoSevensRounds => "Rounds without 7s",
+            RuleOption::BankRate => "Bank trade rate",
+            RuleOption::EventDeck => "Event card deck",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            RuleOption::VpTarget => "vp_target",
+            RuleOption::DiscardLimit => "discard_limit",
+            RuleOption::BankSize => "bank_size",
+            RuleOption::DVCount(DVCard::Knight) => "dv_knight",
+            RuleOption::DVCount(DVCard::RoadBuilding) => "dv_road_building",
+            RuleOption::DVCount(DVCard::YearOfPlenty) => "dv_year_of_plenty",
+            RuleOption::DVCount(DVCard::Monopoly) => "dv_monopoly",
+            RuleOption::DVCount(DVCard::VictoryPoint) => "dv_victory_point",
+            RuleOption::FriendlyRobber => "friendly_robber",
+            RuleOption::NoSevensRounds => "no_sevens_rounds",
+            RuleOption::BankRate => "bank_rate",
+            RuleOption::EventDeck => "event_deck",
+        }
+    }
+
+    fn range(self) -> (usize, usize) {
+        match self {
+            RuleOption::VpTarget => (3, 20),
+            RuleOption::DiscardLimit => (5, 15),
+            RuleOption::BankSize => (5, 30),
+            RuleOption::DVCount(_) => (0, 25),
+            RuleOption::FriendlyRobber => (0, 1),
+            RuleOption::NoSevensRounds => (0, 5),
+            RuleOption::BankRate => (2, 4),
+            RuleOption::EventDeck => (0, 1),
+        }
+    }
+
+    pub fn is_toggle(self) -> bool {
+        matches!(self, RuleOption::FriendlyRobber | RuleOption::EventDeck)
+    }
+}
+
+impl RuleConfig {
+    /// The official rules for a game of this size and ruleset
+    pub fn new(num_players: usize, ruleset: Ruleset) -> RuleConfig {
+        let extension = num_players > 4;
+        RuleConfig {
+            vp_target: if ruleset == Ruleset::CitiesKnights {13} else {10},
+            discard_limit: 7,
+            bank_size: if extension {24} else {19},
+            dv_bank: if extension {EXTENSION_DV_BANK_HAND} else {STARTING_DV_BANK_HAND},
+            friendly_robber: false,
+            no_sevens_rounds: 0,
+            bank_rate: 4,
+            event_deck: false,
+        }
+    }
+
+    pub fn get_bank(&self) -> ResHand {
+        ResHand::from_count(self.bank_size)
+    }
+
+    /// The board's rate for `color`, or the house rate if that's better
+    pub fn trade_rate(&self, board: &Board, color: PlayerColor, resource: Resource) -> usize {
+        board.trade_rate(color, resource).min(self.bank_rate)
+    }
+
+    pub fn get(&self, option: RuleOption) -> usize {
+        match option {
+            RuleOption::VpTarget => self.vp_target,
+            RuleOption::DiscardLimit => self.discard_limit,
+            RuleOption::BankSize => self.bank_size,
+            RuleOption::DVCount(dv) => self.dv_bank[dv],
+            RuleOption::FriendlyRobber => self.friendly_robber as usize,
+            RuleOption::NoSevensRounds => self.no_sevens_rounds,
+            RuleOption::BankRate => self.bank_rate,
+            RuleOption::EventDeck => self.event_deck as usize,
+        }
+    }
+
+    /// Sets an option, clamped to its allowed range
+    pub fn set(&mut self, option: RuleOption, value: usize) {
+        let (low, high) = option.range();
+        let value = value.clamp(low, high);
+        match option {
+            RuleOption::VpTarget => self.vp_target = value,
+            RuleOption::DiscardLimit => self.discard_limit = value,
+            RuleOption::BankSize => self.bank_size = value,
+            RuleOption::DVCount(dv) => self.dv_bank[dv] = value,
+            RuleOption::FriendlyRobber => self.friendly_robber = value == 1,
+            RuleOption::NoSevensRounds => self.no_sevens_rounds = value,
+            RuleOption::BankRate => self.bank_rate = value,
+            RuleOption::EventDeck => self.event_deck = value == 1,
+        }
+    }
+
+    pub fn increment(&mut self, option: RuleOption) {
+        self.set(option, self.get(option) + 1);
+    }
+
+    pub fn decrement(&mut self, option: RuleOption) {
+        self.set(option, self.get(option).saturating_sub(1));
+    }
+
+    pub fn to_text(self) -> String {
+        RULE_OPTIONS.iter()
+            .map(|&option| format!("{} {}\n", option.key(), self.get(option)))
+            .collect()
+    }
+
+    /// Reads `key value` lines over the top of `self`. Missing keys keep their value.
+    pub fn parse(&self, text: &str) -> Result<RuleConfig, String> {
+        let mut rules = *self;
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let err = |msg: String| format!("line {}: {}", line_num + 1, msg);
+
+            let (key, value) = line.split_once(' ').ok_or(err(format!("missing value in '{line}'")))?;
+            let option = RULE_OPTIONS.iter().copied()
+                .find(|option| option.key() == key)
+                .ok_or(err(format!("unknown rule '{key}'")))?;
+            let value = value.trim().parse().map_err(|_| err(format!("bad value '{value}'")))?;
+            rules.set(option, value);
+        }
+        Ok(rules)
+    }
+
+    pub fn load(&self, path: &str) -> Result<RuleConfig, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        self.parse(&text).map_err(|err| format!("{path}: {err}"))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_text()).map_err(|err| format!("{path}: {err}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn house_rules_are_read_over_the_official_ones() {
+        let official = RuleConfig::new(4, Ruleset::Base);
+        let text = "# a friendlier table\ndiscard_limit 9\nfriendly_robber 1\nbank_rate 9\n";
+        let rules = official.parse(text).unwrap();
+        assert_eq!(rules.discard_limit, 9);
+        assert!(rules.friendly_robber);
+        // Clamped to the rules screen's range
+        assert_eq!(rules.bank_rate, 4);
+        assert_eq!(rules.vp_target, official.vp_target);
+        assert_eq!(official.parse(&rules.to_text()).unwrap().to_text(), rules.to_text());
+    }
+
+    #[test]
+    fn house_rules_name_the_bad_line() {
+        let official = RuleConfig::new(4, Ruleset::Base);
+        assert_eq!(official.parse("vp_target 12\nhouse_money 5").unwrap_err(), "line 2: unknown rule 'house_money'");
+        assert_eq!(official.parse("vp_target lots").unwrap_err(), "line 1: bad value 'lots'");
+        assert!(official.parse("discard_limit").is_err());
+    }
+}
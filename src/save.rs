@@ -0,0 +1,810 @@
+use std::fs;
+use std::io::Write;
+
+use crate::{Action, GameState, Selector, SetupState, Table};
+use crate::player_view::PlayerView;
+use crate::game::{
+    DVHand, EventDie, Improvement, Knight, Player, PlayerColor, ProgressCard, ResHand, Resource, Ruleset,
+    StructureType, DV_CARDS, IMPROVEMENTS, RESOURCES
+};
+use crate::bot::Bot;
+use crate::dice::EventDeck;
+use crate::game_log::{clean_chat, GameLog, LogEvent};
+use crate::new_game::Difficulty;
+use crate::rules::RuleConfig;
+use crate::scenario::Scenario;
+
+/// Where the game in progress is saved at the start of every turn
+pub const SAVED_GAME_PATH: &str = "saved_game.txt";
+
+/// Where the last game's turn-start positions are kept for `--replay`
+pub const RECORDED_GAME_PATH: &str = "recorded_game.txt";
+
+/// Opens each position in a recording
+const POSITION_MARKER: &str = "position";
+
+pub fn parse_number(arg: Option<&&str>) -> Result<usize, String> {
+    let arg = arg.copied().unwrap_or("");
+    arg.parse().map_err(|_| format!("bad number '{arg}'"))
+}
+
+pub fn parse_color(arg: Option<&&str>) -> Result<PlayerColor, String> {
+    let arg = arg.copied().unwrap_or("");
+    PlayerColor::from_name(arg).ok_or(format!("unknown color '{arg}'"))
+}
+
+pub fn parse_coords<const N: usize>(args: &[&str]) -> Result<[usize; N], String> {
+    let mut coords = [0; N];
+    for (idx, coord) in coords.iter_mut().enumerate() {
+        *coord = parse_number(args.get(idx))?;
+    }
+    Ok(coords)
+}
+
+fn parse_track(arg: Option<&&str>) -> Result<Improvement, String> {
+    let arg = arg.copied().unwrap_or("");
+    IMPROVEMENTS.iter().copied()
+        .find(|track| format!("{:?}", track) == arg)
+        .ok_or(format!("unknown track '{arg}'"))
+}
+
+fn parse_resource(arg: Option<&&str>) -> Result<Resource, String> {
+    let arg = arg.copied().unwrap_or("");
+    RESOURCES.iter().copied()
+        .find(|res| format!("{:?}", res) == arg)
+        .ok_or(format!("unknown resource '{arg}'"))
+}
+
+fn numbers(values: impl Iterator<Item = usize>) -> String {
+    values.map(|value| value.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+/// The table written out by `write`. During setup that's the placements
+/// so far, with a `setup` line saying who places next.
+fn write_table(table: &Table, write: impl Fn(&GameState) -> String) -> String {
+    let state = match table {
+        Table::Setup(state) => state,
+        Table::Playing(state) => return write(state),
+    };
+    let mut text = write(&(**state).clone().into());
+    text += &format!("\nsetup {} {}", state.current_player, state.all_placed_once as usize);
+    if let Some([r, q, c]) = state.settlement {
+        text += &format!(" {r} {q} {c}");
+    }
+    text + "\n"
+}
+
+/// The game as `viewer` may see it, if there is one: everyone else's cards
+/// are face down, and the decks only show how many cards are left
+pub fn table_to_text(table: &Table, viewer: Option<PlayerColor>) -> String {
+    write_table(table, |state| match viewer {
+        Some(viewer) => PlayerView::new(state, viewer).to_text(),
+        None => to_text(state),
+    })
+}
+
+/// The game as a spectator may see it: every hand face down, or face up for a caster
+pub fn table_to_spectator_text(table: &Table, caster: bool) -> String {
+    write_table(table, |state| PlayerView::spectating(state, state.get_current_color(), caster).to_text())
+}
+
+/// A card of the event deck, both dice run together like `35`
+fn parse_event_card(card: &str) -> Option<[usize; 2]> {
+    let dice: Vec<usize> = card.chars().map(|die| die.to_digit(10).map(|die| die as usize)).collect::<Option<_>>()?;
+    match dice[..] {
+        [die1, die2] if (1..=6).contains(&die1) && (1..=6).contains(&die2) => Some([die1, die2]),
+        _ => None
+    }
+}
+
+fn event_label(event: EventDie) -> String {
+    match event {
+        EventDie::Barbarians => String::from("Barbarians"),
+        EventDie::Gate(track) => format!("{:?}", track),
+    }
+}
+
+/// The actions a turn can be left in between moves; the rest only
+/// last while a seat picks where a piece goes
+fn action_text(action: &Action) -> Option<String> {
+    Some(match action {
+        Action::Discarding => String::from("discarding"),
+        Action::ChoosingGold => String::from("choosing_gold"),
+        Action::MovingRobber => String::from("moving_robber"),
+        Action::ChoosingVictim => String::from("choosing_victim"),
+        Action::ChoosingPirateVictim => String::from("choosing_pirate_victim"),
+        Action::RoadBuilding(placed_once) => format!("road_building {}", *placed_once as usize),
+        Action::PromotingKnight(Some(left)) => format!("promoting_knight {left}"),
+        Action::Giving => String::from("giving"),
+        Action::Sabotaged => String::from("sabotaged"),
+        Action::HarborOffering(partner) => format!("harbor_offering {:?}", partner),
+        Action::HarborReturning => String::from("harbor_returning"),
+        Action::Taking(victim) => format!("taking {:?}", victim),
+        Action::Spying(victim) => format!("spying {:?}", victim),
+        Action::Deserting => String::from("deserting"),
+        Action::PlacingDeserter(level) => format!("placing_deserter {level}"),
+        Action::Displaced(knight, [r, q, c]) =>
+            format!("displaced {:?} {} {} {r} {q} {c}", knight.color, knight.level, knight.active as usize),
+        _ => return None
+    })
+}
+
+fn parse_action(args: &[&str]) -> Result<Action, String> {
+    let name = args.first().copied().unwrap_or("");
+    Ok(match name {
+        "discarding" => Action::Discarding,
+        "choosing_gold" => Action::ChoosingGold,
+        "moving_robber" => Action::MovingRobber,
+        "choosing_victim" => Action::ChoosingVictim,
+        "choosing_pirate_victim" => Action::ChoosingPirateVictim,
+        "road_building" => Action::RoadBuilding(parse_number(args.get(1))? == 1),
+        "promoting_knight" => Action::PromotingKnight(Some(parse_number(args.get(1))?)),
+        "giving" => Action::Giving,
+        "sabotaged" => Action::Sabotaged,
+        "harbor_offering" => Action::HarborOffering(parse_color(args.get(1))?),
+        "harbor_returning" => Action::HarborReturning,
+        "taking" => Action::Taking(parse_color(args.get(1))?),
+        "spying" => Action::Spying(parse_color(args.get(1))?),
+        "deserting" => Action::Deserting,
+        "placing_deserter" => Action::PlacingDeserter(parse_number(args.get(1))?),
+        "displaced" => {
+            let knight = Knight {
+                color: parse_color(args.get(1))?,
+                level: parse_number(args.get(2))?,
+                active: parse_number(args.get(3))? == 1,
+            };
+            Action::Displaced(knight, parse_coords(args.get(4..).unwrap_or(&[]))?)
+        },
+        _ => return Err(format!("unknown action '{name}'"))
+    })
+}
+
+/// The game as it stands. Of the log only the chat is kept, and the
+/// statistics aren't; a loaded game starts them over. The event deck keeps
+/// its cards in the order they'll be drawn. Mid-turn lines are only written when
+/// they differ from the start of a turn, so saves made then don't have them.
+pub fn to_text(state: &GameState) -> String {
+    let board = &state.board;
+    let mut text = format!("ruleset {:?}\n", state.ruleset);
+    for line in state.rules.to_text().lines() {
+        text += &format!("rule {line}\n");
+    }
+    if state.companion {
+        text += "companion\n";
+    }
+    if state.hot_seat {
+        text += "hot_seat\n";
+    }
+
+    text += &format!("\nround {}\nturn {}\n", state.round, state.turn_player);
+    if state.current_player != state.turn_player {
+        text += &format!("current {}\n", state.current_player);
+    }
+    if let Some([die1, die2]) = state.roll {
+        text += &format!("roll {die1} {die2}\n");
+    }
+    if let Some(cards) = state.dice.cards_left() {
+        let cards: Vec<String> = cards.iter().map(|[die1, die2]| format!("{die1}{die2}")).collect();
+        text += format!("event_deck {}", cards.join(" ")).trim_end();
+        text += "\n";
+    }
+    if let Some(event) = state.event {
+        text += &format!("event {}\n", event_label(event));
+    }
+    if let Some(action) = action_text(&state.action) {
+        text += &format!("action {action}\n");
+    }
+    if state.gold_owed.iter().any(|&owed| owed > 0) {
+        text += &format!("gold_owed {}\n", numbers(state.gold_owed.iter().copied()));
+    }
+    if state.special_building {
+        text += "special_building\n";
+    }
+    if state.played_dv {
+        text += "played_dv\n";
+    }
+    if state.moved_ship {
+        text += "moved_ship\n";
+    }
+    if let Some(res) = state.merchant_fleet {
+        text += &format!("merchant_fleet {:?}\n", res);
+    }
+    for &[r, q, e] in state.new_ships.iter() {
+        text += &format!("new_ship {r} {q} {e}\n");
+    }
+    if let Some(winner) = state.winner {
+        text += &format!("winner {:?}\n", winner);
+    }
+    if let Some(color) = state.largest_army {
+        text += &format!("largest_army {:?} {}\n", color, state.largest_army_size);
+    }
+    if let Some(color) = state.longest_road {
+        text += &format!("longest_road {:?} {}\n", color, state.longest_road_size);
+    }
+    for &island in state.home_islands.iter() {
+        text += &format!("home_island {island}\n");
+    }
+    for &(color, island) in state.settled_islands.iter() {
+        text += &format!("settled_island {:?} {island}\n", color);
+    }
+    if state.ruleset == Ruleset::CitiesKnights {
+        text += &format!("barbarians {} {}\n", state.barbarians, state.barbarians_attacked as usize);
+        for (&track, deck) in IMPROVEMENTS.iter().zip(state.progress_decks.iter()) {
+            let labels: Vec<String> = deck.iter().map(|card| card.into_label()).collect();
+            text += format!("progress_deck {:?} {}", track, labels.join(" ")).trim_end();
+            text += "\n";
+        }
+        for (&track, metropolis) in IMPROVEMENTS.iter().zip(state.metropolises.iter()) {
+            if let &Some((color, [r, q, c])) = metropolis {
+                text += &format!("metropolis {:?} {:?} {r} {q} {c}\n", track, color);
+            }
+        }
+    }
+
+    text += "\n";
+    for player in state.players.iter() {
+        text += &format!("player {}\n", player.to_text());
+    }
+    for (player, bot) in state.players.iter().zip(state.bots.iter()) {
+        if let Some(bot) = bot {
+            text += &format!("bot {:?} {:?}\n", player.get_color(), bot.difficulty);
+        }
+    }
+    text += &format!("\nbank {}\n", numbers(RESOURCES.iter().map(|&res| board.bank[res])));
+    // A board seen face down only knows how many cards are left
+    match board.hidden_dvs {
+        Some(count) => text += &format!("dv_deck {count}\n"),
+        None => text += &format!("dv_bank {}\n", numbers(DV_CARDS.iter().map(|&dv| board.dv_bank[dv]))),
+    }
+
+    // Scenario::parse sees the board section without these markers
+    let scenario = Scenario { island_bonus: state.island_bonus, board: board.clone() };
+    text += &format!("\nboard\n{}end_board\n\n", scenario.to_text());
+
+    for &[r, q, c] in board.layout.corner_coords.iter() {
+        if let Some(structure) = board.structures[r][q][c] {
+            let kind = if structure.structure_type == StructureType::City {"city"} else {"settlement"};
+            text += &format!("{kind} {r} {q} {c} {:?}\n", structure.color);
+        }
+        if let Some(knight) = board.knights[r][q][c] {
+            text += &format!("knight {r} {q} {c} {:?} {} {}\n", knight.color, knight.level, knight.active as usize);
+        }
+        if board.has_wall([r, q, c]) {
+            text += &format!("wall {r} {q} {c}\n");
+        }
+    }
+    if let Some(([r, q], color)) = board.merchant {
+        text += &format!("merchant {r} {q} {:?}\n", color);
+    }
+    for &[r, q, e] in board.layout.edge_coords.iter() {
+        if let Some(color) = board.roads[r][q][e] {
+            text += &format!("road {r} {q} {e} {:?}\n", color);
+        }
+        if let Some(color) = board.ships[r][q][e] {
+            text += &format!("ship {r} {q} {e} {:?}\n", color);
+        }
+    }
+
+    if state.log.chat().next().is_some() {
+        text += "\n";
+    }
+    for (color, line) in state.log.chat() {
+        text += &format!("chat {:?} {line}\n", color);
+    }
+    text
+}
+
+pub fn parse(text: &str) -> Result<GameState, String> {
+    match parse_table(text)? {
+        Table::Playing(state) => Ok(*state),
+        Table::Setup(_) => Err(String::from("the game is still being set up"))
+    }
+}
+
+/// A game at either stage, the way `table_to_text` writes it
+pub fn parse_table(text: &str) -> Result<Table, String> {
+    let mut ruleset = Ruleset::Base;
+    // Rules and board lines are handed on with blank lines in place of
+    // everything else, so their errors keep the right line numbers
+    let mut rules_text = String::new();
+    let mut board_text = String::new();
+    let mut companion = false;
+    let mut hot_seat = false;
+    let mut round = 0;
+    let mut turn = 0;
+    let mut current = None;
+    let mut roll = None;
+    let mut event_deck = None;
+    let mut event = None;
+    let mut action = Action::Idling;
+    let mut gold_owed = None;
+    let mut special_building = false;
+    let mut played_dv = false;
+    let mut moved_ship = false;
+    let mut merchant_fleet = None;
+    let mut new_ships = Vec::new();
+    let mut setup = None;
+    let mut winner = None;
+    let mut largest_army = None;
+    let mut longest_road = None;
+    let mut home_islands = Vec::new();
+    let mut settled_islands = Vec::new();
+    let mut barbarians = (0, false);
+    let mut progress_decks = vec![Vec::new(); IMPROVEMENTS.len()];
+    let mut metropolises = [None; 3];
+    let mut players = Vec::new();
+    let mut difficulties = Vec::new();
+    let mut bank = None;
+    let mut dv_bank = None;
+    let mut dv_deck = None;
+    let mut pieces = Vec::new();
+    let mut walls = Vec::new();
+    let mut merchant = None;
+    let mut log = GameLog::default();
+
+    let mut in_board = false;
+    for (line_num, line) in text.lines().enumerate() {
+        let err = |msg: String| format!("line {}: {}", line_num + 1, msg);
+        let trimmed = line.split('#').next().unwrap().trim();
+        if in_board {
+            if trimmed == "end_board" {
+                in_board = false;
+            } else {
+                board_text += line;
+            }
+            board_text += "\n";
+            rules_text += "\n";
+            continue;
+        }
+        board_text += "\n";
+
+        let (keyword, rest) = trimmed.split_once(' ').unwrap_or((trimmed, ""));
+        if keyword == "rule" {
+            rules_text += rest;
+        }
+        rules_text += "\n";
+
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        match keyword {
+            "" | "rule" => (),
+            "ruleset" => ruleset = Ruleset::from_name(rest).ok_or(err(format!("unknown ruleset '{rest}'")))?,
+            "companion" => companion = true,
+            "hot_seat" => hot_seat = true,
+            "round" => round = parse_number(args.first()).map_err(err)?,
+            "turn" => turn = parse_number(args.first()).map_err(err)?,
+            "current" => current = Some(parse_number(args.first()).map_err(err)?),
+            "roll" => {
+                let dice = parse_coords::<2>(&args).map_err(err)?;
+                if dice.iter().any(|die| !(1..=6).contains(die)) {
+                    return Err(err(format!("bad roll '{rest}'")));
+                }
+                roll = Some(dice);
+            },
+            "event_deck" => event_deck = Some(args.iter()
+                .map(|card| parse_event_card(card).ok_or(err(format!("bad event card '{card}'"))))
+                .collect::<Result<Vec<[usize; 2]>, String>>()?),
+            "event" => event = Some(match args.first() {
+                Some(&"Barbarians") => EventDie::Barbarians,
+                _ => EventDie::Gate(parse_track(args.first()).map_err(err)?)
+            }),
+            "action" => action = parse_action(&args).map_err(err)?,
+            "gold_owed" => gold_owed = Some(args.iter()
+                .map(|arg| parse_number(Some(arg)))
+                .collect::<Result<Vec<usize>, String>>()
+                .map_err(err)?),
+            "special_building" => special_building = true,
+            "played_dv" => played_dv = true,
+            "moved_ship" => moved_ship = true,
+            "merchant_fleet" => merchant_fleet = Some(parse_resource(args.first()).map_err(err)?),
+            "new_ship" => new_ships.push(parse_coords::<3>(&args).map_err(err)?),
+            "setup" => {
+                let [current, all_placed_once] = parse_coords::<2>(&args).map_err(err)?;
+                let settlement = match args.len() {
+                    2 => None,
+                    _ => Some(parse_coords::<3>(&args[2..]).map_err(err)?)
+                };
+                setup = Some((current, all_placed_once == 1, settlement));
+            },
+            "winner" => winner = Some(parse_color(args.first()).map_err(err)?),
+            "largest_army" => largest_army = Some((
+                parse_color(args.first()).map_err(err)?,
+                parse_number(args.get(1)).map_err(err)?
+            )),
+            "longest_road" => longest_road = Some((
+                parse_color(args.first()).map_err(err)?,
+                parse_number(args.get(1)).map_err(err)?
+            )),
+            "home_island" => home_islands.push(parse_number(args.first()).map_err(err)?),
+            "settled_island" => settled_islands.push((
+                parse_color(args.first()).map_err(err)?,
+                parse_number(args.get(1)).map_err(err)?
+            )),
+            "barbarians" => barbarians = (
+                parse_number(args.first()).map_err(err)?,
+                parse_number(args.get(1)).map_err(err)? == 1
+            ),
+            "progress_deck" => {
+                let track = parse_track(args.first()).map_err(err)?;
+                progress_decks[track.index()] = args[1..].iter()
+                    .map(|&label| ProgressCard::from_label(label).ok_or(err(format!("unknown progress card '{label}'"))))
+                    .collect::<Result<_, _>>()?;
+            },
+            "metropolis" => {
+                let track = parse_track(args.first()).map_err(err)?;
+                let color = parse_color(args.get(1)).map_err(err)?;
+                let corner = parse_coords::<3>(args.get(2..).unwrap_or(&[])).map_err(err)?;
+                metropolises[track.index()] = Some((color, corner));
+            },
+            "player" => players.push(Player::parse(rest).map_err(err)?),
+            "bot" => {
+                let color = parse_color(args.first()).map_err(err)?;
+                let name = args.get(1).copied().unwrap_or("");
+                let difficulty = Difficulty::from_name(name).ok_or(err(format!("unknown difficulty '{name}'")))?;
+                difficulties.push((color, difficulty));
+            },
+            "bank" => {
+                let counts = parse_coords::<5>(&args).map_err(err)?;
+                let mut hand = ResHand::new();
+                for (&res, count) in RESOURCES.iter().zip(counts) {
+                    hand[res] = count;
+                }
+                bank = Some(hand);
+            },
+            "dv_bank" => {
+                let counts = parse_coords::<5>(&args).map_err(err)?;
+                let mut hand = DVHand::new();
+                for (&dv, count) in DV_CARDS.iter().zip(counts) {
+                    hand[dv] = count;
+                }
+                dv_bank = Some(hand);
+            },
+            "dv_deck" => dv_deck = Some(parse_number(args.first()).map_err(err)?),
+            "board" => in_board = true,
+            "settlement" | "city" | "road" | "ship" | "knight" => pieces.push((line_num, keyword, args)),
+            "wall" => walls.push((line_num, parse_coords::<3>(&args).map_err(err)?)),
+            "merchant" => merchant = Some((
+                parse_coords::<2>(&args).map_err(err)?,
+                parse_color(args.get(2)).map_err(err)?
+            )),
+            "chat" => {
+                let color = parse_color(args.first()).map_err(err)?;
+                // Taken from the whole line, since what was said can have a '#' in it
+                let said = line.trim().splitn(3, ' ').nth(2).unwrap_or("");
+                let said = clean_chat(said).ok_or(err(String::from("chat with nothing said")))?;
+                log.add(LogEvent::Chat(color, said));
+            },
+            _ => return Err(err(format!("unknown keyword '{keyword}'")))
+        }
+    }
+    if in_board {
+        return Err(String::from("board is missing its 'end_board'"));
+    }
+    if players.is_empty() {
+        return Err(String::from("no players"));
+    }
+    if turn >= players.len() {
+        return Err(format!("turn {turn} is past the last player"));
+    }
+    if current.is_some_and(|current| current >= players.len()) || setup.is_some_and(|(current, _, _)| current >= players.len()) {
+        return Err(String::from("current player is past the last player"));
+    }
+    if gold_owed.as_ref().is_some_and(|owed: &Vec<usize>| owed.len() != players.len()) {
+        return Err(String::from("gold_owed needs one number per player"));
+    }
+
+    let Scenario { island_bonus, mut board } = Scenario::parse(&board_text)?;
+    for (line_num, keyword, args) in pieces {
+        let err = |msg: String| format!("line {}: {}", line_num + 1, msg);
+        let coords = parse_coords::<3>(&args).map_err(err)?;
+        let color = parse_color(args.get(3)).map_err(err)?;
+        let on_board = match keyword {
+            "road" | "ship" => board.layout.edge_coords.contains(&coords),
+            _ => board.layout.corner_coords.contains(&coords)
+        };
+        if !on_board {
+            return Err(err(format!("{keyword} is off the board")));
+        }
+        match keyword {
+            "settlement" => board.place_setup_settlement(coords, color),
+            "city" => board.place_setup_city(coords, color),
+            "road" => board.place_setup_road(coords, color),
+            "ship" => board.place_setup_ship(coords, color),
+            _ => {
+                board.place_knight(coords, color);
+                for _ in 1..parse_number(args.get(4)).map_err(err)? {
+                    board.promote_knight(coords);
+                }
+                if parse_number(args.get(5)).map_err(err)? == 1 {
+                    board.activate_knight(coords);
+                }
+            }
+        }
+    }
+    // Walls stand under cities, so they go on once the cities are down
+    for (line_num, corner) in walls {
+        let [r, q, c] = corner;
+        let is_city = board.layout.corner_coords.contains(&corner)
+            && board.structures[r][q][c].is_some_and(|s| s.structure_type == StructureType::City);
+        if !is_city {
+            return Err(format!("line {}: wall isn't under a city", line_num + 1));
+        }
+        board.place_free_wall(corner);
+    }
+    if let Some((hex, _)) = merchant {
+        if !board.layout.is_on_board(hex) {
+            return Err(String::from("merchant is off the board"));
+        }
+    }
+    board.merchant = merchant;
+    board.bank = bank.ok_or(String::from("no bank"))?;
+    match (dv_bank, dv_deck) {
+        (Some(hand), _) => board.dv_bank = hand,
+        (None, Some(count)) => board.hidden_dvs = Some(count),
+        (None, None) => return Err(String::from("no dv_bank")),
+    }
+
+    let rules = RuleConfig::new(players.len(), ruleset).parse(&rules_text)?;
+    // CPU seats saved before bots had a difficulty play at normal
+    let bots = players.iter()
+        .map(|player| (!player.is_human()).then(|| {
+            let difficulty = difficulties.iter()
+                .find(|&&(color, _)| player.is_color(color))
+                .map_or(Difficulty::Normal, |&(_, difficulty)| difficulty);
+            Bot::new(difficulty)
+        }))
+        .collect();
+    let mut setup_state = SetupState {
+        num_players: players.len(),
+        ruleset,
+        rules,
+        island_bonus,
+        board,
+        players,
+        progress_decks,
+        current_player: 0,
+        all_placed_once: true,
+        settlement: None,
+        finished: true,
+        hot_seat,
+        bots,
+        log,
+    };
+    if let Some((current, all_placed_once, settlement)) = setup {
+        setup_state.current_player = current;
+        setup_state.all_placed_once = all_placed_once;
+        setup_state.settlement = settlement;
+        setup_state.finished = false;
+        return Ok(Table::Setup(Box::new(setup_state)));
+    }
+
+    let mut state: GameState = setup_state.into();
+    if companion {
+        state.start_companion();
+    }
+    state.round = round;
+    state.turn_player = turn;
+    state.winner = winner;
+    state.home_islands = home_islands;
+    state.settled_islands = settled_islands;
+    (state.barbarians, state.barbarians_attacked) = barbarians;
+    state.metropolises = metropolises;
+    if let Some((color, size)) = largest_army {
+        state.largest_army = Some(color);
+        state.largest_army_size = size;
+    }
+    // Building the state hands out the longest road afresh, so put the saved holder back
+    state.longest_road = longest_road.map(|(color, _)| color);
+    state.longest_road_size = longest_road.map_or(4, |(_, size)| size);
+    for player in state.players.iter_mut() {
+        player.set_longest_road(state.longest_road == Some(player.get_color()));
+    }
+
+    state.current_player = current.unwrap_or(turn);
+    state.roll = roll;
+    state.event = event;
+    if let Some(cards) = event_deck {
+        if !state.rules.event_deck {
+            return Err(String::from("event_deck without the event deck rule"));
+        }
+        state.dice = Box::new(EventDeck::from_cards(cards));
+    }
+    // Cards picked out of a hand are picked on the selector, which starts empty
+    match action {
+        Action::Discarding | Action::Sabotaged => state.selector = Some(Selector::Discarding(ResHand::new())),
+        Action::ChoosingGold => state.selector = Some(Selector::ChoosingGold(ResHand::new())),
+        Action::Giving | Action::HarborOffering(_) => state.selector = Some(Selector::Giving(ResHand::new())),
+        Action::Taking(_) => state.selector = Some(Selector::Taking(ResHand::new())),
+        _ => ()
+    }
+    state.action = action;
+    if let Some(gold_owed) = gold_owed {
+        state.gold_owed = gold_owed;
+    }
+    state.special_building = special_building;
+    state.played_dv = played_dv;
+    state.moved_ship = moved_ship;
+    state.merchant_fleet = merchant_fleet;
+    state.new_ships = new_ships;
+    Ok(Table::Playing(Box::new(state)))
+}
+
+pub fn save(state: &GameState, path: &str) -> Result<(), String> {
+    fs::write(path, to_text(state)).map_err(|err| format!("{path}: {err}"))
+}
+
+pub fn load(path: &str) -> Result<GameState, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    parse(&text).map_err(|err| format!("{path}: {err}"))
+}
+
+/// Adds the game as it stands to the recording at `path`.
+/// The first position starts the file over.
+pub fn record(state: &GameState, path: &str, first: bool) -> Result<(), String> {
+    let file = if first {
+        fs::File::create(path)
+    } else {
+        fs::OpenOptions::new().append(true).open(path)
+    };
+    file.and_then(|mut file| write!(file, "{POSITION_MARKER}\n{}", to_text(state)))
+        .map_err(|err| format!("{path}: {err}"))
+}
+
+/// Every position of a recording, in the order they were played.
+/// Line numbers in errors count from the line after the position's marker.
+pub fn load_recording(path: &str) -> Result<Vec<GameState>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    let mut positions: Vec<String> = Vec::new();
+    for line in text.lines() {
+        if line.trim() == POSITION_MARKER {
+            positions.push(String::new());
+        } else if let Some(position) = positions.last_mut() {
+            *position += line;
+            *position += "\n";
+        } else if !line.trim().is_empty() {
+            return Err(format!("{path}: expected '{POSITION_MARKER}' first"));
+        }
+    }
+    if positions.is_empty() {
+        return Err(format!("{path}: no positions"));
+    }
+    positions.iter().enumerate()
+        .map(|(idx, position)| parse(position).map_err(|err| format!("{path}: position {}: {err}", idx + 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::game::DVCard;
+    use crate::new_game::{BoardChoice, NewGame};
+    use crate::rules::RuleOption;
+    use crate::{play_test_game, test_game};
+
+    fn player_lines(text: &str) -> Vec<&str> {
+        text.lines().filter(|line| line.starts_with("player ")).collect()
+    }
+
+    fn playing(table: &Table) -> &GameState {
+        match table {
+            Table::Playing(state) => state,
+            Table::Setup(_) => panic!("expected a game in play")
+        }
+    }
+
+    #[test]
+    fn saves_keep_house_rules_and_the_event_deck() {
+        let mut new_game = NewGame::new(21);
+        let mut rules = new_game.get_rules();
+        for (option, value) in [
+            (RuleOption::VpTarget, 12),
+            (RuleOption::DiscardLimit, 9),
+            (RuleOption::BankSize, 24),
+            (RuleOption::DVCount(DVCard::Knight), 10),
+            (RuleOption::FriendlyRobber, 1),
+            (RuleOption::NoSevensRounds, 2),
+            (RuleOption::BankRate, 3),
+            (RuleOption::EventDeck, 1),
+        ] {
+            rules.set(option, value);
+        }
+        new_game.rules = Some(rules);
+        let mut state = play_test_game(new_game, 5);
+
+        let text = to_text(&state);
+        assert!(text.lines().any(|line| line.starts_with("event_deck ")));
+        let mut loaded = parse(&text).unwrap();
+        assert_eq!(to_text(&loaded), text);
+        assert_eq!(loaded.rules.to_text(), rules.to_text());
+
+        // Both decks deal the same rolls, reshuffles included
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut loaded_rng = StdRng::seed_from_u64(1);
+        for _ in 0..40 {
+            assert_eq!(state.dice.roll(&mut rng), loaded.dice.roll(&mut loaded_rng));
+        }
+    }
+
+    #[test]
+    fn saves_without_the_event_deck_have_no_cards() {
+        let state = test_game(BoardChoice::Random, 2, 3);
+        let text = to_text(&state);
+        assert!(!text.lines().any(|line| line.starts_with("event_deck")));
+        assert!(parse(&(text + "event_deck 11 66\n")).is_err());
+    }
+
+    #[test]
+    fn saves_keep_progress_cards_half_played() {
+        let mut state = test_game(BoardChoice::CitiesKnights, 4, 4);
+        let color = state.get_current_color();
+        let victim = state.players[(state.current_player + 1) % state.num_players].get_color();
+        let settlement = state.board.layout.corner_coords.iter().copied()
+            .find(|&corner| state.board.structure_is_color(corner, color))
+            .unwrap();
+        state.board.place_setup_city(settlement, color);
+        state.board.place_free_wall(settlement);
+        let hex = state.board.layout.hexes_touched(settlement).next().unwrap();
+        state.board.merchant = Some((hex, color));
+        state.merchant_fleet = Some(Resource::Sheep);
+        state.action = Action::Taking(victim);
+        state.selector = Some(Selector::Taking(ResHand::new()));
+
+        let text = to_text(&state);
+        assert!(text.contains(&format!("action taking {:?}\n", victim)));
+        let loaded = parse(&text).unwrap();
+        assert_eq!(to_text(&loaded), text);
+        assert!(loaded.board.has_wall(settlement));
+        assert!(matches!(loaded.selector, Some(Selector::Taking(_))));
+        assert_eq!(loaded.trade_rate(color, Resource::Sheep), 2);
+    }
+
+    #[test]
+    fn seat_text_hides_the_other_hands() {
+        let table = Table::Playing(Box::new(test_game(BoardChoice::CitiesKnights, 3, 6)));
+        let state = playing(&table);
+        let viewer = state.players[1].get_color();
+        assert!(state.players.iter().any(|player| !player.is_color(viewer) && player.get_hand().size() > 0));
+
+        let text = table_to_text(&table, Some(viewer));
+        for (player, line) in state.players.iter().zip(player_lines(&text)) {
+            if player.is_color(viewer) {
+                assert_eq!(line, format!("player {}", player.to_text()));
+            } else {
+                assert_eq!(line, format!("player {}", player.face_down().to_text()));
+                assert!(!line.contains(" hand ") && !line.contains(" commodities ") && !line.contains(" progress"));
+            }
+        }
+        assert!(!text.contains("dv_bank"));
+        assert!(text.contains(&format!("dv_deck {}\n", state.board.dv_bank.size())));
+        assert!(!text.lines().any(|line| line.starts_with("progress_deck") && line.split_whitespace().count() > 2));
+    }
+
+    #[test]
+    fn seat_text_reads_back_as_the_same_view() {
+        let table = Table::Playing(Box::new(test_game(BoardChoice::Random, 5, 8)));
+        let viewer = playing(&table).players[0].get_color();
+        let text = table_to_text(&table, Some(viewer));
+
+        let seen = parse_table(&text).unwrap();
+        assert_eq!(table_to_text(&seen, Some(viewer)), text);
+        for (player, seen) in playing(&table).players.iter().zip(playing(&seen).players.iter()) {
+            assert_eq!(player.get_card_counts(), seen.get_card_counts());
+        }
+    }
+
+    #[test]
+    fn spectator_text_hides_every_hand_but_a_casters() {
+        let table = Table::Playing(Box::new(test_game(BoardChoice::Random, 11, 6)));
+        let players = &playing(&table).players;
+
+        let public = table_to_spectator_text(&table, false);
+        for (player, line) in players.iter().zip(player_lines(&public)) {
+            assert_eq!(line, format!("player {}", player.face_down().to_text()));
+        }
+        let cast = table_to_spectator_text(&table, true);
+        for (player, line) in players.iter().zip(player_lines(&cast)) {
+            assert_eq!(line, format!("player {}", player.to_text()));
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs;
+
+use crate::game::{
+    Board, Hex, Layout, Port, Resource, Terrain,
+    STARTING_BANK_HAND, STARTING_DV_BANK_HAND
+};
+
+/// A fixed Seafarers map, read from a scenario file like `scenarios/new_shores.txt`
+pub struct Scenario {
+    pub island_bonus: usize,
+    pub board: Board,
+}
+
+fn parse_resource(code: &str) -> Option<Resource> {
+    match code {
+        "Wd" => Some(Resource::Wood),
+        "Br" => Some(Resource::Brick),
+        "Wh" => Some(Resource::Wheat),
+        "Sh" => Some(Resource::Sheep),
+        "Or" => Some(Resource::Ore),
+        _ => None
+    }
+}
+
+fn resource_code(res: Resource) -> &'static str {
+    match res {
+        Resource::Wood => "Wd",
+        Resource::Brick => "Br",
+        Resource::Wheat => "Wh",
+        Resource::Sheep => "Sh",
+        Resource::Ore => "Or",
+    }
+}
+
+fn hex_code(hex: Option<Hex>) -> String {
+    let Some(hex) = hex else { return String::from("..") };
+    let code = match hex.terrain {
+        Terrain::Sea => "Se",
+        Terrain::Desert => "De",
+        Terrain::Gold => "Au",
+        Terrain::Resource(res) => resource_code(res)
+    };
+    if hex.number == 0 { code.to_string() } else { format!("{code}{}", hex.number) }
+}
+
+fn parse_hex(token: &str) -> Result<Option<Hex>, String> {
+    if token == ".." {
+        return Ok(None);
+    }
+    let (code, number) = token.split_at(token.len().min(2));
+    let number = if number.is_empty() {
+        0
+    } else {
+        number.parse::<usize>().map_err(|_| format!("bad number token in '{token}'"))?
+    };
+
+    let terrain = match code {
+        "Se" => Terrain::Sea,
+        "De" => Terrain::Desert,
+        "Au" => Terrain::Gold,
+        _ => Terrain::Resource(parse_resource(code).ok_or(format!("unknown hex '{token}'"))?)
+    };
+    let needs_number = matches!(terrain, Terrain::Gold | Terrain::Resource(_));
+    if needs_number != (number != 0) {
+        return Err(format!("hex '{token}' has the wrong number token"));
+    }
+    Ok(Some(Hex { terrain, number }))
+}
+
+fn parse_coords<const N: usize>(args: &[&str]) -> Result<[usize; N], String> {
+    if args.len() < N {
+        return Err(format!("expected {N} coordinates"));
+    }
+    let mut coords = [0; N];
+    for (coord, arg) in coords.iter_mut().zip(args) {
+        *coord = arg.parse().map_err(|_| format!("bad coordinate '{arg}'"))?;
+    }
+    Ok(coords)
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Scenario, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        Scenario::parse(&text).map_err(|err| format!("{path}: {err}"))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_text()).map_err(|err| format!("{path}: {err}"))
+    }
+
+    /// Writes the board back out in the format `parse` reads
+    pub fn to_text(&self) -> String {
+        let board = &self.board;
+        let mut text = String::new();
+        if self.island_bonus > 0 {
+            text += &format!("island_bonus {}\n\n", self.island_bonus);
+        }
+
+        text += "map\n";
+        for row in board.hexes.iter() {
+            let codes: Vec<String> = row.iter().map(|&hex| format!("{:<4}", hex_code(hex))).collect();
+            text += codes.join(" ").trim_end();
+            text += "\n";
+        }
+        text += "end\n\n";
+
+        for (&[r, q, e], port) in board.layout.port_coords.iter().zip(board.ports.iter()) {
+            let code = match *port {
+                Port::ThreeForOne => "3:1",
+                Port::TwoForOne(res) => resource_code(res)
+            };
+            text += &format!("port {r} {q} {e} {code}\n");
+        }
+
+        let [r, q] = board.robber;
+        text += &format!("\nrobber {r} {q}\n");
+        if let Some([r, q]) = board.pirate {
+            text += &format!("pirate {r} {q}\n");
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Scenario, String> {
+        let mut island_bonus = 0;
+        let mut rows: Vec<Vec<Option<Hex>>> = Vec::new();
+        let mut ports: Vec<([usize; 3], Port)> = Vec::new();
+        let mut robber = None;
+        let mut pirate = None;
+
+        let mut in_map = false;
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let err = |msg: String| format!("line {}: {}", line_num + 1, msg);
+
+            if in_map {
+                if line == "end" {
+                    in_map = false;
+                } else {
+                    rows.push(line.split_whitespace().map(parse_hex).collect::<Result<_, _>>().map_err(err)?);
+                }
+                continue;
+            }
+
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let args: Vec<&str> = rest.split_whitespace().collect();
+            match keyword {
+                "island_bonus" => island_bonus = rest.trim().parse().map_err(|_| err(format!("bad island bonus '{rest}'")))?,
+                "map" => in_map = true,
+                "port" => {
+                    let coords = parse_coords::<3>(&args).map_err(err)?;
+                    let port = match args.get(3).copied() {
+                        Some("3:1") => Port::ThreeForOne,
+                        Some(code) => Port::TwoForOne(parse_resource(code).ok_or(err(format!("unknown port '{code}'")))?),
+                        None => return Err(err(String::from("port is missing its type")))
+                    };
+                    ports.push((coords, port));
+                },
+                "robber" => robber = Some(parse_coords::<2>(&args).map_err(err)?),
+                "pirate" => pirate = Some(parse_coords::<2>(&args).map_err(err)?),
+                _ => return Err(err(format!("unknown keyword '{keyword}'")))
+            }
+        }
+        if in_map {
+            return Err(String::from("map is missing its 'end'"));
+        }
+
+        let cols = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let hex_coords: Vec<[usize; 2]> = rows.iter().enumerate()
+            .flat_map(|(r, row)| row.iter().enumerate().filter(|(_, hex)| hex.is_some()).map(move |(q, _)| [r, q]))
+            .collect();
+        if hex_coords.is_empty() {
+            return Err(String::from("map has no hexes"));
+        }
+        let mut hexes = vec![vec![None; cols]; rows.len()];
+        for &[r, q] in hex_coords.iter() {
+            hexes[r][q] = rows[r][q];
+        }
+
+        let layout = Layout::new(hex_coords, ports.iter().map(|(coords, _)| *coords).collect());
+        let ports = ports.into_iter().map(|(_, port)| port).collect();
+        let mut board = Board::from_hexes(layout, hexes, ports, STARTING_BANK_HAND, STARTING_DV_BANK_HAND);
+
+        for &[r, q, e] in board.layout.port_coords.iter() {
+            if e >= 6 || !board.is_land([r, q]) {
+                return Err(format!("port {r} {q} {e} is not on a land hex"));
+            }
+        }
+        if let Some(robber) = robber {
+            if !board.is_land(robber) {
+                return Err(String::from("the robber must start on land"));
+            }
+            board.robber = robber;
+        }
+        if let Some(pirate) = pirate {
+            if !board.is_sea(pirate) {
+                return Err(String::from("the pirate must start at sea"));
+            }
+            board.pirate = Some(pirate);
+        }
+
+        Ok(Scenario { island_bonus, board })
+    }
+}
@@ -1,7 +1,8 @@
-use crate::game::{HEX_COORDS, CORNER_COORDS, EDGE_COORDS, PORT_COORDS};
+use crate::game::Layout;
 use macroquad::window::{screen_width, screen_height};
 
 const SQRT_3: f32 = 1.732050807568877293527446341505872367_f32;
+const MAX_BUTTONS_PER_ROW: usize = 7;
 
 const fn min(a: f32, b: f32) -> f32 {
     if a < b {a} else {b}
@@ -29,13 +30,13 @@ impl Zone {
 }
 
 pub struct ScreenCoords {
-    pub centers: [[f32; 2]; 19],
-    pub corners: [[f32; 2]; 54],
-    pub edges: [[f32; 2]; 72],
-    pub ports: [[f32; 3]; 9],
+    pub centers: Vec<[f32; 2]>,
+    pub corners: Vec<[f32; 2]>,
+    pub edges: Vec<[f32; 2]>,
+    pub ports: Vec<[f32; 3]>,
     pub hex_size: f32,
     pub hand_zone: [f32; 4],
-    pub cards: [[f32; 2]; 10],
+    pub cards: [[f32; 2]; 12],
     pub card_size: [f32; 2],
     pub trade_button: [f32; 2],
     pub trade_button_size: f32,
@@ -49,26 +50,39 @@ pub struct ScreenCoords {
     pub selector_buttons: [[f32; 2]; 2],
     pub selector_button_size: f32,
     pub menu_zone: [f32; 4],
-    pub buttons: [[f32; 2]; 5],
+    pub buttons: Vec<[f32; 2]>,
     pub button_size: f32,
     pub dice: [[f32; 2]; 2],
     pub dice_size: f32,
+    pub event_die: [f32; 2],
     pub info_zone: [f32; 4],
+    pub summary_zone: [f32; 4],
+    pub log_zone: [f32; 4],
+    /// The line being typed in the chat panel, below its canned questions
+    pub chat_zone: [f32; 4],
+    pub canned_buttons: [[f32; 2]; 5],
+    pub canned_button_size: [f32; 2],
+    pub stats_zone: [f32; 4],
+    pub stats_button: [f32; 2],
+    pub income_button: [f32; 2],
+    pub quit_button: [f32; 2],
+    pub toggle_button_size: f32,
+    pub income_zone: [f32; 4],
     pub robber_clickable_radius: f32,
     pub build_clickable_radius: f32,
     pub city_clickable_radius: f32,
 }
 
 impl ScreenCoords {
-    pub fn new() -> ScreenCoords {
+    pub fn new(layout: &Layout, num_buttons: usize) -> ScreenCoords {
         let mut coords = ScreenCoords {
-            centers: [[0.0; 2]; 19],
-            corners: [[0.0; 2]; 54],
-            edges: [[0.0; 2]; 72],
-            ports: [[0.0; 3]; 9],
+            centers: vec![[0.0; 2]; layout.hex_coords.len()],
+            corners: vec![[0.0; 2]; layout.corner_coords.len()],
+            edges: vec![[0.0; 2]; layout.edge_coords.len()],
+            ports: vec![[0.0; 3]; layout.port_coords.len()],
             hex_size: 0.0,
             hand_zone: [0.0; 4],
-            cards: [[0.0; 2]; 10],
+            cards: [[0.0; 2]; 12],
             card_size: [0.0; 2],
             trade_button: [0.0; 2],
             trade_button_size: 0.0,
@@ -82,81 +96,130 @@ impl ScreenCoords {
             selector_buttons: [[0.0; 2]; 2],
             selector_button_size: 0.0,
             menu_zone: [0.0; 4],
-            buttons: [[0.0; 2]; 5],
+            buttons: vec![[0.0; 2]; num_buttons],
             button_size: 0.0,
             dice: [[0.0; 2]; 2],
             dice_size: 0.0,
+            event_die: [0.0; 2],
             info_zone: [0.0; 4],
+            summary_zone: [0.0; 4],
+            log_zone: [0.0; 4],
+            chat_zone: [0.0; 4],
+            canned_buttons: [[0.0; 2]; 5],
+            canned_button_size: [0.0; 2],
+            stats_zone: [0.0; 4],
+            stats_button: [0.0; 2],
+            income_button: [0.0; 2],
+            quit_button: [0.0; 2],
+            toggle_button_size: 0.0,
+            income_zone: [0.0; 4],
             robber_clickable_radius: 0.0,
             build_clickable_radius: 0.0,
             city_clickable_radius: 0.0,
         };
-        coords.update();
+        coords.update(layout);
         coords
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, layout: &Layout) {
         let width = screen_width();
         let height = screen_height();
 
-        let board_zone = Zone::new(width, height, 0.0, 0.0, 1.0, 0.85);
+        let board_zone = Zone::new(width, height, 0.0, 0.0, 0.75, 0.85);
         let hand_zone = Zone::new(width, height, 0.0, 0.85, 0.60, 0.15);
         let trade_button_zone = Zone::new(width, height, 0.0, 0.75, 0.10, 0.10);
         let menu_zone = Zone::new(width, height, 0.60, 0.85, 0.40, 0.15);
         let dice_zone = Zone::new(width, height, 0.80, 0.70, 0.20, 0.15);
         let info_zone_zone = Zone::new(width, height, 0.0, 0.0, 0.20, 0.10);
         let selector_zone = Zone::new(width, height, 0.0, 0.45, 0.25, 0.40);
+        let summary_zone = Zone::new(width, height, 0.75, 0.0, 0.25, 0.45);
+        let log_zone = Zone::new(width, height, 0.75, 0.46, 0.25, 0.17);
+        let chat_zone = Zone::new(width, height, 0.75, 0.635, 0.25, 0.06);
+        let stats_zone = Zone::new(width, height, 0.05, 0.05, 0.90, 0.75);
+        let toggle_zone = Zone::new(width, height, 0.0, 0.11, 0.20, 0.07);
+        let income_zone = Zone::new(width, height, 0.72, 0.05, 0.28, 0.63);
 
         self.hand_zone = hand_zone.as_arr();
         self.menu_zone = menu_zone.as_arr();
 
-        self.update_board_coords(board_zone);
+        self.update_board_coords(board_zone, layout);
         self.update_cards(hand_zone);
         self.update_trade_button(trade_button_zone);
         self.update_selector(selector_zone);
         self.update_buttons(menu_zone);
         self.update_dice(dice_zone);
         self.update_info_zone(info_zone_zone);
+        self.summary_zone = summary_zone.as_arr();
+        self.log_zone = log_zone.as_arr();
+        self.update_chat(chat_zone);
+        self.stats_zone = stats_zone.as_arr();
+        self.income_zone = income_zone.as_arr();
+        self.update_toggle_buttons(toggle_zone);
+    }
+
+    /// Where a port's marker is drawn, pushed out to sea from its edge
+    pub fn port_marker(&self, idx: usize) -> [f32; 2] {
+        ScreenCoords::calculate_port_marker(&self.ports[idx], self.hex_size)
+    }
+
+    pub fn calculate_port_marker(port: &[f32; 3], hex_size: f32) -> [f32; 2] {
+        let stretch_factor = 0.35 * hex_size;
+        let &[x, y, rotation] = port;
+        [x - stretch_factor * f32::cos((rotation + 45.0).to_radians()),
+        y - stretch_factor * f32::sin((rotation + 45.0).to_radians())]
     }
 
-    fn update_board_coords(&mut self, zone: Zone) {
-        let hex_size = 0.1 * min(zone.width, zone.height);
+    fn update_board_coords(&mut self, zone: Zone, layout: &Layout) {
+        // Board extents in units of hex columns (x) and rows (y)
+        let xs = layout.hex_coords.iter().map(|&[r, q]| q as f32 + 0.5 * r as f32);
+        let ys = layout.hex_coords.iter().map(|&[r, _]| r as f32);
+        let min_x = xs.clone().fold(f32::INFINITY, f32::min);
+        let max_x = xs.fold(f32::NEG_INFINITY, f32::max);
+        let min_y = ys.clone().fold(f32::INFINITY, f32::min);
+        let max_y = ys.fold(f32::NEG_INFINITY, f32::max);
+
+        let board_width = SQRT_3 * (max_x - min_x + 1.0) + 2.0;
+        let board_height = 1.5 * (max_y - min_y) + 4.0;
+        let hex_size = min(zone.width / board_width, zone.height / board_height);
         self.hex_size = hex_size;
         self.build_clickable_radius = 0.2 * hex_size;
         self.city_clickable_radius = 0.04 * hex_size;
         self.robber_clickable_radius = 0.5 * hex_size;
 
-        self.update_centers(&zone);
-        self.update_corners(&zone);
-        self.update_edges(&zone);
-        self.update_ports(&zone);
+        let origin = [
+            zone.x + 0.5 * zone.width - hex_size * SQRT_3 * 0.5 * (min_x + max_x),
+            zone.y + 0.5 * zone.height - hex_size * 1.5 * 0.5 * (min_y + max_y)
+        ];
+
+        self.centers = layout.hex_coords.iter().map(
+            |hex| ScreenCoords::calculate_center(&origin, hex, hex_size)
+        ).collect();
+        self.corners = layout.corner_coords.iter().map(
+            |corner| ScreenCoords::calculate_corner(&origin, corner, hex_size)
+        ).collect();
+        self.edges = layout.edge_coords.iter().map(
+            |edge| ScreenCoords::calculate_edge(&origin, edge, hex_size)
+        ).collect();
+        self.ports = layout.port_coords.iter().map(
+            |port| ScreenCoords::calculate_port(&origin, port, hex_size)
+        ).collect();
     }
 
-    fn calculate_center(zone: &Zone, hex: &[usize; 2], hex_size: f32) -> [f32; 2] {
-        let Zone {x, y, width, height} = zone;
+    pub fn calculate_center(origin: &[f32; 2], hex: &[usize; 2], hex_size: f32) -> [f32; 2] {
+        let &[x, y] = origin;
         let [r, q] = *hex;
 
         let q_shift: f32 = hex_size * SQRT_3;
         let r_shift_x: f32 = hex_size * 0.5 * SQRT_3;
         let r_shift_y: f32 = hex_size * 1.5;
 
-        let start_x: f32 = x + 0.5 * width - (2.0 * q_shift + 2.0 * r_shift_x);
-        let start_y: f32 = y + 0.5 * height - (2.0 * r_shift_y);
-
-        [start_x + q_shift * q as f32 + r_shift_x * r as f32,
-        start_y + r_shift_y * r as f32]
+        [x + q_shift * q as f32 + r_shift_x * r as f32,
+        y + r_shift_y * r as f32]
     } 
 
-    fn update_centers(&mut self, zone: &Zone) {
-        for idx in 0..HEX_COORDS.len() {
-            let pos = &HEX_COORDS[idx];
-            self.centers[idx] = ScreenCoords::calculate_center(zone, &pos, self.hex_size);
-        }
-    }
-
-    fn calculate_corner(zone: &Zone, corner: &[usize; 3], hex_size: f32) -> [f32; 2] {
+    pub fn calculate_corner(origin: &[f32; 2], corner: &[usize; 3], hex_size: f32) -> [f32; 2] {
         let [r, q, c] = corner;
-        let [x, y] = ScreenCoords::calculate_center(zone, &[*r, *q], hex_size);
+        let [x, y] = ScreenCoords::calculate_center(origin, &[*r, *q], hex_size);
         [x + match c {
             0 => 0.0,
             1 => 0.5 * SQRT_3 * hex_size,
@@ -177,16 +240,9 @@ impl ScreenCoords {
         }]
     }
 
-    fn update_corners(&mut self, zone: &Zone) {
-        for idx in 0..CORNER_COORDS.len() {
-            let corner = &CORNER_COORDS[idx];
-            self.corners[idx] = ScreenCoords::calculate_corner(zone, corner, self.hex_size);
-        }
-    }
-
-    fn calculate_edge(zone: &Zone, edge: &[usize; 3], hex_size: f32) -> [f32; 2] {
+    pub fn calculate_edge(origin: &[f32; 2], edge: &[usize; 3], hex_size: f32) -> [f32; 2] {
         let [r, q, e] = edge;
-        let [x, y] = ScreenCoords::calculate_center(zone, &[*r, *q], hex_size);
+        let [x, y] = ScreenCoords::calculate_center(origin, &[*r, *q], hex_size);
         [x + match e {
             0 => -0.25 * SQRT_3 * hex_size,
             1 => 0.25 * SQRT_3 * hex_size,
@@ -207,16 +263,9 @@ impl ScreenCoords {
         }]
     }
 
-    fn update_edges(&mut self, zone: &Zone) {
-        for idx in 0..EDGE_COORDS.len() {
-            let edge = &EDGE_COORDS[idx];
-            self.edges[idx] = ScreenCoords::calculate_edge(zone, edge, self.hex_size);
-        }
-    }
-
-    fn calculate_port(zone: &Zone, port: &[usize; 3], hex_size: f32) -> [f32; 3] {
+    pub fn calculate_port(origin: &[f32; 2], port: &[usize; 3], hex_size: f32) -> [f32; 3] {
         let [r, q, e] = port;
-        let [x, y] = ScreenCoords::calculate_center(zone, &[*r, *q], hex_size);
+        let [x, y] = ScreenCoords::calculate_center(origin, &[*r, *q], hex_size);
         [x + match e {
             0 => -0.25 * SQRT_3 * hex_size,
             1 => 0.25 * SQRT_3 * hex_size,
@@ -238,17 +287,10 @@ impl ScreenCoords {
         (e * 60 + 60 - 45) as f32]
     }
 
-    fn update_ports(&mut self, zone: &Zone) {
-        for idx in 0..PORT_COORDS.len() {
-            let port = &PORT_COORDS[idx];
-            self.ports[idx] = ScreenCoords::calculate_port(zone, port, self.hex_size);
-        }
-    }
-
     fn update_cards(&mut self, zone: Zone) {
         let Zone { x, y, width, height } = zone;
 
-        let card_height = min(0.9 * height, width / 10.2);
+        let card_height = min(0.9 * height, width / (self.cards.len() as f32 + 0.2));
         let card_width = 0.7 * card_height;
         self.card_size = [card_width, card_height];
 
@@ -309,15 +351,22 @@ impl ScreenCoords {
     fn update_buttons(&mut self, zone: Zone) {
         let Zone { x, y, width, height } = zone;
 
-        let button_size = min(height, width / 5.0);
+        // Longer menus wrap onto a second row
+        let rows = self.buttons.len().div_ceil(MAX_BUTTONS_PER_ROW);
+        let per_row = self.buttons.len().div_ceil(rows);
+        let row_height = height / rows as f32;
+
+        let num_buttons = per_row as f32;
+        let button_size = min(row_height, width / num_buttons);
         self.button_size = button_size;
 
-        let shift = self.button_size + (width - 5.0 * button_size) / 5.0;
+        let shift = self.button_size + (width - num_buttons * button_size) / num_buttons;
         let start_x = x + shift - button_size;
-        let button_y = y + 0.5 * height - 0.5 * button_size;
-    
-        for idx in 0..self.buttons.len() {
-            self.buttons[idx] = [start_x + idx as f32 * shift, button_y];
+
+        for (idx, button) in self.buttons.iter_mut().enumerate() {
+            let (row, col) = (idx / per_row, idx % per_row);
+            let button_y = y + (row as f32 + 0.5) * row_height - 0.5 * button_size;
+            *button = [start_x + col as f32 * shift, button_y];
         }
     }
 
@@ -330,6 +379,26 @@ impl ScreenCoords {
         let x1 = x + 0.5 * width - 1.1 * dice_size;
         let x2 = x + 0.5 * width + 0.1 * dice_size;
         self.dice = [[x1, y], [x2, y]];
+        self.event_die = [x1 - 1.2 * dice_size, y];
+    }
+
+    fn update_toggle_buttons(&mut self, zone: Zone) {
+        let Zone { x, y, width, height } = zone;
+        let size = min(0.5 * width, height);
+        self.toggle_button_size = size;
+        self.stats_button = [x + 0.1 * size, y];
+        self.income_button = [x + 1.2 * size, y];
+        self.quit_button = [x + 2.3 * size, y];
+    }
+
+    fn update_chat(&mut self, zone: Zone) {
+        let row_height = zone.height / 2.0;
+        let slot = zone.width / self.canned_buttons.len() as f32;
+        self.canned_button_size = [0.9 * slot, 0.9 * row_height];
+        for (idx, button) in self.canned_buttons.iter_mut().enumerate() {
+            *button = [zone.x + idx as f32 * slot, zone.y];
+        }
+        self.chat_zone = [zone.x, zone.y + row_height, zone.width, row_height];
     }
 
     fn update_info_zone(&mut self, zone: Zone) {
@@ -374,4 +443,210 @@ impl ScreenCoords {
 //         cards[i] = [start_x + i as f32 * shift, y];
 //     }
 //     cards
-// }
\ No newline at end of file
+// }
+/// Layout of the house rules screen: one row per option with -/+ buttons,
+/// and Load/Save/Start buttons along the bottom
+pub struct RulesCoords {
+    pub labels: Vec<[f32; 2]>,
+    pub values: Vec<[f32; 2]>,
+    pub minus_buttons: Vec<[f32; 2]>,
+    pub plus_buttons: Vec<[f32; 2]>,
+    pub button_size: f32,
+    pub font_size: f32,
+    pub bottom_buttons: [[f32; 2]; 3],
+    pub bottom_button_size: [f32; 2],
+    pub status: [f32; 2],
+}
+
+impl RulesCoords {
+    pub fn new(num_rows: usize) -> RulesCoords {
+        let mut coords = RulesCoords {
+            labels: vec![[0.0; 2]; num_rows],
+            values: vec![[0.0; 2]; num_rows],
+            minus_buttons: vec![[0.0; 2]; num_rows],
+            plus_buttons: vec![[0.0; 2]; num_rows],
+            button_size: 0.0,
+            font_size: 0.0,
+            bottom_buttons: [[0.0; 2]; 3],
+            bottom_button_size: [0.0; 2],
+            status: [0.0; 2],
+        };
+        coords.update();
+        coords
+    }
+
+    pub fn update(&mut self) {
+        let width = screen_width();
+        let height = screen_height();
+
+        let rows = Zone::new(width, height, 0.1, 0.05, 0.8, 0.75);
+        let row_height = rows.height / self.labels.len() as f32;
+        self.button_size = 0.8 * row_height;
+        self.font_size = 0.8 * row_height;
+
+        for idx in 0..self.labels.len() {
+            let y = rows.y + idx as f32 * row_height;
+            self.labels[idx] = [rows.x, y + 0.7 * row_height];
+            self.values[idx] = [rows.x + 0.6 * rows.width, y + 0.7 * row_height];
+            self.minus_buttons[idx] = [rows.x + 0.75 * rows.width, y + 0.1 * row_height];
+            self.plus_buttons[idx] = [rows.x + 0.75 * rows.width + 1.5 * self.button_size, y + 0.1 * row_height];
+        }
+
+        let bottom = Zone::new(width, height, 0.1, 0.83, 0.8, 0.1);
+        self.bottom_button_size = [0.28 * bottom.width, bottom.height];
+        for (idx, button) in self.bottom_buttons.iter_mut().enumerate() {
+            *button = [bottom.x + idx as f32 * 0.36 * bottom.width, bottom.y];
+        }
+        self.status = [bottom.x, bottom.y + 1.5 * bottom.height];
+    }
+}
+
+/// Layout of the start screen: the seat count, one row per seat, the board,
+/// rules and seed rows, and Load/Start buttons along the bottom
+pub struct MainMenuCoords {
+    pub title: [f32; 2],
+    pub labels: [[f32; 2]; 4],
+    pub seats_value: [f32; 2],
+    pub seats_minus: [f32; 2],
+    pub seats_plus: [f32; 2],
+    pub seat_labels: Vec<[f32; 2]>,
+    pub swatches: Vec<[f32; 2]>,
+    pub kind_buttons: Vec<[f32; 2]>,
+    pub board_button: [f32; 2],
+    pub rules_button: [f32; 2],
+    pub seed_value: [f32; 2],
+    pub seed_button: [f32; 2],
+    pub button_size: f32,
+    pub wide_button_size: [f32; 2],
+    pub font_size: f32,
+    pub bottom_buttons: [[f32; 2]; 2],
+    pub bottom_button_size: [f32; 2],
+    pub status: [f32; 2],
+}
+
+impl MainMenuCoords {
+    pub fn new(max_seats: usize) -> MainMenuCoords {
+        let mut coords = MainMenuCoords {
+            title: [0.0; 2],
+            labels: [[0.0; 2]; 4],
+            seats_value: [0.0; 2],
+            seats_minus: [0.0; 2],
+            seats_plus: [0.0; 2],
+            seat_labels: vec![[0.0; 2]; max_seats],
+            swatches: vec![[0.0; 2]; max_seats],
+            kind_buttons: vec![[0.0; 2]; max_seats],
+            board_button: [0.0; 2],
+            rules_button: [0.0; 2],
+            seed_value: [0.0; 2],
+            seed_button: [0.0; 2],
+            button_size: 0.0,
+            wide_button_size: [0.0; 2],
+            font_size: 0.0,
+            bottom_buttons: [[0.0; 2]; 2],
+            bottom_button_size: [0.0; 2],
+            status: [0.0; 2],
+        };
+        coords.update();
+        coords
+    }
+
+    pub fn update(&mut self) {
+        let width = screen_width();
+        let height = screen_height();
+
+        // Title, seat count, the seats, then board, rules and seed
+        let rows = Zone::new(width, height, 0.1, 0.03, 0.8, 0.78);
+        let num_rows = self.seat_labels.len() + 5;
+        let row_height = rows.height / num_rows as f32;
+        let row_y = |idx: usize| rows.y + idx as f32 * row_height;
+        let text_y = |idx: usize| row_y(idx) + 0.7 * row_height;
+        let control_x = rows.x + 0.35 * rows.width;
+        self.button_size = 0.8 * row_height;
+        self.wide_button_size = [0.45 * rows.width, 0.8 * row_height];
+        self.font_size = 0.7 * row_height;
+
+        self.title = [rows.x, text_y(0)];
+        let label_rows = [1, num_rows - 3, num_rows - 2, num_rows - 1];
+        for (label, row) in self.labels.iter_mut().zip(label_rows) {
+            *label = [rows.x, text_y(row)];
+        }
+        self.seats_value = [control_x, text_y(1)];
+        self.seats_minus = [control_x + 1.5 * self.button_size, row_y(1) + 0.1 * row_height];
+        self.seats_plus = [control_x + 3.0 * self.button_size, row_y(1) + 0.1 * row_height];
+
+        for idx in 0..self.seat_labels.len() {
+            let row = idx + 2;
+            self.seat_labels[idx] = [rows.x + 0.05 * rows.width, text_y(row)];
+            self.swatches[idx] = [control_x, row_y(row) + 0.1 * row_height];
+            self.kind_buttons[idx] = [control_x + 1.5 * self.button_size, row_y(row) + 0.1 * row_height];
+        }
+
+        self.board_button = [control_x, row_y(num_rows - 3) + 0.1 * row_height];
+        self.rules_button = [control_x, row_y(num_rows - 2) + 0.1 * row_height];
+        self.seed_value = [control_x, text_y(num_rows - 1)];
+        self.seed_button = [rows.x + rows.width - self.wide_button_size[0], row_y(num_rows - 1) + 0.1 * row_height];
+
+        let bottom = Zone::new(width, height, 0.1, 0.83, 0.8, 0.1);
+        self.bottom_button_size = [0.45 * bottom.width, bottom.height];
+        for (idx, button) in self.bottom_buttons.iter_mut().enumerate() {
+            *button = [bottom.x + idx as f32 * 0.55 * bottom.width, bottom.y];
+        }
+        self.status = [bottom.x, bottom.y + 1.5 * bottom.height];
+    }
+}
+
+/// The lobby: a title, the room being set up, then the room list. Every
+/// row has its text on the left and can have a button on the right.
+pub struct LobbyCoords {
+    pub title: [f32; 2],
+    pub labels: Vec<[f32; 2]>,
+    pub buttons: Vec<[f32; 2]>,
+    pub minus: [f32; 2],
+    pub plus: [f32; 2],
+    pub button_size: f32,
+    pub wide_button_size: [f32; 2],
+    pub font_size: f32,
+    pub status: [f32; 2],
+}
+
+impl LobbyCoords {
+    pub fn new(num_rows: usize) -> LobbyCoords {
+        let mut coords = LobbyCoords {
+            title: [0.0; 2],
+            labels: vec![[0.0; 2]; num_rows],
+            buttons: vec![[0.0; 2]; num_rows],
+            minus: [0.0; 2],
+            plus: [0.0; 2],
+            button_size: 0.0,
+            wide_button_size: [0.0; 2],
+            font_size: 0.0,
+            status: [0.0; 2],
+        };
+        coords.update();
+        coords
+    }
+
+    pub fn update(&mut self) {
+        let width = screen_width();
+        let height = screen_height();
+
+        let rows = Zone::new(width, height, 0.1, 0.03, 0.8, 0.87);
+        let num_rows = self.labels.len() + 1;
+        let row_height = rows.height / num_rows as f32;
+        let row_y = |idx: usize| rows.y + idx as f32 * row_height;
+        let text_y = |idx: usize| row_y(idx) + 0.7 * row_height;
+        self.button_size = 0.8 * row_height;
+        self.wide_button_size = [0.3 * rows.width, 0.8 * row_height];
+        self.font_size = 0.6 * row_height;
+
+        self.title = [rows.x, text_y(0)];
+        let button_x = rows.x + rows.width - self.wide_button_size[0];
+        for idx in 0..self.labels.len() {
+            self.labels[idx] = [rows.x, text_y(idx + 1)];
+            self.buttons[idx] = [button_x, row_y(idx + 1) + 0.1 * row_height];
+        }
+        self.minus = [button_x, row_y(1) + 0.1 * row_height];
+        self.plus = [button_x + 1.5 * self.button_size, row_y(1) + 0.1 * row_height];
+        self.status = [rows.x, rows.y + rows.height + 0.5 * row_height];
+    }
+}
@@ -0,0 +1,1295 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvError, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::bot::Bot;
+use crate::cli::Options;
+use crate::game::{PlayerColor, Ruleset, PLAYER_COLORS};
+use crate::game_log::clean_chat;
+use crate::moves::Move;
+use crate::notation::{parse_move, to_notation};
+use crate::new_game::{BoardChoice, Difficulty, NewGame, Seat, SeatKind, MAX_SEATS, MIN_SEATS};
+use crate::rules::RuleConfig;
+use crate::{random_seed, save, SetupState, Table, BOT_DELAY};
+
+pub const SERVER_USAGE: &str = "\
+Usage: catan-server [options]
+
+Deals a game and waits for a client to join each human seat with
+`catan --join HOST:PORT`. The CPUs start once every seat is taken.
+Seated players can chat with a `chat TEXT` line. Each seat comes with
+a token, so a client that drops out can take it back with
+`--resume TOKEN`. The game is saved as it goes, and `--load`
+on that save carries it on after a restart. Anyone can watch with
+`catan --watch HOST:PORT`.
+
+With `--lobby` the server deals no game of its own. Players find it with
+`catan --rooms HOST:PORT`, open rooms with the rules they like, fill
+the seats with each other or with CPUs, and each room starts once
+everyone in it is ready. Lobby games aren't saved.
+
+  --port N            listen on port N (default 7878)
+  --players N         seats for people to join (default 2)
+  --cpus N            CPU seats (default enough for a table of four)
+  --difficulty D      easy, normal or hard for every CPU, and for dropped seats
+  --seed S            deal the board and dice from S
+  --rules FILE        house rules, as saved from the rules screen
+  --board FILE        a Seafarers map
+  --cities-knights    play Cities & Knights on a random board
+  --load FILE         carry on a saved game, or one this server saved
+  --timeout SECS      hand a dropped seat to a CPU after SECS (default 60, 0 for never)
+  --save FILE         where the game is saved as it goes (default server_game.txt)
+  --caster            spectators see every hand
+  --delay N           spectators see the game N moves late (default 0)
+  --lobby             host rooms the players set up, instead of one game
+  --help              show this message";
+
+pub const DEFAULT_PORT: u16 = 7878;
+pub const DEFAULT_TIMEOUT: u64 = 60;
+pub const SERVER_SAVE_PATH: &str = "server_game.txt";
+
+/// The game is written out at most this often, and only when it's changed
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A seat's own snapshot of the game is sent between these lines
+pub const STATE_MARKER: &str = "state";
+pub const END_STATE_MARKER: &str = "end_state";
+
+/// A room in the lobby lists itself on one line, and the list ends with this one
+pub const END_ROOMS_MARKER: &str = "end_rooms";
+
+/// What a connection's reader thread passes on to the game thread
+enum Event {
+    Connected(usize, TcpStream),
+    Line(usize, String),
+    Left(usize),
+}
+
+/// A connection, which has a seat once it's joined or resumed one
+struct Client {
+    id: usize,
+    stream: TcpStream,
+    seat: Option<PlayerColor>,
+    watching: bool,
+}
+
+/// A seat somebody has taken. It outlasts their connection, so the
+/// token can take it back.
+struct Session {
+    seat: PlayerColor,
+    token: String,
+    /// When the connection dropped, if it has
+    away_since: Option<Instant>,
+}
+
+/// The one real copy of the game. Clients send moves in notation, and
+/// every move is checked here before it's made. After each change every
+/// client is sent the game as its seat may see it.
+pub struct Server {
+    table: Table,
+    clients: Vec<Client>,
+    sessions: Vec<Session>,
+    rng: StdRng,
+    /// How long a dropped seat waits before `stand_in` plays it, if it ever does
+    timeout: Option<Duration>,
+    stand_in: Bot,
+    /// Lobby games aren't saved
+    save_path: Option<String>,
+    last_save: Instant,
+    unsaved: bool,
+    next_bot_move: Instant,
+    /// Spectators see every hand
+    caster: bool,
+    /// How many moves behind the spectators are
+    delay: usize,
+    /// The spectators' snapshots, oldest first. They're shown the front one.
+    spectator_history: VecDeque<String>,
+}
+
+fn send(stream: &mut TcpStream, text: &str) {
+    // A client that's gone is noticed by its reader thread
+    let _ = stream.write_all(text.as_bytes());
+}
+
+fn new_token() -> String {
+    format!("{:016x}", rand::rng().random::<u64>())
+}
+
+impl Server {
+    pub fn new(table: Table, rng: StdRng) -> Server {
+        Server {
+            table,
+            clients: Vec::new(),
+            sessions: Vec::new(),
+            rng,
+            timeout: Some(Duration::from_secs(DEFAULT_TIMEOUT)),
+            stand_in: Bot::new(Difficulty::Normal),
+            save_path: Some(String::from(SERVER_SAVE_PATH)),
+            last_save: Instant::now(),
+            unsaved: false,
+            next_bot_move: Instant::now(),
+            caster: false,
+            delay: 0,
+            spectator_history: VecDeque::new(),
+        }
+    }
+
+    fn get_client_idx(&self, id: usize) -> Option<usize> {
+        self.clients.iter().position(|client| client.id == id)
+    }
+
+    fn get_seat_idx(&self, seat: PlayerColor) -> usize {
+        self.table.get_players().iter().position(|player| player.is_color(seat)).unwrap()
+    }
+
+    /// The first human seat nobody has taken yet
+    fn get_free_seat(&self) -> Option<PlayerColor> {
+        self.table.get_players().iter().zip(self.table.get_bots().iter())
+            .filter(|(_, bot)| bot.is_none())
+            .map(|(player, _)| player.get_color())
+            .find(|&color| self.sessions.iter().all(|session| session.seat != color))
+    }
+
+    fn is_full(&self) -> bool {
+        self.get_free_seat().is_none()
+    }
+
+    fn snapshot(&self, seat: PlayerColor) -> String {
+        format!("{STATE_MARKER}\n{}{END_STATE_MARKER}\n", save::table_to_text(&self.table, Some(seat)))
+    }
+
+    /// Adds the game as it stands to what the spectators will see, and
+    /// returns what they're to be shown now
+    fn spectator_snapshot(&mut self) -> String {
+        let text = format!(
+            "{STATE_MARKER}\n{}{END_STATE_MARKER}\n", save::table_to_spectator_text(&self.table, self.caster)
+        );
+        if self.spectator_history.back() != Some(&text) {
+            self.spectator_history.push_back(text);
+        }
+        while self.spectator_history.len() > self.delay + 1 {
+            self.spectator_history.pop_front();
+        }
+        self.spectator_history.front().unwrap().clone()
+    }
+
+    fn broadcast(&mut self) {
+        let watched = self.spectator_snapshot();
+        for idx in 0..self.clients.len() {
+            let text = match self.clients[idx].seat {
+                Some(seat) => self.snapshot(seat),
+                None if self.clients[idx].watching => watched.clone(),
+                None => continue,
+            };
+            send(&mut self.clients[idx].stream, &text);
+        }
+    }
+
+    /// Tells every seated client, and the server's own log
+    fn notify(&mut self, text: &str) {
+        println!("{text}");
+        for client in self.clients.iter_mut().filter(|client| client.seat.is_some()) {
+            send(&mut client.stream, &format!("notice {text}\n"));
+        }
+    }
+
+    /// Sends the game out after it's changed. The CPUs wait a moment before
+    /// answering, so people can see what happened.
+    fn changed(&mut self) {
+        self.broadcast();
+        self.unsaved = true;
+        self.next_bot_move = Instant::now() + Duration::from_secs_f64(BOT_DELAY);
+    }
+
+    fn connect(&mut self, id: usize, stream: TcpStream) {
+        self.clients.push(Client { id, stream, seat: None, watching: false });
+    }
+
+    /// Gives the client a seat, and the token that takes it back
+    fn sit(&mut self, id: usize, seat: PlayerColor) {
+        let idx = self.get_client_idx(id).unwrap();
+        let token = new_token();
+        send(&mut self.clients[idx].stream, &format!("seat {:?} {token}\n", seat));
+        println!("Client {id} joined as {:?}", seat);
+        self.sessions.push(Session { seat, token, away_since: None });
+        self.clients[idx].seat = Some(seat);
+    }
+
+    fn join(&mut self, id: usize) -> Result<(), String> {
+        let seat = self.get_free_seat().ok_or("every seat is taken")?;
+        self.sit(id, seat);
+        self.changed();
+        Ok(())
+    }
+
+    fn watch(&mut self, id: usize) -> Result<(), String> {
+        let idx = self.get_client_idx(id).unwrap();
+        let mode = if self.caster {"caster"} else {"public"};
+        send(&mut self.clients[idx].stream, &format!("watching {mode} {}\n", self.delay));
+        println!("Client {id} is watching");
+        self.clients[idx].watching = true;
+        let text = self.spectator_snapshot();
+        send(&mut self.clients[idx].stream, &text);
+        Ok(())
+    }
+
+    fn has_session(&self, token: &str) -> bool {
+        self.sessions.iter().any(|session| session.token == token)
+    }
+
+    /// Takes back the seat `token` was given for, from a CPU if one had it
+    fn resume(&mut self, id: usize, token: &str) -> Result<(), String> {
+        let session_idx = self.sessions.iter().position(|session| session.token == token)
+            .ok_or("unknown session token")?;
+        let seat = self.sessions[session_idx].seat;
+        // An older connection still holding the seat gives it up
+        if let Some(old) = self.clients.iter().position(|client| client.seat == Some(seat)) {
+            let _ = self.clients[old].stream.shutdown(Shutdown::Both);
+            self.clients.remove(old);
+        }
+        let idx = self.get_client_idx(id).unwrap();
+        send(&mut self.clients[idx].stream, &format!("seat {:?} {token}\n", seat));
+        self.clients[idx].seat = Some(seat);
+        self.sessions[session_idx].away_since = None;
+        let seat_idx = self.get_seat_idx(seat);
+        self.table.get_bots_mut()[seat_idx] = None;
+        self.changed();
+        self.notify(&format!("{:?} is back", seat));
+        Ok(())
+    }
+
+    fn leave(&mut self, id: usize) {
+        let Some(idx) = self.get_client_idx(id) else { return };
+        let Some(seat) = self.clients.remove(idx).seat else { return };
+        if let Some(session) = self.sessions.iter_mut().find(|session| session.seat == seat) {
+            session.away_since = Some(Instant::now());
+        }
+        let text = match self.timeout {
+            Some(timeout) => format!("{:?} lost connection; a CPU takes the seat in {}s", seat, timeout.as_secs()),
+            None => format!("{:?} lost connection; the seat waits for them", seat),
+        };
+        self.notify(&text);
+    }
+
+    /// When the next dropped seat is due to be handed to a CPU
+    fn next_takeover(&self) -> Option<Instant> {
+        let timeout = self.timeout?;
+        self.sessions.iter()
+            .filter(|session| self.table.get_bots()[self.get_seat_idx(session.seat)].is_none())
+            .filter_map(|session| session.away_since)
+            .map(|since| since + timeout)
+            .min()
+    }
+
+    fn take_over_seats(&mut self) {
+        let Some(timeout) = self.timeout else { return };
+        for session_idx in 0..self.sessions.len() {
+            let seat = self.sessions[session_idx].seat;
+            let seat_idx = self.get_seat_idx(seat);
+            let due = self.sessions[session_idx].away_since.is_some_and(|since| since.elapsed() >= timeout);
+            if due && self.table.get_bots()[seat_idx].is_none() {
+                self.table.get_bots_mut()[seat_idx] = Some(self.stand_in);
+                self.unsaved = true;
+                self.notify(&format!("A CPU is playing {:?} until they're back", seat));
+            }
+        }
+    }
+
+    fn next_save(&self) -> Option<Instant> {
+        (self.unsaved && self.save_path.is_some()).then_some(self.last_save + SAVE_INTERVAL)
+    }
+
+    /// Writes out the sessions and the whole game, for `load` after a restart
+    fn save(&mut self) {
+        let Some(path) = &self.save_path else { return };
+        let mut text = String::new();
+        for session in self.sessions.iter() {
+            text += &format!("session {:?} {}\n", session.seat, session.token);
+        }
+        text += "\n";
+        text += &save::table_to_text(&self.table, None);
+        if let Err(err) = fs::write(path, text) {
+            eprintln!("Warning: game not saved: {}: {}", path, err);
+        }
+        self.last_save = Instant::now();
+        self.unsaved = false;
+    }
+
+    /// Passes a chat line on to the table and keeps it in the log. It's
+    /// sent on its own rather than in a snapshot, so nobody's half-made
+    /// choice is thrown away; spectators running late read it in the log
+    /// once they catch up.
+    fn chat(&mut self, seat: PlayerColor, text: &str) -> Result<(), String> {
+        let text = clean_chat(text).ok_or("nothing to say")?;
+        let line = format!("chat {:?} {text}\n", seat);
+        println!("{:?}: {text}", seat);
+        self.table.add_chat(seat, text);
+        let live = self.delay == 0;
+        for client in self.clients.iter_mut().filter(|client| client.seat.is_some() || (client.watching && live)) {
+            send(&mut client.stream, &line);
+        }
+        self.unsaved = true;
+        Ok(())
+    }
+
+    fn check_move(&self, seat: PlayerColor, line: &str) -> Result<Move, String> {
+        let mv = parse_move(&self.table, line)?;
+        if !self.is_full() {
+            return Err(String::from("waiting for every seat to be taken"));
+        }
+        if self.table.get_current_color() != seat {
+            return Err(format!("it's {:?}'s move", self.table.get_current_color()));
+        }
+        if !self.table.can_make_move(&mv) {
+            return Err(format!("'{}' can't be played now", to_notation(&self.table, &mv)));
+        }
+        Ok(mv)
+    }
+
+    /// A client's first line asks for a seat; after that they're moves
+    fn handle_line(&mut self, id: usize, line: &str) {
+        let Some(idx) = self.get_client_idx(id) else { return };
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        if self.clients[idx].watching {
+            send(&mut self.clients[idx].stream, "error spectators can't make moves\n");
+            return;
+        }
+        let result = match (self.clients[idx].seat, keyword) {
+            (None, "join") => self.join(id),
+            (None, "watch") => self.watch(id),
+            (None, "resume") => self.resume(id, rest.trim()),
+            (None, _) => Err(String::from("join or resume a seat, or watch, first")),
+            (Some(_), "join" | "resume" | "watch") => Err(String::from("you already have a seat")),
+            (Some(seat), "chat") => self.chat(seat, rest),
+            (Some(seat), _) => self.check_move(seat, line).map(|mv| {
+                self.table.play(Some(&mv), &mut self.rng);
+                self.changed();
+            }),
+        };
+        if let Err(err) = result {
+            send(&mut self.clients[idx].stream, &format!("error {err}\n"));
+        }
+    }
+
+    fn bot_to_move(&self) -> bool {
+        self.is_full() && !self.table.is_over() && self.table.get_current_bot().is_some()
+    }
+
+    /// When there's next something to do without being asked
+    fn wake(&self) -> Option<Instant> {
+        [self.bot_to_move().then_some(self.next_bot_move), self.next_takeover(), self.next_save()]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    /// Does whatever has come due: dropped seats to hand over, a CPU's move, a save
+    fn tick(&mut self) {
+        self.take_over_seats();
+        if self.bot_to_move() && Instant::now() >= self.next_bot_move {
+            self.table.play(None, &mut self.rng);
+            self.changed();
+        }
+        if self.next_save().is_some_and(|at| Instant::now() >= at) {
+            self.save();
+        }
+    }
+
+    fn run(mut self, events: Receiver<Event>) {
+        loop {
+            let Ok(event) = next_event(&events, self.wake()) else { return };
+            match event {
+                Some(Event::Connected(id, stream)) => self.connect(id, stream),
+                Some(Event::Line(id, line)) => self.handle_line(id, &line),
+                Some(Event::Left(id)) => self.leave(id),
+                None => ()
+            }
+            self.tick();
+        }
+    }
+}
+
+/// The next event, or `None` once `wake` comes round first
+fn next_event(events: &Receiver<Event>, wake: Option<Instant>) -> Result<Option<Event>, RecvError> {
+    match wake {
+        Some(wake) => match events.recv_timeout(wake.saturating_duration_since(Instant::now())) {
+            Ok(event) => Ok(Some(event)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(RecvError),
+        },
+        None => events.recv().map(Some)
+    }
+}
+
+/// How a seat in a room that hasn't started is filled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoomSeat {
+    Open,
+    /// Somebody's sitting there, and whether they're ready
+    Taken(bool),
+    Cpu(Difficulty),
+}
+
+impl RoomSeat {
+    pub fn label(self) -> &'static str {
+        match self {
+            RoomSeat::Open => "Open",
+            RoomSeat::Taken(false) => "Not ready",
+            RoomSeat::Taken(true) => "Ready",
+            RoomSeat::Cpu(difficulty) => SeatKind::Cpu(difficulty).label(),
+        }
+    }
+
+    fn to_text(self) -> String {
+        match self {
+            RoomSeat::Open => String::from("open"),
+            RoomSeat::Taken(false) => String::from("taken"),
+            RoomSeat::Taken(true) => String::from("ready"),
+            RoomSeat::Cpu(difficulty) => format!("{:?}", difficulty).to_lowercase(),
+        }
+    }
+
+    fn parse(text: &str) -> Option<RoomSeat> {
+        match text {
+            "open" => Some(RoomSeat::Open),
+            "taken" => Some(RoomSeat::Taken(false)),
+            "ready" => Some(RoomSeat::Taken(true)),
+            _ => Difficulty::from_name(text).map(RoomSeat::Cpu)
+        }
+    }
+}
+
+/// A room as the lobby lists it
+#[derive(Debug, Clone)]
+pub struct RoomSummary {
+    pub id: usize,
+    pub started: bool,
+    pub ruleset: Ruleset,
+    pub vp_target: usize,
+    pub seats: Vec<(PlayerColor, RoomSeat)>,
+}
+
+impl RoomSummary {
+    /// `room 3 open Base 10 Red:ready Blue:open Green:hard`
+    pub fn to_text(&self) -> String {
+        let status = if self.started {"playing"} else {"open"};
+        let mut text = format!("room {} {status} {:?} {}", self.id, self.ruleset, self.vp_target);
+        for (color, seat) in self.seats.iter() {
+            text += &format!(" {:?}:{}", color, seat.to_text());
+        }
+        text
+    }
+
+    pub fn parse(line: &str) -> Result<RoomSummary, String> {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        if args.len() < 5 || args[0] != "room" {
+            return Err(format!("bad room '{line}'"));
+        }
+        let id = args[1].parse().map_err(|_| format!("bad room number '{}'", args[1]))?;
+        let started = match args[2] {
+            "open" => false,
+            "playing" => true,
+            status => return Err(format!("bad room status '{status}'"))
+        };
+        let ruleset = Ruleset::from_name(args[3]).ok_or(format!("unknown ruleset '{}'", args[3]))?;
+        let vp_target = args[4].parse().map_err(|_| format!("bad VP target '{}'", args[4]))?;
+        let seats = args[5..].iter()
+            .map(|arg| {
+                let (color, seat) = arg.split_once(':').ok_or(format!("bad seat '{arg}'"))?;
+                let color = PlayerColor::from_name(color).ok_or(format!("unknown color '{color}'"))?;
+                let seat = RoomSeat::parse(seat).ok_or(format!("bad seat '{arg}'"))?;
+                Ok((color, seat))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(RoomSummary { id, started, ruleset, vp_target, seats })
+    }
+
+    /// `Room 3: Cities & Knights to 13 VPs, 2 of 4 seats taken`
+    pub fn describe(&self) -> String {
+        let game = match self.ruleset {
+            Ruleset::Base => "Base game",
+            Ruleset::Seafarers => "Seafarers",
+            Ruleset::CitiesKnights => "Cities & Knights",
+        };
+        let taken = self.seats.iter().filter(|(_, seat)| *seat != RoomSeat::Open).count();
+        let status = if self.started {String::from("playing")} else {format!("{taken} of {} seats taken", self.seats.len())};
+        format!("Room {}: {game} to {} VPs, {status}", self.id, self.vp_target)
+    }
+
+    pub fn can_join(&self) -> bool {
+        !self.started && self.seats.iter().any(|(_, seat)| *seat == RoomSeat::Open)
+    }
+}
+
+/// A table in the lobby, filling up or playing. Once it starts, its game
+/// is a `Server` of its own, and the players in it are that server's clients.
+struct Room {
+    id: usize,
+    ruleset: Ruleset,
+    rules: RuleConfig,
+    seats: Vec<(PlayerColor, RoomSeat)>,
+    game: Option<Server>,
+}
+
+impl Room {
+    fn summary(&self) -> RoomSummary {
+        RoomSummary {
+            id: self.id,
+            started: self.game.is_some(),
+            ruleset: self.ruleset,
+            vp_target: self.rules.vp_target,
+            seats: self.seats.clone(),
+        }
+    }
+
+    fn get_seat_idx(&self, color: PlayerColor) -> Result<usize, String> {
+        self.seats.iter().position(|&(other, _)| other == color)
+            .ok_or(format!("room {} has no {:?} seat", self.id, color))
+    }
+
+    /// Every seat filled, and everybody sitting in one ready
+    fn is_ready(&self) -> bool {
+        self.game.is_none()
+        && self.seats.iter().all(|(_, seat)| matches!(seat, RoomSeat::Taken(true) | RoomSeat::Cpu(_)))
+        && self.seats.iter().any(|(_, seat)| matches!(seat, RoomSeat::Taken(_)))
+    }
+
+    /// Nobody's waiting for it to start, or everyone's gone from its game.
+    /// Dropped seats aren't waited for, however long the timeout.
+    fn is_done(&self) -> bool {
+        match &self.game {
+            Some(game) => game.clients.is_empty(),
+            None => self.seats.iter().all(|(_, seat)| !matches!(seat, RoomSeat::Taken(_)))
+        }
+    }
+}
+
+/// Somebody connected to the lobby
+struct Visitor {
+    id: usize,
+    stream: TcpStream,
+    /// The room they're in, playing or watching once it's started
+    room: Option<usize>,
+    /// Their seat in a room that hasn't started
+    seat: Option<PlayerColor>,
+}
+
+/// Many games on one server. Players list the rooms, open one with the
+/// rules and seat count they want, sit down or fill seats with CPUs, and
+/// the room's game is dealt once everyone in it is ready. Each game runs
+/// on its own, and only hears from the players in its room.
+pub struct Lobby {
+    visitors: Vec<Visitor>,
+    rooms: Vec<Room>,
+    next_room_id: usize,
+    /// Timeouts, stand-ins and spectating for every room's game
+    options: Options,
+}
+
+impl Lobby {
+    pub fn new(options: Options) -> Lobby {
+        Lobby { visitors: Vec::new(), rooms: Vec::new(), next_room_id: 1, options }
+    }
+
+    fn get_visitor_idx(&self, id: usize) -> Option<usize> {
+        self.visitors.iter().position(|visitor| visitor.id == id)
+    }
+
+    fn get_room_idx(&self, room_id: usize) -> Result<usize, String> {
+        self.rooms.iter().position(|room| room.id == room_id).ok_or(format!("no room {room_id}"))
+    }
+
+    /// The room the visitor is sitting in, as long as it hasn't started
+    fn get_waiting_room_idx(&self, idx: usize) -> Result<usize, String> {
+        self.visitors[idx].seat
+            .and(self.visitors[idx].room)
+            .and_then(|room_id| self.get_room_idx(room_id).ok())
+            .ok_or(String::from("sit down in a room first"))
+    }
+
+    fn send_rooms(&mut self, idx: usize) {
+        let mut text: String = self.rooms.iter().map(|room| room.summary().to_text() + "\n").collect();
+        text += END_ROOMS_MARKER;
+        text += "\n";
+        send(&mut self.visitors[idx].stream, &text);
+    }
+
+    /// Everyone not in a game hears about every change to the rooms
+    fn broadcast_rooms(&mut self) {
+        for idx in 0..self.visitors.len() {
+            let playing = self.visitors[idx].room.is_some() && self.visitors[idx].seat.is_none();
+            if !playing {
+                self.send_rooms(idx);
+            }
+        }
+    }
+
+    fn create(&mut self, idx: usize, rest: &str) -> Result<(), String> {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let num_seats: usize = args.first().and_then(|arg| arg.parse().ok())
+            .ok_or("create needs a seat count and a ruleset")?;
+        if !(MIN_SEATS..=MAX_SEATS).contains(&num_seats) {
+            return Err(format!("{num_seats} seats; a table has {MIN_SEATS} to {MAX_SEATS}"));
+        }
+        let ruleset = args.get(1).and_then(|name| Ruleset::from_name(name))
+            .ok_or("create needs a seat count and a ruleset")?;
+        if ruleset == Ruleset::Seafarers {
+            return Err(String::from("Seafarers needs a map, and the lobby has none"));
+        }
+        if !args.len().is_multiple_of(2) {
+            return Err(String::from("house rules come as rule and value pairs"));
+        }
+        let text: String = args[2..].chunks(2).map(|pair| format!("{} {}\n", pair[0], pair[1])).collect();
+        let rules = RuleConfig::new(num_seats, ruleset).parse(&text).map_err(|err| format!("house rules: {err}"))?;
+
+        let id = self.next_room_id;
+        self.next_room_id += 1;
+        let seats = PLAYER_COLORS[..num_seats].iter().map(|&color| (color, RoomSeat::Open)).collect();
+        self.rooms.push(Room { id, ruleset, rules, seats, game: None });
+        println!("Client {} opened room {id}", self.visitors[idx].id);
+        self.join(idx, &id.to_string())
+    }
+
+    /// Sits down in a room that hasn't started, in the seat named or the
+    /// first open one. A seat the visitor had already is given up.
+    fn join(&mut self, idx: usize, rest: &str) -> Result<(), String> {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let room_id: usize = args.first().and_then(|arg| arg.parse().ok()).ok_or("pick a room: join ROOM [COLOR]")?;
+        let room_idx = self.get_room_idx(room_id)?;
+        let room = &self.rooms[room_idx];
+        if room.game.is_some() {
+            return Err(format!("room {room_id} has started; watch it instead"));
+        }
+        let seat = match args.get(1) {
+            Some(name) => PlayerColor::from_name(name).ok_or(format!("unknown color '{name}'"))?,
+            None => room.seats.iter()
+                .find(|(_, seat)| *seat == RoomSeat::Open)
+                .map(|&(color, _)| color)
+                .ok_or(format!("room {room_id} is full"))?
+        };
+        let seat_idx = room.get_seat_idx(seat)?;
+        let own = self.visitors[idx].room == Some(room_id) && self.visitors[idx].seat == Some(seat);
+        if room.seats[seat_idx].1 != RoomSeat::Open && !own {
+            return Err(format!("{:?}'s seat isn't open", seat));
+        }
+
+        if self.visitors[idx].room.is_some() {
+            self.leave(idx)?;
+        }
+        self.rooms[room_idx].seats[seat_idx].1 = RoomSeat::Taken(false);
+        self.visitors[idx].room = Some(room_id);
+        self.visitors[idx].seat = Some(seat);
+        send(&mut self.visitors[idx].stream, &format!("sitting {room_id} {:?}\n", seat));
+        println!("Client {} sat down as {:?} in room {room_id}", self.visitors[idx].id, seat);
+        Ok(())
+    }
+
+    /// Gets up from a room's seat, or out of its game, and back to the list
+    fn leave(&mut self, idx: usize) -> Result<(), String> {
+        let room_id = self.visitors[idx].room.ok_or("you're not in a room")?;
+        if let Ok(room_idx) = self.get_room_idx(room_id) {
+            let room = &mut self.rooms[room_idx];
+            match (&mut room.game, self.visitors[idx].seat) {
+                (Some(game), _) => game.leave(self.visitors[idx].id),
+                (None, Some(seat)) => {
+                    let seat_idx = room.get_seat_idx(seat)?;
+                    room.seats[seat_idx].1 = RoomSeat::Open;
+                },
+                (None, None) => ()
+            }
+        }
+        self.visitors[idx].room = None;
+        self.visitors[idx].seat = None;
+        send(&mut self.visitors[idx].stream, "lobby\n");
+        Ok(())
+    }
+
+    /// Puts a CPU in an open seat of the visitor's room, or takes one out
+    fn fill_seat(&mut self, idx: usize, rest: &str, cpu: bool) -> Result<(), String> {
+        let room_idx = self.get_waiting_room_idx(idx)?;
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let name = args.first().ok_or("name the seat's color")?;
+        let color = PlayerColor::from_name(name).ok_or(format!("unknown color '{name}'"))?;
+        let difficulty = match args.get(1) {
+            Some(name) => Difficulty::from_name(name).ok_or(format!("unknown difficulty '{name}'"))?,
+            None => Difficulty::Normal
+        };
+        let room = &mut self.rooms[room_idx];
+        let seat_idx = room.get_seat_idx(color)?;
+        room.seats[seat_idx].1 = match (room.seats[seat_idx].1, cpu) {
+            (RoomSeat::Taken(_), _) => return Err(format!("somebody's sitting in {:?}'s seat", color)),
+            (_, true) => RoomSeat::Cpu(difficulty),
+            (_, false) => RoomSeat::Open,
+        };
+        Ok(())
+    }
+
+    fn set_ready(&mut self, idx: usize, ready: bool) -> Result<(), String> {
+        let room_idx = self.get_waiting_room_idx(idx)?;
+        let room = &mut self.rooms[room_idx];
+        let seat_idx = room.get_seat_idx(self.visitors[idx].seat.unwrap())?;
+        room.seats[seat_idx].1 = RoomSeat::Taken(ready);
+        Ok(())
+    }
+
+    /// Hands the visitor's connection to a room's game, which takes it from `line`
+    fn enter_game(&mut self, idx: usize, room_idx: usize, line: &str) -> Result<(), String> {
+        if self.visitors[idx].room.is_some() {
+            self.leave(idx)?;
+        }
+        let id = self.visitors[idx].id;
+        let stream = self.visitors[idx].stream.try_clone().map_err(|err| err.to_string())?;
+        let room = &mut self.rooms[room_idx];
+        let game = room.game.as_mut().unwrap();
+        game.connect(id, stream);
+        game.handle_line(id, line);
+        self.visitors[idx].room = Some(room.id);
+        Ok(())
+    }
+
+    fn watch(&mut self, idx: usize, rest: &str) -> Result<(), String> {
+        let room_id: usize = rest.trim().parse().map_err(|_| "pick a room: watch ROOM")?;
+        let room_idx = self.get_room_idx(room_id)?;
+        if self.rooms[room_idx].game.is_none() {
+            return Err(format!("room {room_id} hasn't started"));
+        }
+        self.enter_game(idx, room_idx, "watch")
+    }
+
+    /// Finds the game the token was given out in
+    fn resume(&mut self, idx: usize, token: &str) -> Result<(), String> {
+        let room_idx = self.rooms.iter()
+            .position(|room| room.game.as_ref().is_some_and(|game| game.has_session(token)))
+            .ok_or("unknown session token")?;
+        self.enter_game(idx, room_idx, &format!("resume {token}"))
+    }
+
+    /// Deals the room's game and seats everyone sitting in it
+    fn start(&mut self, room_idx: usize) {
+        let room = &mut self.rooms[room_idx];
+        let new_game = NewGame {
+            seats: room.seats.iter()
+                .map(|&(color, seat)| match seat {
+                    RoomSeat::Cpu(difficulty) => Seat { color, kind: SeatKind::Cpu(difficulty) },
+                    _ => Seat { color, kind: SeatKind::Human }
+                })
+                .collect(),
+            board: if room.ruleset == Ruleset::CitiesKnights { BoardChoice::CitiesKnights } else { BoardChoice::Random },
+            rules: Some(room.rules),
+            seed: random_seed(),
+        };
+        let mut rng = StdRng::seed_from_u64(new_game.seed);
+        let mut state = match SetupState::from_new_game(&new_game, &mut rng) {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("Warning: room {} not dealt: {}", room.id, err);
+                return;
+            }
+        };
+        state.hot_seat = false;
+        let mut game = Server::new(Table::Setup(Box::new(state)), rng);
+        game.save_path = None;
+        configure(&mut game, &self.options);
+
+        for visitor in self.visitors.iter_mut().filter(|visitor| visitor.room == Some(room.id)) {
+            let (Some(seat), Ok(stream)) = (visitor.seat.take(), visitor.stream.try_clone()) else { continue };
+            game.connect(visitor.id, stream);
+            game.sit(visitor.id, seat);
+        }
+        game.changed();
+        println!("Room {} dealt from seed {}", room.id, new_game.seed);
+        room.game = Some(game);
+    }
+
+    /// Starts the rooms that are ready, closes the ones nobody's left in,
+    /// and tells everyone in the lobby
+    fn update_rooms(&mut self) {
+        for room_idx in 0..self.rooms.len() {
+            if self.rooms[room_idx].is_ready() {
+                self.start(room_idx);
+            }
+        }
+        self.rooms.retain(|room| !room.is_done());
+        self.broadcast_rooms();
+    }
+
+    /// Lines from somebody in a game go to the game, except `leave`.
+    /// Everyone else is in the lobby.
+    fn handle_line(&mut self, id: usize, line: &str) {
+        let Some(idx) = self.get_visitor_idx(id) else { return };
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let playing = self.visitors[idx].room
+            .and_then(|room_id| self.get_room_idx(room_id).ok())
+            .filter(|&room_idx| self.rooms[room_idx].game.is_some());
+        if let (Some(room_idx), false) = (playing, keyword == "leave") {
+            self.rooms[room_idx].game.as_mut().unwrap().handle_line(id, line);
+            return;
+        }
+
+        let result = match keyword {
+            "rooms" => {
+                self.send_rooms(idx);
+                return;
+            },
+            "create" => self.create(idx, rest),
+            "join" => self.join(idx, rest),
+            "leave" => self.leave(idx),
+            "bot" => self.fill_seat(idx, rest, true),
+            "open" => self.fill_seat(idx, rest, false),
+            "ready" => self.set_ready(idx, true),
+            "unready" => self.set_ready(idx, false),
+            "watch" => self.watch(idx, rest),
+            "resume" => self.resume(idx, rest.trim()),
+            _ if self.visitors[idx].seat.is_some() => Err(String::from("the game hasn't started")),
+            _ => Err(String::from("join or open a room first")),
+        };
+        match result {
+            Ok(()) => self.update_rooms(),
+            Err(err) => send(&mut self.visitors[idx].stream, &format!("error {err}\n")),
+        }
+    }
+
+    fn disconnect(&mut self, id: usize) {
+        let Some(idx) = self.get_visitor_idx(id) else { return };
+        let _ = self.leave(idx);
+        self.visitors.remove(idx);
+        self.update_rooms();
+    }
+
+    fn run(mut self, events: Receiver<Event>) {
+        loop {
+            let wake = self.rooms.iter().filter_map(|room| room.game.as_ref()).filter_map(Server::wake).min();
+            let Ok(event) = next_event(&events, wake) else { return };
+            match event {
+                Some(Event::Connected(id, stream)) => self.visitors.push(Visitor { id, stream, room: None, seat: None }),
+                Some(Event::Line(id, line)) => self.handle_line(id, &line),
+                Some(Event::Left(id)) => self.disconnect(id),
+                None => ()
+            }
+            for game in self.rooms.iter_mut().filter_map(|room| room.game.as_mut()) {
+                game.tick();
+            }
+            if self.rooms.iter().any(Room::is_done) {
+                self.update_rooms();
+            }
+        }
+    }
+}
+
+/// Passes on everything one client sends, line by line
+fn read_client(id: usize, stream: TcpStream, events: Sender<Event>) {
+    let Ok(writer) = stream.try_clone() else { return };
+    if events.send(Event::Connected(id, writer)).is_err() {
+        return;
+    }
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if events.send(Event::Line(id, line)).is_err() {
+            return;
+        }
+    }
+    let _ = events.send(Event::Left(id));
+}
+
+/// Every connection made to `listener`, each read on its own thread
+fn listen(listener: TcpListener) -> Receiver<Event> {
+    let (sender, events) = mpsc::channel();
+    thread::spawn(move || {
+        for (id, stream) in listener.incoming().enumerate() {
+            let Ok(stream) = stream else { continue };
+            let sender = sender.clone();
+            thread::spawn(move || read_client(id, stream, sender));
+        }
+    });
+    events
+}
+
+/// Runs the game on `listener` until the process ends
+pub fn serve(listener: TcpListener, server: Server) {
+    server.run(listen(listener));
+}
+
+/// Runs the lobby's rooms on `listener` until the process ends
+pub fn serve_lobby(listener: TcpListener, lobby: Lobby) {
+    lobby.run(listen(listener));
+}
+
+/// A game `Server::save` wrote, with its sessions, or any saved game.
+/// Every seat starts out away until its player comes back.
+fn load(path: &str) -> Result<(Table, Vec<Session>), String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    let mut sessions: Vec<Session> = Vec::new();
+    let mut session_lines = Vec::new();
+    let mut game = String::new();
+    for (idx, line) in text.lines().enumerate() {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        if args.first() == Some(&"session") {
+            if args.len() != 3 {
+                return Err(format!("{path}: line {}: session needs a color and a token", idx + 1));
+            }
+            let seat = save::parse_color(args.get(1)).map_err(|err| format!("{path}: line {}: {err}", idx + 1))?;
+            session_lines.push(idx + 1);
+            sessions.push(Session { seat, token: args[2].to_string(), away_since: Some(Instant::now()) });
+        } else {
+            // Kept as a blank line, so errors still give the file's line numbers
+            game += line;
+        }
+        game += "\n";
+    }
+    let mut table = save::parse_table(&game).map_err(|err| format!("{path}: {err}"))?;
+    for (i, (session, line)) in sessions.iter().zip(session_lines).enumerate() {
+        if !table.get_players().iter().any(|player| player.is_color(session.seat)) {
+            return Err(format!("{path}: line {line}: nobody plays {:?} in this game", session.seat));
+        }
+        if sessions[..i].iter().any(|other| other.seat == session.seat) {
+            return Err(format!("{path}: line {line}: {:?} already has a session", session.seat));
+        }
+    }
+    match &mut table {
+        Table::Setup(state) => state.hot_seat = false,
+        Table::Playing(state) => state.hot_seat = false,
+    }
+    Ok((table, sessions))
+}
+
+fn deal(options: &mut Options) -> Result<Server, String> {
+    let mut server = match &options.load {
+        Some(path) => {
+            let (table, sessions) = load(path)?;
+            let mut server = Server::new(table, StdRng::from_rng(&mut rand::rng()));
+            server.sessions = sessions;
+            server
+        },
+        None => {
+            options.players.get_or_insert(2);
+            let seed = options.seed.unwrap_or_else(random_seed);
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut state = SetupState::from_new_game(&options.new_game(seed)?, &mut rng)?;
+            state.hot_seat = false;
+            println!("Dealt from seed {seed}");
+            Server::new(Table::Setup(Box::new(state)), rng)
+        }
+    };
+    configure(&mut server, options);
+    if let Some(path) = &options.save {
+        server.save_path = Some(path.clone());
+    }
+    Ok(server)
+}
+
+/// What a game takes from the command line once it's dealt
+fn configure(server: &mut Server, options: &Options) {
+    let timeout = options.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    server.timeout = (timeout > 0).then_some(Duration::from_secs(timeout));
+    server.stand_in = Bot::new(options.difficulty.unwrap_or(Difficulty::Normal));
+    server.caster = options.caster;
+    server.delay = options.delay.unwrap_or(0);
+}
+
+pub fn main() {
+    let mut options = Options::parse(std::env::args().skip(1))
+        .and_then(|options| options.check_server().map(|_| options))
+        .unwrap_or_else(|err| {
+            eprintln!("Error: {}\n\n{}", err, SERVER_USAGE);
+            std::process::exit(2)
+        });
+    if options.help {
+        println!("{}", SERVER_USAGE);
+        return;
+    }
+
+    let port = options.port.unwrap_or(DEFAULT_PORT);
+    let bind = || TcpListener::bind(("0.0.0.0", port)).unwrap_or_else(|err| {
+        eprintln!("Error: port {port}: {err}");
+        std::process::exit(1)
+    });
+    if options.lobby {
+        let listener = bind();
+        println!("Lobby open on port {port}");
+        serve_lobby(listener, Lobby::new(options));
+        return;
+    }
+
+    let server = deal(&mut options).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(1)
+    });
+    let listener = bind();
+    println!("Listening on port {port}");
+    serve(listener, server);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player_view::PlayerView;
+    use crate::test_game;
+
+    fn test_server(seed: u64) -> Server {
+        let mut server = Server::new(Table::Playing(Box::new(test_game(BoardChoice::Random, seed, 4))), StdRng::seed_from_u64(seed));
+        server.save_path = None;
+        server
+    }
+
+    /// The server's end of a loopback connection, and the client's
+    fn loopback() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        (stream, client)
+    }
+
+    fn reader(client: TcpStream) -> BufReader<TcpStream> {
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        BufReader::new(client)
+    }
+
+    /// The next game the client is sent
+    fn read_snapshot(client: &mut BufReader<TcpStream>) -> String {
+        let mut lines = client.lines().map(|line| line.unwrap());
+        lines.by_ref().find(|line| line == STATE_MARKER).unwrap();
+        lines.take_while(|line| line != END_STATE_MARKER).map(|line| line + "\n").collect()
+    }
+
+    #[test]
+    fn each_seat_is_sent_only_its_own_hand() {
+        let mut server = test_server(10);
+        server.table.get_bots_mut()[1] = None;
+        let mut clients = Vec::new();
+        for id in [4, 7] {
+            let (stream, client) = loopback();
+            server.connect(id, stream);
+            server.join(id).unwrap();
+            clients.push(reader(client));
+        }
+
+        for (session, client) in server.sessions.iter().zip(clients.iter_mut()) {
+            // The second seat's join sent the first seat a newer game
+            let mut text = read_snapshot(client);
+            if session.seat == server.sessions[0].seat {
+                text = read_snapshot(client);
+            }
+            let sent = save::parse_table(&text).unwrap();
+            for (player, seen) in server.table.get_players().iter().zip(sent.get_players().iter()) {
+                let expected = if player.is_color(session.seat) { player.clone() } else { player.face_down() };
+                assert_eq!(seen.to_text(), expected.to_text());
+            }
+        }
+    }
+
+    #[test]
+    fn moves_are_checked_for_the_seat_to_act() {
+        let mut server = test_server(12);
+        let current = server.table.get_current_color();
+        let waiting = server.table.get_players().iter().map(|player| player.get_color()).find(|&color| color != current).unwrap();
+        let Table::Playing(state) = &server.table else { panic!("expected a game in play") };
+        let mv = Bot::new(Difficulty::Normal).play_step(&PlayerView::new(state, current), &mut StdRng::seed_from_u64(0));
+        let text = to_notation(&server.table, &mv);
+
+        assert!(server.check_move(current, &text).is_err());
+        for (player, bot) in server.table.get_players().iter().zip(server.table.get_bots().iter()) {
+            if bot.is_none() {
+                server.sessions.push(Session { seat: player.get_color(), token: new_token(), away_since: None });
+            }
+        }
+        assert_eq!(server.check_move(current, &text), Ok(mv));
+        assert!(server.check_move(waiting, &text).is_err());
+        assert!(server.check_move(current, &format!("{text} {text}")).is_err());
+        assert!(server.check_move(current, "S@999").is_err());
+        assert!(server.check_move(current, "R8").is_err());
+        assert!(server.check_move(current, "fly").is_err());
+    }
+
+    #[test]
+    fn spectators_run_the_set_number_of_moves_behind() {
+        let mut server = test_server(13);
+        server.delay = 2;
+        server.table.get_bots_mut()[0] = Some(Bot::new(Difficulty::Normal));
+        let mut rng = StdRng::seed_from_u64(13);
+        let mut live = Vec::new();
+        for moves in 0..8 {
+            live.push(format!("{STATE_MARKER}\n{}{END_STATE_MARKER}\n", save::table_to_spectator_text(&server.table, false)));
+            assert_eq!(server.spectator_snapshot(), live[moves.max(2) - 2]);
+            server.table.play(None, &mut rng);
+        }
+    }
+
+    #[test]
+    fn watchers_are_told_the_mode_and_cant_make_moves() {
+        let mut server = test_server(14);
+        server.caster = true;
+        server.delay = 3;
+        let (stream, client) = loopback();
+        server.connect(5, stream);
+        server.handle_line(5, "watch");
+        let mut client = reader(client);
+
+        let mut line = String::new();
+        client.read_line(&mut line).unwrap();
+        assert_eq!(line, "watching caster 3\n");
+        let text = read_snapshot(&mut client);
+        assert_eq!(text, save::table_to_spectator_text(&server.table, true));
+        server.handle_line(5, "end");
+        client.read_line(&mut line).unwrap();
+        assert!(line.ends_with("error spectators can't make moves\n"));
+    }
+
+    /// The next line the client is sent that starts with `keyword`
+    fn read_line_starting(client: &mut BufReader<TcpStream>, keyword: &str) -> String {
+        client.lines().map(|line| line.unwrap()).find(|line| line.starts_with(keyword)).unwrap()
+    }
+
+    #[test]
+    fn chat_goes_to_the_table_and_live_watchers() {
+        let mut server = test_server(15);
+        server.table.get_bots_mut()[1] = None;
+        let (stream, seated) = loopback();
+        server.connect(4, stream);
+        server.join(4).unwrap();
+        let seat = server.sessions[0].seat;
+        let (stream, watcher) = loopback();
+        server.connect(6, stream);
+        server.handle_line(6, "watch");
+        let (mut seated, mut watcher) = (reader(seated), reader(watcher));
+
+        server.handle_line(4, "chat   anyone have ore?  ");
+        let line = format!("chat {:?} anyone have ore?", seat);
+        assert_eq!(read_line_starting(&mut seated, "chat "), line);
+        assert_eq!(read_line_starting(&mut watcher, "chat "), line);
+        let text = save::table_to_text(&server.table, None);
+        assert!(text.lines().any(|kept| kept == line));
+
+        server.handle_line(4, "chat ");
+        assert_eq!(read_line_starting(&mut seated, "error "), "error nothing to say");
+        server.handle_line(6, "chat me too");
+        assert_eq!(read_line_starting(&mut watcher, "error "), "error spectators can't make moves");
+    }
+
+    #[test]
+    fn each_room_plays_its_own_game() {
+        let mut lobby = Lobby::new(Options::default());
+        let mut clients = Vec::new();
+        for id in 0..2 {
+            let (stream, client) = loopback();
+            lobby.visitors.push(Visitor { id, stream, room: None, seat: None });
+            clients.push(client);
+        }
+        for id in 0..2 {
+            for line in ["create 2 Base", "bot Blue", "ready"] {
+                lobby.handle_line(id, line);
+            }
+        }
+        lobby.handle_line(1, "join 1");
+        lobby.handle_line(0, "chat hi from one");
+        lobby.handle_line(1, "chat hi from two");
+
+        assert_eq!(lobby.rooms.len(), 2);
+        for (id, room) in lobby.rooms.iter().enumerate() {
+            assert_eq!(room.id, id + 1);
+            let game = room.game.as_ref().expect("both rooms were ready");
+            assert_eq!(game.clients.iter().map(|client| client.id).collect::<Vec<_>>(), [id]);
+            assert_eq!(game.sessions.len(), 1);
+            let text = save::table_to_text(&game.table, None);
+            let said: Vec<&str> = text.lines().filter(|line| line.starts_with("chat ")).collect();
+            assert_eq!(said, [if id == 0 {"chat Red hi from one"} else {"chat Red hi from two"}]);
+        }
+        assert_ne!(lobby.rooms[0].game.as_ref().unwrap().sessions[0].token, lobby.rooms[1].game.as_ref().unwrap().sessions[0].token);
+    }
+
+    #[test]
+    fn rooms_close_once_everyone_has_left() {
+        // Without a timeout the seat would wait for its token forever
+        let mut lobby = Lobby::new(Options { timeout: Some(0), ..Options::default() });
+        let (stream, _client) = loopback();
+        lobby.visitors.push(Visitor { id: 3, stream, room: None, seat: None });
+        for line in ["create 2 Base", "bot Blue", "ready"] {
+            lobby.handle_line(3, line);
+        }
+        assert!(lobby.rooms[0].game.as_ref().is_some_and(|game| game.timeout.is_none()));
+
+        lobby.disconnect(3);
+        assert!(lobby.rooms.is_empty());
+    }
+
+    #[test]
+    fn dropped_seats_go_to_a_cpu_until_the_token_comes_back() {
+        let mut server = test_server(8);
+        server.timeout = Some(Duration::ZERO);
+        let (stream, _client) = loopback();
+        server.connect(1, stream);
+        server.join(1).unwrap();
+        let seat = server.sessions[0].seat;
+        let token = server.sessions[0].token.clone();
+        let seat_idx = server.get_seat_idx(seat);
+
+        server.leave(1);
+        server.take_over_seats();
+        assert!(server.table.get_bots()[seat_idx].is_some());
+
+        let (stream, _client) = loopback();
+        server.connect(2, stream);
+        assert!(server.resume(2, "0000000000000000").is_err());
+        server.resume(2, &token).unwrap();
+        assert!(server.table.get_bots()[seat_idx].is_none());
+        let idx = server.get_client_idx(2).unwrap();
+        assert_eq!(server.clients[idx].seat, Some(seat));
+    }
+
+    #[test]
+    fn saved_server_games_keep_their_sessions() {
+        let mut server = test_server(9);
+        let seat = server.table.get_players()[0].get_color();
+        let token = String::from("0123456789abcdef");
+        server.sessions.push(Session { seat, token: token.clone(), away_since: None });
+        let path = std::env::temp_dir().join(format!("catan-server-test-{}.txt", std::process::id()));
+        server.save_path = Some(path.to_string_lossy().into_owned());
+        server.save();
+
+        let loaded = load(server.save_path.as_ref().unwrap());
+        fs::remove_file(&path).unwrap();
+        let (table, sessions) = loaded.unwrap();
+        assert_eq!(save::table_to_text(&table, None), save::table_to_text(&server.table, None));
+        assert_eq!(sessions.len(), 1);
+        assert_eq!((sessions[0].seat, &sessions[0].token), (seat, &token));
+        // Nobody is connected after a restart, so the seat waits for its token
+        assert!(sessions[0].away_since.is_some());
+        assert!(table.get_bots()[0].is_none());
+    }
+
+    #[test]
+    fn sessions_are_only_loaded_for_seats_at_the_table() {
+        let mut server = test_server(11);
+        let seated = server.table.get_players()[0].get_color();
+        let missing = PLAYER_COLORS.into_iter()
+            .find(|&color| server.table.get_players().iter().all(|player| !player.is_color(color)))
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("catan-server-sessions-{}.txt", std::process::id()));
+        server.save_path = Some(path.to_string_lossy().into_owned());
+
+        let mut results = Vec::new();
+        for seats in [[seated, missing], [seated, seated]] {
+            server.sessions = seats.iter()
+                .map(|&seat| Session { seat, token: new_token(), away_since: None })
+                .collect();
+            server.save();
+            results.push(load(server.save_path.as_ref().unwrap()).map(|_| ()));
+        }
+        fs::remove_file(&path).unwrap();
+        assert!(results[0].as_ref().unwrap_err().contains(&format!("nobody plays {:?}", missing)));
+        assert!(results[1].as_ref().unwrap_err().contains("already has a session"));
+    }
+}
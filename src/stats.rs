@@ -0,0 +1,45 @@
+use crate::game::{roll_probability, PlayerColor, ResHand, PLAYER_COLORS};
+
+/// Running totals for one player
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlayerStats {
+    /// Everything the dice have paid this player
+    pub produced: ResHand,
+    pub robber_moves: usize,
+    pub lost_to_robber: usize,
+    pub discarded: usize,
+    pub trades: usize,
+    pub dvs_bought: usize,
+    pub dvs_played: usize,
+}
+
+/// Counters fed as the game goes, for the statistics panel
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// How many times each sum has been rolled, indexed by the sum
+    pub rolls: [usize; 13],
+    players: [PlayerStats; PLAYER_COLORS.len()],
+}
+
+impl Stats {
+    pub fn add_roll(&mut self, sum: usize) {
+        self.rolls[sum] += 1;
+    }
+
+    pub fn num_rolls(&self) -> usize {
+        self.rolls.iter().sum()
+    }
+
+    /// How many times `sum` should have come up by now
+    pub fn expected_rolls(&self, sum: usize) -> f32 {
+        self.num_rolls() as f32 * roll_probability(sum)
+    }
+
+    pub fn get(&self, color: PlayerColor) -> &PlayerStats {
+        &self.players[color as usize]
+    }
+
+    pub fn get_mut(&mut self, color: PlayerColor) -> &mut PlayerStats {
+        &mut self.players[color as usize]
+    }
+}
@@ -0,0 +1,378 @@
+use std::fs;
+
+use macroquad::color::{Color, BEIGE, BLACK, DARKBLUE, DARKGRAY, GOLD, GRAY, LIGHTGRAY, MAROON, SKYBLUE, WHITE, YELLOW};
+
+use crate::game::{pips, Board, Hex, Player, PlayerColor, Port, StructureType, Terrain, IMPROVEMENTS};
+use crate::screen_coords::ScreenCoords;
+use crate::Table;
+
+const SQRT_3: f32 = 1.732050807568877293527446341505872367_f32;
+
+/// Hexes are drawn at a fixed size; an SVG scales to whatever it's shown at
+const HEX_SIZE: f32 = 60.0;
+/// Room around the board for the ports
+const MARGIN: f32 = 1.2 * HEX_SIZE;
+/// Height of a line of the key under the board
+const LINE_HEIGHT: f32 = 0.45 * HEX_SIZE;
+const FONT: &str = "font-family=\"sans-serif\" text-anchor=\"middle\"";
+
+fn paint(color: Color) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(color.r), channel(color.g), channel(color.b))
+}
+
+fn points(points: &[[f32; 2]]) -> String {
+    points.iter().map(|[x, y]| format!("{x:.1},{y:.1}")).collect::<Vec<String>>().join(" ")
+}
+
+fn polygon(corners: &[[f32; 2]], fill: Color, stroke: Color, thickness: f32) -> String {
+    format!(
+        "<polygon points=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{thickness:.1}\"/>\n",
+        points(corners), paint(fill), paint(stroke)
+    )
+}
+
+fn rectangle(x: f32, y: f32, width: f32, height: f32, fill: Color, thickness: f32) -> String {
+    polygon(&[[x, y], [x + width, y], [x + width, y + height], [x, y + height]], fill, BLACK, thickness)
+}
+
+fn circle(center: [f32; 2], radius: f32, fill: Color, stroke: Color, thickness: f32) -> String {
+    let [x, y] = center;
+    format!(
+        "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{radius:.1}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{thickness:.1}\"/>\n",
+        paint(fill), paint(stroke)
+    )
+}
+
+fn line(from: [f32; 2], to: [f32; 2], color: Color, thickness: f32) -> String {
+    format!(
+        "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{thickness:.1}\" stroke-linecap=\"round\"/>\n",
+        from[0], from[1], to[0], to[1], paint(color)
+    )
+}
+
+/// `text` centered on `x`, sitting on `y`
+fn label(x: f32, y: f32, size: f32, color: Color, bold: bool, text: &str) -> String {
+    let weight = if bold {" font-weight=\"bold\""} else {""};
+    format!(
+        "<text x=\"{x:.1}\" y=\"{y:.1}\" font-size=\"{size:.1}\" fill=\"{}\" {FONT}{weight}>{text}</text>\n",
+        paint(color)
+    )
+}
+
+fn hex_svg(origin: &[f32; 2], coords: [usize; 2], hex: Hex) -> String {
+    let [r, q] = coords;
+    let corners: Vec<[f32; 2]> = (0..6)
+        .map(|c| ScreenCoords::calculate_corner(origin, &[r, q, c], HEX_SIZE))
+        .collect();
+    let (fill, stroke) = match hex.terrain {
+        Terrain::Resource(res) => (res.into(), BLACK),
+        Terrain::Gold => (GOLD, BLACK),
+        Terrain::Desert => (YELLOW, BLACK),
+        Terrain::Sea => (SKYBLUE, DARKBLUE),
+    };
+    let mut svg = polygon(&corners, fill, stroke, HEX_SIZE / 20.0);
+    if hex.number == 0 {
+        return svg;
+    }
+
+    // The number token, with a dot under the number for each way two dice can make it
+    let [x, y] = ScreenCoords::calculate_center(origin, &coords, HEX_SIZE);
+    let color = if hex.number == 6 || hex.number == 8 {MAROON} else {BLACK};
+    svg += &circle([x, y], HEX_SIZE / 2.0, BEIGE, BLACK, HEX_SIZE / 30.0);
+    svg += &label(x, y + 0.15 * HEX_SIZE, 0.45 * HEX_SIZE, color, true, &hex.number.to_string());
+    let spacing = 0.09 * HEX_SIZE;
+    let count = pips(hex.number);
+    let start_x = x - 0.5 * spacing * count.saturating_sub(1) as f32;
+    for idx in 0..count {
+        svg += &circle([start_x + idx as f32 * spacing, y + 0.3 * HEX_SIZE], 0.035 * HEX_SIZE, color, color, 0.0);
+    }
+    svg
+}
+
+fn port_svg(origin: &[f32; 2], coords: [usize; 3], port: &Port) -> String {
+    let spot = ScreenCoords::calculate_port(origin, &coords, HEX_SIZE);
+    let [x, y] = ScreenCoords::calculate_port_marker(&spot, HEX_SIZE);
+    let radius = HEX_SIZE / 3.0;
+    let corners: Vec<[f32; 2]> = (0..4)
+        .map(|idx| (spot[2] + 90.0 * idx as f32).to_radians())
+        .map(|angle| [x + radius * angle.cos(), y + radius * angle.sin()])
+        .collect();
+    let (fill, rate) = match port {
+        Port::ThreeForOne => (WHITE, "3:1"),
+        Port::TwoForOne(res) => ((*res).into(), "2:1"),
+    };
+    polygon(&corners, fill, BLACK, HEX_SIZE / 30.0)
+        + &label(x, y + 0.1 * HEX_SIZE, 0.28 * HEX_SIZE, BLACK, true, rate)
+}
+
+/// A road or ship's side, from one of its corners to the other
+fn edge_ends(origin: &[f32; 2], edge: [usize; 3]) -> ([f32; 2], [f32; 2]) {
+    let [r, q, e] = edge;
+    let middle = ScreenCoords::calculate_edge(origin, &edge, HEX_SIZE);
+    let coverage = 0.9;
+    let end = |c: usize| {
+        let [x, y] = ScreenCoords::calculate_corner(origin, &[r, q, c], HEX_SIZE);
+        [middle[0] + coverage * (x - middle[0]), middle[1] + coverage * (y - middle[1])]
+    };
+    (end((e + 5) % 6), end(e))
+}
+
+fn road_svg(origin: &[f32; 2], edge: [usize; 3], color: PlayerColor) -> String {
+    let (from, to) = edge_ends(origin, edge);
+    line(from, to, BLACK, HEX_SIZE / 7.0) + &line(from, to, color.into(), HEX_SIZE / 10.0)
+}
+
+fn ship_svg(origin: &[f32; 2], edge: [usize; 3], color: PlayerColor) -> String {
+    let [x, y] = ScreenCoords::calculate_edge(origin, &edge, HEX_SIZE);
+    let thickness = HEX_SIZE / 30.0;
+    let hull_w = 0.45 * HEX_SIZE;
+    let hull_h = 0.12 * HEX_SIZE;
+    let sail_h = 0.3 * HEX_SIZE;
+    let hull_y = y + 0.5 * hull_h;
+    rectangle(x - 0.5 * hull_w, hull_y, hull_w, hull_h, color.into(), thickness)
+        + &polygon(&[[x, hull_y - sail_h], [x, hull_y], [x + 0.6 * sail_h, hull_y]], WHITE, BLACK, thickness)
+}
+
+fn settlement_svg(corner: [f32; 2], color: PlayerColor) -> String {
+    let base = HEX_SIZE / 2.5;
+    let height = HEX_SIZE / 3.0;
+    let [x, y] = [corner[0] - 0.5 * base, corner[1] - 0.5 * height];
+    polygon(
+        &[[x, y], [x + 0.5 * base, y - 0.5 * height], [x + base, y], [x + base, y + height], [x, y + height]],
+        color.into(), BLACK, HEX_SIZE / 20.0
+    )
+}
+
+fn city_svg(corner: [f32; 2], color: PlayerColor) -> String {
+    let base = HEX_SIZE / 2.0;
+    let height = HEX_SIZE / 4.0;
+    let [x, y] = [corner[0] - 0.5 * base, corner[1] - 0.5 * height];
+    polygon(
+        &[
+            [x, y], [x + 0.5 * base, y], [x + 0.5 * base, y - 0.5 * height], [x + 0.75 * base, y - height],
+            [x + base, y - 0.5 * height], [x + base, y + height], [x, y + height]
+        ],
+        color.into(), BLACK, HEX_SIZE / 20.0
+    )
+}
+
+fn wall_svg(corner: [f32; 2]) -> String {
+    let (width, height) = (0.7 * HEX_SIZE, HEX_SIZE / 8.0);
+    rectangle(corner[0] - 0.5 * width, corner[1] + 0.1 * HEX_SIZE, width, height, DARKGRAY, HEX_SIZE / 20.0)
+}
+
+fn knight_svg(corner: [f32; 2], color: PlayerColor, level: usize, active: bool) -> String {
+    let radius = HEX_SIZE / 4.5;
+    let thickness = HEX_SIZE / 20.0;
+    let fill = if active {color.into()} else {LIGHTGRAY};
+    circle(corner, radius + thickness, fill, BLACK, thickness)
+        + &circle(corner, radius, fill, color.into(), 2.0 * thickness)
+        + &label(corner[0], corner[1] + 0.14 * HEX_SIZE, HEX_SIZE / 2.5, BLACK, true, &level.to_string())
+}
+
+fn robber_svg(center: [f32; 2]) -> String {
+    let [x, y] = center;
+    let thickness = HEX_SIZE / 20.0;
+    let (w1, h1, w2, h2) = (0.4 * HEX_SIZE, 0.8 * HEX_SIZE, 0.6 * HEX_SIZE, 0.25 * HEX_SIZE);
+    rectangle(x - 0.5 * w1, y - h1 + h2, w1, h1, GRAY, thickness)
+        + &rectangle(x - 0.5 * w2, y + 0.5 * h2, w2, h2, GRAY, thickness)
+}
+
+fn merchant_svg(center: [f32; 2], color: PlayerColor) -> String {
+    let [x, y] = [center[0] + 0.45 * HEX_SIZE, center[1]];
+    let (w, h) = (0.15 * HEX_SIZE, 0.3 * HEX_SIZE);
+    polygon(&[[x, y - h], [x - w, y + 0.2 * HEX_SIZE], [x + w, y + 0.2 * HEX_SIZE]], color.into(), BLACK, HEX_SIZE / 20.0)
+}
+
+fn pirate_svg(center: [f32; 2]) -> String {
+    let [x, y] = center;
+    let thickness = HEX_SIZE / 20.0;
+    let (w, h, mast_h) = (0.7 * HEX_SIZE, 0.25 * HEX_SIZE, 0.55 * HEX_SIZE);
+    rectangle(x - 0.5 * w, y, w, h, BLACK, 0.0)
+        + &line([x, y], [x, y - mast_h], BLACK, thickness)
+        + &polygon(
+            &[[x, y - mast_h], [x, y - 0.1 * HEX_SIZE], [x - 0.35 * HEX_SIZE, y - 0.1 * HEX_SIZE]],
+            DARKGRAY, BLACK, thickness
+        )
+}
+
+/// The board, drawn the way the window draws it: hexes and their number
+/// tokens, ports, roads and ships, buildings and their walls, knights, the
+/// robber, the pirate and the merchant. Metropolises get their flag above the city. Returns the
+/// drawing and the size of the box it fills.
+fn board_svg(board: &Board, metropolises: &[([usize; 3], Color)]) -> (String, [f32; 2]) {
+    let layout = &board.layout;
+    let unplaced: Vec<[f32; 2]> = layout.hex_coords.iter()
+        .map(|hex| ScreenCoords::calculate_center(&[0.0, 0.0], hex, HEX_SIZE))
+        .collect();
+    let min_x = unplaced.iter().map(|&[x, _]| x).fold(f32::INFINITY, f32::min);
+    let max_x = unplaced.iter().map(|&[x, _]| x).fold(f32::NEG_INFINITY, f32::max);
+    let min_y = unplaced.iter().map(|&[_, y]| y).fold(f32::INFINITY, f32::min);
+    let max_y = unplaced.iter().map(|&[_, y]| y).fold(f32::NEG_INFINITY, f32::max);
+    let half_width = 0.5 * SQRT_3 * HEX_SIZE;
+    let origin = [MARGIN + half_width - min_x, MARGIN + HEX_SIZE - min_y];
+    let size = [max_x - min_x + 2.0 * (MARGIN + half_width), max_y - min_y + 2.0 * (MARGIN + HEX_SIZE)];
+
+    let mut svg = String::new();
+    for &[r, q] in layout.hex_coords.iter() {
+        if let Some(hex) = board.hexes[r][q] {
+            svg += &hex_svg(&origin, [r, q], hex);
+        }
+    }
+    for (&coords, port) in layout.port_coords.iter().zip(board.ports.iter()) {
+        svg += &port_svg(&origin, coords, port);
+    }
+    for &[r, q, e] in layout.edge_coords.iter() {
+        if let Some(color) = board.roads[r][q][e] {
+            svg += &road_svg(&origin, [r, q, e], color);
+        }
+        if let Some(color) = board.ships[r][q][e] {
+            svg += &ship_svg(&origin, [r, q, e], color);
+        }
+    }
+    for &[r, q, c] in layout.corner_coords.iter() {
+        let corner = ScreenCoords::calculate_corner(&origin, &[r, q, c], HEX_SIZE);
+        if board.has_wall([r, q, c]) {
+            svg += &wall_svg(corner);
+        }
+        if let Some(structure) = board.structures[r][q][c] {
+            svg += &match structure.structure_type {
+                StructureType::Settlement => settlement_svg(corner, structure.color),
+                _ => city_svg(corner, structure.color),
+            };
+        }
+        if let Some(knight) = board.knights[r][q][c] {
+            svg += &knight_svg(corner, knight.color, knight.level, knight.active);
+        }
+        for &(_, track_color) in metropolises.iter().filter(|&&(metropolis, _)| layout.reduce_corner(metropolis) == [r, q, c]) {
+            let flag = HEX_SIZE / 3.0;
+            svg += &rectangle(corner[0] - 0.5 * flag, corner[1] - 1.5 * flag, flag, flag, track_color, flag / 10.0);
+        }
+    }
+    svg += &robber_svg(ScreenCoords::calculate_center(&origin, &board.robber, HEX_SIZE));
+    if let Some(pirate) = board.pirate {
+        svg += &pirate_svg(ScreenCoords::calculate_center(&origin, &pirate, HEX_SIZE));
+    }
+    if let Some((hex, color)) = board.merchant {
+        svg += &merchant_svg(ScreenCoords::calculate_center(&origin, &hex, HEX_SIZE), color);
+    }
+    (svg, size)
+}
+
+/// A line under the board for each seat, as anyone at the table sees it,
+/// then how the turn stands
+fn key_svg(players: &[Player], current: usize, status: &str, top: f32, width: f32) -> String {
+    let mut svg = String::new();
+    for (idx, player) in players.iter().enumerate() {
+        let y = top + LINE_HEIGHT * (idx + 1) as f32;
+        let x = 0.5 * width;
+        let swatch = 0.6 * LINE_HEIGHT;
+        svg += &rectangle(x - 3.0 * HEX_SIZE, y - swatch, swatch, swatch, player.get_color().into(), swatch / 10.0);
+        let text = format!(
+            "{:?}: {} VP, {} cards{}",
+            player.get_color(),
+            player.get_visible_vps(),
+            player.get_hand().size(),
+            if idx == current {", to play"} else {""}
+        );
+        svg += &label(x, y - 0.1 * LINE_HEIGHT, 0.6 * LINE_HEIGHT, BLACK, idx == current, &text);
+    }
+    let y = top + LINE_HEIGHT * (players.len() + 1) as f32;
+    svg + &label(0.5 * width, y - 0.1 * LINE_HEIGHT, 0.6 * LINE_HEIGHT, BLACK, false, status)
+}
+
+/// The table as an SVG picture: the board, and under it a line for each
+/// seat. Nothing about it needs a window, so it can be drawn anywhere.
+pub fn table_to_svg(table: &Table) -> String {
+    let (board, metropolises, players, current, status) = match table {
+        Table::Setup(state) => (&state.board, Vec::new(), &state.players, state.current_player, String::from("Setting up")),
+        Table::Playing(state) => {
+            let metropolises = IMPROVEMENTS.iter().zip(state.metropolises.iter())
+                .filter_map(|(&track, metropolis)| metropolis.map(|(_, corner)| (corner, Color::from(track))))
+                .collect();
+            let status = match (state.winner, state.roll) {
+                (Some(winner), _) => format!("{:?} won", winner),
+                (None, Some([d1, d2])) => format!("Round {}, rolled {}", state.round + 1, d1 + d2),
+                (None, None) => format!("Round {}, not rolled yet", state.round + 1),
+            };
+            (&state.board, metropolises, &state.players, state.current_player, status)
+        },
+    };
+    let (board_svg, [width, board_height]) = board_svg(board, &metropolises);
+    let height = board_height + LINE_HEIGHT * (players.len() + 2) as f32;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\" viewBox=\"0 0 {width:.0} {height:.0}\">\n"
+    );
+    svg += &format!("<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n", paint(WHITE));
+    svg += &board_svg;
+    svg += &key_svg(players, current, &status, board_height, width);
+    svg += "</svg>\n";
+    svg
+}
+
+pub fn save_svg(table: &Table, path: &str) -> Result<(), String> {
+    fs::write(path, table_to_svg(table)).map_err(|err| format!("{path}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::new_game::BoardChoice;
+    use crate::{deal, test_game};
+
+    /// Every `<text>` in the picture, in order
+    fn texts(svg: &str) -> Vec<&str> {
+        svg.split("<text ").skip(1)
+            .map(|text| &text[text.find('>').unwrap() + 1..text.find("</text>").unwrap()])
+            .collect()
+    }
+
+    #[test]
+    fn a_dealt_board_is_drawn_without_a_window() {
+        let options = Options::parse(["--seed", "3", "--svg", "board.svg"].map(String::from).into_iter()).unwrap();
+        let (table, _) = deal(&options).unwrap();
+        let svg = table_to_svg(&table);
+        let Table::Setup(state) = &table else { panic!("expected a game being set up") };
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        let numbers: Vec<String> = state.board.layout.hex_coords.iter()
+            .filter_map(|&[r, q]| state.board.hexes[r][q].filter(|hex| hex.number > 0))
+            .map(|hex| hex.number.to_string())
+            .collect();
+        let texts = texts(&svg);
+        assert_eq!(texts.iter().filter(|text| text.ends_with(":1")).count(), state.board.ports.len());
+        assert!(numbers.iter().all(|number| texts.contains(&number.as_str())));
+        assert_eq!(texts.last(), Some(&"Setting up"));
+        assert!(texts.contains(&"Red: 0 VP, 0 cards, to play"));
+    }
+
+    #[test]
+    fn a_game_in_play_shows_each_seat_and_the_turn() {
+        let state = test_game(BoardChoice::CitiesKnights, 8, 2);
+        let status = match state.roll {
+            Some([d1, d2]) => format!("Round {}, rolled {}", state.round + 1, d1 + d2),
+            None => format!("Round {}, not rolled yet", state.round + 1),
+        };
+        let lines: Vec<String> = state.players.iter()
+            .map(|player| format!("{:?}: {} VP", player.get_color(), player.get_visible_vps()))
+            .collect();
+        let table = Table::Playing(Box::new(state));
+        let svg = table_to_svg(&table);
+        let texts = texts(&svg);
+
+        assert_eq!(texts.last(), Some(&status.as_str()));
+        for line in lines {
+            assert!(texts.iter().any(|text| text.starts_with(&line)), "{line}");
+        }
+
+        let path = std::env::temp_dir().join("catan_svg_test.svg");
+        let path = path.to_str().unwrap();
+        save_svg(&table, path).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), svg);
+        fs::remove_file(path).unwrap();
+        assert!(save_svg(&table, "no/such/dir/board.svg").unwrap_err().starts_with("no/such/dir/board.svg: "));
+    }
+}
@@ -0,0 +1,1189 @@
+use std::io::{self, BufRead, Write};
+use std::mem;
+
+use rand::rngs::StdRng;
+
+use crate::cli::Options;
+use crate::game::{
+    Board, Commodity, DVCard, Improvement, Player, PlayerColor, Port, ProgressCard, ResHand, Resource, Ruleset,
+    StructureType, Terrain, BARBARIAN_TRACK_LEN, COMMODITIES, DV_CARDS, IMPROVEMENTS, PROGRESS_CARDS, RESOURCES
+};
+use crate::dice::{DiceSource, ManualDice};
+use crate::moves::Move;
+use crate::notation::{
+    fit_move, locate, parse_line, parse_move, to_notation, to_played_notation, Notated, Rolled, NOTATION_HELP
+};
+use crate::player_view::PlayerView;
+use crate::save::{self, RECORDED_GAME_PATH, SAVED_GAME_PATH};
+use crate::svg;
+use crate::{deal, Action, MenuButton, Table};
+
+pub const REPL_HELP: &str = "\
+Type moves in notation for whoever is to act; the CPUs move by themselves.
+A line can start with the seat it's for, like `Red: R8 Rd@30 E`.
+
+  board              the board as the seat acting sees it
+  at R,Q  at R,Q,N   what a hex, corner or side is in notation
+  at H9  at @12      and what a number stands for
+  history            every move so far, in lines to type back in
+  svg FILE           draw the game as a picture
+  help, quit";
+
+pub const TUI_HELP: &str = "\
+Hexes are R,Q, as written in each one. A hex's corners count clockwise
+from its top (0) to its upper left (5), and its sides clockwise from its
+upper left (0) to its left (5); any hex a corner or side touches will do.
+
+  roll                              roll the dice
+  build road|ship R,Q,S             or a road from Road Building
+  build settlement|city R,Q,C
+  move ship R,Q,S to R,Q,S
+  buy dv                            draw a development card
+  play knight|road building         a development card
+  play year of plenty RES RES
+  play monopoly RES
+  play CARD [ON]                    a progress card, by name, and what it's
+                                    played on: play engineer R,Q,C,
+                                    play inventor R,Q and R,Q, play spy COLOR
+  trade 4 ore for 1 wood            with the bank or a port
+  robber R,Q                        the robber, or the pirate onto the sea
+  steal COLOR
+  discard 2 wood 1 ore
+  gold 1 wheat                      what a gold hex pays
+  build knight R,Q,C                and activate, promote or chase R,Q,C
+  build wall R,Q,C
+  give 1 wood  give cloth           a gift, or what a Commercial Harbor trades
+  take 2 ore  take CARD             for a Master Merchant or a Spy
+  remove knight R,Q,C               the one a Deserter takes
+  place knight R,Q,C                a deserter, or a knight Intrigue moved
+  improve trade|politics|science
+  pass                              end the turn
+  board, help, quit
+
+Moves in notation, like `Rd@30`, work as well; see catan --repl.";
+
+const RESET: &str = "\x1b[0m";
+const CLEAR: &str = "\x1b[2J\x1b[H";
+const BOLD: &str = "1";
+const DIM: &str = "2";
+const INVERSE: &str = "7";
+const HOT_NUMBER: &str = "1;31";
+
+/// Columns from one hex's center to the next along a row, and rows down to the next row
+const HEX_WIDTH: isize = 8;
+const HEX_HEIGHT: isize = 4;
+/// Where each corner and side of a hex is drawn, from its center
+const CORNER_OFFSETS: [[isize; 2]; 6] = [[0, -3], [4, -1], [4, 1], [0, 3], [-4, 1], [-4, -1]];
+const EDGE_OFFSETS: [[isize; 2]; 6] = [[-2, -2], [2, -2], [4, 0], [2, 2], [-2, 2], [-4, 0]];
+const EDGE_CHARS: [char; 6] = ['/', '\\', '|', '/', '\\', '|'];
+/// Room either side of the board for the ports
+const MARGIN: isize = 12;
+
+fn paint(text: &str, style: &str) -> String {
+    if style.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{style}m{text}{RESET}")
+    }
+}
+
+fn color_style(color: PlayerColor) -> &'static str {
+    match color {
+        PlayerColor::Red => "1;31",
+        PlayerColor::Blue => "1;34",
+        PlayerColor::Orange => "1;38;5;208",
+        PlayerColor::White => "1;97",
+        PlayerColor::Green => "1;32",
+        PlayerColor::Brown => "1;38;5;130",
+    }
+}
+
+/// Inactive knights, in their owner's color without the weight
+fn faded_style(color: PlayerColor) -> &'static str {
+    match color {
+        PlayerColor::Red => "31",
+        PlayerColor::Blue => "34",
+        PlayerColor::Orange => "38;5;208",
+        PlayerColor::White => "37",
+        PlayerColor::Green => "32",
+        PlayerColor::Brown => "38;5;130",
+    }
+}
+
+fn name(color: PlayerColor) -> String {
+    paint(&format!("{:?}", color), color_style(color))
+}
+
+fn resource_name(res: Resource) -> &'static str {
+    match res {
+        Resource::Wood => "wood",
+        Resource::Brick => "brick",
+        Resource::Wheat => "wheat",
+        Resource::Sheep => "sheep",
+        Resource::Ore => "ore",
+    }
+}
+
+fn resource_style(res: Resource) -> &'static str {
+    match res {
+        Resource::Wood => "32",
+        Resource::Brick => "31",
+        Resource::Wheat => "33",
+        Resource::Sheep => "92",
+        Resource::Ore => "37",
+    }
+}
+
+fn terrain_label(terrain: Terrain) -> (&'static str, &'static str) {
+    match terrain {
+        Terrain::Resource(res) => (resource_name(res), resource_style(res)),
+        Terrain::Gold => ("gold", "93"),
+        Terrain::Desert => ("desert", "2;33"),
+        Terrain::Sea => ("sea", "34"),
+    }
+}
+
+fn dv_name(card: DVCard) -> &'static str {
+    match card {
+        DVCard::Knight => "knight",
+        DVCard::RoadBuilding => "road building",
+        DVCard::YearOfPlenty => "year of plenty",
+        DVCard::Monopoly => "monopoly",
+        DVCard::VictoryPoint => "victory point",
+    }
+}
+
+/// A progress card's name the way `play` takes it, like "master merchant"
+fn progress_name(card: ProgressCard) -> String {
+    let mut text = String::new();
+    for ch in format!("{:?}", card).chars() {
+        if ch.is_uppercase() && !text.is_empty() {
+            text.push(' ');
+        }
+        text.push(ch.to_ascii_lowercase());
+    }
+    text
+}
+
+/// Characters in rows, each drawn in its own style
+struct Canvas {
+    cells: Vec<Vec<(char, &'static str)>>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Canvas {
+        Canvas { cells: vec![vec![(' ', ""); width]; height] }
+    }
+
+    fn put(&mut self, x: isize, y: isize, text: &str, style: &'static str) {
+        let Some(row) = usize::try_from(y).ok().and_then(|y| self.cells.get_mut(y)) else { return };
+        for (idx, ch) in text.chars().enumerate() {
+            if let Some(cell) = usize::try_from(x + idx as isize).ok().and_then(|x| row.get_mut(x)) {
+                *cell = (ch, style);
+            }
+        }
+    }
+
+    /// `text` with its middle on column `x`
+    fn put_centered(&mut self, x: isize, y: isize, text: &str, style: &'static str) {
+        self.put(x - text.chars().count() as isize / 2, y, text, style);
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        for row in self.cells.iter() {
+            let len = row.iter().rposition(|&(ch, _)| ch != ' ').map_or(0, |idx| idx + 1);
+            let mut run = String::new();
+            let mut run_style = "";
+            for &(ch, style) in row[..len].iter() {
+                if style != run_style {
+                    text += &paint(&run, run_style);
+                    run.clear();
+                    run_style = style;
+                }
+                run.push(ch);
+            }
+            text += &paint(&run, run_style);
+            text += "\n";
+        }
+        text
+    }
+}
+
+/// What's on a corner: a settlement `S`, a city `C`, a metropolis `M`,
+/// or a knight as its level, bold once it's active
+fn corner_glyph(board: &Board, metropolises: &[[usize; 3]], corner: [usize; 3]) -> (char, &'static str) {
+    let [r, q, c] = corner;
+    if let Some(knight) = board.knights[r][q][c] {
+        let level = char::from_digit(knight.level as u32, 10).unwrap_or('?');
+        return (level, if knight.active {color_style(knight.color)} else {faded_style(knight.color)});
+    }
+    match board.structures[r][q][c] {
+        Some(structure) => {
+            let reduced = board.layout.reduce_corner(corner);
+            let glyph = if metropolises.iter().any(|&metropolis| board.layout.reduce_corner(metropolis) == reduced) {
+                'M'
+            } else if structure.structure_type == StructureType::City {
+                'C'
+            } else {
+                'S'
+            };
+            (glyph, color_style(structure.color))
+        },
+        None => ('.', DIM)
+    }
+}
+
+fn port_label(port: &Port) -> (String, &'static str) {
+    match port {
+        Port::ThreeForOne => (String::from("3:1"), BOLD),
+        Port::TwoForOne(res) => (format!("{} 2:1", &resource_name(*res)[..2]), resource_style(*res)),
+    }
+}
+
+/// The board in text. Each hex shows its terrain, number and coordinates,
+/// and the robber or pirate if they're on it. A port is written in the sea
+/// hex it faces, or outside its side when there's none.
+fn board_text(board: &Board, metropolises: &[[usize; 3]]) -> String {
+    let layout = &board.layout;
+    let [rows, cols] = layout.size();
+    let center = |[r, q]: [usize; 2]| -> [isize; 2] {
+        [MARGIN + 4 + HEX_WIDTH * q as isize + HEX_WIDTH / 2 * r as isize, 4 + HEX_HEIGHT * r as isize]
+    };
+    let width = MARGIN * 2 + 9 + HEX_WIDTH * cols as isize + HEX_WIDTH / 2 * rows as isize;
+    let height = 9 + HEX_HEIGHT * rows.saturating_sub(1) as isize;
+    let mut canvas = Canvas::new(width as usize, height as usize);
+
+    for (&[r, q, e], port) in layout.port_coords.iter().zip(board.ports.iter()) {
+        let [x, y] = center([r, q]);
+        let (label, style) = port_label(port);
+        let len = label.chars().count() as isize;
+        if let Some(sea) = layout.edge_hexes([r, q, e]).find(|&hex| board.is_sea(hex)) {
+            let [x, y] = center(sea);
+            canvas.put_centered(x, y, &label, style);
+            continue;
+        }
+        match e {
+            0 => canvas.put(x - len, y - 4, &label, style),
+            1 => canvas.put(x + 1, y - 4, &label, style),
+            2 => canvas.put(x + 6, y, &label, style),
+            3 => canvas.put(x + 1, y + 4, &label, style),
+            4 => canvas.put(x - len, y + 4, &label, style),
+            _ => canvas.put(x - 6 - len, y, &label, style),
+        }
+    }
+    for &[r, q] in layout.hex_coords.iter() {
+        let [x, y] = center([r, q]);
+        let Some(hex) = board.hexes[r][q] else { continue };
+        let (label, style) = terrain_label(hex.terrain);
+        canvas.put_centered(x, y - 1, label, style);
+        if hex.number > 0 {
+            let style = if matches!(hex.number, 6 | 8) {HOT_NUMBER} else {BOLD};
+            canvas.put_centered(x, y, &hex.number.to_string(), style);
+        }
+        if board.robber == [r, q] {
+            canvas.put_centered(x, y + 1, "robber", INVERSE);
+        } else if board.pirate == Some([r, q]) {
+            canvas.put_centered(x, y + 1, "pirate", INVERSE);
+        }
+        canvas.put_centered(x, y + 2, &format!("{r},{q}"), DIM);
+    }
+    for &[r, q] in layout.hex_coords.iter() {
+        let [x, y] = center([r, q]);
+        for e in 0..6 {
+            let [dx, dy] = EDGE_OFFSETS[e];
+            let (glyph, style) = match (board.roads[r][q][e], board.ships[r][q][e]) {
+                (Some(color), _) => (EDGE_CHARS[e], color_style(color)),
+                (None, Some(color)) => ('~', color_style(color)),
+                (None, None) => (EDGE_CHARS[e], DIM),
+            };
+            canvas.put(x + dx, y + dy, &glyph.to_string(), style);
+        }
+        for (c, [dx, dy]) in CORNER_OFFSETS.into_iter().enumerate() {
+            let (glyph, style) = corner_glyph(board, metropolises, [r, q, c]);
+            canvas.put(x + dx, y + dy, &glyph.to_string(), style);
+        }
+    }
+    // Trim the margin nothing was drawn in
+    let left = canvas.cells.iter().filter_map(|row| row.iter().position(|&(ch, _)| ch != ' ')).min().unwrap_or(0);
+    for row in canvas.cells.iter_mut() {
+        row.drain(..left);
+    }
+    canvas.cells.retain(|row| row.iter().any(|&(ch, _)| ch != ' '));
+    canvas.to_text()
+        + &paint("S settlement  C city  M metropolis  1-3 knight  ~ ship", DIM)
+        + "\n"
+}
+
+/// Every seat at a glance, as the view's seat sees it
+fn summary_text(view: &PlayerView) -> String {
+    let mut text = String::new();
+    for seat in view.get_opponents() {
+        let color = seat.color;
+        let marker = if color == view.get_current_color() {"> "} else {"  "};
+        text += &format!(
+            "{marker}{}{:width$} {:>2} VP  {:>2} cards  ",
+            name(color),
+            if seat.is_human {""} else {" (cpu)"},
+            seat.vps,
+            seat.cards.resources,
+            width = 14 - format!("{:?}", color).len()
+        );
+        if view.get_ruleset() == Ruleset::CitiesKnights {
+            text += &format!("{} progress", seat.cards.progress_cards);
+        } else {
+            text += &format!("{} dv  {} knights", seat.cards.dvs, seat.knights);
+        }
+        text += &format!("  road {}", seat.road_len);
+        if view.get_longest_road() == Some(color) {
+            text += "  Longest Road";
+        }
+        if view.get_largest_army() == Some(color) {
+            text += "  Largest Army";
+        }
+        text += "\n";
+    }
+    if let Some([d1, d2]) = view.get_roll() {
+        text += &format!("Rolled {d1} + {d2} = {}", d1 + d2);
+        if let Some(event) = view.get_event() {
+            text += &format!(", event {:?}", event);
+        }
+        text += "\n";
+    }
+    if view.get_ruleset() == Ruleset::CitiesKnights {
+        text += &format!("Barbarians {}/{}\n", view.get_barbarians(), BARBARIAN_TRACK_LEN);
+    }
+    text
+}
+
+/// The view's own cards, with what each resource trades at
+fn hand_lines(view: &PlayerView) -> String {
+    let color = view.get_viewer();
+    let Some(player) = view.get_own_player() else { return String::new() };
+    let hand = player.get_hand();
+    let cards: Vec<String> = RESOURCES.iter()
+        .map(|&res| format!(
+            "{} {} ({}:1)",
+            paint(resource_name(res), resource_style(res)),
+            hand[res],
+            view.trade_rate(color, res)
+        ))
+        .collect();
+    let mut text = format!("Hand: {}\n", cards.join("  "));
+
+    let playable = player.get_dvs();
+    let all_dvs = player.get_combined_dvs();
+    let dvs: Vec<String> = DV_CARDS.iter()
+        .filter(|&&card| all_dvs[card] > 0)
+        .map(|&card| match all_dvs[card] - playable[card] {
+            0 => format!("{} {}", all_dvs[card], dv_name(card)),
+            new => format!("{} {} ({new} new)", all_dvs[card], dv_name(card)),
+        })
+        .collect();
+    if !dvs.is_empty() {
+        text += &format!("Development cards: {}\n", dvs.join(", "));
+    }
+    if view.get_ruleset() == Ruleset::CitiesKnights {
+        let commodities = player.get_commodities();
+        let commodities: Vec<String> = COMMODITIES.iter()
+            .map(|&com| format!("{} {}", format!("{:?}", com).to_lowercase(), commodities[com]))
+            .collect();
+        let tracks: Vec<String> = IMPROVEMENTS.iter()
+            .map(|&track| format!("{} {}", format!("{:?}", track).to_lowercase(), player.get_improvement(track)))
+            .collect();
+        text += &format!("Commodities: {}  Improvements: {}\n", commodities.join("  "), tracks.join("  "));
+        let progress: Vec<String> = player.get_progress_counts().iter()
+            .map(|&(card, count)| format!("{count} {}", progress_name(card)))
+            .collect();
+        if !progress.is_empty() {
+            text += &format!("Progress cards: {}\n", progress.join(", "));
+        }
+    }
+    text
+}
+
+/// The command a menu button stands for
+fn button_command(button: MenuButton) -> Option<String> {
+    Some(String::from(match button {
+        MenuButton::BuyDV => "buy dv",
+        MenuButton::Knight => "build knight R,Q,C",
+        MenuButton::ActivateKnight => "activate R,Q,C",
+        MenuButton::PromoteKnight => "promote R,Q,C",
+        MenuButton::ChaseRobber => "chase R,Q,C",
+        MenuButton::Road => "build road R,Q,S",
+        MenuButton::Ship => "build ship R,Q,S",
+        MenuButton::MoveShip => "move ship R,Q,S to R,Q,S",
+        MenuButton::Settlement => "build settlement R,Q,C",
+        MenuButton::City => "build city R,Q,C",
+        MenuButton::Wall => "build wall R,Q,C",
+        MenuButton::Improve(track) => return Some(format!("improve {}", format!("{:?}", track).to_lowercase())),
+        MenuButton::RecordDV(_) => return None,
+        MenuButton::Pass => "pass",
+    }))
+}
+
+/// What a progress card is played on, as `play` takes it
+fn progress_args(card: ProgressCard) -> &'static str {
+    match card {
+        ProgressCard::Alchemist => " DIE DIE",
+        ProgressCard::Crane => " TRACK",
+        ProgressCard::Engineer | ProgressCard::Medicine | ProgressCard::Intrigue => " R,Q,C",
+        ProgressCard::Diplomat => " R,Q,S",
+        ProgressCard::Inventor => " R,Q and R,Q",
+        ProgressCard::Merchant | ProgressCard::Bishop => " R,Q",
+        ProgressCard::ResourceMonopoly | ProgressCard::MerchantFleet => " RES",
+        ProgressCard::TradeMonopoly => " paper|cloth|coin",
+        ProgressCard::MasterMerchant | ProgressCard::Deserter | ProgressCard::Spy => " COLOR",
+        _ => ""
+    }
+}
+
+/// What the seat acting now can do, as commands to type
+fn menu(table: &Table) -> Vec<String> {
+    let state = match table {
+        Table::Setup(state) => {
+            let mut commands = Vec::new();
+            if state.settlement.is_none() {
+                commands.push(String::from("build settlement R,Q,C"));
+            } else {
+                commands.push(String::from("build road R,Q,S"));
+                if state.ruleset == Ruleset::Seafarers {
+                    commands.push(String::from("build ship R,Q,S"));
+                }
+            }
+            return commands;
+        },
+        Table::Playing(state) => state,
+    };
+    let color = state.get_current_color();
+    let player = state.get_current_player();
+    let mut commands = Vec::new();
+    match state.action {
+        Action::Discarding => commands.push(format!("discard {} cards, like discard 2 wood 1 ore", player.get_hand().size() / 2)),
+        Action::ChoosingGold => commands.push(format!("gold {} cards, like gold 1 wheat", state.gold_owed[state.current_player])),
+        Action::MovingRobber => commands.push(String::from("robber R,Q")),
+        Action::ChoosingVictim | Action::ChoosingPirateVictim => {
+            let victims: Vec<PlayerColor> = state.players.iter()
+                .map(|player| player.get_color())
+                .filter(|&victim| state.can_make_move(&Move::Steal(victim)))
+                .collect();
+            let names: Vec<String> = victims.iter().map(|&victim| format!("{:?}", victim).to_lowercase()).collect();
+            commands.push(format!("steal {}", names.join("|")));
+        },
+        Action::RoadBuilding(_) => commands.push(String::from("build road R,Q,S for free")),
+        Action::PromotingKnight(Some(_)) => commands.push(String::from("promote R,Q,C for free")),
+        Action::Giving => commands.push(format!("give {} cards, like give 1 wood 1 ore", state.cards_to_give())),
+        Action::Sabotaged => commands.push(format!("discard {} cards, like discard 1 wood", player.get_hand().size() / 2)),
+        Action::HarborOffering(partner) => commands.push(format!("give {:?} 1 card, like give 1 wood", partner)),
+        Action::HarborReturning => {
+            let commodities = player.get_commodities();
+            let names: Vec<String> = COMMODITIES.iter()
+                .filter(|&&com| commodities[com] > 0)
+                .map(|&com| format!("{:?}", com).to_lowercase())
+                .collect();
+            commands.push(format!("give {}", names.join("|")));
+        },
+        Action::Taking(victim) => {
+            let hand = state.get_player(victim).unwrap().get_hand();
+            let cards: Vec<String> = RESOURCES.iter()
+                .filter(|&&res| hand[res] > 0)
+                .map(|&res| format!("{} {}", hand[res], resource_name(res)))
+                .collect();
+            commands.push(format!("take {} of {:?}'s {}", state.cards_to_give(), victim, cards.join(" ")));
+        },
+        Action::Spying(victim) => {
+            let cards: Vec<String> = state.get_player(victim).unwrap().get_progress_counts().iter()
+                .map(|&(card, _)| progress_name(card))
+                .collect();
+            commands.push(format!("take {}", cards.join("|")));
+        },
+        Action::Deserting => commands.push(String::from("remove knight R,Q,C")),
+        Action::PlacingDeserter(_) | Action::Displaced(..) => commands.push(String::from("place knight R,Q,C")),
+        _ => {
+            if state.can_make_move(&Move::Roll) {
+                commands.push(String::from("roll"));
+            }
+            let menu = state.get_menu();
+            for (button, available) in menu.into_iter().zip(state.get_available_actions(color)) {
+                if let (true, Some(command)) = (available, button_command(button)) {
+                    if button != MenuButton::Pass {
+                        commands.push(command);
+                    }
+                }
+            }
+            let wood = ResHand::from_monopoly(Resource::Wood, 2);
+            let cards = [
+                (Move::PlayDV(DVCard::Knight), "play knight"),
+                (Move::PlayDV(DVCard::RoadBuilding), "play road building"),
+                (Move::YearOfPlenty(wood), "play year of plenty RES RES"),
+                (Move::Monopoly(Resource::Wood), "play monopoly RES"),
+            ];
+            for (mv, command) in cards {
+                if state.can_make_move(&mv) {
+                    commands.push(command.to_string());
+                }
+            }
+            for (card, _) in player.get_progress_counts() {
+                if state.can_play_progress_card(card) && state.selector.is_none() {
+                    commands.push(format!("play {}{}", progress_name(card), progress_args(card)));
+                }
+            }
+            if state.roll.is_some() && !state.special_building {
+                commands.push(String::from("trade GIVE for GET"));
+            }
+            if state.can_make_move(&Move::Pass) {
+                commands.push(String::from("pass"));
+            }
+        }
+    }
+    commands
+}
+
+fn screen(table: &Table, viewer: PlayerColor) -> String {
+    match table {
+        Table::Setup(state) => {
+            let mut text = board_text(&state.board, &[]);
+            let order: Vec<String> = state.players.iter().enumerate()
+                .map(|(idx, player)| {
+                    let marker = if idx == state.current_player {">"} else {""};
+                    format!("{marker}{}", name(player.get_color()))
+                })
+                .collect();
+            text += &format!("Setup: {}\n", order.join(" "));
+            text
+        },
+        Table::Playing(state) => {
+            let view = PlayerView::new(state, viewer);
+            let metropolises: Vec<[usize; 3]> = view.get_metropolises().iter().flatten().map(|&(_, corner)| corner).collect();
+            board_text(view.get_board(), &metropolises) + &summary_text(&view) + &hand_lines(&view)
+        },
+    }
+}
+
+enum Command {
+    Play(Move),
+    Board,
+    Help,
+    Quit,
+}
+
+fn parse_resource(word: &str) -> Option<Resource> {
+    let word = word.to_lowercase();
+    let word = match word.as_str() {
+        "lumber" => "wood",
+        "grain" => "wheat",
+        "wool" => "sheep",
+        word => word,
+    };
+    RESOURCES.iter().copied()
+        .find(|&res| word == resource_name(res) || word.strip_suffix('s') == Some(resource_name(res)))
+}
+
+/// Cards written like `2 wood 1 ore` or `wheat sheep`, one of each if no count is given
+fn parse_cards(words: &[&str]) -> Result<ResHand, String> {
+    let mut hand = ResHand::new();
+    let mut count = None;
+    for &word in words {
+        if word == "and" {
+            continue;
+        }
+        if let Ok(number) = word.parse() {
+            count = Some(number);
+        } else {
+            let res = parse_resource(word).ok_or(format!("unknown resource '{word}'"))?;
+            hand[res] += count.take().unwrap_or(1);
+        }
+    }
+    Ok(hand)
+}
+
+fn parse_numbers<const N: usize>(words: &[&str]) -> Result<[usize; N], String> {
+    if words.len() != N {
+        return Err(format!("expected {N} numbers, not '{}'", words.join(" ")));
+    }
+    save::parse_coords(words)
+}
+
+fn parse_hex(board: &Board, words: &[&str]) -> Result<[usize; 2], String> {
+    let hex = parse_numbers(words)?;
+    if !board.layout.is_on_board(hex) {
+        return Err(format!("there's no hex {},{}", hex[0], hex[1]));
+    }
+    Ok(hex)
+}
+
+/// A corner given from any hex it touches, in its canonical form
+fn parse_corner(board: &Board, words: &[&str]) -> Result<[usize; 3], String> {
+    let [r, q, c] = parse_numbers(words)?;
+    if !board.layout.is_on_board([r, q]) || c >= 6 {
+        return Err(format!("there's no corner {r},{q},{c}"));
+    }
+    Ok(board.layout.reduce_corner([r, q, c]))
+}
+
+fn parse_edge(board: &Board, words: &[&str]) -> Result<[usize; 3], String> {
+    let [r, q, e] = parse_numbers(words)?;
+    if !board.layout.is_on_board([r, q]) || e >= 6 {
+        return Err(format!("there's no side {r},{q},{e}"));
+    }
+    Ok(board.layout.reduce_edge([r, q, e]))
+}
+
+fn parse_track(word: &str) -> Result<Improvement, String> {
+    IMPROVEMENTS.iter().copied()
+        .find(|&improvement: &Improvement| format!("{:?}", improvement).eq_ignore_ascii_case(word))
+        .ok_or(format!("unknown track '{word}'"))
+}
+
+fn parse_commodity(word: &str) -> Result<Commodity, String> {
+    COMMODITIES.iter().copied()
+        .find(|&com| format!("{:?}", com).eq_ignore_ascii_case(word))
+        .ok_or(format!("unknown commodity '{word}'"))
+}
+
+/// The progress card a line starts with, by name or label, and the words after it.
+/// The longest name wins, so "merchant fleet" isn't read as the Merchant.
+fn parse_progress_name<'a, 'b>(words: &'a [&'b str]) -> Result<(ProgressCard, &'a [&'b str]), String> {
+    PROGRESS_CARDS.iter().copied()
+        .filter_map(|card| {
+            let name = progress_name(card);
+            let name: Vec<&str> = name.split(' ').collect();
+            if words.starts_with(&name) {
+                Some((card, &words[name.len()..]))
+            } else if words.first().is_some_and(|word| card.into_label().eq_ignore_ascii_case(word)) {
+                Some((card, &words[1..]))
+            } else {
+                None
+            }
+        })
+        .min_by_key(|(_, rest)| rest.len())
+        .ok_or(format!("unknown progress card '{}'", words.join(" ")))
+}
+
+/// A progress card and what it's played on
+fn parse_progress_play(board: &Board, words: &[&str]) -> Result<Move, String> {
+    let (card, args) = parse_progress_name(words)?;
+    let first = || args.first().copied().ok_or(format!("what is the {} played on?", progress_name(card)));
+    Ok(match card {
+        ProgressCard::Alchemist => {
+            let dice = parse_numbers(args)?;
+            if !dice.iter().all(|die| (1..=6).contains(die)) {
+                return Err(String::from("dice only come up 1 to 6"));
+            }
+            Move::Alchemist(dice)
+        },
+        ProgressCard::Crane => Move::Crane(parse_track(first()?)?),
+        ProgressCard::Engineer => Move::Engineer(parse_corner(board, args)?),
+        ProgressCard::Medicine => Move::Medicine(parse_corner(board, args)?),
+        ProgressCard::Intrigue => Move::Intrigue(parse_corner(board, args)?),
+        ProgressCard::Diplomat => Move::Diplomat(parse_edge(board, args)?),
+        ProgressCard::Inventor => {
+            let args: Vec<&str> = args.iter().copied().filter(|&word| word != "and").collect();
+            if args.len() != 4 {
+                return Err(String::from("play inventor R,Q and R,Q"));
+            }
+            Move::Inventor(parse_hex(board, &args[..2])?, parse_hex(board, &args[2..])?)
+        },
+        ProgressCard::Merchant => Move::Merchant(parse_hex(board, args)?),
+        ProgressCard::Bishop => Move::Bishop(parse_hex(board, args)?),
+        ProgressCard::ResourceMonopoly | ProgressCard::MerchantFleet => {
+            let word = first()?;
+            let res = parse_resource(word).ok_or(format!("unknown resource '{word}'"))?;
+            if card == ProgressCard::ResourceMonopoly { Move::ResourceMonopoly(res) } else { Move::MerchantFleet(res) }
+        },
+        ProgressCard::TradeMonopoly => Move::TradeMonopoly(parse_commodity(first()?)?),
+        ProgressCard::MasterMerchant => Move::MasterMerchant(save::parse_color(Some(&first()?))?),
+        ProgressCard::Deserter => Move::Deserter(save::parse_color(Some(&first()?))?),
+        ProgressCard::Spy => Move::Spy(save::parse_color(Some(&first()?))?),
+        _ => Move::PlayProgress(card)
+    })
+}
+
+/// The card played with `play`, named by the rest of the line
+fn parse_play(table: &Table, words: &[&str]) -> Result<Move, String> {
+    let (ruleset, board) = match table {
+        Table::Setup(state) => (state.ruleset, &state.board),
+        Table::Playing(state) => (state.ruleset, &state.board),
+    };
+    if ruleset == Ruleset::CitiesKnights {
+        return parse_progress_play(board, words);
+    }
+    // Card names run up to the first resource
+    let split = words.iter().position(|word| parse_resource(word).is_some()).unwrap_or(words.len());
+    let (card, resources) = words.split_at(split);
+    let card = card.join(" ");
+    let resource = || resources.first().and_then(|word| parse_resource(word)).ok_or(String::from("which resource?"));
+    Ok(match card.as_str() {
+        "knight" => Move::PlayDV(DVCard::Knight),
+        "road building" => Move::PlayDV(DVCard::RoadBuilding),
+        "year of plenty" | "yop" => Move::YearOfPlenty(parse_cards(resources)?),
+        "monopoly" => Move::Monopoly(resource()?),
+        _ => return Err(format!("unknown development card '{card}'"))
+    })
+}
+
+fn parse_command(line: &str, table: &Table) -> Result<Command, String> {
+    let lower = line.to_lowercase().replace(',', " ");
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let (board, state) = match table {
+        Table::Setup(state) => (&state.board, None),
+        Table::Playing(state) => (&state.board, Some(state)),
+    };
+    let road_building = state.is_some_and(|state| matches!(state.action, Action::RoadBuilding(_)));
+    let free_knight = state.is_some_and(|state| matches!(state.action, Action::PlacingDeserter(_) | Action::Displaced(..)));
+    let mv = match words.as_slice() {
+        [] | ["board"] => return Ok(Command::Board),
+        ["help"] | ["?"] => return Ok(Command::Help),
+        ["quit"] | ["exit"] => return Ok(Command::Quit),
+        ["roll"] => Move::Roll,
+        ["pass"] | ["end"] | ["done"] => Move::Pass,
+        ["buy", ..] => Move::BuyDV,
+        ["build", "road", rest @ ..] => {
+            let edge = parse_edge(board, rest)?;
+            match state {
+                None => Move::PlaceRoad(edge),
+                Some(_) if road_building => Move::FreeRoad(edge),
+                Some(_) => Move::BuildRoad(edge),
+            }
+        },
+        ["build", "ship", rest @ ..] => {
+            let edge = parse_edge(board, rest)?;
+            if state.is_some() { Move::BuildShip(edge) } else { Move::PlaceShip(edge) }
+        },
+        ["build", "settlement", rest @ ..] => {
+            let corner = parse_corner(board, rest)?;
+            if state.is_some() { Move::BuildSettlement(corner) } else { Move::PlaceSettlement(corner) }
+        },
+        ["build", "city", rest @ ..] => Move::BuildCity(parse_corner(board, rest)?),
+        ["build", "knight", rest @ ..] | ["place", "knight", rest @ ..] => {
+            let corner = parse_corner(board, rest)?;
+            if free_knight { Move::PlaceKnight(corner) } else { Move::BuildKnight(corner) }
+        },
+        ["remove", "knight", rest @ ..] => Move::RemoveKnight(parse_corner(board, rest)?),
+        ["build", "wall", rest @ ..] => Move::BuildWall(parse_corner(board, rest)?),
+        ["activate", rest @ ..] => Move::ActivateKnight(parse_corner(board, rest)?),
+        ["promote", rest @ ..] => Move::PromoteKnight(parse_corner(board, rest)?),
+        ["chase", rest @ ..] => Move::ChaseRobber(parse_corner(board, rest)?),
+        ["move", "ship", rest @ ..] => {
+            let to = rest.iter().position(|&word| word == "to").ok_or("move ship FROM to TO")?;
+            Move::ShiftShip(parse_edge(board, &rest[..to])?, parse_edge(board, &rest[to + 1..])?)
+        },
+        ["move", "robber" | "pirate", rest @ ..] | ["robber" | "pirate", rest @ ..] => Move::PlaceRobber(parse_hex(board, rest)?),
+        ["steal", color] => Move::Steal(save::parse_color(Some(color))?),
+        ["discard", rest @ ..] => Move::Discard(parse_cards(rest)?),
+        ["gold", rest @ ..] => Move::ChooseGold(parse_cards(rest)?),
+        ["give", word] if parse_commodity(word).is_ok() => Move::GiveCommodity(parse_commodity(word)?),
+        ["give", rest @ ..] => Move::Give(parse_cards(rest)?),
+        ["take", rest @ ..] => match parse_cards(rest) {
+            Ok(hand) => Move::Take(hand),
+            Err(err) => match parse_progress_name(rest) {
+                Ok((card, [])) => Move::TakeProgress(card),
+                _ => return Err(err)
+            }
+        },
+        ["trade", rest @ ..] => {
+            let split = rest.iter().position(|&word| word == "for").ok_or("trade GIVE for GET")?;
+            Move::BankTrade(parse_cards(&rest[..split])?, parse_cards(&rest[split + 1..])?)
+        },
+        ["improve", track] => Move::Improve(parse_track(track)?),
+        ["play", rest @ ..] => parse_play(table, rest)?,
+        [word, ..] => parse_move(table, line).map_err(|_| format!("unknown command '{word}'; try help"))?,
+    };
+    Ok(Command::Play(mv))
+}
+
+/// The seats played from this terminal
+fn humans(players: &[Player]) -> Vec<PlayerColor> {
+    players.iter().filter(|player| player.is_human()).map(|player| player.get_color()).collect()
+}
+
+fn read_line(lines: &mut impl Iterator<Item = io::Result<String>>) -> Result<Option<String>, String> {
+    io::stdout().flush().map_err(|err| err.to_string())?;
+    lines.next().transpose().map_err(|err| err.to_string())
+}
+
+/// Plays a game in the terminal: a new one from the options, or `--load`ed.
+/// The board is drawn in text, the CPUs move between prompts, and every
+/// human seat types its moves. Several humans hand the keyboard round, and
+/// the screen is cleared in between. Saved at the start of every turn like
+/// a game in the window.
+pub fn play(options: &Options) -> Result<(), String> {
+    let (mut table, mut rng) = deal(options)?;
+    let humans = humans(table.get_players());
+    if humans.is_empty() {
+        return Err(String::from("--tui needs a human seat; --headless plays without one"));
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut viewer = None;
+    let mut seen_log = 0;
+    let mut last_saved = None;
+    let mut message = String::new();
+    loop {
+        if let Table::Playing(state) = &table {
+            let turn = (state.round, state.turn_player);
+            if (state.is_turn_start() && last_saved != Some(turn)) || state.winner.is_some() {
+                if let Err(err) = save::save(state, SAVED_GAME_PATH) {
+                    eprintln!("Warning: game not saved: {}", err);
+                }
+                if let Err(err) = save::record(state, RECORDED_GAME_PATH, last_saved.is_none()) {
+                    eprintln!("Warning: game not recorded: {}", err);
+                }
+                last_saved = Some(turn);
+            }
+        }
+        if !table.is_over() && table.get_current_bot().is_some() {
+            table.play(None, &mut rng);
+            continue;
+        }
+
+        let color = if table.is_over() {viewer.unwrap_or(humans[0])} else {table.get_current_color()};
+        if humans.len() > 1 && viewer.is_some_and(|viewer| viewer != color) && !table.is_over() {
+            print!("{CLEAR}Pass the keyboard to {} and press Enter ", name(color));
+            if read_line(&mut lines)?.is_none() {
+                return Ok(());
+            }
+            print!("{CLEAR}");
+        }
+        viewer = Some(color);
+
+        print!("\n{}", screen(&table, color));
+        if let Table::Playing(state) = &table {
+            let view = PlayerView::new(state, color);
+            let log = view.get_log();
+            let mut entries = log.recent(Some(color), 0, log.size().saturating_sub(seen_log));
+            entries.reverse();
+            for entry in entries {
+                println!("  {entry}");
+            }
+            seen_log = log.size();
+            if let Some(winner) = state.winner {
+                println!("{} wins!", name(winner));
+                return Ok(());
+            }
+        }
+        if !message.is_empty() {
+            println!("{message}");
+        }
+        println!("You can: {}", menu(&table).join("; "));
+        print!("{}> ", name(color));
+        let Some(line) = read_line(&mut lines)? else { return Ok(()) };
+
+        message = match parse_command(&line, &table) {
+            Ok(Command::Play(mv)) => if table.can_make_move(&mv) {
+                table.play(Some(&mv), &mut rng);
+                String::new()
+            } else {
+                format!("'{}' can't be played now", line.trim())
+            },
+            Ok(Command::Board) => String::new(),
+            Ok(Command::Help) => TUI_HELP.to_string(),
+            Ok(Command::Quit) => return Ok(()),
+            Err(err) => err
+        };
+    }
+}
+
+/// Makes a move read from notation. A roll written with its dice comes
+/// up that way. One written with just its total is split between the
+/// dice, except under Cities & Knights, where the red die counts.
+fn play_notated(table: &mut Table, notated: Notated, rng: &mut StdRng) -> Result<Move, String> {
+    let mv = fit_move(table, notated.mv);
+    if !table.can_make_move(&mv) {
+        return Err(String::from("can't be played now"));
+    }
+    match (notated.roll, &mut *table) {
+        (Some(rolled), Table::Playing(state)) => {
+            let entered = match rolled {
+                Rolled::Dice(dice) => dice,
+                Rolled::Total(_) if state.ruleset == Ruleset::CitiesKnights =>
+                    return Err(String::from("needs both dice under Cities & Knights, like R3+5")),
+                Rolled::Total(total) => [total / 2, total - total / 2],
+            };
+            let total = entered[0] + entered[1];
+            let fair = entered.iter().all(|die| (1..=6).contains(die));
+            if !fair || (total == 7 && state.round < state.rules.no_sevens_rounds) {
+                return Err(format!("can't come up {total}"));
+            }
+            // The real dice are still thrown, so the rest of the game draws
+            // the same cards and steals the same ones as when it was written
+            state.dice.roll(rng);
+            let mut dice: Box<dyn DiceSource> = Box::new(ManualDice::default());
+            dice.enter(entered);
+            let dice = mem::replace(&mut state.dice, dice);
+            state.apply_move(&mv, rng);
+            state.dice = dice;
+        },
+        _ => table.play(Some(&mv), rng),
+    }
+    Ok(mv)
+}
+
+/// Moves in notation, a line for each run of them one seat made
+fn history_text(history: &[(PlayerColor, String)]) -> String {
+    let mut text = String::new();
+    let mut last = None;
+    for &(color, ref mv) in history {
+        if last == Some(color) {
+            text += &format!(" {mv}");
+        } else {
+            if last.is_some() {
+                text += "\n";
+            }
+            text += &format!("{:?}: {mv}", color);
+            last = Some(color);
+        }
+    }
+    text
+}
+
+/// Plays a game typed in notation, a line at a time, for working through
+/// a position or writing one down for a bug report. It's set up like a
+/// game in the terminal, and the CPUs still move by themselves, but
+/// nothing is saved. `history` prints the game so far in lines that can
+/// be typed back in.
+pub fn repl(options: &Options) -> Result<(), String> {
+    let (mut table, mut rng) = deal(options)?;
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut history: Vec<(PlayerColor, String)> = Vec::new();
+    let mut seen_log = 0;
+    print!("{}", screen(&table, table.get_current_color()));
+    loop {
+        let first_new = history.len();
+        while !table.is_over() && table.get_current_bot().is_some() {
+            let color = table.get_current_color();
+            // The game's own randomness is kept for the game, so typing the
+            // moves back in plays out the same
+            let mv = table.get_bot_move(&mut rand::rng());
+            table.play(Some(&mv), &mut rng);
+            history.push((color, to_played_notation(&table, &mv)));
+        }
+        if history.len() > first_new {
+            println!("{}", history_text(&history[first_new..]));
+        }
+        if let Table::Playing(state) = &table {
+            let mut entries = state.log.recent(None, 0, state.log.size().saturating_sub(seen_log));
+            entries.reverse();
+            let won = !entries.is_empty() && state.winner.is_some();
+            for entry in entries {
+                println!("  {entry}");
+            }
+            seen_log = state.log.size();
+            if let (true, Some(winner)) = (won, state.winner) {
+                println!("{} wins!", name(winner));
+            }
+        }
+
+        if table.is_over() {
+            print!("> ");
+        } else {
+            print!("{}> ", name(table.get_current_color()));
+        }
+        let Some(line) = read_line(&mut lines)? else { return Ok(()) };
+        let line = line.split_once('#').map_or(line.as_str(), |(line, _)| line).trim();
+        let layout = table.get_layout();
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("", _) => continue,
+            ("board", _) => {
+                print!("{}", screen(&table, table.get_current_color()));
+                continue;
+            },
+            ("help", _) | ("?", _) => {
+                println!("{REPL_HELP}\n\n{NOTATION_HELP}");
+                continue;
+            },
+            ("quit", _) | ("exit", _) => return Ok(()),
+            ("svg", path) => {
+                match svg::save_svg(&table, path.trim()) {
+                    Ok(()) => println!("Drew the game in {}", path.trim()),
+                    Err(err) => println!("{err}"),
+                }
+                continue;
+            },
+            ("history", _) => {
+                println!("{}", history_text(&history));
+                continue;
+            },
+            ("at", place) => {
+                match locate(layout, &place.replace(' ', "")) {
+                    Ok(text) | Err(text) => println!("{text}"),
+                }
+                continue;
+            },
+            _ => {}
+        }
+
+        // A line may say whose it is, as `history` writes them
+        let (seat, moves) = match line.split_once(':') {
+            Some((seat, moves)) if PlayerColor::from_name(seat.trim()).is_some() => (PlayerColor::from_name(seat.trim()), moves),
+            _ => (None, line),
+        };
+        let moves = match parse_line(layout, moves) {
+            Ok(moves) => moves,
+            Err(err) => {
+                println!("{err}");
+                continue;
+            }
+        };
+        if let Some(seat) = seat.filter(|&seat| table.is_over() || seat != table.get_current_color()) {
+            println!("it isn't {}'s move", name(seat));
+            continue;
+        }
+        for notated in moves {
+            if table.is_over() {
+                println!("the game is over");
+                break;
+            }
+            let color = table.get_current_color();
+            match play_notated(&mut table, notated, &mut rng) {
+                Ok(mv) => history.push((color, to_played_notation(&table, &mv))),
+                Err(err) => {
+                    println!("'{}' {err}", to_notation(&table, &fit_move(&table, notated.mv)));
+                    break;
+                }
+            }
+        }
+        if history.len() > first_new {
+            println!("{}", history_text(&history[first_new..]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::new_game::{BoardChoice, NewGame};
+    use crate::{test_game, SetupState};
+
+    /// What comes up when the seat to act rolls as written
+    fn roll_as_written(board: BoardChoice, rolled: Rolled) -> Result<Option<[usize; 2]>, String> {
+        let mut table = Table::Playing(Box::new(test_game(board, 50, 1)));
+        play_notated(&mut table, Notated { mv: Move::Roll, roll: Some(rolled) }, &mut StdRng::seed_from_u64(50))?;
+        let Table::Playing(state) = &table else { unreachable!() };
+        Ok(state.roll)
+    }
+
+    #[test]
+    fn written_rolls_come_up_as_written() {
+        assert_eq!(roll_as_written(BoardChoice::Random, Rolled::Total(8)), Ok(Some([4, 4])));
+        assert_eq!(roll_as_written(BoardChoice::Random, Rolled::Dice([6, 2])), Ok(Some([6, 2])));
+        assert_eq!(roll_as_written(BoardChoice::CitiesKnights, Rolled::Dice([3, 5])), Ok(Some([3, 5])));
+        assert!(roll_as_written(BoardChoice::Random, Rolled::Total(13)).is_err());
+    }
+
+    #[test]
+    fn progress_cards_are_read_by_their_longest_name() {
+        let table = Table::Playing(Box::new(test_game(BoardChoice::CitiesKnights, 50, 1)));
+        let Table::Playing(state) = &table else { unreachable!() };
+        let play = |line: &str| match parse_command(line, &table) {
+            Ok(Command::Play(mv)) => Ok(mv),
+            Ok(_) => Err(String::from("not a move")),
+            Err(err) => Err(err),
+        };
+        assert_eq!(play("play merchant fleet wood"), Ok(Move::MerchantFleet(Resource::Wood)));
+        assert_eq!(play("play Resource Monopoly ore"), Ok(Move::ResourceMonopoly(Resource::Ore)));
+        assert_eq!(play("play trade monopoly cloth"), Ok(Move::TradeMonopoly(Commodity::Cloth)));
+        assert_eq!(play("play alchemist 3 5"), Ok(Move::Alchemist([3, 5])));
+        assert_eq!(play("play warlord"), Ok(Move::PlayProgress(ProgressCard::Warlord)));
+        let [hex1, hex2] = [state.board.layout.hex_coords[3], state.board.layout.hex_coords[9]];
+        let line = format!("play inventor {},{} and {},{}", hex1[0], hex1[1], hex2[0], hex2[1]);
+        assert_eq!(play(&line), Ok(Move::Inventor(hex1, hex2)));
+        assert_eq!(play("take warlord"), Ok(Move::TakeProgress(ProgressCard::Warlord)));
+        assert_eq!(play("give coin"), Ok(Move::GiveCommodity(Commodity::Coin)));
+        assert!(play("play alchemist 3 7").is_err());
+    }
+
+    /// The move a line reads as, or why it doesn't read
+    fn read_move(line: &str, table: &Table) -> Result<Move, String> {
+        match parse_command(line, table)? {
+            Command::Play(mv) => Ok(mv),
+            _ => Err(String::from("not a move")),
+        }
+    }
+
+    #[test]
+    fn commands_are_read_as_moves() {
+        let table = Table::Playing(Box::new(test_game(BoardChoice::Random, 50, 1)));
+        let Table::Playing(state) = &table else { unreachable!() };
+        let layout = &state.board.layout;
+        let [r, q] = layout.hex_coords[5];
+        assert_eq!(read_move("roll", &table), Ok(Move::Roll));
+        assert_eq!(read_move("End", &table), Ok(Move::Pass));
+        assert_eq!(read_move(&format!("build road {r},{q},4"), &table), Ok(Move::BuildRoad(layout.reduce_edge([r, q, 4]))));
+        assert_eq!(read_move(&format!("build city {r} {q} 1"), &table), Ok(Move::BuildCity(layout.reduce_corner([r, q, 1]))));
+        assert_eq!(read_move(&format!("robber {r},{q}"), &table), Ok(Move::PlaceRobber([r, q])));
+        assert_eq!(read_move("steal blue", &table), Ok(Move::Steal(PlayerColor::Blue)));
+        let mut hand = ResHand::from_monopoly(Resource::Wheat, 2);
+        hand[Resource::Sheep] = 1;
+        assert_eq!(read_move("discard 2 grain and wool", &table), Ok(Move::Discard(hand)));
+        assert_eq!(read_move("trade 4 lumber for ore", &table),
+            Ok(Move::BankTrade(ResHand::from_monopoly(Resource::Wood, 4), ResHand::from_monopoly(Resource::Ore, 1))));
+        assert_eq!(read_move("play monopoly bricks", &table), Ok(Move::Monopoly(Resource::Brick)));
+        assert!(matches!(parse_command("", &table), Ok(Command::Board)));
+        assert!(matches!(parse_command("?", &table), Ok(Command::Help)));
+        assert!(matches!(parse_command("quit", &table), Ok(Command::Quit)));
+    }
+
+    #[test]
+    fn bad_commands_say_what_went_wrong() {
+        let table = Table::Playing(Box::new(test_game(BoardChoice::Random, 50, 1)));
+        assert_eq!(read_move("build road 0,0,7", &table).unwrap_err(), "there's no side 0,0,7");
+        assert_eq!(read_move("build city 40,40,0", &table).unwrap_err(), "there's no corner 40,40,0");
+        assert_eq!(read_move("trade 4 wood", &table).unwrap_err(), "trade GIVE for GET");
+        assert_eq!(read_move("fly away", &table).unwrap_err(), "unknown command 'fly'; try help");
+    }
+
+    #[test]
+    fn setup_builds_are_placements() {
+        let new_game = NewGame::new(50);
+        let setup_state = SetupState::from_new_game(&new_game, &mut StdRng::seed_from_u64(50)).unwrap();
+        let [r, q] = setup_state.board.layout.hex_coords[5];
+        let corner = setup_state.board.layout.reduce_corner([r, q, 0]);
+        let table = Table::Setup(Box::new(setup_state));
+        assert_eq!(read_move(&format!("build settlement {r},{q},0"), &table), Ok(Move::PlaceSettlement(corner)));
+    }
+
+    /// `text` without its colors
+    fn plain(text: &str) -> String {
+        let mut plain = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('\x1b') {
+            plain += &rest[..start];
+            rest = &rest[start..];
+            rest = &rest[rest.find('m').unwrap() + 1..];
+        }
+        plain + rest
+    }
+
+    #[test]
+    fn the_board_text_shows_hexes_pieces_and_ports() {
+        let mut state = test_game(BoardChoice::Random, 50, 0);
+        let board = &mut state.board;
+        for corners in board.structures.iter_mut().flatten() {
+            corners.fill(None);
+        }
+        let [r, q] = board.layout.hex_coords[5];
+        board.place_setup_city([r, q, 0], PlayerColor::Orange);
+        let text = board_text(board, &[]);
+
+        for &[r, q] in board.layout.hex_coords.iter() {
+            assert!(plain(&text).contains(&format!("{r},{q}")), "{r},{q}");
+        }
+        assert_eq!(text.matches("robber").count(), 1);
+        assert!(text.contains(&paint("C", color_style(PlayerColor::Orange))));
+        assert!(!text.contains(&paint("S", color_style(PlayerColor::Orange))));
+        let ports = board.ports.iter().filter(|port| matches!(port, Port::ThreeForOne)).count();
+        assert_eq!(text.matches("3:1").count(), ports);
+        assert_eq!(board_text(board, &[[r, q, 0]]).matches(&paint("M", color_style(PlayerColor::Orange))).count(), 1);
+    }
+
+    #[test]
+    fn cities_and_knights_rolls_need_the_red_die() {
+        let err = roll_as_written(BoardChoice::CitiesKnights, Rolled::Total(8)).unwrap_err();
+        assert!(err.contains("both dice"), "{err}");
+    }
+}